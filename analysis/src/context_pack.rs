@@ -28,6 +28,7 @@ pub struct ContextPackResponse {
 }
 
 pub fn build_prompt(
+    sentry: &Sentry,
     day: Option<NaiveDate>,
     blocks: &[MemoryBlock],
     sessions: &[SalientSession],
@@ -136,7 +137,6 @@ pub fn build_prompt(
         out.push_str("\n\n[TRUNCATED]\n");
     }
 
-    let sentry = Sentry::new();
     let (redacted, flags) = sentry.scan_and_redact(&out);
     (redacted, flags)
 }
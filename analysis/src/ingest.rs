@@ -1,39 +1,25 @@
 use crate::models::{Dataset, ScoredTurn, SessionBundle, SessionSummary, TurnSummary};
 use crate::salience::{score_session, score_turn, tokenize};
 use anyhow::{Context, Result};
-use chrono::{NaiveDate, Utc};
-use contrail_types::MasterLog;
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+use contrail_types::{Artifact, MasterLog};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use scrapers::event_index::{EventIndex, EventQuery};
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::path::Path;
 use std::path::PathBuf;
 
 pub fn load_dataset(log_path: &Path, day_filter: Option<NaiveDate>) -> Result<Dataset> {
-    let file = File::open(log_path).context("open master_log.jsonl")?;
-    let reader = BufReader::new(file);
-    let mut logs = Vec::new();
-
-    for line in reader.lines() {
-        let line = line?;
-        match serde_json::from_str::<MasterLog>(&line) {
-            Ok(log) => {
-                if let Some(day) = day_filter {
-                    let ts_day = log.timestamp.date_naive();
-                    if ts_day != day {
-                        continue;
-                    }
-                }
-                logs.push(log);
-            }
-            Err(err) => {
-                eprintln!("Skipping malformed log line: {err}");
-            }
-        }
+    let index = EventIndex::open_synced(log_path).context("sync event index")?;
+    let mut query = EventQuery::default();
+    if let Some(day) = day_filter {
+        let start = day.and_time(NaiveTime::MIN).and_utc();
+        query.since = Some(start);
+        query.until = Some(start + Duration::days(1) - Duration::milliseconds(1));
     }
+    let logs = index.query(&query)?;
 
     // Group by source + session_id
     let mut grouped: HashMap<(String, String), Vec<MasterLog>> = HashMap::new();
@@ -52,6 +38,8 @@ pub fn load_dataset(log_path: &Path, day_filter: Option<NaiveDate>) -> Result<Da
         let mut branches = HashSet::new();
         let mut file_effects_total = 0usize;
         let mut clipboard_hits = 0usize;
+        let mut tool_calls = 0usize;
+        let mut tool_errors = 0usize;
         let mut interrupted = false;
         let mut turns = Vec::new();
         let mut project_context_counts: HashMap<String, usize> = HashMap::new();
@@ -60,37 +48,43 @@ pub fn load_dataset(log_path: &Path, day_filter: Option<NaiveDate>) -> Result<Da
             *project_context_counts
                 .entry(log.project_context.clone())
                 .or_insert(0) += 1;
-            let mut meta = log.metadata.clone();
+            let meta = log.metadata.clone();
             // Pull cues from metadata
-            if let Some(obj) = meta.as_object_mut() {
-                if obj
-                    .get("interrupted")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false)
-                {
-                    interrupted = true;
-                }
-                if let Some(arr) = obj.get("file_effects").and_then(|v| v.as_array()) {
-                    file_effects_total += arr.len();
-                }
-                if obj
-                    .get("copied_to_clipboard")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false)
-                {
-                    clipboard_hits += 1;
-                }
-                if let Some(branch) = obj.get("git_branch").and_then(|v| v.as_str()) {
-                    branches.insert(branch.trim().to_string());
+            if meta.interrupted == Some(true) {
+                interrupted = true;
+            }
+            if let Some(effects) = &meta.file_effects {
+                file_effects_total += effects.len();
+            }
+            if meta.copied_to_clipboard == Some(true) {
+                clipboard_hits += 1;
+            }
+            if let Some(branch) = &meta.git_branch {
+                branches.insert(branch.trim().to_string());
+            }
+            if let Some(model) = &meta.model {
+                models.insert(model.to_string());
+            }
+
+            let artifacts = log.interaction.artifacts.clone().unwrap_or_default();
+            for artifact in &artifacts {
+                match artifact {
+                    Artifact::ToolCall { .. } => tool_calls += 1,
+                    Artifact::Patch { .. } => file_effects_total += 1,
+                    _ => {}
                 }
-                if let Some(model) = obj.get("model").and_then(|v| v.as_str()) {
-                    models.insert(model.to_string());
+                if artifact.is_failure() {
+                    tool_errors += 1;
                 }
             }
 
             let content_snippet = snippet(&log.interaction.content);
-            let (turn_score, mut cues) =
-                score_turn(&log.interaction.content, &log.interaction.role, &meta);
+            let (turn_score, mut cues) = score_turn(
+                &log.interaction.content,
+                &log.interaction.role,
+                &meta,
+                &artifacts,
+            );
             let tokens = tokenize(&log.interaction.content)
                 .into_iter()
                 .collect::<HashSet<_>>();
@@ -113,7 +107,9 @@ pub fn load_dataset(log_path: &Path, day_filter: Option<NaiveDate>) -> Result<Da
                     role: log.interaction.role.clone(),
                     content_snippet,
                     metadata: meta,
+                    artifacts,
                 },
+                content: log.interaction.content.clone(),
                 tokens,
                 salience: turn_score,
                 cues,
@@ -147,6 +143,8 @@ pub fn load_dataset(log_path: &Path, day_filter: Option<NaiveDate>) -> Result<Da
             interrupted,
             file_effects: file_effects_total,
             clipboard_hits,
+            tool_calls,
+            tool_errors,
             models: to_sorted_vec(models),
             git_branches: to_sorted_vec(branches),
             score: 0.0,
@@ -157,7 +155,7 @@ pub fn load_dataset(log_path: &Path, day_filter: Option<NaiveDate>) -> Result<Da
     }
 
     // Order newest first by default
-    sessions.sort_by(|a, b| b.summary.ended_at.cmp(&a.summary.ended_at));
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.summary.ended_at));
 
     Ok(Dataset {
         sessions,
@@ -225,8 +223,7 @@ fn infer_codex_project_context(events: &[MasterLog]) -> Option<String> {
         Lazy::new(|| Regex::new(r"(/(?:Users|Volumes|private|opt|tmp)/[^\n\r]+)").unwrap());
 
     for log in events.iter().take(250) {
-        if let Some(obj) = log.metadata.as_object()
-            && let Some(cwd) = obj.get("cwd").and_then(Value::as_str)
+        if let Some(cwd) = log.metadata.cwd.as_deref()
             && let Some(root) = project_root_from_path(cwd)
         {
             return Some(root);
@@ -8,6 +8,7 @@ mod salience;
 mod search;
 
 use crate::models::ScoredTurn;
+use crate::search::ProbeMode;
 
 use axum::{
     Json, Router,
@@ -23,11 +24,15 @@ use memory::{MemoryRecord, append_memory, read_memories};
 use memory_blocks::{MemoryBlock, MemoryBlockUpdate};
 use models::{
     Dataset, ProbeResponse, ProjectSummary, ProjectsResponse, SalientResponse, SalientSession,
-    SessionsResponse, TurnSummary,
+    SearchResponse, SessionsResponse, TurnSummary,
 };
+use scrapers::config::ContrailConfig;
+use scrapers::event_index::{EventIndex, EventQuery};
+use scrapers::search_index::{SearchIndex, SearchQuery, events_index_path};
+use scrapers::semantic::{DocumentVectors, EMBEDDING_CACHE_FILE, Embedder, embedder_from_config};
+use scrapers::sentry::Sentry;
 use serde::Deserialize;
 use std::env;
-use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
@@ -41,6 +46,11 @@ struct AppState {
     data: Arc<RwLock<Dataset>>,
     memory_io_lock: Arc<Mutex<()>>,
     llm: Option<llm::LlmClient>,
+    sentry: Arc<Sentry>,
+    embedder: Arc<dyn Embedder>,
+    /// Per-turn vectors for hybrid probes, in the embedding cache.
+    turn_vectors: Arc<DocumentVectors>,
+    search_index_path: PathBuf,
 }
 
 #[derive(Debug)]
@@ -91,6 +101,8 @@ struct DayLimitQuery {
     project: Option<String>,
     refresh: Option<bool>,
     q: Option<String>,
+    /// Probe ranking: `tokens` (default) or `hybrid`.
+    mode: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -174,29 +186,23 @@ const DEFAULT_PROBES: &[&str] = &[
     "function_call_output failed",
 ];
 
+/// The configured embedder, built on a blocking thread: the HTTP embedder's
+/// `reqwest::blocking` client panics when it is created on a runtime thread.
+async fn load_embedder(config: &ContrailConfig) -> anyhow::Result<Arc<dyn Embedder>> {
+    let config = config.clone();
+    let embedder = tokio::task::spawn_blocking(move || embedder_from_config(&config))
+        .await
+        .map_err(|e| anyhow::anyhow!("join error: {e}"))??;
+    Ok(Arc::from(embedder))
+}
+
 pub async fn run() -> anyhow::Result<()> {
-    let log_path = env::var("CONTRAIL_LOG_PATH")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| {
-            dirs::home_dir()
-                .expect("Could not find home directory")
-                .join(".contrail/logs/master_log.jsonl")
-        });
-    let memory_path = env::var("CONTRAIL_MEMORY_PATH")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| {
-            dirs::home_dir()
-                .expect("Could not find home directory")
-                .join(".contrail/analysis/memories.jsonl")
-        });
-    let memory_blocks_path = env::var("CONTRAIL_MEMORY_BLOCKS_PATH")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| {
-            dirs::home_dir()
-                .expect("Could not find home directory")
-                .join(".contrail/analysis/memory_blocks.json")
-        });
+    let config = ContrailConfig::load()?;
+    let log_path = config.log_path.clone();
+    let memory_path = config.analysis_memories.clone();
+    let memory_blocks_path = config.analysis_memory_blocks.clone();
 
+    let sentry = Sentry::from_config(&config)?;
     let initial_dataset = ingest::load_dataset(&log_path, None)?;
     let state = AppState {
         log_path,
@@ -204,7 +210,13 @@ pub async fn run() -> anyhow::Result<()> {
         memory_blocks_path,
         data: Arc::new(RwLock::new(initial_dataset)),
         memory_io_lock: Arc::new(Mutex::new(())),
-        llm: llm::LlmClient::from_env()?,
+        llm: llm::LlmClient::from_config(&config)?,
+        sentry: Arc::new(sentry),
+        embedder: load_embedder(&config).await?,
+        turn_vectors: Arc::new(DocumentVectors::open(
+            &config.search_index_dir.join(EMBEDDING_CACHE_FILE),
+        )?),
+        search_index_path: events_index_path(&config.search_index_dir),
     };
 
     let app = Router::new()
@@ -214,6 +226,7 @@ pub async fn run() -> anyhow::Result<()> {
         .route("/api/projects", get(get_projects))
         .route("/api/salient", get(get_salient))
         .route("/api/probe", get(get_probe))
+        .route("/api/search", get(get_search))
         .route("/api/session_events", get(get_session_events))
         .route("/api/context_pack", get(get_context_pack))
         .route("/api/import_history", post(import_history))
@@ -235,7 +248,7 @@ pub async fn run() -> anyhow::Result<()> {
         .with_state(state)
         .layer(CorsLayer::permissive());
 
-    let bind_addr = env::var("ANALYSIS_BIND").unwrap_or_else(|_| "127.0.0.1:3210".to_string());
+    let bind_addr = config.analysis_bind;
     println!("✈️  Contrail Analysis running at http://{bind_addr}");
     let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
     axum::serve(listener, app)
@@ -253,7 +266,7 @@ async fn index() -> Html<&'static str> {
 }
 
 async fn import_history(State(state): State<AppState>) -> ApiResult<Json<ImportHistoryResponse>> {
-    let config = scrapers::config::ContrailConfig::from_env().map_err(ApiError::internal)?;
+    let config = scrapers::config::ContrailConfig::load().map_err(ApiError::internal)?;
     let log_path = state.log_path.clone();
 
     let stats =
@@ -264,7 +277,8 @@ async fn import_history(State(state): State<AppState>) -> ApiResult<Json<ImportH
 
     // Best-effort: match core_daemon behavior so it won't re-import.
     if let Some(home) = dirs::home_dir() {
-        let marker_path = home.join(".contrail/state/history_import_done.json");
+        let marker_path =
+            scrapers::config::data_dir(&home).join(scrapers::config::HISTORY_IMPORT_MARKER_REL);
         if let Some(dir) = marker_path.parent() {
             let _ = std::fs::create_dir_all(dir);
         }
@@ -364,7 +378,7 @@ async fn get_sessions(
 
     let sort = query.sort.as_deref().unwrap_or("score");
     match sort {
-        "recent" => sessions.sort_by(|a, b| b.ended_at.cmp(&a.ended_at)),
+        "recent" => sessions.sort_by_key(|s| std::cmp::Reverse(s.ended_at)),
         _ => sessions.sort_by(|a, b| b.score.total_cmp(&a.score)),
     }
 
@@ -416,7 +430,7 @@ async fn get_projects(
     }
 
     let mut projects: Vec<ProjectSummary> = map.into_values().collect();
-    projects.sort_by(|a, b| b.last_ended_at.cmp(&a.last_ended_at));
+    projects.sort_by_key(|p| std::cmp::Reverse(p.last_ended_at));
 
     Ok(Json(ProjectsResponse {
         projects,
@@ -464,13 +478,72 @@ async fn get_probe(
         .filter(|s| !s.trim().is_empty())
         .ok_or_else(|| ApiError::bad_request(anyhow::anyhow!("probe requires ?q=<query>")))?;
 
-    let matches = search::probe(&dataset, &probe, day, limit);
+    let mode = query
+        .mode
+        .as_deref()
+        .map(str::parse::<ProbeMode>)
+        .transpose()
+        .map_err(ApiError::bad_request)?
+        .unwrap_or_default();
+
+    let day_filter = dataset.day_filter;
+    let matches = match mode {
+        ProbeMode::Tokens => search::probe(&dataset, &probe, day, limit),
+        ProbeMode::Hybrid => {
+            let embedder = state.embedder.clone();
+            let vectors = state.turn_vectors.clone();
+            let query = probe.clone();
+            tokio::task::spawn_blocking(move || {
+                search::probe_hybrid(&dataset, &query, day, limit, embedder.as_ref(), &vectors)
+            })
+            .await
+            .map_err(|e| ApiError::internal(anyhow::anyhow!("join error: {e}")))?
+            .map_err(ApiError::internal)?
+        }
+    };
     let prompt_suggestion = search::build_probe_prompt(&probe, &matches);
     Ok(Json(ProbeResponse {
         query: probe,
         matches,
         prompt_suggestion,
-        day: dataset.day_filter,
+        day: day_filter,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    q: String,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+async fn get_search(
+    State(state): State<AppState>,
+    Query(params): Query<SearchParams>,
+) -> ApiResult<Json<SearchResponse>> {
+    let query: SearchQuery = params.q.parse().map_err(ApiError::bad_request)?;
+    if query.is_empty() {
+        return Err(ApiError::bad_request(anyhow::anyhow!(
+            "search requires ?q=<query>"
+        )));
+    }
+    let limit = params.limit.unwrap_or(20).clamp(1, 200);
+    let offset = params.offset.unwrap_or(0);
+    let index_path = state.search_index_path.clone();
+    let log_path = state.log_path.clone();
+
+    let hits = tokio::task::spawn_blocking(move || {
+        let mut index = SearchIndex::open(&index_path)?;
+        index.sync_events(&log_path)?;
+        index.search(&query, limit, offset, None)
+    })
+    .await
+    .map_err(|e| ApiError::internal(anyhow::anyhow!("join error: {e}")))?
+    .map_err(ApiError::internal)?;
+
+    Ok(Json(SearchResponse {
+        query: params.q,
+        hits,
     }))
 }
 
@@ -489,11 +562,12 @@ async fn get_session_events(
         .unwrap_or(20_000)
         .clamp(200, 200_000);
     let log_path = state.log_path.clone();
+    let sentry = state.sentry.clone();
     let source_tool = query.source_tool.clone();
     let session_id = query.session_id.clone();
 
     let logs = tokio::task::spawn_blocking(move || {
-        read_session_events(&log_path, &source_tool, &session_id, max_chars)
+        read_session_events(&log_path, &sentry, &source_tool, &session_id, max_chars)
     })
     .await
     .map_err(|e| ApiError::internal(anyhow::anyhow!("join error: {e}")))?
@@ -570,7 +644,7 @@ async fn get_context_pack(
         let _guard = state.memory_io_lock.lock().await;
         let mut blocks =
             memory_blocks::read_blocks(&state.memory_blocks_path).map_err(ApiError::internal)?;
-        blocks.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        blocks.sort_by_key(|b| std::cmp::Reverse(b.updated_at));
         blocks.truncate(25);
         blocks
     } else {
@@ -586,6 +660,7 @@ async fn get_context_pack(
     };
 
     let (prompt, flags) = context_pack::build_prompt(
+        &state.sentry,
         day,
         &memory_blocks,
         &top_sessions,
@@ -632,8 +707,7 @@ async fn create_memory_block(
         )));
     }
 
-    let sentry = scrapers::sentry::Sentry::new();
-    let (value, flags) = sentry.scan_and_redact(&body.value);
+    let (value, flags) = state.sentry.scan_and_redact(&body.value);
 
     let block = MemoryBlock {
         id: uuid::Uuid::new_v4(),
@@ -672,8 +746,7 @@ async fn update_memory_block(
                 "value cannot be empty"
             )));
         }
-        let sentry = scrapers::sentry::Sentry::new();
-        let (redacted, flags) = sentry.scan_and_redact(value);
+        let (redacted, flags) = state.sentry.scan_and_redact(value);
         update.value = Some(redacted);
         update.security_flags = Some(flags);
     }
@@ -711,7 +784,9 @@ async fn create_memory_with_llm(
     axum::Json(body): axum::Json<AutoProbeBody>,
 ) -> ApiResult<Json<MemoryRecord>> {
     let llm = state.llm.clone().ok_or_else(|| {
-        ApiError::bad_request(anyhow::anyhow!("LLM not configured (set OPENAI_API_KEY)"))
+        ApiError::bad_request(anyhow::anyhow!(
+            "LLM not configured (set CONTRAIL_LLM_API_KEY, or see [llm] in config.toml)"
+        ))
     })?;
 
     let day = parse_day(&body.day)?;
@@ -746,7 +821,9 @@ async fn run_default_autoprobes(
     axum::Json(body): axum::Json<DefaultAutoProbeBody>,
 ) -> ApiResult<Json<Vec<MemoryRecord>>> {
     let llm = state.llm.clone().ok_or_else(|| {
-        ApiError::bad_request(anyhow::anyhow!("LLM not configured (set OPENAI_API_KEY)"))
+        ApiError::bad_request(anyhow::anyhow!(
+            "LLM not configured (set CONTRAIL_LLM_API_KEY, or see [llm] in config.toml)"
+        ))
     })?;
     let day = parse_day(&body.day)?;
     let dataset = ensure_dataset(&state, day, false).await?;
@@ -851,38 +928,36 @@ fn expand_tilde_path(p: &std::path::Path) -> PathBuf {
 }
 
 fn read_session_events(
-    log_path: &PathBuf,
+    log_path: &std::path::Path,
+    sentry: &Sentry,
     source_tool: &str,
     session_id: &str,
     max_content_chars: usize,
 ) -> anyhow::Result<Vec<MasterLog>> {
-    let file =
-        std::fs::File::open(log_path).map_err(|e| anyhow::anyhow!("open {:?}: {}", log_path, e))?;
-    let reader = BufReader::new(file);
-    let sentry = scrapers::sentry::Sentry::new();
+    let index = EventIndex::open_synced(log_path)?;
+    let events = index.query(&EventQuery {
+        source_tool: Some(source_tool.to_string()),
+        session_id: Some(session_id.to_string()),
+        ..Default::default()
+    })?;
 
     let mut out = Vec::new();
-    for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => continue,
-        };
-        let Ok(mut log) = serde_json::from_str::<MasterLog>(&line) else {
-            continue;
-        };
-        if log.source_tool != source_tool || log.session_id != session_id {
-            continue;
-        }
-
+    for mut log in events {
         let mut clipped = String::new();
         for c in log.interaction.content.chars().take(max_content_chars) {
             clipped.push(c);
         }
 
-        let (redacted, flags) = sentry.scan_and_redact(&clipped);
+        let (redacted, mut flags) = sentry.scan_and_redact(&clipped);
         if redacted != log.interaction.content {
             log.interaction.content = redacted;
         }
+        if let Some(artifacts) = log.interaction.artifacts.as_mut() {
+            for artifact in artifacts.iter_mut() {
+                artifact.truncate_text(max_content_chars);
+            }
+            sentry.redact_artifacts(artifacts, &mut flags);
+        }
         if flags.has_pii || !flags.redacted_secrets.is_empty() {
             log.security_flags.has_pii |= flags.has_pii;
             let mut merged: std::collections::HashSet<String> =
@@ -896,6 +971,24 @@ fn read_session_events(
         out.push(log);
     }
 
-    out.sort_by_key(|l| l.timestamp);
     Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrapers::semantic::EmbedderKind;
+
+    #[tokio::test]
+    async fn http_embedder_is_built_inside_the_runtime() {
+        let home = std::env::temp_dir().join(format!("contrail-embedder-{}", std::process::id()));
+        let mut config = ContrailConfig::defaults(&home);
+        config.search_embedder = EmbedderKind::Http;
+        config.search_index_dir = home.join("index");
+
+        let embedder = load_embedder(&config).await.unwrap();
+        assert!(embedder.id().starts_with("http:"));
+        drop(embedder);
+        std::fs::remove_dir_all(&home).ok();
+    }
+}
@@ -1,79 +1,284 @@
+//! LLM providers behind the autoprobe endpoints, chosen by the `[llm]` config section.
+//!
+//! - `openai`: any OpenAI-compatible `/chat/completions` endpoint. Point `base_url` at
+//!   a local llama.cpp or Ollama server to keep prompts on the machine.
+//! - `anthropic`: the Anthropic Messages API.
+//! - `mock`: a canned, deterministic reply, for tests and offline demos.
+//!
+//! The key comes from `CONTRAIL_LLM_API_KEY`. Only when `base_url` points at
+//! `api.openai.com` does `openai` fall back to `OPENAI_API_KEY` (or
+//! `~/.config/openai/api_key`), so that key never goes to a third-party endpoint;
+//! `anthropic` falls back to `ANTHROPIC_API_KEY`.
+
 use anyhow::{Context, Result, bail};
 use reqwest::Client;
+use scrapers::config::{ContrailConfig, LlmProviderKind};
 use serde_json::Value;
 use std::fs;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+const OPENAI_HOST: &str = "api.openai.com";
+const SYSTEM_PROMPT: &str = "You are a concise analyst generating structured hypotheses and follow-up questions from AI coding session traces. Respond with JSON only.";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const ANTHROPIC_MAX_TOKENS: u32 = 4096;
+
+pub type LlmFuture<'a> = Pin<Box<dyn Future<Output = Result<Completion>> + Send + 'a>>;
+
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub system: String,
+    pub prompt: String,
+    pub model: String,
+    pub temperature: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    /// The reply's text.
+    pub text: String,
+    /// The provider's full response body.
+    pub raw: Value,
+}
+
+pub trait LlmProvider: Send + Sync {
+    fn complete<'a>(&'a self, request: &'a ChatRequest) -> LlmFuture<'a>;
+}
 
 #[derive(Clone)]
 pub struct LlmClient {
-    http: Client,
-    api_key: String,
+    provider: Arc<dyn LlmProvider>,
     model: String,
 }
 
 impl LlmClient {
-    pub fn from_env() -> Result<Option<Self>> {
-        let api_key = match std::env::var("OPENAI_API_KEY") {
-            Ok(k) if !k.trim().is_empty() => Some(k),
-            _ => read_key_file(),
-        };
-        let Some(api_key) = api_key else {
-            return Ok(None);
+    pub fn new(provider: Arc<dyn LlmProvider>, model: impl Into<String>) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+        }
+    }
+
+    /// The configured provider, or `None` when it needs an API key and none is set.
+    /// An OpenAI-compatible endpoint at a non-default `base_url` may go without one.
+    pub fn from_config(config: &ContrailConfig) -> Result<Option<Self>> {
+        let configured_key = env_key("CONTRAIL_LLM_API_KEY");
+        let provider: Arc<dyn LlmProvider> = match config.llm_provider {
+            LlmProviderKind::OpenAi => {
+                let openai_hosted = is_openai_host(&config.llm_base_url);
+                let api_key = if openai_hosted {
+                    configured_key
+                        .or_else(|| env_key("OPENAI_API_KEY"))
+                        .or_else(read_key_file)
+                } else {
+                    configured_key
+                };
+                if api_key.is_none() && openai_hosted {
+                    return Ok(None);
+                }
+                Arc::new(OpenAiProvider::new(&config.llm_base_url, api_key))
+            }
+            LlmProviderKind::Anthropic => {
+                let Some(api_key) = configured_key.or_else(|| env_key("ANTHROPIC_API_KEY")) else {
+                    return Ok(None);
+                };
+                Arc::new(AnthropicProvider::new(&config.llm_base_url, api_key))
+            }
+            LlmProviderKind::Mock => Arc::new(MockProvider),
         };
-        let model = std::env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-5.1".to_string());
-        Ok(Some(Self {
-            http: Client::new(),
-            api_key,
-            model,
-        }))
+        Ok(Some(Self::new(provider, config.llm_model.clone())))
     }
 
+    /// Ask for JSON about `prompt`. Returns `{"raw": <response>, "parsed": <reply>}`,
+    /// where `parsed` is the reply as JSON, or as a string if it isn't valid JSON.
     pub async fn chat(
         &self,
         prompt: &str,
         model_override: Option<String>,
         temperature: Option<f32>,
     ) -> Result<Value> {
-        let model = model_override.unwrap_or_else(|| self.model.clone());
-        let body = serde_json::json!({
-            "model": model,
-            "messages": [
-                {"role": "system", "content": "You are a concise analyst generating structured hypotheses and follow-up questions from AI coding session traces. Respond with JSON only."},
-                {"role": "user", "content": prompt}
-            ],
-            "temperature": temperature.unwrap_or(0.0),
-        });
+        let request = ChatRequest {
+            system: SYSTEM_PROMPT.to_string(),
+            prompt: prompt.to_string(),
+            model: model_override.unwrap_or_else(|| self.model.clone()),
+            temperature: temperature.unwrap_or(0.0),
+        };
+        let completion = self.provider.complete(&request).await?;
+        let parsed = serde_json::from_str::<Value>(strip_code_fence(&completion.text))
+            .unwrap_or_else(|_| Value::String(completion.text.clone()));
 
-        let res = self
-            .http
-            .post("https://api.openai.com/v1/chat/completions")
-            .bearer_auth(&self.api_key)
-            .json(&body)
-            .send()
-            .await
-            .context("send chat request")?;
+        Ok(serde_json::json!({
+            "raw": completion.raw,
+            "parsed": parsed
+        }))
+    }
+}
+
+// ── OpenAI-compatible ───────────────────────────────────────────────────
+
+pub struct OpenAiProvider {
+    http: Client,
+    url: String,
+    api_key: Option<String>,
+}
 
-        if !res.status().is_success() {
-            let status = res.status();
-            let text = res.text().await.unwrap_or_default();
-            bail!("LLM call failed: {} - {}", status, text);
+impl OpenAiProvider {
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            http: Client::new(),
+            url: format!("{}/chat/completions", base_url.trim_end_matches('/')),
+            api_key,
         }
+    }
+}
 
-        let json: Value = res.json().await.context("decode chat response")?;
-        let content = json
-            .pointer("/choices/0/message/content")
-            .cloned()
-            .unwrap_or(Value::String(String::from("")));
-        let parsed_json = if let Some(text) = content.as_str() {
-            serde_json::from_str::<Value>(text).unwrap_or_else(|_| Value::String(text.to_string()))
-        } else {
-            content
-        };
+impl LlmProvider for OpenAiProvider {
+    fn complete<'a>(&'a self, request: &'a ChatRequest) -> LlmFuture<'a> {
+        Box::pin(async move {
+            let body = serde_json::json!({
+                "model": request.model,
+                "messages": [
+                    {"role": "system", "content": request.system},
+                    {"role": "user", "content": request.prompt}
+                ],
+                "temperature": request.temperature,
+            });
+            let mut req = self.http.post(&self.url).json(&body);
+            if let Some(key) = &self.api_key {
+                req = req.bearer_auth(key);
+            }
+            let raw = send(req).await?;
+            let text = raw
+                .pointer("/choices/0/message/content")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            Ok(Completion { text, raw })
+        })
+    }
+}
 
-        Ok(serde_json::json!({
-            "raw": json,
-            "parsed": parsed_json
-        }))
+// ── Anthropic Messages ──────────────────────────────────────────────────
+
+pub struct AnthropicProvider {
+    http: Client,
+    url: String,
+    api_key: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(base_url: &str, api_key: String) -> Self {
+        Self {
+            http: Client::new(),
+            url: format!("{}/messages", base_url.trim_end_matches('/')),
+            api_key,
+        }
+    }
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn complete<'a>(&'a self, request: &'a ChatRequest) -> LlmFuture<'a> {
+        Box::pin(async move {
+            let body = serde_json::json!({
+                "model": request.model,
+                "max_tokens": ANTHROPIC_MAX_TOKENS,
+                "system": request.system,
+                "messages": [{"role": "user", "content": request.prompt}],
+                "temperature": request.temperature,
+            });
+            let req = self
+                .http
+                .post(&self.url)
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .json(&body);
+            let raw = send(req).await?;
+            let text = anthropic_text(&raw);
+            Ok(Completion { text, raw })
+        })
+    }
+}
+
+/// The text blocks of a Messages response, joined.
+fn anthropic_text(raw: &Value) -> String {
+    raw.get("content")
+        .and_then(Value::as_array)
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(Value::as_str) == Some("text"))
+                .filter_map(|b| b.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("")
+        })
+        .unwrap_or_default()
+}
+
+// ── Mock ────────────────────────────────────────────────────────────────
+
+/// Replies with JSON in the shape the probe prompt asks for, derived only from the
+/// request, so the same request always gets the same reply.
+pub struct MockProvider;
+
+impl LlmProvider for MockProvider {
+    fn complete<'a>(&'a self, request: &'a ChatRequest) -> LlmFuture<'a> {
+        Box::pin(async move {
+            let query = request
+                .prompt
+                .lines()
+                .skip_while(|line| *line != "Query:")
+                .nth(1)
+                .unwrap_or_default();
+            let reply = serde_json::json!({
+                "hypotheses": [format!("mock hypothesis for {query:?}")],
+                "risks": [],
+                "questions": [],
+                "next_steps": [],
+            });
+            let text = reply.to_string();
+            let raw = serde_json::json!({
+                "provider": "mock",
+                "model": request.model,
+                "prompt_chars": request.prompt.chars().count(),
+                "text": text,
+            });
+            Ok(Completion { text, raw })
+        })
+    }
+}
+
+// ── Helpers ─────────────────────────────────────────────────────────────
+
+async fn send(req: reqwest::RequestBuilder) -> Result<Value> {
+    let res = req.send().await.context("send chat request")?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        bail!("LLM call failed: {} - {}", status, text);
     }
+    res.json().await.context("decode chat response")
+}
+
+/// Models often wrap JSON in a Markdown code fence despite being asked not to.
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(inner) = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+    else {
+        return trimmed;
+    };
+    inner.strip_suffix("```").unwrap_or(inner).trim()
+}
+
+/// Whether `base_url` is OpenAI's own API, the only place the OpenAI key may go.
+fn is_openai_host(base_url: &str) -> bool {
+    reqwest::Url::parse(base_url).is_ok_and(|url| url.host_str() == Some(OPENAI_HOST))
+}
+
+fn env_key(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|k| !k.trim().is_empty())
 }
 
 fn read_key_file() -> Option<String> {
@@ -93,3 +298,51 @@ fn read_key_file() -> Option<String> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn mock_provider_is_deterministic() {
+        let client = LlmClient::new(Arc::new(MockProvider), "mock");
+        let prompt = "Analyze.\nQuery:\nflaky tests\nSnippets:\n- ...";
+        let first = client.chat(prompt, None, None).await.unwrap();
+        let second = client.chat(prompt, None, None).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            first["parsed"]["hypotheses"][0],
+            "mock hypothesis for \"flaky tests\""
+        );
+        assert_eq!(first["raw"]["model"], "mock");
+
+        let other = client
+            .chat(prompt, Some("bigger".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(other["raw"]["model"], "bigger");
+    }
+
+    #[test]
+    fn extracts_reply_text() {
+        let raw = serde_json::json!({
+            "content": [
+                {"type": "text", "text": "{\"a\":"},
+                {"type": "tool_use", "id": "x"},
+                {"type": "text", "text": "1}"}
+            ]
+        });
+        assert_eq!(anthropic_text(&raw), "{\"a\":1}");
+        assert_eq!(strip_code_fence("```json\n{\"a\":1}\n```"), "{\"a\":1}");
+        assert_eq!(strip_code_fence(" plain "), "plain");
+    }
+
+    #[test]
+    fn openai_key_only_goes_to_openai() {
+        assert!(is_openai_host("https://api.openai.com/v1"));
+        assert!(!is_openai_host("http://localhost:8080/v1"));
+        assert!(!is_openai_host("https://api.openai.com.evil.example/v1"));
+        assert!(!is_openai_host("https://proxy.example/api.openai.com/v1"));
+        assert!(!is_openai_host("not a url"));
+    }
+}
@@ -1,4 +1,5 @@
 use chrono::{DateTime, NaiveDate, Utc};
+use contrail_types::{Artifact, Metadata};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -11,12 +12,16 @@ pub struct TurnSummary {
     pub project_context: String,
     pub role: String,
     pub content_snippet: String,
-    pub metadata: serde_json::Value,
+    pub metadata: Metadata,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<Artifact>,
 }
 
 #[derive(Clone, Debug)]
 pub struct ScoredTurn {
     pub turn: TurnSummary,
+    /// The full message; `turn.content_snippet` is cut short.
+    pub content: String,
     pub tokens: HashSet<String>,
     pub salience: f32,
     pub cues: Vec<String>,
@@ -33,6 +38,9 @@ pub struct SessionSummary {
     pub interrupted: bool,
     pub file_effects: usize,
     pub clipboard_hits: usize,
+    /// Tool calls recorded as artifacts, and how many calls or commands failed.
+    pub tool_calls: usize,
+    pub tool_errors: usize,
     pub models: Vec<String>,
     pub git_branches: Vec<String>,
     pub score: f32,
@@ -88,6 +96,12 @@ pub struct ProbeResponse {
     pub day: Option<NaiveDate>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub query: String,
+    pub hits: Vec<scrapers::search_index::Hit>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct MemoriesResponse {
     pub memories: Vec<crate::memory::MemoryRecord>,
@@ -1,9 +1,18 @@
 use crate::models::{ScoredTurn, SessionSummary};
 use chrono::{DateTime, Utc};
+use contrail_types::{Artifact, Metadata};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-pub fn score_turn(content: &str, role: &str, metadata: &serde_json::Value) -> (f32, Vec<String>) {
+/// Session-end snapshots touching at least this many lines get a `large_diff` cue.
+const LARGE_DIFF_LINES: u64 = 200;
+
+pub fn score_turn(
+    content: &str,
+    role: &str,
+    metadata: &Metadata,
+    artifacts: &[Artifact],
+) -> (f32, Vec<String>) {
     let mut score = 1.0;
     let mut cues = Vec::new();
 
@@ -31,27 +40,44 @@ pub fn score_turn(content: &str, role: &str, metadata: &serde_json::Value) -> (f
         cues.push("long".to_string());
     }
 
-    if let Some(obj) = metadata.as_object() {
-        if obj
-            .get("interrupted")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false)
-        {
-            score += 0.5;
-            cues.push("interrupted".to_string());
-        }
-        if obj.get("file_effects").and_then(|v| v.as_array()).is_some() {
-            score += 0.6;
-            cues.push("file_effects".to_string());
-        }
-        if obj
-            .get("copied_to_clipboard")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false)
-        {
-            score += 0.3;
-            cues.push("clipboard".to_string());
-        }
+    if metadata.interrupted == Some(true) {
+        score += 0.5;
+        cues.push("interrupted".to_string());
+    }
+    if metadata.file_effects.is_some() {
+        score += 0.6;
+        cues.push("file_effects".to_string());
+    }
+    if metadata
+        .files_deleted
+        .as_ref()
+        .is_some_and(|f| !f.is_empty())
+    {
+        score += 0.2;
+        cues.push("deleted_files".to_string());
+    }
+    let lines_changed =
+        metadata.diff_insertions.unwrap_or(0) + metadata.diff_deletions.unwrap_or(0);
+    if lines_changed >= LARGE_DIFF_LINES {
+        score += 0.3;
+        cues.push("large_diff".to_string());
+    }
+    if metadata.copied_to_clipboard == Some(true) {
+        score += 0.3;
+        cues.push("clipboard".to_string());
+    }
+
+    if artifacts.iter().any(Artifact::is_failure) {
+        score += 0.4;
+        cues.push("tool_error".to_string());
+    }
+    if !cues.iter().any(|c| c == "file_effects")
+        && artifacts
+            .iter()
+            .any(|a| matches!(a, Artifact::Patch { .. }))
+    {
+        score += 0.6;
+        cues.push("file_effects".to_string());
     }
 
     (score, cues)
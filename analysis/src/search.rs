@@ -1,6 +1,31 @@
-use crate::models::{Dataset, ProbeMatch};
+use crate::models::{Dataset, ProbeMatch, ScoredTurn, SessionBundle};
 use crate::salience::tokenize;
+use anyhow::Result;
 use chrono::NaiveDate;
+use scrapers::semantic::{DocumentVectors, Embedder, rank_hybrid_stored};
+use std::str::FromStr;
+
+/// How `/api/probe` ranks turns.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeMode {
+    /// Share of query tokens a turn contains.
+    #[default]
+    Tokens,
+    /// BM25 blended with embedding similarity, so rephrasings still match.
+    Hybrid,
+}
+
+impl FromStr for ProbeMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "tokens" | "lexical" => Ok(Self::Tokens),
+            "hybrid" | "semantic" => Ok(Self::Hybrid),
+            other => anyhow::bail!("unknown probe mode {other:?} (expected tokens or hybrid)"),
+        }
+    }
+}
 
 pub fn probe(
     dataset: &Dataset,
@@ -15,31 +40,13 @@ pub fn probe(
     }
 
     let mut matches = Vec::new();
-    for session in &dataset.sessions {
-        if let Some(day_filter) = day.or(dataset.day_filter)
-            && session.summary.started_at.date_naive() != day_filter
-        {
+    for (session, turn) in candidates(dataset, day) {
+        let overlap: usize = turn.tokens.intersection(&q_set).count();
+        if overlap == 0 {
             continue;
         }
-
-        for turn in &session.turns {
-            let overlap: usize = turn.tokens.intersection(&q_set).count();
-            if overlap == 0 {
-                continue;
-            }
-            let coverage = overlap as f32 / q_set.len().max(1) as f32;
-            let score = coverage * 2.0 + turn.salience * 0.3 + session.summary.score * 0.05;
-            matches.push(ProbeMatch {
-                session_id: session.summary.session_id.clone(),
-                source_tool: session.summary.source_tool.clone(),
-                project_context: session.summary.project_context.clone(),
-                timestamp: turn.turn.timestamp,
-                role: turn.turn.role.clone(),
-                content_snippet: turn.turn.content_snippet.clone(),
-                score,
-                cues: turn.cues.clone(),
-            });
-        }
+        let coverage = overlap as f32 / q_set.len().max(1) as f32;
+        matches.push(probe_match(session, turn, coverage));
     }
 
     matches.sort_by(|a, b| b.score.total_cmp(&a.score));
@@ -47,6 +54,61 @@ pub fn probe(
     matches
 }
 
+/// Like [`probe`], but ranks full turn content by hybrid BM25 + embedding
+/// relevance. Turn vectors are kept in `vectors` by event id, so only the query and
+/// new turns are embedded. Blocks on the embedder, so call it off the async runtime.
+pub fn probe_hybrid(
+    dataset: &Dataset,
+    query: &str,
+    day: Option<NaiveDate>,
+    limit: usize,
+    embedder: &dyn Embedder,
+    vectors: &DocumentVectors,
+) -> Result<Vec<ProbeMatch>> {
+    let turns: Vec<_> = candidates(dataset, day).collect();
+    let docs: Vec<(&str, &str)> = turns
+        .iter()
+        .map(|(_, turn)| (turn.turn.event_id.as_str(), turn.content.as_str()))
+        .collect();
+
+    let mut matches: Vec<ProbeMatch> = rank_hybrid_stored(embedder, vectors, query, &docs)?
+        .into_iter()
+        .map(|(i, relevance)| probe_match(turns[i].0, turns[i].1, relevance))
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+fn candidates(
+    dataset: &Dataset,
+    day: Option<NaiveDate>,
+) -> impl Iterator<Item = (&SessionBundle, &ScoredTurn)> {
+    let day_filter = day.or(dataset.day_filter);
+    dataset
+        .sessions
+        .iter()
+        .filter(move |session| {
+            day_filter.is_none_or(|d| session.summary.started_at.date_naive() == d)
+        })
+        .flat_map(|session| session.turns.iter().map(move |turn| (session, turn)))
+}
+
+/// `relevance` (0..=1) dominates; salience and session score break ties.
+fn probe_match(session: &SessionBundle, turn: &ScoredTurn, relevance: f32) -> ProbeMatch {
+    let score = relevance * 2.0 + turn.salience * 0.3 + session.summary.score * 0.05;
+    ProbeMatch {
+        session_id: session.summary.session_id.clone(),
+        source_tool: session.summary.source_tool.clone(),
+        project_context: session.summary.project_context.clone(),
+        timestamp: turn.turn.timestamp,
+        role: turn.turn.role.clone(),
+        content_snippet: turn.turn.content_snippet.clone(),
+        score,
+        cues: turn.cues.clone(),
+    }
+}
+
 pub fn build_probe_prompt(query: &str, matches: &[ProbeMatch]) -> Option<String> {
     if matches.is_empty() {
         return None;
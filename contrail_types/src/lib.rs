@@ -3,8 +3,17 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+mod metadata;
+
+use metadata::{legacy_schema_version, KNOWN_KEYS};
+pub use metadata::{Metadata, SCHEMA_VERSION};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(from = "RawMasterLog")]
 pub struct MasterLog {
+    /// [`SCHEMA_VERSION`] for events built by this release; older lines are
+    /// migrated on load and report the current version.
+    pub schema_version: u32,
     pub event_id: Uuid,
     pub timestamp: DateTime<Utc>,
     pub source_tool: String,
@@ -12,7 +21,40 @@ pub struct MasterLog {
     pub session_id: String,
     pub interaction: Interaction,
     pub security_flags: SecurityFlags,
-    pub metadata: serde_json::Value,
+    pub metadata: Metadata,
+}
+
+/// On-disk shape of any schema version; `metadata` is migrated in [`From`].
+#[derive(Deserialize)]
+struct RawMasterLog {
+    #[serde(default = "legacy_schema_version")]
+    schema_version: u32,
+    event_id: Uuid,
+    timestamp: DateTime<Utc>,
+    source_tool: String,
+    project_context: String,
+    session_id: String,
+    interaction: Interaction,
+    security_flags: SecurityFlags,
+    #[serde(default)]
+    metadata: serde_json::Value,
+}
+
+impl From<RawMasterLog> for MasterLog {
+    fn from(raw: RawMasterLog) -> Self {
+        Self {
+            // Lines from a newer release keep their version; older ones are now current.
+            schema_version: raw.schema_version.max(SCHEMA_VERSION),
+            event_id: raw.event_id,
+            timestamp: raw.timestamp,
+            source_tool: raw.source_tool,
+            project_context: raw.project_context,
+            session_id: raw.session_id,
+            interaction: raw.interaction,
+            security_flags: raw.security_flags,
+            metadata: Metadata::from_value(raw.metadata),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -23,10 +65,191 @@ pub struct Interaction {
     pub artifacts: Option<Vec<Artifact>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Artifact {
-    pub r#type: String,
-    pub content: String,
+/// Structured side output of a turn, serialized with a `type` tag
+/// (`tool_call`, `tool_result`, `patch`, `command`, `file_read`, `image`).
+/// Any other artifact, e.g. a kind added by a newer release, is kept verbatim as
+/// [`Artifact::Other`], so its line still loads and is written back unchanged.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Artifact {
+    ToolCall {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
+        name: String,
+        /// Arguments as the tool received them, usually a JSON string.
+        input: String,
+    },
+    ToolResult {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
+        output: String,
+        #[serde(default)]
+        is_error: bool,
+    },
+    Patch {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        diff: String,
+    },
+    Command {
+        command: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+    },
+    FileRead {
+        path: String,
+    },
+    /// Images are recorded by reference only; their bytes are never logged.
+    Image {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        media_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+    /// An artifact this release doesn't know, as it was read.
+    #[serde(untagged)]
+    Other(serde_json::Value),
+}
+
+impl Artifact {
+    /// The `type` tags this release knows.
+    pub const KINDS: [&'static str; 6] = [
+        "tool_call",
+        "tool_result",
+        "patch",
+        "command",
+        "file_read",
+        "image",
+    ];
+
+    /// The serialized `type` tag.
+    pub fn kind(&self) -> &str {
+        match self {
+            Artifact::ToolCall { .. } => "tool_call",
+            Artifact::ToolResult { .. } => "tool_result",
+            Artifact::Patch { .. } => "patch",
+            Artifact::Command { .. } => "command",
+            Artifact::FileRead { .. } => "file_read",
+            Artifact::Image { .. } => "image",
+            Artifact::Other(value) => value
+                .get("type")
+                .and_then(|kind| kind.as_str())
+                .unwrap_or("unknown"),
+        }
+    }
+
+    /// True for failed tool results and commands that exited non-zero.
+    pub fn is_failure(&self) -> bool {
+        match self {
+            Artifact::ToolResult { is_error, .. } => *is_error,
+            Artifact::Command { exit_code, .. } => exit_code.is_some_and(|code| code != 0),
+            _ => false,
+        }
+    }
+
+    /// The file this artifact touches, if any.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Artifact::Patch { path, .. } | Artifact::Image { path, .. } => path.as_deref(),
+            Artifact::FileRead { path } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Free-text fields that can carry secrets, for redaction and vault restore.
+    pub fn text_fields_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Artifact::ToolCall { input, .. } => vec![input],
+            Artifact::ToolResult { output, .. } => vec![output],
+            Artifact::Patch { diff, .. } => vec![diff],
+            Artifact::Command {
+                command, output, ..
+            } => {
+                let mut fields = vec![command];
+                fields.extend(output.as_mut());
+                fields
+            }
+            Artifact::FileRead { .. } | Artifact::Image { .. } => Vec::new(),
+            // Unknown fields may hold anything: every string but the tag counts.
+            Artifact::Other(value) => {
+                let mut fields = Vec::new();
+                match value {
+                    serde_json::Value::Object(map) => {
+                        for (key, field) in map {
+                            if key != "type" {
+                                collect_strings_mut(field, &mut fields);
+                            }
+                        }
+                    }
+                    other => collect_strings_mut(other, &mut fields),
+                }
+                fields
+            }
+        }
+    }
+
+    /// Truncate free-text fields to at most `max_chars` characters each.
+    pub fn truncate_text(&mut self, max_chars: usize) {
+        for text in self.text_fields_mut() {
+            if let Some((idx, _)) = text.char_indices().nth(max_chars) {
+                text.truncate(idx);
+            }
+        }
+    }
+
+    /// A one-line description, e.g. `command (exit 1): cargo test`.
+    pub fn summary(&self) -> String {
+        match self {
+            Artifact::ToolCall { name, .. } => format!("tool_call: {name}"),
+            Artifact::ToolResult { is_error, .. } => {
+                if *is_error {
+                    "tool_result: error".to_string()
+                } else {
+                    "tool_result: ok".to_string()
+                }
+            }
+            Artifact::Patch { path, .. } => {
+                format!("patch: {}", path.as_deref().unwrap_or("(unknown file)"))
+            }
+            Artifact::Command {
+                command, exit_code, ..
+            } => {
+                let command = command.lines().next().unwrap_or_default();
+                match exit_code {
+                    Some(code) => format!("command (exit {code}): {command}"),
+                    None => format!("command: {command}"),
+                }
+            }
+            Artifact::FileRead { path } => format!("file_read: {path}"),
+            Artifact::Image { media_type, path } => {
+                let what = path
+                    .as_deref()
+                    .or(media_type.as_deref())
+                    .unwrap_or("inline");
+                format!("image: {what}")
+            }
+            Artifact::Other(_) => self.kind().to_string(),
+        }
+    }
+}
+
+fn collect_strings_mut<'a>(value: &'a mut serde_json::Value, out: &mut Vec<&'a mut String>) {
+    match value {
+        serde_json::Value::String(text) => out.push(text),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_strings_mut(item, out);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for field in map.values_mut() {
+                collect_strings_mut(field, out);
+            }
+        }
+        _ => {}
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,11 +264,27 @@ pub struct SeqEntry {
     pub entry: MasterLog,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Sent by a replica when pulling: everything up to and including `ack_seq` has
+/// been applied locally, so the source should resume after it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
 pub struct SyncAck {
     pub ack_seq: u64,
 }
 
+/// One page of a pull. `log_id` identifies the source's sequence space; if it
+/// changes (the source index was rebuilt from scratch), replicas restart from zero.
+/// `next_seq` is the last sequence number the page covers, including events the
+/// source skipped; replicas acknowledge it rather than the last entry's `seq`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncBatch {
+    pub log_id: Uuid,
+    pub head_seq: u64,
+    /// Absent from older sources, which never skip silently; 0 then.
+    #[serde(default)]
+    pub next_seq: u64,
+    pub entries: Vec<SeqEntry>,
+}
+
 impl MasterLog {
     pub fn validate_schema(&self) -> Result<()> {
         validate_log_value(&serde_json::to_value(self)?)
@@ -69,6 +308,13 @@ pub fn validate_log_value(value: &serde_json::Value) -> Result<()> {
         .context("timestamp missing or not string")?;
     DateTime::parse_from_rfc3339(timestamp).context("timestamp must be RFC3339")?;
 
+    if let Some(version) = obj.get("schema_version") {
+        ensure!(
+            version.as_u64().is_some_and(|v| v >= 1),
+            "schema_version must be a positive integer"
+        );
+    }
+
     ensure_string(obj, "source_tool")?;
     ensure_string(obj, "project_context")?;
     ensure_string(obj, "session_id")?;
@@ -86,8 +332,14 @@ pub fn validate_log_value(value: &serde_json::Value) -> Result<()> {
             .context("artifacts must be an array when present")?;
         for artifact in artifacts_array {
             let artifact_obj = artifact.as_object().context("artifact must be an object")?;
-            ensure_string(artifact_obj, "type")?;
-            ensure_string(artifact_obj, "content")?;
+            let kind = ensure_string(artifact_obj, "type")?;
+            // Unknown kinds are accepted as-is; known ones must have their fields.
+            let parsed = serde_json::from_value::<Artifact>(artifact.clone())
+                .with_context(|| format!("invalid {kind} artifact"))?;
+            ensure!(
+                !(matches!(parsed, Artifact::Other(_)) && Artifact::KINDS.contains(&kind)),
+                "invalid {kind} artifact"
+            );
         }
     }
 
@@ -113,11 +365,16 @@ pub fn validate_log_value(value: &serde_json::Value) -> Result<()> {
         );
     }
 
-    let metadata = obj.get("metadata").context("metadata missing")?;
-    ensure!(
-        metadata.is_object(),
-        "metadata must be a JSON object (can be empty)"
-    );
+    let metadata = obj
+        .get("metadata")
+        .context("metadata missing")?
+        .as_object()
+        .context("metadata must be a JSON object (can be empty)")?;
+    for (key, kind) in KNOWN_KEYS {
+        if let Some(value) = metadata.get(*key) {
+            ensure!(kind.matches(value), "metadata.{key} must be {kind:?}");
+        }
+    }
 
     Ok(())
 }
@@ -139,6 +396,7 @@ mod tests {
     #[test]
     fn accepts_valid_schema() -> Result<()> {
         let log = MasterLog {
+            schema_version: SCHEMA_VERSION,
             event_id: Uuid::new_v4(),
             timestamp: Utc::now(),
             source_tool: "cursor".to_string(),
@@ -153,7 +411,7 @@ mod tests {
                 has_pii: false,
                 redacted_secrets: vec![],
             },
-            metadata: serde_json::json!({"example": true}),
+            metadata: Metadata::from_value(serde_json::json!({"example": true})),
         };
 
         log.validate_schema()?;
@@ -191,4 +449,130 @@ mod tests {
 
         assert!(validate_log_value(&invalid).is_err());
     }
+
+    #[test]
+    fn artifacts_round_trip_with_type_tags() -> Result<()> {
+        let artifacts = vec![
+            Artifact::ToolCall {
+                call_id: Some("call_1".to_string()),
+                name: "shell".to_string(),
+                input: r#"{"command":["ls"]}"#.to_string(),
+            },
+            Artifact::Command {
+                command: "cargo test".to_string(),
+                exit_code: Some(101),
+                output: None,
+            },
+        ];
+        let value = serde_json::to_value(&artifacts)?;
+        assert_eq!(value[0]["type"], "tool_call");
+        assert_eq!(value[1]["type"], "command");
+        assert!(value[1].get("output").is_none());
+
+        let back: Vec<Artifact> = serde_json::from_value(value)?;
+        assert_eq!(back, artifacts);
+        assert!(back[1].is_failure());
+        assert_eq!(back[1].summary(), "command (exit 101): cargo test");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_known_artifacts() {
+        let invalid = serde_json::json!({
+            "event_id": Uuid::new_v4(),
+            "timestamp": Utc::now().to_rfc3339(),
+            "source_tool": "cursor",
+            "project_context": "/tmp/project",
+            "session_id": "session-123",
+            "interaction": {
+                "role": "assistant",
+                "content": "hello",
+                "artifacts": [{ "type": "command", "exit_code": 1 }]
+            },
+            "security_flags": { "has_pii": false, "redacted_secrets": [] },
+            "metadata": {}
+        });
+        let err = validate_log_value(&invalid).unwrap_err();
+        assert!(err.to_string().contains("invalid command artifact"));
+
+        let mut unknown = invalid.clone();
+        unknown["interaction"]["artifacts"] = serde_json::json!([{ "type": "hologram" }]);
+        assert!(validate_log_value(&unknown).is_ok());
+        unknown["interaction"]["artifacts"] = serde_json::json!([{ "content": "?" }]);
+        assert!(validate_log_value(&unknown).is_err());
+    }
+
+    #[test]
+    fn migrates_lines_without_schema_version() -> Result<()> {
+        let legacy = serde_json::json!({
+            "event_id": Uuid::new_v4(),
+            "timestamp": Utc::now().to_rfc3339(),
+            "source_tool": "cursor",
+            "project_context": "/tmp/project",
+            "session_id": "session-123",
+            "interaction": { "role": "assistant", "content": "hello" },
+            "security_flags": { "has_pii": false, "redacted_secrets": [] },
+            "metadata": { "usage_total_tokens": "42", "cursor_workspace_hash": "abc" }
+        });
+
+        let log: MasterLog = serde_json::from_value(legacy)?;
+        assert_eq!(log.schema_version, SCHEMA_VERSION);
+        assert_eq!(log.metadata.usage_total_tokens, Some(42));
+        assert_eq!(log.metadata.extra["cursor_workspace_hash"], "abc");
+        log.validate_schema()?;
+
+        let written = serde_json::to_value(&log)?;
+        assert_eq!(written["schema_version"], SCHEMA_VERSION);
+        assert_eq!(written["metadata"]["usage_total_tokens"], 42);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_artifact_kinds_round_trip() -> Result<()> {
+        let line = serde_json::json!({
+            "schema_version": SCHEMA_VERSION,
+            "event_id": Uuid::new_v4(),
+            "timestamp": "2026-01-01T00:00:00Z",
+            "source_tool": "cursor",
+            "project_context": "/tmp/project",
+            "session_id": "session-123",
+            "interaction": {
+                "role": "assistant",
+                "content": "hello",
+                "artifacts": [
+                    { "type": "file_read", "path": "src/lib.rs" },
+                    { "type": "web_fetch", "url": "https://example.com", "body": ["token sk-1"] }
+                ]
+            },
+            "security_flags": { "has_pii": false, "redacted_secrets": [] },
+            "metadata": {}
+        });
+
+        let mut log: MasterLog = serde_json::from_value(line.clone())?;
+        let artifacts = log.interaction.artifacts.as_mut().unwrap();
+        assert_eq!(artifacts[0].kind(), "file_read");
+        assert_eq!(artifacts[1].kind(), "web_fetch");
+        assert_eq!(artifacts[1].summary(), "web_fetch");
+        assert_eq!(artifacts[1].text_fields_mut().len(), 2);
+        assert_eq!(serde_json::to_value(&log)?, line);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_mistyped_known_metadata_keys() {
+        let invalid = serde_json::json!({
+            "schema_version": SCHEMA_VERSION,
+            "event_id": Uuid::new_v4(),
+            "timestamp": Utc::now().to_rfc3339(),
+            "source_tool": "cursor",
+            "project_context": "/tmp/project",
+            "session_id": "session-123",
+            "interaction": { "role": "assistant", "content": "hello" },
+            "security_flags": { "has_pii": false, "redacted_secrets": [] },
+            "metadata": { "interrupted": "yes" }
+        });
+
+        let err = validate_log_value(&invalid).unwrap_err();
+        assert!(err.to_string().contains("metadata.interrupted"));
+    }
 }
@@ -0,0 +1,334 @@
+//! Typed `MasterLog.metadata`.
+//!
+//! Keys every consumer relies on are typed fields; anything else a parser records
+//! lands in [`Metadata::extra`] and round-trips unchanged. The JSON shape is the
+//! same flat object as before, so lines written before `schema_version` existed
+//! still load: [`Metadata::from_value`] coerces their known keys into shape first.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+/// Version written by this build. Lines without `schema_version` are version 1.
+pub const SCHEMA_VERSION: u32 = 2;
+
+pub(crate) fn legacy_schema_version() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Metadata {
+    // ── Provenance ──────────────────────────────────────────────────────
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    /// The source's own timestamp, as RFC 3339.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_timestamp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_inferred: Option<bool>,
+
+    // ── Model and repository ────────────────────────────────────────────
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_repository_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit_hash: Option<String>,
+
+    // ── Token usage (per turn, then cumulative for the session) ─────────
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_prompt_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_completion_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_total_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_cached_input_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_cache_creation_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_reasoning_output_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_cumulative_total_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_cumulative_prompt_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_cumulative_completion_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_cumulative_cached_input_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_cumulative_reasoning_output_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_context_window: Option<u64>,
+
+    // ── Timing ──────────────────────────────────────────────────────────
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_time_ms: Option<Number>,
+
+    // ── Session signals ─────────────────────────────────────────────────
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interrupted: Option<bool>,
+    /// Why `interrupted` is set: `truncated`, `user_cancelled`, `rapid_reprompt` or
+    /// `no_completion`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interruption: Option<String>,
+    /// For `rapid_reprompt`: time since the previous assistant turn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reprompt_gap_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copied_to_clipboard: Option<bool>,
+    /// `git status --porcelain` style lines, e.g. `M src/main.rs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_effects: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_created: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_modified: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_deleted: Option<Vec<String>>,
+    /// `git diff --numstat HEAD` totals for the working tree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff_files_changed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff_insertions: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff_deletions: Option<u64>,
+
+    /// Source-specific keys (`codex_source`, `antigravity_file`, ...).
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FieldKind {
+    Text,
+    Bool,
+    Count,
+    Number,
+    TextList,
+}
+
+/// Every typed key and the JSON shape it must have.
+pub(crate) const KNOWN_KEYS: &[(&str, FieldKind)] = &[
+    ("user", FieldKind::Text),
+    ("hostname", FieldKind::Text),
+    ("cwd", FieldKind::Text),
+    ("conversation_id", FieldKind::Text),
+    ("original_timestamp", FieldKind::Text),
+    ("imported", FieldKind::Bool),
+    ("timestamp_inferred", FieldKind::Bool),
+    ("model", FieldKind::Text),
+    ("git_branch", FieldKind::Text),
+    ("git_repository_url", FieldKind::Text),
+    ("git_commit_hash", FieldKind::Text),
+    ("usage_prompt_tokens", FieldKind::Count),
+    ("usage_completion_tokens", FieldKind::Count),
+    ("usage_total_tokens", FieldKind::Count),
+    ("usage_cached_input_tokens", FieldKind::Count),
+    ("usage_cache_creation_tokens", FieldKind::Count),
+    ("usage_reasoning_output_tokens", FieldKind::Count),
+    ("usage_cumulative_total_tokens", FieldKind::Count),
+    ("usage_cumulative_prompt_tokens", FieldKind::Count),
+    ("usage_cumulative_completion_tokens", FieldKind::Count),
+    ("usage_cumulative_cached_input_tokens", FieldKind::Count),
+    ("usage_cumulative_reasoning_output_tokens", FieldKind::Count),
+    ("model_context_window", FieldKind::Count),
+    ("latency_ms", FieldKind::Number),
+    ("duration_ms", FieldKind::Number),
+    ("wall_time_ms", FieldKind::Number),
+    ("interrupted", FieldKind::Bool),
+    ("interruption", FieldKind::Text),
+    ("reprompt_gap_ms", FieldKind::Count),
+    ("copied_to_clipboard", FieldKind::Bool),
+    ("file_effects", FieldKind::TextList),
+    ("files_created", FieldKind::TextList),
+    ("files_modified", FieldKind::TextList),
+    ("files_deleted", FieldKind::TextList),
+    ("diff_files_changed", FieldKind::Count),
+    ("diff_insertions", FieldKind::Count),
+    ("diff_deletions", FieldKind::Count),
+];
+
+impl FieldKind {
+    pub(crate) fn matches(self, value: &Value) -> bool {
+        match self {
+            FieldKind::Text => value.is_string(),
+            FieldKind::Bool => value.is_boolean(),
+            FieldKind::Count => value.is_u64(),
+            FieldKind::Number => value.is_number(),
+            FieldKind::TextList => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_string)),
+        }
+    }
+
+    /// Best-effort conversion of values older writers produced: numbers and bools
+    /// stored as strings, whole-number floats, a single file effect or
+    /// `{"path": ...}` objects instead of a list of strings.
+    fn coerce(self, value: Value) -> Result<Value, Value> {
+        if self.matches(&value) {
+            return Ok(value);
+        }
+        let coerced = match (self, &value) {
+            (FieldKind::Text, Value::Number(n)) => Some(Value::String(n.to_string())),
+            (FieldKind::Bool, Value::String(s)) => match s.trim() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            (FieldKind::Count, Value::String(s)) => s.trim().parse::<u64>().ok().map(Value::from),
+            (FieldKind::Count, Value::Number(n)) => n
+                .as_f64()
+                .filter(|f| *f >= 0.0 && f.fract() == 0.0 && *f <= u64::MAX as f64)
+                .map(|f| Value::from(f as u64)),
+            (FieldKind::Number, Value::String(s)) => {
+                s.trim()
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map(|n| {
+                        // Keep integers integral so re-serialized lines don't gain a `.0`.
+                        s.trim()
+                            .parse::<i64>()
+                            .map(Value::from)
+                            .unwrap_or(Value::Number(n))
+                    })
+            }
+            (FieldKind::TextList, Value::String(s)) => Some(Value::Array(
+                s.lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| Value::String(line.to_string()))
+                    .collect(),
+            )),
+            (FieldKind::TextList, Value::Array(items)) => items
+                .iter()
+                .map(|item| match item {
+                    Value::String(s) => Some(Value::String(s.clone())),
+                    other => other
+                        .get("path")
+                        .and_then(Value::as_str)
+                        .map(|p| Value::String(p.to_string())),
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array),
+            _ => None,
+        };
+        coerced.ok_or(value)
+    }
+}
+
+impl Metadata {
+    /// Build metadata from a parser's flat key map; see [`Metadata::from_value`].
+    pub fn from_map(map: Map<String, Value>) -> Self {
+        Self::from_value(Value::Object(map))
+    }
+
+    /// Load metadata written by any schema version. Known keys are coerced to their
+    /// typed shape; a value that can't be coerced is kept under `legacy_<key>` in
+    /// [`Metadata::extra`] rather than dropped. Non-object metadata becomes empty.
+    pub fn from_value(value: Value) -> Self {
+        let Value::Object(mut map) = value else {
+            return Self::default();
+        };
+        for (key, kind) in KNOWN_KEYS {
+            let Some(value) = map.remove(*key) else {
+                continue;
+            };
+            match kind.coerce(value) {
+                Ok(value) => {
+                    map.insert(key.to_string(), value);
+                }
+                Err(Value::Null) => {}
+                Err(original) => {
+                    map.insert(format!("legacy_{key}"), original);
+                }
+            }
+        }
+        let value = Value::Object(map);
+        match Self::deserialize(&value) {
+            Ok(meta) => meta,
+            Err(_) => Self {
+                extra: match value {
+                    Value::Object(map) => map,
+                    _ => Map::new(),
+                },
+                ..Self::default()
+            },
+        }
+    }
+
+    /// True when nothing is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl From<Map<String, Value>> for Metadata {
+    fn from(map: Map<String, Value>) -> Self {
+        Self::from_map(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn every_known_key_has_a_typed_field() {
+        let sample = |kind: FieldKind| match kind {
+            FieldKind::Text => json!("x"),
+            FieldKind::Bool => json!(true),
+            FieldKind::Count => json!(3),
+            FieldKind::Number => json!(1.5),
+            FieldKind::TextList => json!(["M a.rs"]),
+        };
+        let map: Map<String, Value> = KNOWN_KEYS
+            .iter()
+            .map(|(key, kind)| (key.to_string(), sample(*kind)))
+            .collect();
+        let meta = Metadata::from_map(map.clone());
+        assert!(meta.extra.is_empty(), "untyped keys: {:?}", meta.extra);
+        assert_eq!(serde_json::to_value(&meta).unwrap(), Value::Object(map));
+    }
+
+    #[test]
+    fn migrates_legacy_shapes_and_keeps_unknown_keys() {
+        let meta = Metadata::from_value(json!({
+            "usage_total_tokens": "1200",
+            "latency_ms": "250",
+            "interrupted": "true",
+            "file_effects": [{"path": "src/main.rs"}, "M README.md"],
+            "model": null,
+            "copied_to_clipboard": {"weird": true},
+            "codex_source": "vscode"
+        }));
+        assert_eq!(meta.usage_total_tokens, Some(1200));
+        assert_eq!(meta.latency_ms, Some(Number::from(250)));
+        assert_eq!(meta.interrupted, Some(true));
+        assert_eq!(
+            meta.file_effects,
+            Some(vec!["src/main.rs".to_string(), "M README.md".to_string()])
+        );
+        assert_eq!(meta.model, None);
+        assert_eq!(meta.copied_to_clipboard, None);
+        assert_eq!(
+            meta.extra["legacy_copied_to_clipboard"],
+            json!({"weird": true})
+        );
+        assert_eq!(meta.extra["codex_source"], "vscode");
+    }
+}
@@ -2,7 +2,6 @@ use anyhow::Context;
 use scrapers::config::ContrailConfig;
 use scrapers::history_import;
 use scrapers::log_writer::LogWriter;
-use scrapers::rotation;
 use scrapers::watchers::Harvester;
 use std::fs;
 use std::path::PathBuf;
@@ -21,7 +20,7 @@ pub async fn run() -> anyhow::Result<()> {
 
     info!("starting contrail daemon");
 
-    let config = ContrailConfig::from_env()?;
+    let config = ContrailConfig::load()?;
 
     let contrail_dir = config
         .log_path
@@ -31,35 +30,21 @@ pub async fn run() -> anyhow::Result<()> {
     fs::create_dir_all(&contrail_dir).context("Failed to create log directory")?;
     info!(path = ?contrail_dir, "ensured log directory exists");
 
-    match rotation::rotate_if_needed(
-        &config.log_path,
-        config.log_max_bytes,
-        config.log_keep_files,
-    ) {
-        Ok(outcome) => {
-            if outcome.rotated {
-                info!(
-                    archive = ?outcome.archive_path,
-                    pruned = outcome.pruned,
-                    "rotated master log at startup"
-                );
-            }
-        }
-        Err(e) => {
-            error!(err = ?e, "startup log rotation failed; continuing without rotation");
-        }
-    }
-
     maybe_import_history(&config);
 
-    let log_writer = LogWriter::new(config.log_path.clone());
+    let log_writer = LogWriter::new(
+        config.log_path.clone(),
+        Duration::from_millis(config.log_fsync_ms),
+        config.rotation_limits(),
+    );
 
     let enable_cursor = config.enable_cursor;
     let enable_codex = config.enable_codex;
     let enable_antigravity = config.enable_antigravity;
     let enable_claude = config.enable_claude;
+    let enable_gemini = config.enable_gemini;
 
-    let harvester = Arc::new(Harvester::new(log_writer, config));
+    let harvester = Arc::new(Harvester::new(log_writer, config)?);
 
     let h1 = harvester.clone();
     let cursor_handle = task::spawn(async move {
@@ -106,6 +91,20 @@ pub async fn run() -> anyhow::Result<()> {
         }
     });
 
+    let h7 = harvester.clone();
+    let antigravity_conversations_handle = task::spawn(async move {
+        if !enable_antigravity {
+            return;
+        }
+        loop {
+            if let Err(e) = h7.run_antigravity_conversations_watcher().await {
+                error!(err = ?e, "antigravity conversations watcher failed");
+            }
+            warn!("antigravity conversations watcher exited; restarting in 2s");
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+    });
+
     let h4 = harvester.clone();
     let claude_handle = task::spawn(async move {
         if !enable_claude {
@@ -135,7 +134,22 @@ pub async fn run() -> anyhow::Result<()> {
         }
     });
 
-    if !(enable_cursor || enable_codex || enable_antigravity || enable_claude) {
+    let h6 = harvester.clone();
+    let gemini_handle = task::spawn(async move {
+        if !enable_gemini {
+            info!("gemini watcher disabled");
+            return;
+        }
+        loop {
+            if let Err(e) = h6.run_gemini_watcher().await {
+                error!(err = ?e, "gemini watcher failed");
+            }
+            warn!("gemini watcher exited; restarting in 2s");
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+    });
+
+    if !(enable_cursor || enable_codex || enable_antigravity || enable_claude || enable_gemini) {
         warn!("all watchers are disabled; daemon will stay idle until shutdown");
     }
 
@@ -145,8 +159,10 @@ pub async fn run() -> anyhow::Result<()> {
     cursor_handle.abort();
     codex_handle.abort();
     antigravity_handle.abort();
+    antigravity_conversations_handle.abort();
     claude_handle.abort();
     claude_projects_handle.abort();
+    gemini_handle.abort();
 
     drop(harvester);
 
@@ -161,7 +177,8 @@ fn maybe_import_history(config: &ContrailConfig) {
         Some(h) => h,
         None => return,
     };
-    let marker_path = home.join(scrapers::config::HISTORY_IMPORT_MARKER_REL);
+    let marker_path =
+        scrapers::config::data_dir(&home).join(scrapers::config::HISTORY_IMPORT_MARKER_REL);
     if marker_path.exists() {
         return;
     }
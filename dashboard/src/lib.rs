@@ -1,6 +1,7 @@
 use axum::{
     Json, Router,
     extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
     response::{
         Html,
         sse::{Event, KeepAlive, Sse},
@@ -8,16 +9,22 @@ use axum::{
     routing::get,
 };
 use chrono::{DateTime, Duration, Utc};
+use scrapers::config::ContrailConfig;
+use scrapers::event_index::{EventIndex, EventQuery};
+use scrapers::log_index::read_log_from;
+use scrapers::rotation::last_rotation;
+use scrapers::types::{SyncAck, SyncBatch};
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::VecDeque;
 use std::convert::Infallible;
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, Read, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration as StdDuration;
+use subtle::ConstantTimeEq;
 use tokio::{
     fs,
     io::{AsyncReadExt, AsyncSeekExt},
@@ -26,16 +33,30 @@ use tokio::{
 use tower_http::cors::CorsLayer;
 
 pub async fn run() {
-    let log_path = env::var("CONTRAIL_LOG_PATH")
-        .map(PathBuf::from)
+    let config = ContrailConfig::load().expect("failed to load contrail config");
+    let log_path = config.log_path;
+
+    let sync_token = env::var("CONTRAIL_SYNC_TOKEN")
         .ok()
-        .or_else(|| dirs::home_dir().map(|h| h.join(".contrail/logs/master_log.jsonl")))
-        .expect("Could not resolve CONTRAIL_LOG_PATH or home directory");
+        .filter(|t| !t.trim().is_empty());
+
+    let bind_addr = config.dashboard_bind;
+    let listener = tokio::net::TcpListener::bind(&bind_addr).await.unwrap();
+    let loopback = listener
+        .local_addr()
+        .map(|addr| addr.ip().is_loopback())
+        .unwrap_or(false);
+    if sync_token.is_none() && !loopback {
+        eprintln!(
+            "warning: /api/sync/pull is disabled on {bind_addr}; set CONTRAIL_SYNC_TOKEN to serve it off loopback"
+        );
+    }
 
     let (live_tx, _) = broadcast::channel(2048);
     let state = Arc::new(AppState {
         log_path: log_path.clone(),
         live_tx: live_tx.clone(),
+        sync_auth: SyncAuth::new(sync_token, loopback),
     });
 
     tokio::spawn(run_live_publisher(log_path, live_tx));
@@ -46,11 +67,11 @@ pub async fn run() {
         .route("/api/logs", get(get_logs))
         .route("/api/stream", get(stream_logs))
         .layer(CorsLayer::permissive())
+        // Added after the CORS layer so browsers on other origins can't pull the log.
+        .route("/api/sync/pull", get(sync_pull))
         .with_state(state);
 
-    let bind_addr = env::var("DASHBOARD_BIND").unwrap_or_else(|_| "127.0.0.1:3000".to_string());
     println!("✈️  Contrail Dashboard running at http://{bind_addr}");
-    let listener = tokio::net::TcpListener::bind(&bind_addr).await.unwrap();
     axum::serve(listener, app)
         .with_graceful_shutdown(shutdown_signal())
         .await
@@ -61,12 +82,52 @@ pub async fn run() {
 struct AppState {
     log_path: PathBuf,
     live_tx: broadcast::Sender<Value>,
+    sync_auth: SyncAuth,
+}
+
+/// Who may call `/api/sync/pull`: bearers of the token when one is set, otherwise
+/// anyone, but only while the dashboard listens on a loopback address.
+#[derive(Clone)]
+struct SyncAuth {
+    token: Option<String>,
+    loopback: bool,
+}
+
+impl SyncAuth {
+    fn new(token: Option<String>, loopback: bool) -> Self {
+        Self { token, loopback }
+    }
+
+    fn check(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let Some(expected) = &self.token else {
+            if self.loopback {
+                return Ok(());
+            }
+            return Err((
+                StatusCode::FORBIDDEN,
+                "sync requires CONTRAIL_SYNC_TOKEN when the dashboard is not bound to loopback"
+                    .to_string(),
+            ));
+        };
+        let provided = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if bool::from(provided.as_bytes().ct_eq(expected.as_bytes())) {
+            Ok(())
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "invalid sync token".to_string()))
+        }
+    }
 }
 
 const DEFAULT_LIVE_LIMIT: usize = 200;
 const DEFAULT_HISTORY_LIMIT: usize = 5_000;
 const MAX_LIVE_LIMIT: usize = 5_000;
 const MAX_ALL_LIMIT: usize = 200_000;
+const DEFAULT_SYNC_LIMIT: usize = 500;
+const MAX_SYNC_LIMIT: usize = 5_000;
 
 async fn index() -> Html<&'static str> {
     Html(include_str!("index.html"))
@@ -104,11 +165,10 @@ async fn get_logs(
     let custom_to = parse_rfc3339(query.to.as_deref());
     let (from_ts, to_ts) = resolve_window_bounds(window, custom_from, custom_to);
 
-    let files = scrapers::log_index::discover_logs(&state.log_path)
-        .unwrap_or_else(|_| vec![state.log_path.clone()]);
+    let log_path = state.log_path.clone();
     let logs = tokio::task::spawn_blocking(move || {
         load_history_logs(
-            &files,
+            &log_path,
             all_limit,
             tool_filter,
             session_filter,
@@ -122,6 +182,39 @@ async fn get_logs(
     Json(logs)
 }
 
+/// Replication pull: sequenced events after the caller's last acknowledged `seq`.
+/// When `CONTRAIL_SYNC_TOKEN` is set, callers must send it as a bearer token; without
+/// one, the endpoint only answers when the dashboard is bound to loopback.
+async fn sync_pull(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(ack): Query<SyncAck>,
+    Query(page): Query<SyncPageQuery>,
+) -> Result<Json<SyncBatch>, (StatusCode, String)> {
+    state.sync_auth.check(&headers)?;
+
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_SYNC_LIMIT)
+        .clamp(1, MAX_SYNC_LIMIT);
+    let log_path = state.log_path.clone();
+    let batch = tokio::task::spawn_blocking(move || -> anyhow::Result<SyncBatch> {
+        let index = EventIndex::open_synced(&log_path)?;
+        let (entries, next_seq) = index.entries_after(ack.ack_seq, limit)?;
+        Ok(SyncBatch {
+            log_id: index.log_id()?,
+            head_seq: index.head_seq()?,
+            next_seq,
+            entries,
+        })
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+
+    Ok(Json(batch))
+}
+
 async fn stream_logs(
     State(state): State<Arc<AppState>>,
     Query(query): Query<StreamQuery>,
@@ -160,14 +253,34 @@ async fn run_live_publisher(log_path: PathBuf, tx: broadcast::Sender<Value>) {
         Err(_) => (0, false),
     };
     let mut carry: Vec<u8> = Vec::new();
+    // The daemon records the archive of each rotation. A new one means `offset`
+    // now points into that archive rather than into the fresh log.
+    let mut rotation = last_rotation(&log_path);
 
     loop {
+        let latest = last_rotation(&log_path);
+        if latest != rotation {
+            if let (true, Some(archive)) = (follow_initialized, &latest) {
+                // Finish the old file, then start at the top of the new one. It may
+                // already be compressed.
+                let archive = archive.clone();
+                let remainder =
+                    tokio::task::spawn_blocking(move || read_log_from(&archive, offset)).await;
+                if let Ok(Ok(bytes)) = remainder {
+                    carry.extend_from_slice(&bytes);
+                    publish_complete_lines(&mut carry, &tx);
+                }
+            }
+            offset = 0;
+            follow_initialized = true;
+            carry.clear();
+            rotation = latest;
+        }
+
         let size = match fs::metadata(&log_path).await {
             Ok(meta) => meta.len(),
             Err(_) => {
-                offset = 0;
-                follow_initialized = false;
-                carry.clear();
+                // Possibly mid-rotation; the marker check above handles the switch.
                 tokio::time::sleep(StdDuration::from_secs(1)).await;
                 continue;
             }
@@ -186,17 +299,9 @@ async fn run_live_publisher(log_path: PathBuf, tx: broadcast::Sender<Value>) {
         if size > offset {
             match read_new_bytes(&log_path, offset).await {
                 Ok(bytes) => {
-                    offset = size;
+                    offset += bytes.len() as u64;
                     carry.extend_from_slice(&bytes);
-
-                    for line in drain_complete_lines(&mut carry) {
-                        if line.trim().is_empty() {
-                            continue;
-                        }
-                        if let Ok(json) = serde_json::from_str::<Value>(&line) {
-                            let _ = tx.send(json);
-                        }
-                    }
+                    publish_complete_lines(&mut carry, &tx);
                 }
                 Err(_) => {
                     // retry next interval
@@ -208,7 +313,18 @@ async fn run_live_publisher(log_path: PathBuf, tx: broadcast::Sender<Value>) {
     }
 }
 
-async fn read_new_bytes(path: &PathBuf, offset: u64) -> std::io::Result<Vec<u8>> {
+fn publish_complete_lines(carry: &mut Vec<u8>, tx: &broadcast::Sender<Value>) {
+    for line in drain_complete_lines(carry) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Ok(json) = serde_json::from_str::<Value>(&line) {
+            let _ = tx.send(json);
+        }
+    }
+}
+
+async fn read_new_bytes(path: &Path, offset: u64) -> std::io::Result<Vec<u8>> {
     let mut file = fs::File::open(path).await?;
     file.seek(SeekFrom::Start(offset)).await?;
     let mut out = Vec::new();
@@ -218,10 +334,7 @@ async fn read_new_bytes(path: &PathBuf, offset: u64) -> std::io::Result<Vec<u8>>
 
 fn drain_complete_lines(carry: &mut Vec<u8>) -> Vec<String> {
     let mut out = Vec::new();
-    loop {
-        let Some(pos) = carry.iter().position(|b| *b == b'\n') else {
-            break;
-        };
+    while let Some(pos) = carry.iter().position(|b| *b == b'\n') {
         let mut line = carry.drain(..=pos).collect::<Vec<_>>();
         if matches!(line.last(), Some(b'\n')) {
             line.pop();
@@ -237,46 +350,31 @@ fn drain_complete_lines(carry: &mut Vec<u8>) -> Vec<String> {
 }
 
 fn load_history_logs(
-    paths: &[PathBuf],
+    log_path: &Path,
     limit: Option<usize>,
     tool_filter: Option<String>,
     session_filter: Option<String>,
     from_ts: Option<DateTime<Utc>>,
     to_ts: Option<DateTime<Utc>>,
 ) -> Vec<Value> {
-    let mut logs: VecDeque<Value> = VecDeque::new();
-
-    for path in paths {
-        let Ok(file) = File::open(path) else {
-            continue;
-        };
-        let reader = std::io::BufReader::new(file);
-        for line in reader.lines() {
-            let Ok(line) = line else {
-                continue;
-            };
-            if line.trim().is_empty() {
-                continue;
-            }
-            let Ok(json) = serde_json::from_str::<Value>(&line) else {
-                continue;
-            };
-            if !matches_filters(&json, tool_filter.as_deref(), session_filter.as_deref()) {
-                continue;
-            }
-            if !matches_time_window(&json, from_ts, to_ts) {
-                continue;
-            }
-            logs.push_back(json);
-            if let Some(max_items) = limit
-                && logs.len() > max_items
-            {
-                let _ = logs.pop_front();
-            }
+    let query = EventQuery {
+        since: from_ts,
+        until: to_ts,
+        source_tool: tool_filter,
+        session_id: session_filter,
+        limit,
+        ..Default::default()
+    };
+    let raw = match EventIndex::open_synced(log_path).and_then(|index| index.query_raw(&query)) {
+        Ok(raw) => raw,
+        Err(err) => {
+            eprintln!("event index query failed: {err:#}");
+            return Vec::new();
         }
-    }
-
-    logs.into_iter().collect()
+    };
+    raw.iter()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .collect()
 }
 
 fn load_tail_logs(
@@ -359,32 +457,6 @@ fn matches_filters(json: &Value, tool: Option<&str>, session_id: Option<&str>) -
     true
 }
 
-fn matches_time_window(
-    json: &Value,
-    from_ts: Option<DateTime<Utc>>,
-    to_ts: Option<DateTime<Utc>>,
-) -> bool {
-    if from_ts.is_none() && to_ts.is_none() {
-        return true;
-    }
-    let Some(ts) = json
-        .get("timestamp")
-        .and_then(Value::as_str)
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc))
-    else {
-        return false;
-    };
-
-    if from_ts.is_some_and(|from| ts < from) {
-        return false;
-    }
-    if to_ts.is_some_and(|to| ts > to) {
-        return false;
-    }
-    true
-}
-
 fn parse_rfc3339(value: Option<&str>) -> Option<DateTime<Utc>> {
     value
         .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
@@ -452,6 +524,11 @@ struct LogsQuery {
     to: Option<String>,
 }
 
+#[derive(Default, Deserialize)]
+struct SyncPageQuery {
+    limit: Option<usize>,
+}
+
 #[derive(Default, Deserialize)]
 struct StreamQuery {
     tool: Option<String>,
@@ -461,3 +538,38 @@ struct StreamQuery {
 async fn shutdown_signal() {
     let _ = tokio::signal::ctrl_c().await;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn sync_needs_a_token_off_loopback() {
+        let open = SyncAuth::new(None, true);
+        assert!(open.check(&HeaderMap::new()).is_ok());
+
+        let exposed = SyncAuth::new(None, false);
+        let err = exposed.check(&bearer("anything")).unwrap_err();
+        assert_eq!(err.0, StatusCode::FORBIDDEN);
+
+        let guarded = SyncAuth::new(Some("secret".to_string()), false);
+        assert!(guarded.check(&bearer("secret")).is_ok());
+        assert_eq!(
+            guarded.check(&bearer("secre")).unwrap_err().0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            guarded.check(&HeaderMap::new()).unwrap_err().0,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! Turn extraction from Antigravity conversation files.
+//!
+//! Antigravity doesn't publish its schema, so turns are found structurally: the
+//! shallowest repeated field whose entries are messages is taken as the turn list,
+//! and each entry's prose strings become that turn's text. Short whitespace-free
+//! strings (ids, enum names, model names) are kept as labels and used to guess the
+//! role; a nested `{1: seconds, 2: nanos}` message in a plausible range is read as
+//! the turn's timestamp.
+
+use crate::wire::{Field, WireValue, parse_message};
+use anyhow::{Context, Result, bail};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+const MAX_DEPTH: usize = 16;
+
+/// Frame magic number, RFC 8878.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Strings shorter than this without whitespace are labels, not prose.
+const LABEL_MAX_CHARS: usize = 64;
+
+/// Accepted range for timestamp seconds: 2017-07-14 to 2100-01-01.
+const TIMESTAMP_SECS: std::ops::RangeInclusive<u64> = 1_500_000_000..=4_102_444_800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Assistant,
+    Unknown,
+}
+
+impl Role {
+    /// The `interaction.role` Contrail records for this turn.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Unknown => "user_or_assistant",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turn {
+    pub role: Role,
+    pub text: String,
+    /// Unix seconds, when the turn carries a timestamp.
+    pub timestamp: Option<i64>,
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Conversation {
+    pub turns: Vec<Turn>,
+}
+
+/// Read and decode one `conversations/*.pb` file.
+pub fn decode_file(path: &Path) -> Result<Conversation> {
+    let data = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    decode_conversation(&data).with_context(|| format!("decode {}", path.display()))
+}
+
+/// Decode a conversation, transparently handling zstd, gzip, zlib or brotli
+/// compression. Fails
+/// when the payload isn't protobuf at all, which is what encrypted files look like.
+pub fn decode_conversation(data: &[u8]) -> Result<Conversation> {
+    let payload = decompress(data)?;
+    let root = match parse_message(&payload) {
+        Ok(fields) if !fields.is_empty() => fields,
+        _ => bail!("not a protobuf conversation (the file may be encrypted)"),
+    };
+
+    let entries = find_turn_list(&root, 0).unwrap_or_else(|| vec![root]);
+    let turns = entries
+        .iter()
+        .filter_map(|fields| extract_turn(fields))
+        .collect();
+    Ok(Conversation { turns })
+}
+
+fn decompress(data: &[u8]) -> Result<Cow<'_, [u8]>> {
+    let mut out = Vec::new();
+    if data.starts_with(&ZSTD_MAGIC) {
+        let out = zstd::decode_all(data).context("zstd decompression failed")?;
+        return Ok(Cow::Owned(out));
+    }
+    if data.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(data)
+            .read_to_end(&mut out)
+            .context("gzip decompression failed")?;
+        return Ok(Cow::Owned(out));
+    }
+    if is_zlib_header(data) && ZlibDecoder::new(data).read_to_end(&mut out).is_ok() {
+        return Ok(Cow::Owned(out));
+    }
+    // Brotli has no magic number, so it is only tried on data that isn't protobuf
+    // as-is, and only kept when it decodes to protobuf.
+    if parse_message(data).is_err() {
+        out.clear();
+        let mut brotli = brotli::Decompressor::new(data, 4096);
+        if brotli.read_to_end(&mut out).is_ok()
+            && parse_message(&out).is_ok_and(|fields| !fields.is_empty())
+        {
+            return Ok(Cow::Owned(out));
+        }
+    }
+    Ok(Cow::Borrowed(data))
+}
+
+/// RFC 1950: deflate method, and the two header bytes are a multiple of 31.
+fn is_zlib_header(data: &[u8]) -> bool {
+    match data {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+enum Node<'a> {
+    Text(&'a str),
+    Message(Vec<Field<'a>>),
+    Opaque,
+}
+
+/// Nested messages almost always start with a tag byte below 0x20 (fields 1-3), so
+/// printable UTF-8 that doesn't is taken as text before trying to parse it.
+fn classify(bytes: &[u8]) -> Node<'_> {
+    let text = std::str::from_utf8(bytes).ok().filter(|s| is_printable(s));
+    if let Some(text) = text
+        && bytes.first().is_some_and(|b| *b >= 0x20)
+    {
+        return Node::Text(text);
+    }
+    if let Ok(fields) = parse_message(bytes)
+        && !fields.is_empty()
+    {
+        return Node::Message(fields);
+    }
+    match text {
+        Some(text) => Node::Text(text),
+        None => Node::Opaque,
+    }
+}
+
+fn is_printable(s: &str) -> bool {
+    !s.trim().is_empty()
+        && s.chars()
+            .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+}
+
+/// The shallowest repeated message field with at least two entries.
+fn find_turn_list<'a>(fields: &[Field<'a>], depth: usize) -> Option<Vec<Vec<Field<'a>>>> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let mut groups: BTreeMap<u32, Vec<Vec<Field<'a>>>> = BTreeMap::new();
+    for field in fields {
+        if let WireValue::Bytes(bytes) = field.value
+            && let Node::Message(child) = classify(bytes)
+        {
+            groups.entry(field.number).or_default().push(child);
+        }
+    }
+
+    let best = groups
+        .values()
+        .filter(|entries| entries.len() >= 2)
+        .max_by_key(|entries| entries.len());
+    if let Some(entries) = best {
+        return Some(entries.clone());
+    }
+    groups
+        .values()
+        .flatten()
+        .find_map(|child| find_turn_list(child, depth + 1))
+}
+
+#[derive(Default)]
+struct TurnParts {
+    prose: Vec<String>,
+    labels: Vec<String>,
+    timestamp: Option<i64>,
+}
+
+fn extract_turn(fields: &[Field<'_>]) -> Option<Turn> {
+    let mut parts = TurnParts::default();
+    collect(fields, 0, &mut parts);
+    if parts.prose.is_empty() {
+        return None;
+    }
+    Some(Turn {
+        role: guess_role(&parts.labels),
+        text: parts.prose.join("\n\n"),
+        timestamp: parts.timestamp,
+        labels: parts.labels,
+    })
+}
+
+fn collect(fields: &[Field<'_>], depth: usize, parts: &mut TurnParts) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    for field in fields {
+        let WireValue::Bytes(bytes) = field.value else {
+            continue;
+        };
+        match classify(bytes) {
+            Node::Text(text) => {
+                let text = text.trim();
+                let is_label =
+                    !text.contains(char::is_whitespace) && text.chars().count() < LABEL_MAX_CHARS;
+                let bucket = if is_label {
+                    &mut parts.labels
+                } else {
+                    &mut parts.prose
+                };
+                if !bucket.iter().any(|seen| seen == text) {
+                    bucket.push(text.to_string());
+                }
+            }
+            Node::Message(child) => {
+                if parts.timestamp.is_none()
+                    && let Some(ts) = as_timestamp(&child)
+                {
+                    parts.timestamp = Some(ts);
+                    continue;
+                }
+                collect(&child, depth + 1, parts);
+            }
+            Node::Opaque => {}
+        }
+    }
+}
+
+/// `google.protobuf.Timestamp`: seconds in field 1, optional nanos in field 2.
+fn as_timestamp(fields: &[Field<'_>]) -> Option<i64> {
+    let mut seconds = None;
+    for field in fields {
+        match (field.number, field.value) {
+            (1, WireValue::Varint(v)) if TIMESTAMP_SECS.contains(&v) => seconds = Some(v),
+            (2, WireValue::Varint(v)) if v < 1_000_000_000 => {}
+            _ => return None,
+        }
+    }
+    seconds.and_then(|s| i64::try_from(s).ok())
+}
+
+fn guess_role(labels: &[String]) -> Role {
+    for label in labels {
+        let label = label.to_ascii_lowercase();
+        if matches!(label.as_str(), "user" | "human") || label.contains("user_input") {
+            return Role::User;
+        }
+        if matches!(label.as_str(), "assistant" | "model" | "agent" | "bot")
+            || label.contains("planner_response")
+        {
+            return Role::Assistant;
+        }
+    }
+    Role::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::encode;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn turn(role: &str, text: &str, seconds: Option<u64>) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode::bytes(&mut out, 1, b"3bcb0a06-ba1b-403b-8a12-1874e8f713ca");
+        encode::bytes(&mut out, 2, role.as_bytes());
+        let mut body = Vec::new();
+        encode::bytes(&mut body, 1, text.as_bytes());
+        encode::uint(&mut body, 2, 7);
+        encode::bytes(&mut out, 3, &body);
+        if let Some(seconds) = seconds {
+            let mut ts = Vec::new();
+            encode::uint(&mut ts, 1, seconds);
+            encode::uint(&mut ts, 2, 500);
+            encode::bytes(&mut out, 4, &ts);
+        }
+        out
+    }
+
+    fn conversation() -> Vec<u8> {
+        let mut steps = Vec::new();
+        encode::bytes(&mut steps, 1, b"conversation title");
+        encode::bytes(
+            &mut steps,
+            5,
+            &turn("USER", "Add a login page", Some(1_767_225_600)),
+        );
+        encode::bytes(
+            &mut steps,
+            5,
+            &turn("MODEL", "I'll add the login page.\nDone.", None),
+        );
+        encode::bytes(&mut steps, 5, &turn("MODEL", "   ", None));
+        let mut root = Vec::new();
+        encode::uint(&mut root, 1, 2);
+        encode::bytes(&mut root, 2, &steps);
+        root
+    }
+
+    #[test]
+    fn extracts_turns_from_the_shallowest_repeated_message() {
+        let decoded = decode_conversation(&conversation()).unwrap();
+        assert_eq!(decoded.turns.len(), 2);
+
+        let first = &decoded.turns[0];
+        assert_eq!(first.role, Role::User);
+        assert_eq!(first.text, "Add a login page");
+        assert_eq!(first.timestamp, Some(1_767_225_600));
+        assert!(first.labels.contains(&"USER".to_string()));
+
+        let second = &decoded.turns[1];
+        assert_eq!(second.role, Role::Assistant);
+        assert_eq!(second.text, "I'll add the login page.\nDone.");
+        assert_eq!(second.timestamp, None);
+    }
+
+    #[test]
+    fn decodes_gzip_payloads() {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&conversation()).unwrap();
+        let decoded = decode_conversation(&gz.finish().unwrap()).unwrap();
+        assert_eq!(decoded.turns.len(), 2);
+    }
+
+    #[test]
+    fn decodes_zstd_payloads() {
+        let compressed = zstd::encode_all(&conversation()[..], 3).unwrap();
+        let decoded = decode_conversation(&compressed).unwrap();
+        assert_eq!(decoded.turns.len(), 2);
+    }
+
+    #[test]
+    fn decodes_brotli_payloads() {
+        let mut compressed = Vec::new();
+        {
+            let mut br = brotli::CompressorWriter::new(&mut compressed, 4096, 9, 22);
+            br.write_all(&conversation()).unwrap();
+        }
+        assert!(parse_message(&compressed).is_err());
+        let decoded = decode_conversation(&compressed).unwrap();
+        assert_eq!(decoded.turns.len(), 2);
+    }
+
+    #[test]
+    fn unknown_roles_and_single_message_payloads() {
+        let mut root = Vec::new();
+        encode::bytes(&mut root, 1, b"just one long answer here");
+        let decoded = decode_conversation(&root).unwrap();
+        assert_eq!(decoded.turns.len(), 1);
+        assert_eq!(decoded.turns[0].role, Role::Unknown);
+        assert_eq!(decoded.turns[0].role.as_str(), "user_or_assistant");
+    }
+
+    #[test]
+    fn rejects_payloads_that_are_not_protobuf() {
+        let noise: Vec<u8> = (0..64u8).map(|i| i.wrapping_mul(97) | 0x07).collect();
+        let err = decode_conversation(&noise).unwrap_err();
+        assert!(err.to_string().contains("may be encrypted"));
+        assert!(decode_conversation(&[]).is_err());
+    }
+}
@@ -0,0 +1,10 @@
+//! Decoder for Antigravity's `~/.gemini/antigravity/conversations/*.pb` files.
+//!
+//! [`decode_file`] turns a conversation file into structured [`Turn`]s without
+//! needing Antigravity's `.proto` definitions; see [`conversation`] for how turns
+//! are recognised. [`wire`] is the underlying schema-less protobuf parser.
+
+pub mod conversation;
+pub mod wire;
+
+pub use conversation::{Conversation, Role, Turn, decode_conversation, decode_file};
@@ -0,0 +1,147 @@
+//! Schema-less protobuf wire-format parsing.
+
+use anyhow::{Context, Result, bail};
+
+/// Highest field number protobuf allows (2^29 - 1).
+const MAX_FIELD_NUMBER: u64 = (1 << 29) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field<'a> {
+    pub number: u32,
+    pub value: WireValue<'a>,
+}
+
+/// Parse `buf` as one message. Fails unless the whole buffer is well-formed, which is
+/// what lets callers tell nested messages apart from strings and opaque bytes.
+pub fn parse_message(buf: &[u8]) -> Result<Vec<Field<'_>>> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        let key = read_varint(buf, &mut pos)?;
+        let number = key >> 3;
+        if number == 0 || number > MAX_FIELD_NUMBER {
+            bail!("invalid field number {number} at offset {pos}");
+        }
+        let value = match key & 0x7 {
+            0 => WireValue::Varint(read_varint(buf, &mut pos)?),
+            1 => {
+                let bytes = take(buf, &mut pos, 8)?;
+                WireValue::Fixed64(u64::from_le_bytes(bytes.try_into()?))
+            }
+            2 => {
+                let len = usize::try_from(read_varint(buf, &mut pos)?)?;
+                WireValue::Bytes(take(buf, &mut pos, len)?)
+            }
+            5 => {
+                let bytes = take(buf, &mut pos, 4)?;
+                WireValue::Fixed32(u32::from_le_bytes(bytes.try_into()?))
+            }
+            // Groups (3/4) are long deprecated; 6 and 7 are not wire types at all.
+            other => bail!("unsupported wire type {other} at offset {pos}"),
+        };
+        fields.push(Field {
+            number: number as u32,
+            value,
+        });
+    }
+    Ok(fields)
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos).context("truncated varint")?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("varint longer than 10 bytes")
+}
+
+fn take<'a>(buf: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let end = pos
+        .checked_add(len)
+        .filter(|end| *end <= buf.len())
+        .context("field runs past end of message")?;
+    let bytes = &buf[*pos..end];
+    *pos = end;
+    Ok(bytes)
+}
+
+/// Minimal encoder for building fixtures in tests.
+#[cfg(test)]
+pub(crate) mod encode {
+    pub fn varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    pub fn uint(out: &mut Vec<u8>, number: u32, value: u64) {
+        varint(out, u64::from(number) << 3);
+        varint(out, value);
+    }
+
+    pub fn bytes(out: &mut Vec<u8>, number: u32, value: &[u8]) {
+        varint(out, (u64::from(number) << 3) | 2);
+        varint(out, value.len() as u64);
+        out.extend_from_slice(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_supported_wire_type() {
+        let mut buf = Vec::new();
+        encode::uint(&mut buf, 1, 300);
+        encode::bytes(&mut buf, 2, b"hi");
+        buf.extend_from_slice(&[(3 << 3) | 1, 1, 0, 0, 0, 0, 0, 0, 0]);
+        buf.extend_from_slice(&[(4 << 3) | 5, 2, 0, 0, 0]);
+
+        let fields = parse_message(&buf).unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                Field {
+                    number: 1,
+                    value: WireValue::Varint(300)
+                },
+                Field {
+                    number: 2,
+                    value: WireValue::Bytes(b"hi")
+                },
+                Field {
+                    number: 3,
+                    value: WireValue::Fixed64(1)
+                },
+                Field {
+                    number: 4,
+                    value: WireValue::Fixed32(2)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_and_invalid_input() {
+        assert!(parse_message(&[0x0A, 0x05, b'a']).is_err());
+        assert!(parse_message(&[0x08, 0x80]).is_err());
+        assert!(parse_message(&[0x0F, 0x00]).is_err());
+        assert!(parse_message(b"plain english text").is_err());
+    }
+}
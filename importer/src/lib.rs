@@ -5,11 +5,20 @@ use scrapers::claude_profile_import::{
     ImportScope, ImportTarget, SetupRequest, setup_claude_profile,
 };
 use scrapers::config::ContrailConfig;
+use scrapers::event_index::EventIndex;
 use scrapers::history_import;
 use scrapers::merge::{self, ExportFilters};
+use scrapers::purge::{self, PurgeFilter, PurgeTargets};
+use scrapers::redact;
+use scrapers::sentry::Sentry;
+use scrapers::vault::{SecretVault, UnlockedVault};
 use std::path::PathBuf;
-#[cfg(target_os = "macos")]
+#[cfg(unix)]
 use std::process::Command;
+use std::time::Duration;
+use uuid::Uuid;
+
+mod sync;
 
 #[derive(Parser)]
 #[command(
@@ -23,7 +32,7 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Import historical logs from Codex, Claude, Cursor, and Antigravity native storage (one-time backfill).
+    /// Import historical logs from Codex, Claude, Cursor, Gemini CLI, and Antigravity native storage (one-time backfill).
     ImportHistory,
 
     /// Export the master log (or a filtered subset) to a portable JSONL file.
@@ -44,7 +53,7 @@ enum Commands {
         #[arg(long)]
         project: Option<String>,
 
-        /// Filter by source tool (cursor, codex-cli, claude-code, antigravity).
+        /// Filter by source tool (cursor, codex-cli, claude-code, gemini-cli, antigravity).
         #[arg(long)]
         tool: Option<String>,
 
@@ -58,12 +67,108 @@ enum Commands {
     /// Deduplicates by event_id UUID first, then by content fingerprint to catch
     /// the same underlying event ingested independently on two machines.
     ///
-    /// Stop the contrail daemon before running this to avoid partial-line interleaving.
+    /// Safe while the daemon runs: appends share its log lock.
     MergeLog {
         /// Path to the JSONL file to merge in.
         file: PathBuf,
     },
 
+    /// Replicate another machine's master log into this one (safe while the daemon runs).
+    ///
+    /// Pulls sequenced events from the remote dashboard's `/api/sync/pull` endpoint and
+    /// resumes from the last acknowledged sequence number on the next run.
+    Sync {
+        /// Remote host, `host:port` (default port 3000), or full URL.
+        #[arg(long)]
+        from: String,
+
+        /// Bearer token expected by the remote (defaults to $CONTRAIL_SYNC_TOKEN).
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Keep polling for new events instead of exiting once caught up.
+        #[arg(long, default_value_t = false)]
+        follow: bool,
+
+        /// Seconds between polls with --follow.
+        #[arg(long, default_value_t = 30)]
+        interval: u64,
+
+        /// Events requested per pull.
+        #[arg(long, default_value_t = 500)]
+        batch_size: usize,
+    },
+
+    /// Re-run the current redaction rules over the master log and its archives.
+    ///
+    /// Without --rewrite this only reports what would change. Stop the contrail
+    /// daemon before rewriting; event_ids are preserved.
+    Redact {
+        /// Rewrite the log files in place (atomically, one file at a time).
+        #[arg(long, default_value_t = false)]
+        rewrite: bool,
+    },
+
+    /// Delete matching events from the master log, its archives, analysis memories
+    /// and memex session copies, leaving tombstones so merges don't bring them back.
+    ///
+    /// All given filters must match. Without --apply this only reports what would be
+    /// deleted. Stop the contrail daemon before applying.
+    Purge {
+        /// Project path prefix.
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Session id.
+        #[arg(long)]
+        session: Option<String>,
+
+        /// Source tool (cursor, codex-cli, claude-code, gemini-cli, antigravity).
+        #[arg(long)]
+        tool: Option<String>,
+
+        /// Only events at or after this timestamp (RFC 3339).
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Only events before this timestamp (RFC 3339).
+        #[arg(long)]
+        before: Option<String>,
+
+        /// Regex matched against event content and artifact text (tool input and
+        /// output, diffs, commands).
+        #[arg(long)]
+        content: Option<String>,
+
+        /// Delete for real.
+        #[arg(long, default_value_t = false)]
+        apply: bool,
+    },
+
+    /// Create the encrypted vault used by reversible redaction (`reversible = true`
+    /// in redaction.toml).
+    InitVault {
+        /// Passphrase protecting the vault (defaults to $CONTRAIL_VAULT_PASSPHRASE).
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+
+    /// Print an event with its vault tokens replaced by the original secrets.
+    Unredact {
+        /// event_id of the event to restore.
+        event_id: Uuid,
+
+        /// Vault passphrase (defaults to $CONTRAIL_VAULT_PASSPHRASE).
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+
+    /// Inspect Contrail's configuration (`~/.contrail/config.toml` plus env overrides).
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
     /// Migrate Claude Code profile (instructions, commands, agents, history) to Codex.
     ImportClaude {
         /// Repo root (makes this a repo-scoped migration; omit for global).
@@ -88,6 +193,12 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the effective config and where each value came from.
+    Show,
+}
+
 #[derive(Clone, Debug, ValueEnum)]
 enum CliImportScope {
     Curated,
@@ -126,6 +237,44 @@ pub fn run() -> Result<()> {
             hostname,
         }) => run_export(output, after, before, project, tool, hostname),
         Some(Commands::MergeLog { file }) => run_merge(file),
+        Some(Commands::Sync {
+            from,
+            token,
+            follow,
+            interval,
+            batch_size,
+        }) => run_sync(from, token, follow, interval, batch_size),
+        Some(Commands::Redact { rewrite }) => run_redact(rewrite),
+        Some(Commands::Purge {
+            project,
+            session,
+            tool,
+            after,
+            before,
+            content,
+            apply,
+        }) => {
+            let filter = PurgeFilter {
+                project_prefix: project,
+                session_id: session,
+                tool,
+                after: parse_optional_ts(after.as_deref(), "--after")?,
+                before: parse_optional_ts(before.as_deref(), "--before")?,
+                content: content
+                    .map(|re| regex::Regex::new(&re))
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!("invalid --content regex: {e}"))?,
+            };
+            run_purge(filter, apply)
+        }
+        Some(Commands::InitVault { passphrase }) => run_init_vault(passphrase),
+        Some(Commands::Unredact {
+            event_id,
+            passphrase,
+        }) => run_unredact(event_id, passphrase),
+        Some(Commands::Config {
+            action: ConfigAction::Show,
+        }) => run_config_show(),
         Some(Commands::ImportClaude {
             repo_root,
             include_global,
@@ -140,7 +289,7 @@ fn run_import_history() -> Result<()> {
     println!("Contrail History Importer");
     println!("Scanning for historical logs (Codex, Claude, Cursor, Antigravity)...");
 
-    let config = ContrailConfig::from_env()?;
+    let config = ContrailConfig::load()?;
     let stats = history_import::import_history(&config)?;
     println!(
         "Import complete: imported={} skipped={} errors={}",
@@ -157,7 +306,7 @@ fn run_export(
     tool: Option<String>,
     hostname: Option<String>,
 ) -> Result<()> {
-    let config = ContrailConfig::from_env()?;
+    let config = ContrailConfig::load()?;
 
     let filters = ExportFilters {
         after: parse_optional_ts(after.as_deref(), "--after")?,
@@ -179,11 +328,7 @@ fn run_export(
 }
 
 fn run_merge(file: PathBuf) -> Result<()> {
-    let config = ContrailConfig::from_env()?;
-
-    if is_contrail_daemon_running() {
-        anyhow::bail!("com.contrail.daemon is running; stop it before merge");
-    }
+    let config = ContrailConfig::load()?;
 
     println!(
         "Merging {} into {}",
@@ -193,12 +338,200 @@ fn run_merge(file: PathBuf) -> Result<()> {
 
     let stats = merge::merge_log(&config.log_path, &file)?;
     println!(
-        "Merge complete: merged={} skipped_uuid={} skipped_fingerprint={} errors={}",
-        stats.merged, stats.skipped_uuid, stats.skipped_fingerprint, stats.errors,
+        "Merge complete: merged={} skipped_uuid={} skipped_fingerprint={} skipped_purged={} errors={}",
+        stats.merged,
+        stats.skipped_uuid,
+        stats.skipped_fingerprint,
+        stats.skipped_purged,
+        stats.errors,
     );
     Ok(())
 }
 
+fn run_sync(
+    from: String,
+    token: Option<String>,
+    follow: bool,
+    interval: u64,
+    batch_size: usize,
+) -> Result<()> {
+    let config = ContrailConfig::load()?;
+    let home =
+        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("could not resolve home directory"))?;
+    let state_dir = scrapers::config::data_dir(&home).join(scrapers::config::SYNC_STATE_DIR_REL);
+
+    let options = sync::SyncOptions {
+        from,
+        token: token.or_else(|| {
+            std::env::var("CONTRAIL_SYNC_TOKEN")
+                .ok()
+                .filter(|t| !t.trim().is_empty())
+        }),
+        batch_size: batch_size.max(1),
+        follow,
+        interval: Duration::from_secs(interval.max(1)),
+    };
+    sync::run_sync(&config.log_path, &state_dir, &options)
+}
+
+fn run_redact(rewrite: bool) -> Result<()> {
+    let config = ContrailConfig::load()?;
+
+    if rewrite && is_contrail_daemon_running() {
+        anyhow::bail!("the contrail daemon is running; stop it before redact --rewrite");
+    }
+
+    // A dry run must not create the token seed as a side effect.
+    let sentry = if rewrite {
+        Sentry::from_config(&config)?
+    } else {
+        Sentry::from_config_read_only(&config)?
+    };
+    let report = redact::redact_logs(&config.log_path, &sentry, rewrite)?;
+    println!(
+        "{} {} of {} events across {} files (malformed={})",
+        if rewrite { "Redacted" } else { "Would redact" },
+        report.events_changed,
+        report.events_scanned,
+        report.files_scanned,
+        report.malformed,
+    );
+    for (label, count) in &report.labels {
+        println!("  {label}: {count}");
+    }
+    if rewrite {
+        println!("Rewrote {} files", report.files_rewritten);
+    } else if report.events_changed > 0 {
+        println!("Run with --rewrite to apply.");
+    }
+    Ok(())
+}
+
+fn run_purge(filter: PurgeFilter, apply: bool) -> Result<()> {
+    if filter.is_empty() {
+        anyhow::bail!(
+            "give at least one of --project, --session, --tool, --after, --before, --content"
+        );
+    }
+    if apply && is_contrail_daemon_running() {
+        anyhow::bail!("the contrail daemon is running; stop it before purge --apply");
+    }
+
+    let config = ContrailConfig::load()?;
+    let targets = PurgeTargets {
+        log_path: config.log_path,
+        memories_path: config.analysis_memories,
+        memory_blocks_path: config.analysis_memory_blocks,
+        search_index_dir: config.search_index_dir,
+    };
+
+    let report = purge::purge(&targets, &filter, apply)?;
+    println!(
+        "{} {} events across {} files, {} memories, {} memory blocks, {} session copies",
+        if apply { "Purged" } else { "Would purge" },
+        report.events_purged,
+        report.files_scanned,
+        report.memories_purged,
+        report.memory_blocks_purged,
+        report.session_copies.len(),
+    );
+    for path in &report.session_copies {
+        println!("  {}", path.display());
+    }
+    if apply {
+        println!("Rewrote {} files", report.files_rewritten);
+        if report.search_index_cleared {
+            println!("Cleared the search index; it is rebuilt on the next search.");
+        }
+    } else if report.events_purged + report.memories_purged + report.memory_blocks_purged > 0
+        || !report.session_copies.is_empty()
+    {
+        println!("Run with --apply to delete.");
+    }
+    Ok(())
+}
+
+fn run_init_vault(passphrase: Option<String>) -> Result<()> {
+    let config = ContrailConfig::load()?;
+    let passphrase = vault_passphrase(passphrase, "init-vault")?;
+    SecretVault::init(&config.vault_dir, &passphrase)?;
+    println!("Created vault at {}", config.vault_dir.display());
+    println!(
+        "Set `reversible = true` in {} and restart the daemon to start using it.",
+        config.redaction_config.display()
+    );
+    Ok(())
+}
+
+fn run_unredact(event_id: Uuid, passphrase: Option<String>) -> Result<()> {
+    let config = ContrailConfig::load()?;
+    let index = EventIndex::open_synced(&config.log_path)?;
+    let mut event = index
+        .get(event_id)?
+        .ok_or_else(|| anyhow::anyhow!("event {event_id} not found"))?;
+
+    let passphrase = vault_passphrase(passphrase, "unredact")?;
+    let vault = UnlockedVault::open(&config.vault_dir, &passphrase)?;
+
+    let mut missing = Vec::new();
+    let mut restore = |text: &mut String| {
+        let (restored, unknown) = vault.restore(text);
+        *text = restored;
+        missing.extend(unknown);
+    };
+    restore(&mut event.interaction.content);
+    for artifact in event.interaction.artifacts.iter_mut().flatten() {
+        for text in artifact.text_fields_mut() {
+            restore(text);
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&event)?);
+    if !missing.is_empty() {
+        eprintln!(
+            "warning: {} token(s) not found in the vault: {}",
+            missing.len(),
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn vault_passphrase(passphrase: Option<String>, action: &str) -> Result<String> {
+    passphrase
+        .or_else(|| std::env::var("CONTRAIL_VAULT_PASSPHRASE").ok())
+        .filter(|p| !p.trim().is_empty())
+        .ok_or_else(|| {
+            anyhow::anyhow!("{action} requires --passphrase or $CONTRAIL_VAULT_PASSPHRASE")
+        })
+}
+
+fn run_config_show() -> Result<()> {
+    let loaded = ContrailConfig::load_with_sources()?;
+    println!(
+        "Config file: {} ({})",
+        loaded.file_path.display(),
+        if loaded.file_found {
+            "loaded"
+        } else {
+            "not found, using defaults"
+        }
+    );
+    let width = loaded
+        .values
+        .iter()
+        .map(|value| value.key.len())
+        .max()
+        .unwrap_or(0);
+    for value in &loaded.values {
+        println!(
+            "  {:width$} = {}  ({})",
+            value.key, value.value, value.source
+        );
+    }
+    Ok(())
+}
+
 fn run_import_claude(
     repo_root: Option<PathBuf>,
     include_global: bool,
@@ -324,8 +657,40 @@ fn parse_optional_ts(value: Option<&str>, flag_name: &str) -> Result<Option<Date
     }
 }
 
-#[cfg(target_os = "macos")]
+/// Whether a daemon is running, started either by `contrail up` (which leaves a pid
+/// file) or as the installed service.
 fn is_contrail_daemon_running() -> bool {
+    is_daemon_pid_running() || is_contrail_service_running()
+}
+
+/// `contrail up` records the daemon's pid in `~/.contrail/run/core_daemon.pid`.
+#[cfg(unix)]
+fn is_daemon_pid_running() -> bool {
+    let root = std::env::var_os("CONTRAIL_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".contrail")));
+    let Some(pid) = root
+        .and_then(|root| std::fs::read_to_string(root.join("run/core_daemon.pid")).ok())
+        .and_then(|raw| raw.trim().parse::<u32>().ok())
+    else {
+        return false;
+    };
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_daemon_pid_running() -> bool {
+    false
+}
+
+#[cfg(target_os = "macos")]
+fn is_contrail_service_running() -> bool {
     Command::new("launchctl")
         .arg("list")
         .arg("com.contrail.daemon")
@@ -334,8 +699,18 @@ fn is_contrail_daemon_running() -> bool {
         .unwrap_or(false)
 }
 
-#[cfg(not(target_os = "macos"))]
-fn is_contrail_daemon_running() -> bool {
+/// Only the unit written by `contrail install-service` is detected.
+#[cfg(target_os = "linux")]
+fn is_contrail_service_running() -> bool {
+    Command::new("systemctl")
+        .args(["--user", "is-active", "--quiet", "contrail.service"])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn is_contrail_service_running() -> bool {
     false
 }
 
@@ -370,6 +745,90 @@ mod tests {
         };
     }
 
+    #[test]
+    fn sync_requires_from() {
+        assert!(Cli::try_parse_from(["importer", "sync"]).is_err());
+        let parsed =
+            Cli::try_parse_from(["importer", "sync", "--from", "laptop.local", "--follow"])
+                .unwrap();
+        let Some(Commands::Sync {
+            from,
+            follow,
+            interval,
+            ..
+        }) = parsed.command
+        else {
+            panic!("expected sync");
+        };
+        assert_eq!(from, "laptop.local");
+        assert!(follow);
+        assert_eq!(interval, 30);
+    }
+
+    #[test]
+    fn redact_defaults_to_dry_run() {
+        let parsed = Cli::try_parse_from(["importer", "redact"]).unwrap();
+        assert!(matches!(
+            parsed.command,
+            Some(Commands::Redact { rewrite: false })
+        ));
+        let parsed = Cli::try_parse_from(["importer", "redact", "--rewrite"]).unwrap();
+        assert!(matches!(
+            parsed.command,
+            Some(Commands::Redact { rewrite: true })
+        ));
+    }
+
+    #[test]
+    fn purge_defaults_to_dry_run() {
+        let parsed = Cli::try_parse_from([
+            "importer",
+            "purge",
+            "--project",
+            "/work/acme",
+            "--before",
+            "2026-01-01T00:00:00Z",
+        ])
+        .unwrap();
+        let Some(Commands::Purge {
+            project,
+            session,
+            before,
+            apply,
+            ..
+        }) = parsed.command
+        else {
+            panic!("expected purge");
+        };
+        assert_eq!(project.as_deref(), Some("/work/acme"));
+        assert_eq!(session, None);
+        assert_eq!(before.as_deref(), Some("2026-01-01T00:00:00Z"));
+        assert!(!apply);
+    }
+
+    #[test]
+    fn config_requires_an_action() {
+        assert!(Cli::try_parse_from(["importer", "config"]).is_err());
+        let parsed = Cli::try_parse_from(["importer", "config", "show"]).unwrap();
+        assert!(matches!(
+            parsed.command,
+            Some(Commands::Config {
+                action: ConfigAction::Show
+            })
+        ));
+    }
+
+    #[test]
+    fn unredact_requires_a_uuid() {
+        assert!(Cli::try_parse_from(["importer", "unredact", "not-a-uuid"]).is_err());
+        let id = Uuid::new_v4().to_string();
+        let parsed = Cli::try_parse_from(["importer", "unredact", &id]).unwrap();
+        let Some(Commands::Unredact { event_id, .. }) = parsed.command else {
+            panic!("expected unredact");
+        };
+        assert_eq!(event_id.to_string(), id);
+    }
+
     #[test]
     fn import_claude_parses_global() {
         let parsed = Cli::try_parse_from(["importer", "import-claude"]).unwrap();
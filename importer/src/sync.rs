@@ -0,0 +1,260 @@
+//! `contrail sync --from <host>`: pull-based replication from another machine's
+//! dashboard (`/api/sync/pull`) into the local master log.
+//!
+//! The last applied sequence number is persisted per remote under
+//! `state/sync/` in the data dir, so interrupted syncs resume where they stopped.
+//! Entries go through the same dedup rules as `merge-log`, except that an event
+//! already held locally is replaced when the source re-sends a rewritten version
+//! (see [`redact::apply_revisions`]).
+
+use anyhow::{Context, Result};
+use scrapers::merge::{MergeStats, Merger};
+use scrapers::redact;
+use scrapers::types::{SyncAck, SyncBatch};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+const DEFAULT_SYNC_PORT: u16 = 3000;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct SyncOptions {
+    pub from: String,
+    pub token: Option<String>,
+    pub batch_size: usize,
+    /// Keep polling every `interval` instead of exiting once caught up.
+    pub follow: bool,
+    pub interval: Duration,
+}
+
+/// Progress against one remote, stored as JSON next to other Contrail state.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct SyncState {
+    log_id: Option<Uuid>,
+    #[serde(flatten)]
+    ack: SyncAck,
+}
+
+pub fn run_sync(log_path: &Path, state_dir: &Path, options: &SyncOptions) -> Result<()> {
+    let base_url = normalize_remote(&options.from)?;
+    let state_path = state_dir.join(format!("{}.json", state_file_stem(&base_url)));
+    let mut state = load_state(&state_path)?;
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let mut merger = Merger::open(log_path)?;
+
+    println!("Syncing from {base_url} into {}", log_path.display());
+    loop {
+        let (stats, revised) = pull_until_caught_up(
+            &client,
+            &base_url,
+            options,
+            log_path,
+            &mut merger,
+            &mut state,
+            &state_path,
+        )?;
+        println!(
+            "Sync pass complete: ack_seq={} merged={} revised={} skipped_uuid={} skipped_fingerprint={} skipped_purged={} errors={}",
+            state.ack.ack_seq,
+            stats.merged,
+            revised,
+            stats.skipped_uuid,
+            stats.skipped_fingerprint,
+            stats.skipped_purged,
+            stats.errors,
+        );
+        if !options.follow {
+            return Ok(());
+        }
+        std::thread::sleep(options.interval);
+    }
+}
+
+/// Returns the merge stats and how many local events were replaced by revisions.
+fn pull_until_caught_up(
+    client: &reqwest::blocking::Client,
+    base_url: &str,
+    options: &SyncOptions,
+    log_path: &Path,
+    merger: &mut Merger,
+    state: &mut SyncState,
+    state_path: &Path,
+) -> Result<(MergeStats, usize)> {
+    let mut stats = MergeStats::default();
+    let mut revised = 0;
+    loop {
+        let batch = fetch_batch(client, base_url, options, state.ack)?;
+
+        if state.log_id.is_some_and(|id| id != batch.log_id) {
+            println!(
+                "Remote log {} was rebuilt; restarting from seq 0",
+                batch.log_id
+            );
+            state.ack = SyncAck::default();
+            state.log_id = Some(batch.log_id);
+            continue;
+        }
+        state.log_id = Some(batch.log_id);
+
+        // A page of events the source couldn't read still moves `next_seq` on.
+        let last_entry = batch.entries.last().map_or(0, |e| e.seq);
+        let next_seq = batch.next_seq.max(last_entry);
+        if next_seq <= state.ack.ack_seq {
+            save_state(state_path, state)?;
+            return Ok((stats, revised));
+        }
+        // Known events come back when the source rewrote them, e.g. redacted them.
+        let mut revisions = Vec::new();
+        for entry in batch.entries {
+            if merger.has_event(&entry.entry.event_id) {
+                revisions.push(entry.entry);
+                continue;
+            }
+            let line = serde_json::to_string(&entry.entry)?;
+            merger.push_line(&line, &mut stats)?;
+        }
+        revised += redact::apply_revisions(log_path, &revisions)?;
+        state.ack.ack_seq = next_seq;
+        save_state(state_path, state)?;
+
+        if next_seq >= batch.head_seq {
+            return Ok((stats, revised));
+        }
+    }
+}
+
+fn fetch_batch(
+    client: &reqwest::blocking::Client,
+    base_url: &str,
+    options: &SyncOptions,
+    ack: SyncAck,
+) -> Result<SyncBatch> {
+    let mut request = client.get(format!("{base_url}/api/sync/pull")).query(&[
+        ("ack_seq", ack.ack_seq.to_string()),
+        ("limit", options.batch_size.to_string()),
+    ]);
+    if let Some(token) = &options.token {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .with_context(|| format!("request {base_url}/api/sync/pull"))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        let body = response.text().unwrap_or_default();
+        anyhow::bail!(
+            "sync pull from {base_url} was refused ({status}): {body}; set the remote's CONTRAIL_SYNC_TOKEN via --token or $CONTRAIL_SYNC_TOKEN"
+        );
+    }
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        anyhow::bail!("sync pull from {base_url} failed ({status}): {body}");
+    }
+    response.json::<SyncBatch>().context("decode sync batch")
+}
+
+/// Accepts `host`, `host:port`, or a full `http(s)://` URL.
+fn normalize_remote(from: &str) -> Result<String> {
+    let trimmed = from.trim().trim_end_matches('/');
+    anyhow::ensure!(!trimmed.is_empty(), "--from must name a host");
+    let with_scheme = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("http://{trimmed}")
+    };
+    let mut url = reqwest::Url::parse(&with_scheme)
+        .with_context(|| format!("invalid sync remote '{from}'"))?;
+    if url.port().is_none() && !trimmed.contains("://") {
+        url.set_port(Some(DEFAULT_SYNC_PORT))
+            .map_err(|_| anyhow::anyhow!("invalid sync remote '{from}'"))?;
+    }
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+fn state_file_stem(base_url: &str) -> String {
+    let without_scheme = base_url.split("://").nth(1).unwrap_or(base_url);
+    without_scheme
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn load_state(path: &Path) -> Result<SyncState> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw).with_context(|| format!("parse {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(SyncState::default()),
+        Err(err) => Err(err).with_context(|| format!("read {}", path.display())),
+    }
+}
+
+fn save_state(path: &Path, state: &SyncState) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    let tmp = tmp_path(path);
+    std::fs::write(&tmp, serde_json::to_vec_pretty(state)?)
+        .with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))?;
+    Ok(())
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_remote_adds_scheme_and_default_port() {
+        assert_eq!(
+            normalize_remote("laptop.local").unwrap(),
+            "http://laptop.local:3000"
+        );
+        assert_eq!(
+            normalize_remote("10.0.0.2:4000").unwrap(),
+            "http://10.0.0.2:4000"
+        );
+        assert_eq!(
+            normalize_remote("https://box.example.com/").unwrap(),
+            "https://box.example.com"
+        );
+    }
+
+    #[test]
+    fn state_round_trips_with_flattened_ack() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sync").join("laptop.local_3000.json");
+        assert_eq!(load_state(&path).unwrap(), SyncState::default());
+
+        let state = SyncState {
+            log_id: Some(Uuid::new_v4()),
+            ack: SyncAck { ack_seq: 42 },
+        };
+        save_state(&path, &state).unwrap();
+        let raw = std::fs::read_to_string(&path).unwrap();
+        assert!(raw.contains("\"ack_seq\": 42"));
+        assert_eq!(load_state(&path).unwrap(), state);
+    }
+
+    #[test]
+    fn state_file_stem_is_filesystem_safe() {
+        assert_eq!(
+            state_file_stem("http://laptop.local:3000"),
+            "laptop.local_3000"
+        );
+    }
+}
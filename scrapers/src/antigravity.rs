@@ -0,0 +1,102 @@
+//! Antigravity conversations (`~/.gemini/antigravity/conversations/<id>.pb`).
+//!
+//! The protobuf decoding lives in the `decoder` crate; this maps its turns onto
+//! [`ParsedLine`]s. A conversation's file stem is the same id as its brain directory,
+//! so decoded turns and task/plan snapshots share a `session_id`.
+
+use crate::git_effects;
+use crate::parse::ParsedLine;
+use anyhow::Result;
+use chrono::DateTime;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Every `*.pb` file directly under `dir`, sorted by path.
+pub fn find_conversation_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("pb"))
+        .collect();
+    files.sort();
+    files
+}
+
+pub fn conversation_id(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// The repository a conversation worked in, found from the first `file://` link in
+/// its turns that resolves to a git work tree. Antigravity doesn't record a cwd.
+pub fn workspace_hint(turns: &[ParsedLine]) -> Option<PathBuf> {
+    turns
+        .iter()
+        .flat_map(|turn| turn.content.split("file://").skip(1))
+        .filter_map(|rest| {
+            let end = rest
+                .find(|c: char| {
+                    c.is_whitespace() || matches!(c, ')' | ']' | '"' | '\'' | '`' | '#')
+                })
+                .unwrap_or(rest.len());
+            let path = PathBuf::from(rest[..end].replace("%20", " "));
+            path.is_absolute().then_some(path)
+        })
+        .take(20)
+        .find_map(|path| {
+            let mut dir = path.as_path();
+            while !dir.is_dir() {
+                dir = dir.parent()?;
+            }
+            git_effects::repo_root(dir)
+        })
+}
+
+/// Decode a conversation file into one [`ParsedLine`] per turn. Fails for files the
+/// decoder can't read (for example encrypted ones).
+pub fn read_conversation(path: &Path) -> Result<Vec<ParsedLine>> {
+    let session_id = conversation_id(path);
+    let conversation = decoder::decode_file(path)?;
+
+    let lines = conversation
+        .turns
+        .into_iter()
+        .enumerate()
+        .map(|(index, turn)| {
+            let mut metadata = Map::new();
+            metadata.insert(
+                "conversation_id".to_string(),
+                Value::String(session_id.clone()),
+            );
+            metadata.insert(
+                "antigravity_turn_index".to_string(),
+                Value::Number((index as u64).into()),
+            );
+            let timestamp = turn
+                .timestamp
+                .and_then(|secs| DateTime::from_timestamp(secs, 0));
+            if let Some(ts) = timestamp.as_ref() {
+                metadata.insert(
+                    "original_timestamp".to_string(),
+                    Value::String(ts.to_rfc3339()),
+                );
+            }
+            ParsedLine {
+                role: turn.role.as_str().to_string(),
+                content: turn.text,
+                timestamp,
+                session_id: Some(session_id.clone()),
+                project_context: None,
+                metadata,
+                artifacts: Vec::new(),
+            }
+        })
+        .collect();
+    Ok(lines)
+}
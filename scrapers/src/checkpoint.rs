@@ -0,0 +1,234 @@
+//! Watcher progress persisted under `state/watchers/` in the data dir.
+//!
+//! Each watcher keeps one JSON file mapping the transcripts it follows to how far it
+//! has logged them: a byte offset for append-only JSONL, a message count for chats
+//! rewritten in place, a content fingerprint for Cursor's databases. Checkpoints
+//! are only saved after the log writer has flushed the events they cover, so a
+//! restarted daemon resumes where the previous one stopped; what it replays is
+//! checked against the master log (see `Harvester::already_logged`).
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Bytes of a file's head hashed to notice it being replaced under the same name.
+const HEAD_BYTES: u64 = 4096;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileCheckpoint {
+    /// Bytes consumed; always at a line boundary.
+    pub offset: u64,
+    pub head_len: u64,
+    pub head_hash: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_context: Option<String>,
+    /// Messages logged from the front of a transcript that is rewritten in place.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messages: Option<usize>,
+    /// Fingerprint of the last snapshot logged from a file that is read whole.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreFile {
+    saved_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    files: BTreeMap<String, FileCheckpoint>,
+}
+
+pub struct CheckpointStore {
+    path: PathBuf,
+    state: StoreFile,
+    dirty: bool,
+}
+
+impl CheckpointStore {
+    /// Load `path`, starting empty when it is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        let state = match fs::read_to_string(path) {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|err| {
+                tracing::warn!(path = ?path, err = %err, "ignoring corrupt watcher checkpoint");
+                StoreFile::default()
+            }),
+            Err(_) => StoreFile::default(),
+        };
+        Self {
+            path: path.to_path_buf(),
+            state,
+            dirty: false,
+        }
+    }
+
+    /// When the store was last saved; `None` on the first run.
+    pub fn saved_at(&self) -> Option<DateTime<Utc>> {
+        self.state.saved_at
+    }
+
+    /// The checkpoint for `path`, provided the file still starts with the bytes it
+    /// had when the checkpoint was taken and hasn't shrunk below the offset.
+    pub fn resume(&self, path: &Path) -> Option<&FileCheckpoint> {
+        let checkpoint = self.state.files.get(&key(path))?;
+        let len = fs::metadata(path).ok()?.len();
+        if len < checkpoint.offset {
+            return None;
+        }
+        (hash_head(path, checkpoint.head_len)? == checkpoint.head_hash).then_some(checkpoint)
+    }
+
+    /// Whether `path` was modified after the last save, i.e. while nothing was
+    /// following it. Always false on the first run.
+    pub fn changed_since_save(&self, path: &Path) -> bool {
+        let Some(saved_at) = self.state.saved_at else {
+            return false;
+        };
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map(|modified| DateTime::<Utc>::from(modified) > saved_at)
+            .unwrap_or(false)
+    }
+
+    /// Where to start following an append-only file at startup: its checkpoint, the
+    /// top if it changed while the daemon was down, otherwise its end. On the very
+    /// first run existing history is left to history import.
+    pub fn start_offset(&self, path: &Path) -> u64 {
+        match self.resume(path) {
+            Some(checkpoint) => checkpoint.offset,
+            None if self.changed_since_save(path) => 0,
+            None => fs::metadata(path).map_or(0, |m| m.len()),
+        }
+    }
+
+    /// The checkpoint for `path` as saved, for files that are rewritten rather than
+    /// appended to and so can't be validated by their head.
+    pub fn get(&self, path: &Path) -> Option<&FileCheckpoint> {
+        self.state.files.get(&key(path))
+    }
+
+    /// Replace the checkpoint for a rewritten file; see [`CheckpointStore::get`].
+    pub fn set(&mut self, path: &Path, checkpoint: FileCheckpoint) {
+        let key = key(path);
+        if self.state.files.get(&key) != Some(&checkpoint) {
+            self.state.files.insert(key, checkpoint);
+            self.dirty = true;
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        path: &Path,
+        offset: u64,
+        session_id: Option<&str>,
+        project_context: Option<&str>,
+    ) {
+        let key = key(path);
+        let fresh = !self.state.files.contains_key(&key);
+        let entry = self.state.files.entry(key).or_default();
+        let head_len = offset.min(HEAD_BYTES);
+        if fresh || entry.head_len != head_len || entry.offset > offset {
+            entry.head_len = head_len;
+            entry.head_hash = hash_head(path, head_len).unwrap_or(0);
+        }
+        entry.offset = offset;
+        entry.session_id = session_id.map(str::to_string);
+        entry.project_context = project_context.map(str::to_string);
+        self.dirty = true;
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.dirty |= self.state.files.remove(&key(path)).is_some();
+    }
+
+    /// Write the store if anything changed since the last save. The file is replaced
+    /// atomically, so a crash mid-save leaves the previous checkpoint intact.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty && self.state.saved_at.is_some() {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+        }
+        self.state.saved_at = Some(Utc::now());
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.state)?)
+            .with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path).with_context(|| format!("replace {}", self.path.display()))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn hash_head(path: &Path, len: u64) -> Option<u64> {
+    let mut buf = Vec::with_capacity(len as usize);
+    File::open(path)
+        .ok()?
+        .take(len)
+        .read_to_end(&mut buf)
+        .ok()?;
+    (buf.len() as u64 == len).then(|| xxhash_rust::xxh3::xxh3_64(&buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_only_files_that_still_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("rollout.jsonl");
+        fs::write(&log, "{\"a\":1}\n{\"b\":2}\n").unwrap();
+        let store_path = dir.path().join("state/codex.json");
+
+        let mut store = CheckpointStore::load(&store_path);
+        assert_eq!(store.saved_at(), None);
+        store.record(&log, 8, Some("sess"), Some("/repo"));
+        store.save().unwrap();
+
+        let store = CheckpointStore::load(&store_path);
+        assert!(store.saved_at().is_some());
+        let checkpoint = store.resume(&log).unwrap();
+        assert_eq!(checkpoint.offset, 8);
+        assert_eq!(checkpoint.session_id.as_deref(), Some("sess"));
+
+        // Replaced by a different file of the same length.
+        fs::write(&log, "{\"c\":3}\n{\"d\":4}\n").unwrap();
+        assert!(store.resume(&log).is_none());
+
+        // Truncated below the offset.
+        fs::write(&log, "{}\n").unwrap();
+        assert!(store.resume(&log).is_none());
+    }
+
+    #[test]
+    fn rewritten_files_keep_counts_and_fingerprints() {
+        let dir = tempfile::tempdir().unwrap();
+        let chat = dir.path().join("session.json");
+        let store_path = dir.path().join("gemini.json");
+
+        let mut store = CheckpointStore::load(&store_path);
+        let checkpoint = FileCheckpoint {
+            session_id: Some("chat".to_string()),
+            messages: Some(4),
+            ..Default::default()
+        };
+        store.set(&chat, checkpoint.clone());
+        store.save().unwrap();
+
+        let mut store = CheckpointStore::load(&store_path);
+        assert_eq!(store.get(&chat), Some(&checkpoint));
+        let saved_at = store.saved_at();
+        store.set(&chat, checkpoint);
+        store.save().unwrap();
+        assert_eq!(store.saved_at(), saved_at);
+    }
+}
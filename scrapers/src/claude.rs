@@ -1,4 +1,7 @@
-use crate::parse::{append_metrics, append_usage, extract_text, parse_timestamp_value, ParsedLine};
+use crate::parse::{
+    append_metrics, append_usage, clip_artifacts, extract_text, parse_timestamp_value, ParsedLine,
+};
+use crate::types::Artifact;
 use serde_json::{Map, Value};
 
 pub fn parse_claude_line(raw: &str) -> Option<ParsedLine> {
@@ -68,6 +71,7 @@ pub fn parse_claude_line(raw: &str) -> Option<ParsedLine> {
         session_id,
         project_context,
         metadata,
+        artifacts: Vec::new(),
     })
 }
 
@@ -156,6 +160,7 @@ pub fn parse_claude_session_line(raw: &str) -> Option<ParsedLine> {
         session_id,
         project_context,
         metadata,
+        artifacts: clip_artifacts(extract_message_artifacts(&json)),
     })
 }
 
@@ -196,6 +201,126 @@ fn extract_message_content(json: &Value) -> Option<String> {
     }
 }
 
+/// Typed artifacts for the tool_use / tool_result / image blocks of message.content.
+fn extract_message_artifacts(json: &Value) -> Vec<Artifact> {
+    let Some(items) = json.pointer("/message/content").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+
+    let mut artifacts = Vec::new();
+    for item in items {
+        match item.get("type").and_then(Value::as_str) {
+            Some("tool_use") => artifacts.extend(tool_use_artifacts(item)),
+            Some("tool_result") => {
+                let content = item.get("content");
+                let output = match content {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(value) => extract_text(value).unwrap_or_default(),
+                    None => String::new(),
+                };
+                artifacts.push(Artifact::ToolResult {
+                    call_id: item
+                        .get("tool_use_id")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    output,
+                    is_error: item
+                        .get("is_error")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false),
+                });
+                // Screenshots and image reads come back inside the result.
+                for block in content.and_then(Value::as_array).into_iter().flatten() {
+                    if block.get("type").and_then(Value::as_str) == Some("image") {
+                        artifacts.push(image_artifact(block));
+                    }
+                }
+            }
+            Some("image") => artifacts.push(image_artifact(item)),
+            _ => {}
+        }
+    }
+    artifacts
+}
+
+/// The tool call itself, plus a command/patch/read artifact for the built-in tools
+/// whose inputs say what they did.
+fn tool_use_artifacts(item: &Value) -> Vec<Artifact> {
+    let name = item
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown")
+        .to_string();
+    let input = item.get("input").cloned().unwrap_or(Value::Null);
+    let field = |key: &str| input.get(key).and_then(Value::as_str).map(str::to_string);
+
+    let detail = match name.as_str() {
+        "Bash" => field("command").map(|command| Artifact::Command {
+            command,
+            exit_code: None,
+            output: None,
+        }),
+        "Read" => field("file_path").map(|path| Artifact::FileRead { path }),
+        "Write" => field("content").map(|content| Artifact::Patch {
+            path: field("file_path"),
+            diff: edit_diff("", &content),
+        }),
+        "Edit" => Some(Artifact::Patch {
+            path: field("file_path"),
+            diff: edit_diff(
+                &field("old_string").unwrap_or_default(),
+                &field("new_string").unwrap_or_default(),
+            ),
+        }),
+        "MultiEdit" => {
+            let diff: String = input
+                .get("edits")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|edit| {
+                    let text = |key: &str| edit.get(key).and_then(Value::as_str).unwrap_or("");
+                    edit_diff(text("old_string"), text("new_string"))
+                })
+                .collect();
+            Some(Artifact::Patch {
+                path: field("file_path"),
+                diff,
+            })
+        }
+        _ => None,
+    };
+
+    let mut artifacts = vec![Artifact::ToolCall {
+        call_id: item.get("id").and_then(Value::as_str).map(str::to_string),
+        name,
+        input: input.to_string(),
+    }];
+    artifacts.extend(detail);
+    artifacts
+}
+
+/// Edit/Write inputs carry before and after text rather than a diff; render them as
+/// removed and added lines.
+fn edit_diff(old: &str, new: &str) -> String {
+    let removed = old.lines().map(|line| format!("-{line}\n"));
+    let added = new.lines().map(|line| format!("+{line}\n"));
+    removed.chain(added).collect()
+}
+
+fn image_artifact(block: &Value) -> Artifact {
+    Artifact::Image {
+        media_type: block
+            .pointer("/source/media_type")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        path: block
+            .pointer("/source/url")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    }
+}
+
 fn extract_cwd(json: &Value) -> Option<String> {
     let candidate_keys = [
         "cwd",
@@ -382,4 +507,57 @@ mod tests {
         let parsed = parse_claude_session_line(tool_result).expect("should parse");
         assert_eq!(parsed.role, "tool_result");
     }
+
+    #[test]
+    fn captures_tool_use_and_results_as_artifacts() {
+        let tool_use = r#"{
+            "type": "assistant",
+            "timestamp": "2025-12-01T00:00:00Z",
+            "sessionId": "test",
+            "message": { "content": [
+                {"type": "text", "text": "Let me fix that."},
+                {"type": "tool_use", "id": "toolu_1", "name": "Edit",
+                 "input": {"file_path": "/repo/src/lib.rs", "old_string": "a", "new_string": "b"}}
+            ] }
+        }"#;
+        let parsed = parse_claude_session_line(tool_use).expect("should parse");
+        assert_eq!(parsed.artifacts.len(), 2);
+        assert!(matches!(
+            &parsed.artifacts[0],
+            Artifact::ToolCall { call_id: Some(id), name, .. } if id == "toolu_1" && name == "Edit"
+        ));
+        assert_eq!(
+            parsed.artifacts[1],
+            Artifact::Patch {
+                path: Some("/repo/src/lib.rs".to_string()),
+                diff: "-a\n+b\n".to_string(),
+            }
+        );
+
+        let tool_result = r#"{
+            "type": "user",
+            "timestamp": "2025-12-01T00:00:01Z",
+            "sessionId": "test",
+            "message": { "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_2", "is_error": true,
+                 "content": [{"type": "text", "text": "exit code 1"},
+                             {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}]}
+            ] }
+        }"#;
+        let parsed = parse_claude_session_line(tool_result).expect("should parse");
+        assert_eq!(
+            parsed.artifacts,
+            vec![
+                Artifact::ToolResult {
+                    call_id: Some("toolu_2".to_string()),
+                    output: "exit code 1".to_string(),
+                    is_error: true,
+                },
+                Artifact::Image {
+                    media_type: Some("image/png".to_string()),
+                    path: None,
+                },
+            ]
+        );
+    }
 }
@@ -1,7 +1,10 @@
 use crate::claude::{parse_claude_line, parse_claude_session_line};
-use crate::config::ContrailConfig;
+use crate::config::{data_dir, ContrailConfig, CLAUDE_CODEX_SETUP_MARKER_REL};
+use crate::event_index::{history_key, EventIndex};
+use crate::log_lock::{repair_tail, LogLock};
+use crate::purge::Tombstones;
 use crate::sentry::Sentry;
-use crate::types::{Interaction, MasterLog};
+use crate::types::{Interaction, MasterLog, SCHEMA_VERSION};
 use anyhow::{Context, Result};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
@@ -977,7 +980,7 @@ fn expand_tilde(raw: &str) -> Result<PathBuf> {
 
 fn write_setup_marker(report: &SetupReport) -> Result<()> {
     let home = dirs::home_dir().context("could not resolve home directory")?;
-    let marker_path = home.join(".contrail/state/claude_codex_setup.json");
+    let marker_path = data_dir(&home).join(CLAUDE_CODEX_SETUP_MARKER_REL);
     if let Some(parent) = marker_path.parent() {
         fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
     }
@@ -1009,14 +1012,18 @@ struct HistoryIngestState {
     sentry: Sentry,
     existing: HashSet<u64>,
     writer: std::io::BufWriter<File>,
+    /// Declared after `writer`, so the lock outlives its final flush on drop.
+    _lock: LogLock,
 }
 
 impl HistoryIngestState {
     fn new() -> Result<Self> {
-        let config = ContrailConfig::from_env()?;
+        let config = ContrailConfig::load()?;
         if let Some(parent) = config.log_path.parent() {
             fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
         }
+        let lock = LogLock::acquire(&config.log_path)?;
+        repair_tail(&config.log_path)?;
         let existing = load_existing_history_keys(&config.log_path)?;
         let file = OpenOptions::new()
             .create(true)
@@ -1024,9 +1031,10 @@ impl HistoryIngestState {
             .open(&config.log_path)
             .with_context(|| format!("open {}", config.log_path.display()))?;
         Ok(Self {
-            sentry: Sentry::new(),
+            sentry: Sentry::from_config(&config)?,
             existing,
             writer: std::io::BufWriter::new(file),
+            _lock: lock,
         })
     }
 
@@ -1089,6 +1097,7 @@ impl HistoryIngestState {
             );
 
             let event = MasterLog {
+                schema_version: SCHEMA_VERSION,
                 event_id: Uuid::new_v4(),
                 timestamp,
                 source_tool: "claude-code".to_string(),
@@ -1100,7 +1109,7 @@ impl HistoryIngestState {
                     artifacts: None,
                 },
                 security_flags,
-                metadata: Value::Object(metadata),
+                metadata: metadata.into(),
             };
             if event.validate_schema().is_err() {
                 stats.errors += 1;
@@ -1125,43 +1134,16 @@ impl HistoryIngestState {
 }
 
 fn load_existing_history_keys(log_path: &Path) -> Result<HashSet<u64>> {
-    let mut out = HashSet::new();
-    if !log_path.exists() {
-        return Ok(out);
-    }
-    let file = File::open(log_path).with_context(|| format!("open {}", log_path.display()))?;
-    let reader = BufReader::new(file);
-    for line in reader.lines() {
-        let line = match line {
-            Ok(value) => value,
-            Err(_) => continue,
-        };
-        let json: Value = match serde_json::from_str(&line) {
-            Ok(value) => value,
-            Err(_) => continue,
-        };
-        let source = json
-            .get("source_tool")
-            .and_then(Value::as_str)
-            .unwrap_or("");
-        let session = json.get("session_id").and_then(Value::as_str).unwrap_or("");
-        let content = json
-            .pointer("/interaction/content")
-            .and_then(Value::as_str)
-            .unwrap_or("");
-        out.insert(dedupe_key(source, session, content));
-    }
-    Ok(out)
+    let mut keys = EventIndex::open_synced(log_path)
+        .and_then(|index| index.history_keys())
+        .with_context(|| format!("load existing events from {}", log_path.display()))?;
+    // Purged events count as recorded, so they are not imported again.
+    keys.extend(Tombstones::load(log_path)?.history_keys());
+    Ok(keys)
 }
 
 fn dedupe_key(source: &str, session: &str, content: &str) -> u64 {
-    let mut combined = String::with_capacity(source.len() + session.len() + content.len() + 2);
-    combined.push_str(source);
-    combined.push('\0');
-    combined.push_str(session);
-    combined.push('\0');
-    combined.push_str(content);
-    xxhash_rust::xxh3::xxh3_64(combined.as_bytes())
+    history_key(source, session, content)
 }
 
 // ---------------------------------------------------------------------------
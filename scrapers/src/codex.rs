@@ -1,4 +1,7 @@
-use crate::parse::{append_metrics, append_usage, extract_text, parse_timestamp_value, ParsedLine};
+use crate::parse::{
+    append_metrics, append_usage, clip_artifacts, extract_text, parse_timestamp_value, ParsedLine,
+};
+use crate::types::Artifact;
 use serde_json::{Map, Value};
 
 pub fn parse_codex_line(raw: &str) -> Option<ParsedLine> {
@@ -104,6 +107,7 @@ pub fn parse_codex_line(raw: &str) -> Option<ParsedLine> {
 
     let content = content_value
         .and_then(extract_text)
+        .or_else(|| turn_aborted_notice(&json))
         .or_else(|| fallback_content(&json))
         .unwrap_or_else(|| raw.to_string());
 
@@ -118,6 +122,7 @@ pub fn parse_codex_line(raw: &str) -> Option<ParsedLine> {
         session_id,
         project_context,
         metadata,
+        artifacts: clip_artifacts(extract_artifacts(&json)),
     })
 }
 
@@ -131,7 +136,9 @@ fn derive_role_override(json: &Value) -> Option<String> {
         if payload_type.eq_ignore_ascii_case("agent_message") {
             return Some("assistant".to_string());
         }
-        if payload_type.eq_ignore_ascii_case("token_count") {
+        if payload_type.eq_ignore_ascii_case("token_count")
+            || payload_type.eq_ignore_ascii_case("turn_aborted")
+        {
             return Some("system".to_string());
         }
     }
@@ -143,6 +150,19 @@ fn derive_role_override(json: &Value) -> Option<String> {
     None
 }
 
+/// `event_msg` / `turn_aborted`, written when the user stops a turn.
+fn turn_aborted_notice(json: &Value) -> Option<String> {
+    if json.pointer("/payload/type").and_then(Value::as_str)? != "turn_aborted" {
+        return None;
+    }
+    Some(
+        match json.pointer("/payload/reason").and_then(Value::as_str) {
+            Some(reason) => format!("Turn aborted: {reason}"),
+            None => "Turn aborted".to_string(),
+        },
+    )
+}
+
 fn fallback_content(json: &Value) -> Option<String> {
     let record_type = json.get("type").and_then(Value::as_str)?;
     if record_type.eq_ignore_ascii_case("session_meta") {
@@ -360,6 +380,169 @@ fn append_token_count_usage(meta: &mut Map<String, Value>, json: &Value) -> Opti
     ))
 }
 
+// ── Artifacts ───────────────────────────────────────────────────────────
+
+fn extract_artifacts(json: &Value) -> Vec<Artifact> {
+    // Rollout files wrap records as `{type: response_item | event_msg, payload}`;
+    // older transcripts put the item at the top level.
+    let item = match json.get("payload") {
+        Some(payload) if payload.get("type").is_some() => payload,
+        _ => json,
+    };
+    let Some(item_type) = item.get("type").and_then(Value::as_str) else {
+        return Vec::new();
+    };
+    let call_id = item
+        .get("call_id")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    match item_type {
+        "function_call" | "custom_tool_call" => {
+            let name = item
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown")
+                .to_string();
+            let input = item
+                .get("arguments")
+                .or_else(|| item.get("input"))
+                .map(value_to_text)
+                .unwrap_or_default();
+            let mut artifacts = Vec::new();
+            if let Some(patch) = find_patch_text(&input) {
+                artifacts.extend(split_apply_patch(&patch));
+            }
+            artifacts.insert(
+                0,
+                Artifact::ToolCall {
+                    call_id,
+                    name,
+                    input,
+                },
+            );
+            artifacts
+        }
+        "local_shell_call" => {
+            let action = item.get("action").cloned().unwrap_or(Value::Null);
+            vec![Artifact::ToolCall {
+                call_id,
+                name: "local_shell".to_string(),
+                input: action.to_string(),
+            }]
+        }
+        "function_call_output" | "custom_tool_call_output" => {
+            let raw = item.get("output").map(value_to_text).unwrap_or_default();
+            // Shell outputs are a JSON string: `{"output": "...", "metadata": {"exit_code": 1}}`.
+            let wrapped = serde_json::from_str::<Value>(&raw).ok();
+            let exit_code = wrapped
+                .as_ref()
+                .and_then(|v| v.pointer("/metadata/exit_code"))
+                .and_then(Value::as_i64);
+            let output = wrapped
+                .as_ref()
+                .and_then(|v| v.get("output"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or(raw);
+            vec![Artifact::ToolResult {
+                call_id,
+                output,
+                is_error: exit_code.is_some_and(|code| code != 0),
+            }]
+        }
+        "exec_command_end" => {
+            let Some(command) = item.get("command").map(command_line) else {
+                return Vec::new();
+            };
+            let output = ["aggregated_output", "formatted_output", "stdout"]
+                .iter()
+                .find_map(|key| item.get(*key).and_then(Value::as_str))
+                .filter(|s| !s.is_empty())
+                .map(str::to_string);
+            vec![Artifact::Command {
+                command,
+                exit_code: item.get("exit_code").and_then(Value::as_i64),
+                output,
+            }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn value_to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// `["bash", "-lc", "cargo test"]` reads as `cargo test`; other argv arrays are
+/// joined with spaces.
+fn command_line(value: &Value) -> String {
+    let Some(argv) = value.as_array() else {
+        return value_to_text(value);
+    };
+    let argv: Vec<&str> = argv.iter().filter_map(Value::as_str).collect();
+    match argv.as_slice() {
+        [shell, flag, script]
+            if matches!(*shell, "bash" | "zsh" | "sh") && matches!(*flag, "-lc" | "-c") =>
+        {
+            script.to_string()
+        }
+        _ => argv.join(" "),
+    }
+}
+
+/// The `apply_patch` envelope, whether passed raw (custom tool), as `{"input": ...}`
+/// or as the second element of a `["apply_patch", ...]` shell command.
+fn find_patch_text(input: &str) -> Option<String> {
+    const BEGIN: &str = "*** Begin Patch";
+    if input.trim_start().starts_with(BEGIN) {
+        return Some(input.to_string());
+    }
+    let args = serde_json::from_str::<Value>(input).ok()?;
+    let candidates = args.get("input").into_iter().chain(
+        args.get("command")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten(),
+    );
+    candidates
+        .filter_map(Value::as_str)
+        .find(|s| s.contains(BEGIN))
+        .map(str::to_string)
+}
+
+/// One [`Artifact::Patch`] per file section of an `apply_patch` envelope.
+fn split_apply_patch(patch: &str) -> Vec<Artifact> {
+    const HEADERS: [&str; 3] = ["*** Add File: ", "*** Update File: ", "*** Delete File: "];
+    let mut patches = Vec::new();
+    let mut current: Option<(String, String)> = None;
+    let mut flush = |current: &mut Option<(String, String)>| {
+        if let Some((path, diff)) = current.take() {
+            patches.push(Artifact::Patch {
+                path: Some(path),
+                diff,
+            });
+        }
+    };
+
+    for line in patch.lines() {
+        if let Some(path) = HEADERS.iter().find_map(|h| line.strip_prefix(h)) {
+            flush(&mut current);
+            current = Some((path.trim().to_string(), format!("{line}\n")));
+        } else if line.starts_with("*** End Patch") {
+            flush(&mut current);
+        } else if let Some((_, diff)) = current.as_mut() {
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    flush(&mut current);
+    patches
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -411,6 +594,15 @@ mod tests {
         assert_eq!(parsed.content, "hello from user");
     }
 
+    #[test]
+    fn parses_turn_aborted_as_system_notice() {
+        let raw =
+            r#"{"type":"event_msg","payload":{"type":"turn_aborted","reason":"interrupted"}}"#;
+        let parsed = parse_codex_line(raw).expect("should parse");
+        assert_eq!(parsed.role, "system");
+        assert_eq!(parsed.content, "Turn aborted: interrupted");
+    }
+
     #[test]
     fn parses_token_count_event_into_usage_metadata() {
         let raw = r#"{
@@ -518,4 +710,93 @@ mod tests {
             Some("019c31ca-c7b9-73d2-8707-61eb9ae9e0c1")
         );
     }
+
+    #[test]
+    fn captures_function_calls_and_outputs_as_artifacts() {
+        let call = r#"{
+            "timestamp": "2025-12-01T10:00:00Z",
+            "type": "response_item",
+            "payload": {
+                "type": "function_call",
+                "name": "shell",
+                "arguments": "{\"command\":[\"bash\",\"-lc\",\"cargo test\"]}",
+                "call_id": "call_1"
+            }
+        }"#;
+        let parsed = parse_codex_line(call).expect("should parse");
+        assert_eq!(
+            parsed.artifacts,
+            vec![Artifact::ToolCall {
+                call_id: Some("call_1".to_string()),
+                name: "shell".to_string(),
+                input: r#"{"command":["bash","-lc","cargo test"]}"#.to_string(),
+            }]
+        );
+
+        let output = r#"{
+            "timestamp": "2025-12-01T10:00:01Z",
+            "type": "response_item",
+            "payload": {
+                "type": "function_call_output",
+                "call_id": "call_1",
+                "output": "{\"output\":\"1 failed\",\"metadata\":{\"exit_code\":101}}"
+            }
+        }"#;
+        let parsed = parse_codex_line(output).expect("should parse");
+        assert_eq!(
+            parsed.artifacts,
+            vec![Artifact::ToolResult {
+                call_id: Some("call_1".to_string()),
+                output: "1 failed".to_string(),
+                is_error: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn splits_apply_patch_into_per_file_patches() {
+        let raw = r#"{
+            "type": "response_item",
+            "payload": {
+                "type": "custom_tool_call",
+                "name": "apply_patch",
+                "call_id": "call_2",
+                "input": "*** Begin Patch\n*** Update File: src/lib.rs\n@@\n-old\n+new\n*** Add File: NOTES.md\n+hello\n*** End Patch"
+            }
+        }"#;
+        let parsed = parse_codex_line(raw).expect("should parse");
+        assert_eq!(parsed.artifacts.len(), 3);
+        assert_eq!(parsed.artifacts[0].kind(), "tool_call");
+        assert_eq!(
+            parsed.artifacts[1],
+            Artifact::Patch {
+                path: Some("src/lib.rs".to_string()),
+                diff: "*** Update File: src/lib.rs\n@@\n-old\n+new\n".to_string(),
+            }
+        );
+        assert_eq!(parsed.artifacts[2].path(), Some("NOTES.md"));
+    }
+
+    #[test]
+    fn captures_exec_command_end_with_exit_code() {
+        let raw = r#"{
+            "type": "event_msg",
+            "payload": {
+                "type": "exec_command_end",
+                "call_id": "call_3",
+                "command": ["bash", "-lc", "ls missing"],
+                "aggregated_output": "ls: missing: No such file or directory",
+                "exit_code": 2
+            }
+        }"#;
+        let parsed = parse_codex_line(raw).expect("should parse");
+        assert_eq!(
+            parsed.artifacts,
+            vec![Artifact::Command {
+                command: "ls missing".to_string(),
+                exit_code: Some(2),
+                output: Some("ls: missing: No such file or directory".to_string()),
+            }]
+        );
+    }
 }
@@ -1,14 +1,81 @@
+//! Effective Contrail configuration, shared by every binary.
+//!
+//! Values come from, in increasing priority: built-in defaults,
+//! `~/.contrail/config.toml` (or `CONTRAIL_CONFIG`), and `CONTRAIL_*` environment
+//! variables. [`ContrailConfig::load_with_sources`] also records where each value
+//! came from, for `contrail config show`.
+//!
+//! ```toml
+//! [log]
+//! path = "~/.local/share/contrail/logs/master_log.jsonl"
+//! fsync_ms = 1000
+//!
+//! [retention]
+//! max_bytes = 524288000
+//! keep_files = 5
+//! max_age_days = 0       # 0 keeps archives regardless of age
+//! rotate = "size"        # or "daily" / "monthly"
+//!
+//! [redaction]
+//! rules = "~/.contrail/redaction.toml"
+//! vault_dir = "~/.contrail/vault"
+//!
+//! [bind]
+//! dashboard = "127.0.0.1:3000"
+//! analysis = "127.0.0.1:3210"
+//!
+//! [search]
+//! index_dir = "~/.local/share/contrail/index"
+//! embedder = "hashing"   # or "http": an OpenAI-compatible embeddings endpoint
+//! embedding_url = "http://127.0.0.1:11434/v1/embeddings"
+//! embedding_model = "nomic-embed-text"
+//!
+//! [llm]
+//! provider = "openai"    # or "anthropic" / "mock"
+//! base_url = "https://api.openai.com/v1"   # e.g. http://127.0.0.1:11434/v1 for Ollama
+//! model = "gpt-5.1"
+//!
+//! [sources.cursor]
+//! enabled = true
+//! storage = "~/.config/Cursor/User/workspaceStorage"
+//! silence_secs = 5
+//!
+//! [sources.codex]
+//! root = "~/.codex/sessions"
+//!
+//! [sources.claude]
+//! history = "~/.claude/history.jsonl"
+//! projects = "~/.claude/projects"
+//!
+//! [sources.gemini]
+//! root = "~/.gemini/tmp"
+//!
+//! [sources.antigravity]
+//! enabled = false
+//! ```
+
+use crate::rotation::{RotationLimits, RotationPolicy};
+use crate::semantic::EmbedderKind;
 use anyhow::{Context, Result};
+use serde::Deserialize;
 use std::env;
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// ── Default path constants ──────────────────────────────────────────────
+
+/// Config file relative to home.
+const DEFAULT_CONFIG_FILE_REL: &str = ".contrail/config.toml";
 
-// ── Default path constants (macOS) ──────────────────────────────────────
+/// Contrail's data directory before it moved to `$XDG_DATA_HOME`, relative to home.
+const LEGACY_DATA_DIR_REL: &str = ".contrail";
 
-/// Master log file relative to home.
-const DEFAULT_LOG_REL: &str = ".contrail/logs/master_log.jsonl";
+/// Master log file relative to [`data_dir`].
+const DEFAULT_LOG_REL: &str = "logs/master_log.jsonl";
 
-/// Cursor workspace storage relative to home.
-const DEFAULT_CURSOR_STORAGE_REL: &str = "Library/Application Support/Cursor/User/workspaceStorage";
+/// Cursor workspace storage relative to [`cursor_user_dir`].
+const CURSOR_WORKSPACE_STORAGE_REL: &str = "workspaceStorage";
 
 /// Codex sessions directory relative to home.
 const DEFAULT_CODEX_ROOT_REL: &str = ".codex/sessions";
@@ -19,19 +86,125 @@ const DEFAULT_CLAUDE_HISTORY_REL: &str = ".claude/history.jsonl";
 /// Claude per-project session files relative to home.
 const DEFAULT_CLAUDE_PROJECTS_REL: &str = ".claude/projects";
 
+/// Gemini CLI per-project chat directories relative to home.
+const DEFAULT_GEMINI_ROOT_REL: &str = ".gemini/tmp";
+
 /// Antigravity brain directory relative to home.
 const DEFAULT_ANTIGRAVITY_BRAIN_REL: &str = ".gemini/antigravity/brain";
 
-/// History import completion marker relative to home.
-pub const HISTORY_IMPORT_MARKER_REL: &str = ".contrail/state/history_import_done.json";
+/// Antigravity conversation protobufs relative to home.
+const DEFAULT_ANTIGRAVITY_CONVERSATIONS_REL: &str = ".gemini/antigravity/conversations";
+
+/// History import completion marker relative to [`data_dir`].
+pub const HISTORY_IMPORT_MARKER_REL: &str = "state/history_import_done.json";
+
+/// Claude/Codex setup import marker relative to [`data_dir`].
+pub const CLAUDE_CODEX_SETUP_MARKER_REL: &str = "state/claude_codex_setup.json";
+
+/// Watcher checkpoints (file offsets) relative to [`data_dir`].
+pub const WATCHER_STATE_DIR_REL: &str = "state/watchers";
+
+/// Per-remote `contrail sync` progress relative to [`data_dir`].
+pub const SYNC_STATE_DIR_REL: &str = "state/sync";
+
+/// Analysis memories relative to home.
+const DEFAULT_ANALYSIS_MEMORIES_REL: &str = ".contrail/analysis/memories.jsonl";
+
+/// Analysis memory blocks relative to home.
+const DEFAULT_ANALYSIS_MEMORY_BLOCKS_REL: &str = ".contrail/analysis/memory_blocks.json";
+
+/// Search indexes and the embedding cache relative to [`data_dir`].
+const DEFAULT_SEARCH_INDEX_DIR_REL: &str = "index";
+
+/// User redaction rules relative to home.
+const DEFAULT_REDACTION_CONFIG_REL: &str = ".contrail/redaction.toml";
+
+/// Encrypted originals for reversible redaction relative to home.
+const DEFAULT_VAULT_DIR_REL: &str = ".contrail/vault";
 
 // ── Default silence thresholds (seconds) ────────────────────────────────
 
 const DEFAULT_CURSOR_SILENCE_SECS: u64 = 5;
 const DEFAULT_CODEX_SILENCE_SECS: u64 = 3;
 const DEFAULT_CLAUDE_SILENCE_SECS: u64 = 5;
+const DEFAULT_GEMINI_SILENCE_SECS: u64 = 5;
 const DEFAULT_LOG_MAX_BYTES: u64 = 524_288_000;
 const DEFAULT_LOG_KEEP_FILES: usize = 5;
+const DEFAULT_LOG_MAX_AGE_DAYS: u64 = 0;
+
+/// Longest the daemon leaves appended lines un-fsynced; 0 syncs every batch.
+const DEFAULT_LOG_FSYNC_MS: u64 = 1000;
+
+// ── Default bind addresses ──────────────────────────────────────────────
+
+const DEFAULT_DASHBOARD_BIND: &str = "127.0.0.1:3000";
+const DEFAULT_ANALYSIS_BIND: &str = "127.0.0.1:3210";
+
+// ── Default embedding endpoint (Ollama) ─────────────────────────────────
+
+const DEFAULT_EMBEDDING_URL: &str = "http://127.0.0.1:11434/v1/embeddings";
+const DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+
+// ── Default LLM endpoints ────────────────────────────────────────────────
+
+const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_OPENAI_MODEL: &str = "gpt-5.1";
+const DEFAULT_ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+const DEFAULT_ANTHROPIC_MODEL: &str = "claude-sonnet-4-5";
+
+/// Which API the analysis service's LLM features talk to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmProviderKind {
+    /// An OpenAI-compatible `/chat/completions` endpoint, including local servers.
+    #[default]
+    OpenAi,
+    /// Anthropic's Messages API.
+    Anthropic,
+    /// Canned, deterministic replies; no network.
+    Mock,
+}
+
+impl LlmProviderKind {
+    fn default_base_url(self) -> &'static str {
+        match self {
+            Self::OpenAi => DEFAULT_OPENAI_BASE_URL,
+            Self::Anthropic => DEFAULT_ANTHROPIC_BASE_URL,
+            Self::Mock => "",
+        }
+    }
+
+    fn default_model(self) -> &'static str {
+        match self {
+            Self::OpenAi => DEFAULT_OPENAI_MODEL,
+            Self::Anthropic => DEFAULT_ANTHROPIC_MODEL,
+            Self::Mock => "mock",
+        }
+    }
+}
+
+impl FromStr for LlmProviderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAi),
+            "anthropic" => Ok(Self::Anthropic),
+            "mock" => Ok(Self::Mock),
+            other => anyhow::bail!("unknown LLM provider {other:?}"),
+        }
+    }
+}
+
+impl fmt::Display for LlmProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::OpenAi => "openai",
+            Self::Anthropic => "anthropic",
+            Self::Mock => "mock",
+        })
+    }
+}
 
 // ── Config struct ───────────────────────────────────────────────────────
 
@@ -42,103 +215,851 @@ pub struct ContrailConfig {
     pub codex_root: PathBuf,
     pub claude_history: PathBuf,
     pub claude_projects: PathBuf,
+    pub gemini_root: PathBuf,
     pub antigravity_brain: PathBuf,
+    pub antigravity_conversations: PathBuf,
+    pub redaction_config: PathBuf,
+    pub vault_dir: PathBuf,
     pub enable_cursor: bool,
     pub enable_codex: bool,
     pub enable_claude: bool,
+    pub enable_gemini: bool,
     pub enable_antigravity: bool,
     pub cursor_silence_secs: u64,
     pub codex_silence_secs: u64,
     pub claude_silence_secs: u64,
+    pub gemini_silence_secs: u64,
     pub log_max_bytes: u64,
     pub log_keep_files: usize,
+    pub log_max_age_days: u64,
+    pub log_rotate: RotationPolicy,
+    pub log_fsync_ms: u64,
+    pub dashboard_bind: String,
+    pub analysis_bind: String,
+    pub search_index_dir: PathBuf,
+    pub analysis_memories: PathBuf,
+    pub analysis_memory_blocks: PathBuf,
+    pub search_embedder: EmbedderKind,
+    pub embedding_url: String,
+    pub embedding_model: String,
+    pub llm_provider: LlmProviderKind,
+    /// Base URL the provider's path (`/chat/completions`, `/messages`) is appended to.
+    pub llm_base_url: String,
+    pub llm_model: String,
+}
+
+/// Where an effective value came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueSource {
+    Default,
+    File,
+    Env(&'static str),
+}
+
+impl fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueSource::Default => f.write_str("default"),
+            ValueSource::File => f.write_str("config file"),
+            ValueSource::Env(var) => write!(f, "env {var}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConfigValue {
+    /// Dotted config-file key, e.g. `sources.cursor.storage`.
+    pub key: &'static str,
+    pub value: String,
+    pub source: ValueSource,
+}
+
+pub struct LoadedConfig {
+    pub config: ContrailConfig,
+    pub file_path: PathBuf,
+    pub file_found: bool,
+    pub values: Vec<ConfigValue>,
 }
 
 impl ContrailConfig {
-    pub fn from_env() -> Result<Self> {
+    /// The shared loader: defaults, then the config file, then env overrides.
+    pub fn load() -> Result<Self> {
+        Ok(Self::load_with_sources()?.config)
+    }
+
+    /// Built-in defaults under `home`, ignoring any config file and the environment.
+    pub fn defaults(home: &Path) -> Self {
+        let (config, _) =
+            resolve(home, FileConfig::default(), |_| None).expect("defaults always resolve");
+        config
+    }
+
+    pub fn load_with_sources() -> Result<LoadedConfig> {
         let home = dirs::home_dir().context("could not resolve home directory")?;
+        let env_lookup = |key: &str| env::var(key).ok();
+        let file_path = match env_lookup("CONTRAIL_CONFIG") {
+            Some(val) if !val.trim().is_empty() => expand_tilde(&val, &home),
+            _ => home.join(DEFAULT_CONFIG_FILE_REL),
+        };
 
-        Ok(Self {
-            log_path: env_path(
-                "CONTRAIL_LOG_PATH",
-                home.join(DEFAULT_LOG_REL),
-                home.as_path(),
-            ),
-            cursor_storage: env_path(
-                "CONTRAIL_CURSOR_STORAGE",
-                home.join(DEFAULT_CURSOR_STORAGE_REL),
-                home.as_path(),
-            ),
-            codex_root: env_path(
-                "CONTRAIL_CODEX_ROOT",
-                home.join(DEFAULT_CODEX_ROOT_REL),
-                home.as_path(),
-            ),
-            claude_history: env_path(
-                "CONTRAIL_CLAUDE_HISTORY",
-                home.join(DEFAULT_CLAUDE_HISTORY_REL),
-                home.as_path(),
-            ),
-            claude_projects: env_path(
-                "CONTRAIL_CLAUDE_PROJECTS",
-                home.join(DEFAULT_CLAUDE_PROJECTS_REL),
-                home.as_path(),
-            ),
-            antigravity_brain: env_path(
-                "CONTRAIL_ANTIGRAVITY_BRAIN",
-                home.join(DEFAULT_ANTIGRAVITY_BRAIN_REL),
-                home.as_path(),
-            ),
-            enable_cursor: env_bool("CONTRAIL_ENABLE_CURSOR", true),
-            enable_codex: env_bool("CONTRAIL_ENABLE_CODEX", true),
-            enable_claude: env_bool("CONTRAIL_ENABLE_CLAUDE", true),
-            enable_antigravity: env_bool("CONTRAIL_ENABLE_ANTIGRAVITY", true),
-            cursor_silence_secs: env_u64(
-                "CONTRAIL_CURSOR_SILENCE_SECS",
-                DEFAULT_CURSOR_SILENCE_SECS,
-            ),
-            codex_silence_secs: env_u64("CONTRAIL_CODEX_SILENCE_SECS", DEFAULT_CODEX_SILENCE_SECS),
-            claude_silence_secs: env_u64(
-                "CONTRAIL_CLAUDE_SILENCE_SECS",
-                DEFAULT_CLAUDE_SILENCE_SECS,
-            ),
-            log_max_bytes: env_u64("CONTRAIL_LOG_MAX_BYTES", DEFAULT_LOG_MAX_BYTES),
-            log_keep_files: env_usize("CONTRAIL_LOG_KEEP_FILES", DEFAULT_LOG_KEEP_FILES),
+        let (file, file_found) = match std::fs::read_to_string(&file_path) {
+            Ok(raw) => {
+                let file: FileConfig = toml::from_str(&raw)
+                    .with_context(|| format!("parse config file {}", file_path.display()))?;
+                (file, true)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                (FileConfig::default(), false)
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("read config file {}", file_path.display()))
+            }
+        };
+
+        let (config, values) = resolve(&home, file, env_lookup)?;
+        Ok(LoadedConfig {
+            config,
+            file_path,
+            file_found,
+            values,
         })
     }
-}
 
-fn env_path(key: &str, default: PathBuf, home: &std::path::Path) -> PathBuf {
-    match env::var(key) {
-        Ok(val) if !val.trim().is_empty() => expand_tilde(&val, home),
-        _ => default,
+    pub fn rotation_limits(&self) -> RotationLimits {
+        RotationLimits {
+            policy: self.log_rotate,
+            max_bytes: self.log_max_bytes,
+            keep_files: self.log_keep_files,
+            max_age_days: self.log_max_age_days,
+        }
     }
 }
 
-fn env_bool(key: &str, default: bool) -> bool {
-    match env::var(key) {
-        Ok(val) => matches!(val.to_lowercase().as_str(), "1" | "true" | "yes" | "on"),
-        Err(_) => default,
+// ── Config file ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    #[serde(default)]
+    log: LogSection,
+    #[serde(default)]
+    retention: RetentionSection,
+    #[serde(default)]
+    redaction: RedactionSection,
+    #[serde(default)]
+    bind: BindSection,
+    #[serde(default)]
+    search: SearchSection,
+    #[serde(default)]
+    analysis: AnalysisSection,
+    #[serde(default)]
+    llm: LlmSection,
+    #[serde(default)]
+    sources: SourcesSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogSection {
+    path: Option<String>,
+    fsync_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RetentionSection {
+    max_bytes: Option<u64>,
+    keep_files: Option<usize>,
+    max_age_days: Option<u64>,
+    rotate: Option<RotationPolicy>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RedactionSection {
+    rules: Option<String>,
+    vault_dir: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindSection {
+    dashboard: Option<String>,
+    analysis: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchSection {
+    index_dir: Option<String>,
+    embedder: Option<EmbedderKind>,
+    embedding_url: Option<String>,
+    embedding_model: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnalysisSection {
+    memories: Option<String>,
+    memory_blocks: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LlmSection {
+    provider: Option<LlmProviderKind>,
+    base_url: Option<String>,
+    model: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourcesSection {
+    #[serde(default)]
+    cursor: CursorSection,
+    #[serde(default)]
+    codex: CodexSection,
+    #[serde(default)]
+    claude: ClaudeSection,
+    #[serde(default)]
+    gemini: GeminiSection,
+    #[serde(default)]
+    antigravity: AntigravitySection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CursorSection {
+    enabled: Option<bool>,
+    storage: Option<String>,
+    silence_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CodexSection {
+    enabled: Option<bool>,
+    root: Option<String>,
+    silence_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClaudeSection {
+    enabled: Option<bool>,
+    history: Option<String>,
+    projects: Option<String>,
+    silence_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GeminiSection {
+    enabled: Option<bool>,
+    root: Option<String>,
+    silence_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AntigravitySection {
+    enabled: Option<bool>,
+    brain: Option<String>,
+    conversations: Option<String>,
+}
+
+// ── Resolution ──────────────────────────────────────────────────────────
+
+fn resolve(
+    home: &Path,
+    file: FileConfig,
+    env_lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(ContrailConfig, Vec<ConfigValue>)> {
+    let mut r = Resolver {
+        home,
+        env_lookup,
+        values: Vec::new(),
+    };
+    let sources = file.sources;
+    // Resolved up front: the provider picks the base URL and model defaults.
+    let llm_provider = r.choice(
+        "llm.provider",
+        "CONTRAIL_LLM_PROVIDER",
+        file.llm.provider,
+        LlmProviderKind::default(),
+    )?;
+    let llm_base_url = r.text(
+        "llm.base_url",
+        "CONTRAIL_LLM_BASE_URL",
+        file.llm.base_url,
+        llm_provider.default_base_url(),
+    );
+    // `OPENAI_MODEL` stands in for an unset model when talking to OpenAI.
+    let openai_model = match llm_provider {
+        LlmProviderKind::OpenAi if file.llm.model.is_none() => r
+            .env("OPENAI_MODEL")
+            .filter(|_| r.env("CONTRAIL_LLM_MODEL").is_none()),
+        _ => None,
+    };
+    let llm_model = if let Some(model) = openai_model {
+        r.record("llm.model", model.clone(), ValueSource::Env("OPENAI_MODEL"));
+        model
+    } else {
+        r.text(
+            "llm.model",
+            "CONTRAIL_LLM_MODEL",
+            file.llm.model,
+            llm_provider.default_model(),
+        )
+    };
+
+    let data = data_dir_from(home, r.env("XDG_DATA_HOME"));
+    let config = ContrailConfig {
+        log_path: r.path(
+            "log.path",
+            "CONTRAIL_LOG_PATH",
+            file.log.path,
+            data.join(DEFAULT_LOG_REL),
+        ),
+        cursor_storage: r.path(
+            "sources.cursor.storage",
+            "CONTRAIL_CURSOR_STORAGE",
+            sources.cursor.storage,
+            cursor_user_dir(home).join(CURSOR_WORKSPACE_STORAGE_REL),
+        ),
+        codex_root: r.path(
+            "sources.codex.root",
+            "CONTRAIL_CODEX_ROOT",
+            sources.codex.root,
+            home.join(DEFAULT_CODEX_ROOT_REL),
+        ),
+        claude_history: r.path(
+            "sources.claude.history",
+            "CONTRAIL_CLAUDE_HISTORY",
+            sources.claude.history,
+            home.join(DEFAULT_CLAUDE_HISTORY_REL),
+        ),
+        claude_projects: r.path(
+            "sources.claude.projects",
+            "CONTRAIL_CLAUDE_PROJECTS",
+            sources.claude.projects,
+            home.join(DEFAULT_CLAUDE_PROJECTS_REL),
+        ),
+        gemini_root: r.path(
+            "sources.gemini.root",
+            "CONTRAIL_GEMINI_ROOT",
+            sources.gemini.root,
+            home.join(DEFAULT_GEMINI_ROOT_REL),
+        ),
+        antigravity_brain: r.path(
+            "sources.antigravity.brain",
+            "CONTRAIL_ANTIGRAVITY_BRAIN",
+            sources.antigravity.brain,
+            home.join(DEFAULT_ANTIGRAVITY_BRAIN_REL),
+        ),
+        antigravity_conversations: r.path(
+            "sources.antigravity.conversations",
+            "CONTRAIL_ANTIGRAVITY_CONVERSATIONS",
+            sources.antigravity.conversations,
+            home.join(DEFAULT_ANTIGRAVITY_CONVERSATIONS_REL),
+        ),
+        redaction_config: r.path(
+            "redaction.rules",
+            "CONTRAIL_REDACTION_CONFIG",
+            file.redaction.rules,
+            home.join(DEFAULT_REDACTION_CONFIG_REL),
+        ),
+        vault_dir: r.path(
+            "redaction.vault_dir",
+            "CONTRAIL_VAULT_DIR",
+            file.redaction.vault_dir,
+            home.join(DEFAULT_VAULT_DIR_REL),
+        ),
+        enable_cursor: r.flag(
+            "sources.cursor.enabled",
+            "CONTRAIL_ENABLE_CURSOR",
+            sources.cursor.enabled,
+            true,
+        ),
+        enable_codex: r.flag(
+            "sources.codex.enabled",
+            "CONTRAIL_ENABLE_CODEX",
+            sources.codex.enabled,
+            true,
+        ),
+        enable_claude: r.flag(
+            "sources.claude.enabled",
+            "CONTRAIL_ENABLE_CLAUDE",
+            sources.claude.enabled,
+            true,
+        ),
+        enable_gemini: r.flag(
+            "sources.gemini.enabled",
+            "CONTRAIL_ENABLE_GEMINI",
+            sources.gemini.enabled,
+            true,
+        ),
+        enable_antigravity: r.flag(
+            "sources.antigravity.enabled",
+            "CONTRAIL_ENABLE_ANTIGRAVITY",
+            sources.antigravity.enabled,
+            true,
+        ),
+        cursor_silence_secs: r.number(
+            "sources.cursor.silence_secs",
+            "CONTRAIL_CURSOR_SILENCE_SECS",
+            sources.cursor.silence_secs,
+            DEFAULT_CURSOR_SILENCE_SECS,
+        )?,
+        codex_silence_secs: r.number(
+            "sources.codex.silence_secs",
+            "CONTRAIL_CODEX_SILENCE_SECS",
+            sources.codex.silence_secs,
+            DEFAULT_CODEX_SILENCE_SECS,
+        )?,
+        claude_silence_secs: r.number(
+            "sources.claude.silence_secs",
+            "CONTRAIL_CLAUDE_SILENCE_SECS",
+            sources.claude.silence_secs,
+            DEFAULT_CLAUDE_SILENCE_SECS,
+        )?,
+        gemini_silence_secs: r.number(
+            "sources.gemini.silence_secs",
+            "CONTRAIL_GEMINI_SILENCE_SECS",
+            sources.gemini.silence_secs,
+            DEFAULT_GEMINI_SILENCE_SECS,
+        )?,
+        log_max_bytes: r.number(
+            "retention.max_bytes",
+            "CONTRAIL_LOG_MAX_BYTES",
+            file.retention.max_bytes,
+            DEFAULT_LOG_MAX_BYTES,
+        )?,
+        log_keep_files: r.number(
+            "retention.keep_files",
+            "CONTRAIL_LOG_KEEP_FILES",
+            file.retention.keep_files,
+            DEFAULT_LOG_KEEP_FILES,
+        )?,
+        log_max_age_days: r.number(
+            "retention.max_age_days",
+            "CONTRAIL_LOG_MAX_AGE_DAYS",
+            file.retention.max_age_days,
+            DEFAULT_LOG_MAX_AGE_DAYS,
+        )?,
+        log_rotate: r.choice(
+            "retention.rotate",
+            "CONTRAIL_LOG_ROTATE",
+            file.retention.rotate,
+            RotationPolicy::default(),
+        )?,
+        log_fsync_ms: r.number(
+            "log.fsync_ms",
+            "CONTRAIL_LOG_FSYNC_MS",
+            file.log.fsync_ms,
+            DEFAULT_LOG_FSYNC_MS,
+        )?,
+        dashboard_bind: r.text(
+            "bind.dashboard",
+            "DASHBOARD_BIND",
+            file.bind.dashboard,
+            DEFAULT_DASHBOARD_BIND,
+        ),
+        analysis_bind: r.text(
+            "bind.analysis",
+            "ANALYSIS_BIND",
+            file.bind.analysis,
+            DEFAULT_ANALYSIS_BIND,
+        ),
+        search_index_dir: r.path(
+            "search.index_dir",
+            "CONTRAIL_INDEX_DIR",
+            file.search.index_dir,
+            data.join(DEFAULT_SEARCH_INDEX_DIR_REL),
+        ),
+        analysis_memories: r.path(
+            "analysis.memories",
+            "CONTRAIL_MEMORY_PATH",
+            file.analysis.memories,
+            home.join(DEFAULT_ANALYSIS_MEMORIES_REL),
+        ),
+        analysis_memory_blocks: r.path(
+            "analysis.memory_blocks",
+            "CONTRAIL_MEMORY_BLOCKS_PATH",
+            file.analysis.memory_blocks,
+            home.join(DEFAULT_ANALYSIS_MEMORY_BLOCKS_REL),
+        ),
+        search_embedder: r.choice(
+            "search.embedder",
+            "CONTRAIL_EMBEDDER",
+            file.search.embedder,
+            EmbedderKind::default(),
+        )?,
+        embedding_url: r.text(
+            "search.embedding_url",
+            "CONTRAIL_EMBEDDING_URL",
+            file.search.embedding_url,
+            DEFAULT_EMBEDDING_URL,
+        ),
+        embedding_model: r.text(
+            "search.embedding_model",
+            "CONTRAIL_EMBEDDING_MODEL",
+            file.search.embedding_model,
+            DEFAULT_EMBEDDING_MODEL,
+        ),
+        llm_provider,
+        llm_base_url,
+        llm_model,
+    };
+    Ok((config, r.values))
+}
+
+struct Resolver<'a, E> {
+    home: &'a Path,
+    env_lookup: E,
+    values: Vec<ConfigValue>,
+}
+
+impl<E: Fn(&str) -> Option<String>> Resolver<'_, E> {
+    fn env(&self, var: &str) -> Option<String> {
+        (self.env_lookup)(var).filter(|val| !val.trim().is_empty())
+    }
+
+    fn record(&mut self, key: &'static str, value: String, source: ValueSource) {
+        self.values.push(ConfigValue { key, value, source });
+    }
+
+    fn path(
+        &mut self,
+        key: &'static str,
+        var: &'static str,
+        file: Option<String>,
+        default: PathBuf,
+    ) -> PathBuf {
+        let (path, source) = if let Some(val) = self.env(var) {
+            (expand_tilde(&val, self.home), ValueSource::Env(var))
+        } else if let Some(val) = file {
+            (expand_tilde(&val, self.home), ValueSource::File)
+        } else {
+            (default, ValueSource::Default)
+        };
+        self.record(key, path.display().to_string(), source);
+        path
+    }
+
+    fn flag(
+        &mut self,
+        key: &'static str,
+        var: &'static str,
+        file: Option<bool>,
+        default: bool,
+    ) -> bool {
+        let (value, source) = if let Some(val) = self.env(var) {
+            (
+                matches!(
+                    val.trim().to_lowercase().as_str(),
+                    "1" | "true" | "yes" | "on"
+                ),
+                ValueSource::Env(var),
+            )
+        } else if let Some(val) = file {
+            (val, ValueSource::File)
+        } else {
+            (default, ValueSource::Default)
+        };
+        self.record(key, value.to_string(), source);
+        value
+    }
+
+    /// Numbers parse like any other choice, so a typo is an error rather than
+    /// a silent fallback.
+    fn number<T: FromStr + fmt::Display>(
+        &mut self,
+        key: &'static str,
+        var: &'static str,
+        file: Option<T>,
+        default: T,
+    ) -> Result<T>
+    where
+        T::Err: fmt::Display,
+    {
+        self.choice(key, var, file, default)
+    }
+
+    /// An env value that doesn't parse is an error rather than silently falling back.
+    fn choice<T: FromStr + fmt::Display>(
+        &mut self,
+        key: &'static str,
+        var: &'static str,
+        file: Option<T>,
+        default: T,
+    ) -> Result<T>
+    where
+        T::Err: fmt::Display,
+    {
+        let (value, source) = if let Some(val) = self.env(var) {
+            let parsed = val
+                .trim()
+                .parse::<T>()
+                .map_err(|e| anyhow::anyhow!("invalid {var}: {e}"))?;
+            (parsed, ValueSource::Env(var))
+        } else if let Some(val) = file {
+            (val, ValueSource::File)
+        } else {
+            (default, ValueSource::Default)
+        };
+        self.record(key, value.to_string(), source);
+        Ok(value)
+    }
+
+    fn text(
+        &mut self,
+        key: &'static str,
+        var: &'static str,
+        file: Option<String>,
+        default: &str,
+    ) -> String {
+        let (value, source) = if let Some(val) = self.env(var) {
+            (val, ValueSource::Env(var))
+        } else if let Some(val) = file {
+            (val, ValueSource::File)
+        } else {
+            (default.to_string(), ValueSource::Default)
+        };
+        self.record(key, value.clone(), source);
+        value
     }
 }
 
-fn env_u64(key: &str, default: u64) -> u64 {
-    match env::var(key) {
-        Ok(val) => val.parse::<u64>().unwrap_or(default),
-        Err(_) => default,
+/// Per-user application config root: `~/Library/Application Support` on macOS,
+/// `%APPDATA%` on Windows, and `$XDG_CONFIG_HOME` (default `~/.config`) elsewhere.
+pub fn platform_config_dir(home: &Path) -> PathBuf {
+    if cfg!(target_os = "macos") {
+        return home.join("Library/Application Support");
     }
+    if cfg!(windows) {
+        return dirs::config_dir().unwrap_or_else(|| home.join("AppData/Roaming"));
+    }
+    match env::var("XDG_CONFIG_HOME") {
+        Ok(val) if Path::new(&val).is_absolute() => PathBuf::from(val),
+        _ => home.join(".config"),
+    }
+}
+
+/// Contrail's data directory (log, state, indexes): `~/.contrail` on macOS and
+/// Windows, and `$XDG_DATA_HOME/contrail` (default `~/.local/share/contrail`)
+/// elsewhere. An install that already keeps its log in `~/.contrail` stays there.
+pub fn data_dir(home: &Path) -> PathBuf {
+    data_dir_from(home, env::var("XDG_DATA_HOME").ok())
 }
 
-fn env_usize(key: &str, default: usize) -> usize {
-    match env::var(key) {
-        Ok(val) => val.parse::<usize>().unwrap_or(default),
-        Err(_) => default,
+fn data_dir_from(home: &Path, xdg_data_home: Option<String>) -> PathBuf {
+    let legacy = home.join(LEGACY_DATA_DIR_REL);
+    if cfg!(target_os = "macos") || cfg!(windows) {
+        return legacy;
     }
+    let base = match xdg_data_home {
+        Some(val) if Path::new(&val).is_absolute() => PathBuf::from(val),
+        _ => home.join(".local/share"),
+    };
+    let dir = base.join("contrail");
+    if !dir.exists() && legacy.join("logs").is_dir() {
+        return legacy;
+    }
+    dir
+}
+
+/// Cursor's `User` directory (workspace and global storage live under it).
+pub fn cursor_user_dir(home: &Path) -> PathBuf {
+    platform_config_dir(home).join("Cursor/User")
 }
 
-fn expand_tilde(input: &str, home: &std::path::Path) -> PathBuf {
+fn expand_tilde(input: &str, home: &Path) -> PathBuf {
     if let Some(rest) = input.strip_prefix("~/") {
         return home.join(rest);
     }
     PathBuf::from(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn try_resolve_with(
+        toml_src: &str,
+        env: &[(&str, &str)],
+    ) -> Result<(ContrailConfig, Vec<ConfigValue>)> {
+        let file: FileConfig = toml::from_str(toml_src).unwrap();
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        resolve(Path::new("/home/me"), file, |key| env.get(key).cloned())
+    }
+
+    fn resolve_with(toml_src: &str, env: &[(&str, &str)]) -> (ContrailConfig, Vec<ConfigValue>) {
+        try_resolve_with(toml_src, env).unwrap()
+    }
+
+    fn source_of(values: &[ConfigValue], key: &str) -> ValueSource {
+        values.iter().find(|v| v.key == key).unwrap().source
+    }
+
+    #[test]
+    fn env_overrides_file_which_overrides_defaults() {
+        let (config, values) = resolve_with(
+            r#"
+            [log]
+            path = "~/logs/master_log.jsonl"
+            fsync_ms = 0
+
+            [retention]
+            max_bytes = 1000
+            keep_files = 2
+            max_age_days = 90
+            rotate = "monthly"
+
+            [bind]
+            dashboard = "0.0.0.0:3000"
+
+            [search]
+            embedder = "http"
+            embedding_model = "all-minilm"
+
+            [llm]
+            provider = "anthropic"
+
+            [analysis]
+            memories = "~/notes/memories.jsonl"
+
+            [sources.antigravity]
+            enabled = false
+            "#,
+            &[
+                ("CONTRAIL_MEMORY_BLOCKS_PATH", "/srv/blocks.json"),
+                ("CONTRAIL_LOG_KEEP_FILES", "9"),
+                ("CONTRAIL_LOG_ROTATE", "daily"),
+                ("CONTRAIL_LLM_MODEL", "claude-haiku-4-5"),
+            ],
+        );
+
+        assert_eq!(
+            config.log_path,
+            PathBuf::from("/home/me/logs/master_log.jsonl")
+        );
+        assert_eq!(source_of(&values, "log.path"), ValueSource::File);
+        assert_eq!(config.log_fsync_ms, 0);
+        assert_eq!(config.log_max_bytes, 1000);
+        assert_eq!(source_of(&values, "retention.max_bytes"), ValueSource::File);
+        assert_eq!(config.log_keep_files, 9);
+        assert_eq!(
+            source_of(&values, "retention.keep_files"),
+            ValueSource::Env("CONTRAIL_LOG_KEEP_FILES")
+        );
+        assert_eq!(config.log_rotate, RotationPolicy::Daily);
+        assert_eq!(config.log_max_age_days, 90);
+        assert_eq!(config.dashboard_bind, "0.0.0.0:3000");
+        assert_eq!(config.analysis_bind, DEFAULT_ANALYSIS_BIND);
+        assert_eq!(source_of(&values, "bind.analysis"), ValueSource::Default);
+        assert_eq!(config.search_embedder, EmbedderKind::Http);
+        assert_eq!(config.embedding_model, "all-minilm");
+        assert_eq!(config.embedding_url, DEFAULT_EMBEDDING_URL);
+        assert_eq!(config.llm_provider, LlmProviderKind::Anthropic);
+        assert_eq!(config.llm_base_url, DEFAULT_ANTHROPIC_BASE_URL);
+        assert_eq!(config.llm_model, "claude-haiku-4-5");
+        assert_eq!(
+            source_of(&values, "llm.model"),
+            ValueSource::Env("CONTRAIL_LLM_MODEL")
+        );
+        assert_eq!(
+            config.analysis_memories,
+            PathBuf::from("/home/me/notes/memories.jsonl")
+        );
+        assert_eq!(
+            config.analysis_memory_blocks,
+            PathBuf::from("/srv/blocks.json")
+        );
+        assert_eq!(
+            source_of(&values, "analysis.memory_blocks"),
+            ValueSource::Env("CONTRAIL_MEMORY_BLOCKS_PATH")
+        );
+        assert!(!config.enable_antigravity);
+        assert!(config.enable_cursor);
+        assert_eq!(
+            config.codex_root,
+            PathBuf::from("/home/me").join(DEFAULT_CODEX_ROOT_REL)
+        );
+    }
+
+    #[test]
+    fn openai_model_fills_in_an_unset_openai_model() {
+        let env = [("OPENAI_MODEL", "gpt-4.1-mini")];
+        let (config, values) = resolve_with("", &env);
+        assert_eq!(config.llm_model, "gpt-4.1-mini");
+        assert_eq!(
+            source_of(&values, "llm.model"),
+            ValueSource::Env("OPENAI_MODEL")
+        );
+
+        let (config, _) = resolve_with("[llm]\nmodel = \"local\"\n", &env);
+        assert_eq!(config.llm_model, "local");
+        let (config, _) = resolve_with("", &[env[0], ("CONTRAIL_LLM_MODEL", "pinned")]);
+        assert_eq!(config.llm_model, "pinned");
+        let (config, _) = resolve_with("[llm]\nprovider = \"anthropic\"\n", &env);
+        assert_eq!(config.llm_model, LlmProviderKind::Anthropic.default_model());
+    }
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    #[test]
+    fn data_defaults_live_under_xdg_data_home() {
+        let (config, _) = resolve_with("", &[("XDG_DATA_HOME", "/data")]);
+        assert_eq!(
+            config.log_path,
+            PathBuf::from("/data/contrail/logs/master_log.jsonl")
+        );
+        assert_eq!(
+            config.search_index_dir,
+            PathBuf::from("/data/contrail/index")
+        );
+
+        let (config, _) = resolve_with("", &[("XDG_DATA_HOME", "relative/data")]);
+        assert_eq!(
+            config.search_index_dir,
+            PathBuf::from("/home/me/.local/share/contrail/index")
+        );
+
+        let home = tempfile::tempdir().unwrap();
+        assert_eq!(
+            data_dir_from(home.path(), None),
+            home.path().join(".local/share/contrail")
+        );
+        std::fs::create_dir_all(home.path().join(".contrail/logs")).unwrap();
+        assert_eq!(
+            data_dir_from(home.path(), None),
+            home.path().join(".contrail")
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<FileConfig>("[sources.cursor]\nstorag = \"x\"\n").is_err());
+        assert!(toml::from_str::<FileConfig>("[retention]\nmax_files = 3\n").is_err());
+        assert!(toml::from_str::<FileConfig>("[retention]\nrotate = \"weekly\"\n").is_err());
+    }
+
+    #[test]
+    fn unparseable_env_values_are_rejected() {
+        let err = try_resolve_with("", &[("CONTRAIL_LOG_ROTATE", "weekly")]).unwrap_err();
+        assert!(err.to_string().contains("CONTRAIL_LOG_ROTATE"));
+
+        let (config, _) = resolve_with("", &[("CONTRAIL_LOG_ROTATE", "daily")]);
+        assert_eq!(config.log_rotate, RotationPolicy::Daily);
+
+        assert!(try_resolve_with("", &[("CONTRAIL_EMBEDDER", "bert")]).is_err());
+        let err = try_resolve_with("", &[("CONTRAIL_LOG_MAX_BYTES", "500MB")]).unwrap_err();
+        assert!(err.to_string().contains("CONTRAIL_LOG_MAX_BYTES"));
+        assert!(try_resolve_with("", &[("CONTRAIL_LLM_PROVIDER", "gemini")]).is_err());
+    }
+}
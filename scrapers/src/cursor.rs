@@ -1,21 +1,29 @@
-use crate::parse::parse_timestamp_value;
+use crate::parse::{clip_artifacts, parse_timestamp_value};
+use crate::types::Artifact;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use rusqlite::Connection;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
 use serde_json::{Map, Value};
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fs;
 use std::hash::{Hash, Hasher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use uuid::Uuid;
 
 const MAX_CONTENT_CHARS: usize = 4000;
 
+/// `ItemTable` key listing the composer conversations that belong to a workspace.
+const WORKSPACE_COMPOSERS_KEY: &str = "composer.composerData";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CursorMessage {
     pub role: String,
     pub content: String,
     pub metadata: Map<String, Value>,
+    pub artifacts: Vec<Artifact>,
 }
 
 pub fn timestamp_from_metadata(meta: &Map<String, Value>) -> Option<DateTime<Utc>> {
@@ -35,9 +43,11 @@ pub fn read_cursor_messages(db_path: &Path) -> Result<Vec<CursorMessage>> {
 
     let conn = Connection::open(&temp_path).context("failed to open Cursor DB snapshot")?;
     let mut stmt = conn.prepare(
-        "SELECT key, value FROM ItemTable WHERE key LIKE '%chat%' OR key LIKE '%composer%' ORDER BY key",
+        "SELECT key, value FROM ItemTable
+         WHERE (key LIKE '%chat%' OR key LIKE '%composer%') AND key != ?1
+         ORDER BY key",
     )?;
-    let rows = stmt.query_map([], |row| {
+    let rows = stmt.query_map([WORKSPACE_COMPOSERS_KEY], |row| {
         let key: String = row.get(0)?;
         let raw: Vec<u8> = row
             .get(1)
@@ -68,6 +78,7 @@ pub fn read_cursor_messages(db_path: &Path) -> Result<Vec<CursorMessage>> {
                 role: "assistant".to_string(),
                 content: format!("{key}: {trimmed}"),
                 metadata: Map::new(),
+                artifacts: Vec::new(),
             });
         }
     }
@@ -76,6 +87,340 @@ pub fn read_cursor_messages(db_path: &Path) -> Result<Vec<CursorMessage>> {
     Ok(messages)
 }
 
+/// Everything a workspace's chat history holds: the legacy `ItemTable` chat data in
+/// its `state.vscdb`, followed by the composer and agent conversations newer Cursor
+/// builds keep in the global `cursorDiskKV` table (see [`read_composer_conversations`]).
+pub fn read_workspace_messages(db_path: &Path) -> Result<Vec<CursorMessage>> {
+    let mut messages = read_cursor_messages(db_path)?;
+    let composer_ids = workspace_composer_ids(db_path)?;
+    if composer_ids.is_empty() {
+        return Ok(messages);
+    }
+    let Some(global_db) = global_state_db(db_path).filter(|p| p.is_file()) else {
+        return Ok(messages);
+    };
+    for conversation in read_composer_conversations(&global_db, &composer_ids)? {
+        messages.extend(conversation.messages);
+    }
+    Ok(messages)
+}
+
+/// One composer (chat or agent-mode) conversation from `cursorDiskKV`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorConversation {
+    pub composer_id: String,
+    pub name: Option<String>,
+    pub messages: Vec<CursorMessage>,
+}
+
+/// `User/globalStorage/state.vscdb` for a `User/workspaceStorage/<hash>/state.vscdb`.
+pub fn global_state_db(workspace_db: &Path) -> Option<PathBuf> {
+    global_state_db_for_storage(workspace_db.parent()?.parent()?)
+}
+
+/// `User/globalStorage/state.vscdb` for the `User/workspaceStorage` directory.
+pub fn global_state_db_for_storage(workspace_storage: &Path) -> Option<PathBuf> {
+    let user_dir = workspace_storage.parent()?;
+    Some(user_dir.join("globalStorage").join("state.vscdb"))
+}
+
+/// A version stamp per composer in the global `cursorDiskKV` table. It changes when
+/// the composer's `composerData` entry does, or when one of its bubbles is added or
+/// changes length, so comparing two results names the composers touched in between.
+/// Bubbles are compared by length only, which keeps this cheap on large databases.
+pub fn composer_versions(global_db: &Path) -> Result<HashMap<String, u64>> {
+    let conn = open_read_only(global_db)?;
+    let mut stmt = match conn.prepare(
+        "SELECT key, CASE WHEN key LIKE 'composerData:%' THEN value ELSE length(value) END
+         FROM cursorDiskKV WHERE key LIKE 'composerData:%' OR key LIKE 'bubbleId:%'",
+    ) {
+        Ok(stmt) => stmt,
+        // No composer storage in this build.
+        Err(rusqlite::Error::SqliteFailure(_, Some(ref msg))) if msg.contains("no such table") => {
+            return Ok(HashMap::new())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut versions: HashMap<String, u64> = HashMap::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        let Some(composer_id) = key
+            .strip_prefix("composerData:")
+            .or_else(|| key.strip_prefix("bubbleId:")?.split(':').next())
+        else {
+            continue;
+        };
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        match row.get_ref(1)? {
+            ValueRef::Text(bytes) | ValueRef::Blob(bytes) => bytes.hash(&mut hasher),
+            ValueRef::Integer(n) => n.hash(&mut hasher),
+            ValueRef::Real(_) | ValueRef::Null => {}
+        }
+        // Rows come in no particular order, so combine order-independently.
+        let version = versions.entry(composer_id.to_string()).or_default();
+        *version = version.wrapping_add(hasher.finish());
+    }
+    Ok(versions)
+}
+
+/// Composer ids a workspace lists under `composer.composerData`, oldest first.
+pub fn workspace_composer_ids(db_path: &Path) -> Result<Vec<String>> {
+    let conn = open_read_only(db_path)?;
+    let raw: Option<String> = conn
+        .query_row(
+            "SELECT value FROM ItemTable WHERE key = ?1",
+            [WORKSPACE_COMPOSERS_KEY],
+            |row| {
+                row.get::<_, String>(0).or_else(|_| {
+                    row.get::<_, Vec<u8>>(0)
+                        .map(|b| String::from_utf8_lossy(&b).into_owned())
+                })
+            },
+        )
+        .optional()
+        .or_else(|e| match e {
+            // Not a VS Code state database; nothing to list.
+            rusqlite::Error::SqliteFailure(_, Some(ref msg)) if msg.contains("no such table") => {
+                Ok(None)
+            }
+            e => Err(e),
+        })?;
+    let Some(value) = raw.and_then(|r| serde_json::from_str::<Value>(&r).ok()) else {
+        return Ok(Vec::new());
+    };
+    let mut composers: Vec<(i64, String)> = value
+        .get("allComposers")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|c| {
+            let id = c.get("composerId")?.as_str()?.to_string();
+            let created = c.get("createdAt").and_then(Value::as_i64).unwrap_or(0);
+            Some((created, id))
+        })
+        .collect();
+    composers.sort();
+    Ok(composers.into_iter().map(|(_, id)| id).collect())
+}
+
+/// Read the given composers from the global database's `cursorDiskKV` table. Each
+/// `composerData:<id>` entry lists its bubbles in order; each bubble is stored under
+/// `bubbleId:<id>:<bubbleId>` with its text, model, tool call and code blocks.
+/// Composers that predate per-bubble storage keep their bubbles inline.
+pub fn read_composer_conversations(
+    global_db: &Path,
+    composer_ids: &[String],
+) -> Result<Vec<CursorConversation>> {
+    let conn = open_read_only(global_db)?;
+    let mut conversations = Vec::new();
+    for composer_id in composer_ids {
+        let Some(composer) = kv_json(&conn, &format!("composerData:{composer_id}"))? else {
+            continue;
+        };
+        let bubbles: Vec<Value> = match composer
+            .get("fullConversationHeadersOnly")
+            .and_then(Value::as_array)
+        {
+            Some(headers) => {
+                let mut bubbles = Vec::with_capacity(headers.len());
+                for header in headers {
+                    let Some(bubble_id) = header.get("bubbleId").and_then(Value::as_str) else {
+                        continue;
+                    };
+                    if let Some(bubble) =
+                        kv_json(&conn, &format!("bubbleId:{composer_id}:{bubble_id}"))?
+                    {
+                        bubbles.push(bubble);
+                    }
+                }
+                bubbles
+            }
+            None => composer
+                .get("conversation")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default(),
+        };
+
+        let mut messages = Vec::new();
+        for bubble in &bubbles {
+            let mut message = match parse_bubble(&composer, bubble) {
+                Some(message) => message,
+                None => continue,
+            };
+            attach_code_block_diffs(&conn, composer_id, bubble, &mut message.artifacts)?;
+            message.artifacts = clip_artifacts(std::mem::take(&mut message.artifacts));
+            messages.push(message);
+        }
+        if messages.is_empty() {
+            continue;
+        }
+        for message in &mut messages {
+            message.metadata.insert(
+                "cursor_composer_id".to_string(),
+                Value::String(composer_id.clone()),
+            );
+        }
+        conversations.push(CursorConversation {
+            composer_id: composer_id.clone(),
+            name: composer
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            messages,
+        });
+    }
+    Ok(conversations)
+}
+
+/// A bubble as a message: `type` 1 is the user, 2 the assistant. Bubbles with
+/// neither text nor a tool call (thinking placeholders, empty drafts) are skipped.
+fn parse_bubble(composer: &Value, bubble: &Value) -> Option<CursorMessage> {
+    let obj = bubble.as_object()?;
+    let role = match obj.get("type").and_then(Value::as_i64) {
+        Some(1) => "user",
+        _ => "assistant",
+    };
+    let text = obj.get("text").and_then(Value::as_str).unwrap_or("").trim();
+    let mut metadata = extract_metadata(obj);
+    let artifacts = extract_artifacts(obj);
+    if text.is_empty() && artifacts.is_empty() {
+        return None;
+    }
+
+    let model = obj
+        .get("modelInfo")
+        .and_then(|m| m.get("modelName"))
+        .or_else(|| composer.get("modelConfig")?.get("modelName"))
+        .filter(|m| m.as_str().is_some_and(|m| !m.is_empty() && m != "default"));
+    if let Some(model) = model {
+        insert_scalar(&mut metadata, "model", model);
+    }
+    if !metadata.contains_key("createdAt") {
+        if let Some(created) = composer.get("createdAt") {
+            insert_scalar(&mut metadata, "createdAt", created);
+        }
+    }
+
+    let content = if text.is_empty() {
+        // A pure tool call; name it so the turn isn't blank.
+        artifacts
+            .iter()
+            .find_map(|a| match a {
+                Artifact::ToolCall { name, .. } => Some(format!("[tool call: {name}]")),
+                _ => None,
+            })
+            .unwrap_or_default()
+    } else {
+        trim_content(text)
+    };
+    Some(CursorMessage {
+        role: role.to_string(),
+        content,
+        metadata,
+        artifacts,
+    })
+}
+
+/// Code blocks a bubble proposed, as patches. When Cursor stored the block's diff
+/// (`codeBlockDiff:<composer>:<diffId>`) that is recorded; otherwise the block
+/// itself, which is the proposed content rather than a diff.
+fn attach_code_block_diffs(
+    conn: &Connection,
+    composer_id: &str,
+    bubble: &Value,
+    artifacts: &mut Vec<Artifact>,
+) -> Result<()> {
+    let Some(blocks) = bubble.get("codeBlocks").and_then(Value::as_array) else {
+        return Ok(());
+    };
+    for block in blocks {
+        let path = block.get("uri").and_then(|uri| {
+            ["fsPath", "path"]
+                .iter()
+                .find_map(|k| uri.get(*k)?.as_str())
+                .map(str::to_string)
+        });
+        let stored_diff = match block.get("diffId").and_then(Value::as_str) {
+            Some(diff_id) => kv_json(conn, &format!("codeBlockDiff:{composer_id}:{diff_id}"))?
+                .and_then(|d| render_model_diff(&d)),
+            None => None,
+        };
+        let Some(diff) = stored_diff.or_else(|| {
+            block
+                .get("content")
+                .and_then(Value::as_str)
+                .filter(|c| !c.trim().is_empty())
+                .map(str::to_string)
+        }) else {
+            continue;
+        };
+        artifacts.push(Artifact::Patch { path, diff });
+    }
+    Ok(())
+}
+
+/// Render a `codeBlockDiff` entry's `newModelDiffWrtV0` hunks. Cursor stores the
+/// replacement lines but only the range of the lines they replace.
+fn render_model_diff(diff: &Value) -> Option<String> {
+    let hunks = diff.get("newModelDiffWrtV0")?.as_array()?;
+    let mut out = String::new();
+    for hunk in hunks {
+        let original = hunk.get("original");
+        let line = |key: &str| {
+            original
+                .and_then(|o| o.get(key))
+                .and_then(Value::as_u64)
+                .unwrap_or(0)
+        };
+        let start = line("startLineNumber");
+        let removed = line("endLineNumberExclusive").saturating_sub(start);
+        let added: Vec<&str> = hunk
+            .get("modified")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        out.push_str(&format!(
+            "@@ -{start},{removed} +{start},{} @@\n",
+            added.len()
+        ));
+        for line in added {
+            out.push('+');
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+fn open_read_only(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .with_context(|| format!("open Cursor DB {}", db_path.display()))?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    Ok(conn)
+}
+
+fn kv_json(conn: &Connection, key: &str) -> Result<Option<Value>> {
+    let raw: Option<Vec<u8>> = conn
+        .query_row(
+            "SELECT value FROM cursorDiskKV WHERE key = ?1",
+            [key],
+            |row| {
+                row.get::<_, Vec<u8>>(0)
+                    .or_else(|_| row.get::<_, String>(0).map(String::into_bytes))
+            },
+        )
+        .optional()?;
+    Ok(raw.and_then(|raw| serde_json::from_slice(&raw).ok()))
+}
+
 fn parse_cursor_value(value: &Value) -> Vec<CursorMessage> {
     let mut messages = Vec::new();
 
@@ -112,6 +457,7 @@ fn parse_message(value: &Value) -> Option<CursorMessage> {
         .unwrap_or("assistant")
         .to_string();
     let metadata = extract_metadata(obj);
+    let artifacts = clip_artifacts(extract_artifacts(obj));
 
     if let Some(content) = obj.get("content") {
         if let Some(text) = extract_text_from_content(content) {
@@ -119,6 +465,7 @@ fn parse_message(value: &Value) -> Option<CursorMessage> {
                 role,
                 content: trim_content(&text),
                 metadata,
+                artifacts,
             });
         }
     }
@@ -128,6 +475,7 @@ fn parse_message(value: &Value) -> Option<CursorMessage> {
             role,
             content: trim_content(text),
             metadata,
+            artifacts,
         });
     }
 
@@ -222,12 +570,11 @@ fn extract_usage(obj: &Map<String, Value>, meta: &mut Map<String, Value>) {
                     "total" | "total_tokens" | "totalTokens" => {
                         insert_scalar(meta, "usage_total_tokens", v)
                     }
-                    "prompt" | "prompt_tokens" | "promptTokens" | "input" => {
+                    "prompt" | "prompt_tokens" | "promptTokens" | "input" | "inputTokens" => {
                         insert_scalar(meta, "usage_prompt_tokens", v)
                     }
-                    "completion" | "completion_tokens" | "completionTokens" | "output" => {
-                        insert_scalar(meta, "usage_completion_tokens", v)
-                    }
+                    "completion" | "completion_tokens" | "completionTokens" | "output"
+                    | "outputTokens" => insert_scalar(meta, "usage_completion_tokens", v),
                     _ => {}
                 }
             }
@@ -252,6 +599,19 @@ fn extract_metrics(obj: &Map<String, Value>, meta: &mut Map<String, Value>) {
 }
 
 fn extract_tools(obj: &Map<String, Value>, meta: &mut Map<String, Value>) {
+    // Composer bubbles carry at most one call, under `toolFormerData`.
+    if let Some(name) = obj
+        .get("toolFormerData")
+        .and_then(|t| t.get("name"))
+        .and_then(Value::as_str)
+    {
+        meta.insert("tool_call_count".to_string(), Value::from(1u64));
+        insert_scalar(
+            meta,
+            "tool_call_first_name",
+            &Value::String(name.to_string()),
+        );
+    }
     let candidates = ["toolCalls", "tool_calls"];
     for key in candidates {
         if let Some(Value::Array(calls)) = obj.get(key) {
@@ -276,6 +636,60 @@ fn extract_tools(obj: &Map<String, Value>, meta: &mut Map<String, Value>) {
     }
 }
 
+/// Tool calls from `toolCalls`/`tool_calls` arrays and composer `toolFormerData`,
+/// with a result artifact when the call's output was stored alongside it.
+fn extract_artifacts(obj: &Map<String, Value>) -> Vec<Artifact> {
+    let calls = ["toolCalls", "tool_calls"]
+        .iter()
+        .filter_map(|key| obj.get(*key).and_then(Value::as_array))
+        .flatten()
+        .chain(obj.get("toolFormerData"));
+
+    let mut artifacts = Vec::new();
+    for call in calls {
+        let Some(call) = call.as_object() else {
+            continue;
+        };
+        let text = |keys: &[&str]| {
+            keys.iter().find_map(|key| match call.get(*key)? {
+                Value::Null => None,
+                Value::String(s) => Some(s.clone()),
+                other => Some(other.to_string()),
+            })
+        };
+        let Some(name) = text(&["name", "toolName"]).or_else(|| {
+            call.get("function")
+                .and_then(|f| f.get("name")?.as_str().map(str::to_string))
+        }) else {
+            continue;
+        };
+        let call_id = text(&["id", "toolCallId"]);
+        let input = text(&["arguments", "args", "params", "rawArgs"])
+            .or_else(|| {
+                call.get("function")
+                    .and_then(|f| f.get("arguments")?.as_str().map(str::to_string))
+            })
+            .unwrap_or_else(|| "{}".to_string());
+
+        artifacts.push(Artifact::ToolCall {
+            call_id: call_id.clone(),
+            name,
+            input,
+        });
+        if let Some(output) = text(&["result", "output"]) {
+            artifacts.push(Artifact::ToolResult {
+                call_id,
+                output,
+                is_error: matches!(
+                    call.get("status").and_then(Value::as_str),
+                    Some("error" | "failed")
+                ),
+            });
+        }
+    }
+    artifacts
+}
+
 fn insert_scalar(meta: &mut Map<String, Value>, key: &str, value: &Value) {
     match value {
         Value::String(s) => {
@@ -344,11 +758,13 @@ mod tests {
                 role: "user".to_string(),
                 content: "hi".to_string(),
                 metadata: Map::new(),
+                artifacts: Vec::new(),
             },
             CursorMessage {
                 role: "assistant".to_string(),
                 content: "hello".to_string(),
                 metadata: Map::new(),
+                artifacts: Vec::new(),
             },
         ];
 
@@ -407,4 +823,179 @@ mod tests {
         assert_eq!(meta.get("temperature").and_then(|v| v.as_f64()), Some(0.2));
         Ok(())
     }
+
+    #[test]
+    fn captures_tool_calls_as_artifacts() {
+        let value = serde_json::json!({
+            "role": "assistant",
+            "text": "running the tests",
+            "toolFormerData": {
+                "toolCallId": "tc_1",
+                "name": "run_terminal_cmd",
+                "rawArgs": "{\"command\":\"npm test\"}",
+                "result": "{\"exitCode\":1}",
+                "status": "error"
+            }
+        });
+
+        let parsed = parse_cursor_value(&serde_json::json!([value]));
+        assert_eq!(
+            parsed[0].artifacts,
+            vec![
+                Artifact::ToolCall {
+                    call_id: Some("tc_1".to_string()),
+                    name: "run_terminal_cmd".to_string(),
+                    input: r#"{"command":"npm test"}"#.to_string(),
+                },
+                Artifact::ToolResult {
+                    call_id: Some("tc_1".to_string()),
+                    output: r#"{"exitCode":1}"#.to_string(),
+                    is_error: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_composer_conversations_from_cursor_disk_kv() -> Result<()> {
+        let user = tempfile::tempdir()?;
+        let workspace = user.path().join("workspaceStorage/ws1");
+        let global = user.path().join("globalStorage");
+        fs::create_dir_all(&workspace)?;
+        fs::create_dir_all(&global)?;
+
+        let ws_db = workspace.join("state.vscdb");
+        let conn = Connection::open(&ws_db)?;
+        conn.execute(
+            "CREATE TABLE ItemTable (key TEXT PRIMARY KEY, value BLOB)",
+            [],
+        )?;
+        conn.execute(
+            "INSERT INTO ItemTable (key, value) VALUES (?1, ?2)",
+            (
+                WORKSPACE_COMPOSERS_KEY,
+                r#"{"allComposers":[{"composerId":"c1","createdAt":1750000000000}]}"#,
+            ),
+        )?;
+
+        let conn = Connection::open(global.join("state.vscdb"))?;
+        conn.execute(
+            "CREATE TABLE cursorDiskKV (key TEXT UNIQUE ON CONFLICT REPLACE, value BLOB)",
+            [],
+        )?;
+        let entries = [
+            (
+                "composerData:c1",
+                serde_json::json!({
+                    "composerId": "c1",
+                    "name": "Fix the tests",
+                    "createdAt": 1750000000000i64,
+                    "modelConfig": {"modelName": "claude-4-sonnet"},
+                    "fullConversationHeadersOnly": [
+                        {"bubbleId": "b1", "type": 1},
+                        {"bubbleId": "b2", "type": 2},
+                        {"bubbleId": "b3", "type": 2}
+                    ]
+                }),
+            ),
+            (
+                "bubbleId:c1:b1",
+                serde_json::json!({"type": 1, "text": "make the tests pass", "createdAt": "2025-06-15T10:00:00Z"}),
+            ),
+            (
+                "bubbleId:c1:b2",
+                serde_json::json!({
+                    "type": 2,
+                    "text": "",
+                    "modelInfo": {"modelName": "gpt-5"},
+                    "tokenCount": {"inputTokens": 900, "outputTokens": 40},
+                    "toolFormerData": {
+                        "toolCallId": "t1",
+                        "name": "run_terminal_cmd",
+                        "rawArgs": "{\"command\":\"cargo test\"}",
+                        "result": "ok",
+                        "status": "completed"
+                    }
+                }),
+            ),
+            (
+                "bubbleId:c1:b3",
+                serde_json::json!({
+                    "type": 2,
+                    "text": "Fixed the off-by-one.",
+                    "codeBlocks": [
+                        {"uri": {"fsPath": "/repo/src/lib.rs"}, "content": "fn f() {}", "diffId": "d1"},
+                        {"uri": {"path": "/repo/README.md"}, "content": "# Title"}
+                    ]
+                }),
+            ),
+            (
+                "codeBlockDiff:c1:d1",
+                serde_json::json!({"newModelDiffWrtV0": [{
+                    "original": {"startLineNumber": 3, "endLineNumberExclusive": 4},
+                    "modified": ["    n + 1", "    // checked"]
+                }]}),
+            ),
+        ];
+        for (key, value) in entries {
+            conn.execute(
+                "INSERT INTO cursorDiskKV (key, value) VALUES (?1, ?2)",
+                (key, value.to_string()),
+            )?;
+        }
+
+        let versions = composer_versions(&global.join("state.vscdb"))?;
+        assert_eq!(versions.keys().collect::<Vec<_>>(), ["c1"]);
+
+        let messages = read_workspace_messages(&ws_db)?;
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[0].content, "make the tests pass");
+        assert_eq!(
+            timestamp_from_metadata(&messages[0].metadata).map(|t| t.to_rfc3339()),
+            Some("2025-06-15T10:00:00+00:00".to_string())
+        );
+        assert_eq!(messages[0].metadata["model"], "claude-4-sonnet");
+
+        let tool_turn = &messages[1];
+        assert_eq!(tool_turn.content, "[tool call: run_terminal_cmd]");
+        assert_eq!(tool_turn.metadata["model"], "gpt-5");
+        assert_eq!(tool_turn.metadata["usage_prompt_tokens"], 900);
+        assert_eq!(
+            tool_turn.metadata["tool_call_first_name"],
+            "run_terminal_cmd"
+        );
+        assert_eq!(tool_turn.metadata["cursor_composer_id"], "c1");
+        assert_eq!(tool_turn.artifacts.len(), 2);
+
+        assert_eq!(
+            messages[2].artifacts,
+            vec![
+                Artifact::Patch {
+                    path: Some("/repo/src/lib.rs".to_string()),
+                    diff: "@@ -3,1 +3,2 @@\n+    n + 1\n+    // checked\n".to_string(),
+                },
+                Artifact::Patch {
+                    path: Some("/repo/README.md".to_string()),
+                    diff: "# Title".to_string(),
+                },
+            ]
+        );
+
+        // A streamed-in bubble touches its composer even if `composerData` doesn't.
+        conn.execute(
+            "INSERT INTO cursorDiskKV (key, value) VALUES (?1, ?2)",
+            (
+                "bubbleId:c1:b3",
+                r#"{"type": 2, "text": "Fixed the off-by-one. Done."}"#,
+            ),
+        )?;
+        let touched = composer_versions(&global.join("state.vscdb"))?;
+        assert_ne!(touched["c1"], versions["c1"]);
+        assert_eq!(
+            composer_versions(&global.join("state.vscdb"))?["c1"],
+            touched["c1"]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,1228 @@
+//! Embedded SQLite index over the master log and its rotated archives.
+//!
+//! The JSONL files remain the source of truth; the index is a derived cache stored
+//! next to the log (`master_log.index.sqlite`). `sync` brings it up to date
+//! incrementally: each file's indexed byte offset is remembered together with a
+//! hash of its leading bytes, so appends only read the new tail, rotated archives
+//! are recognised by content instead of being re-read, and rewritten or truncated
+//! files are re-indexed from scratch. Compressed archives never change, so once one
+//! has been read to the end it is sealed and skipped by later syncs.
+//!
+//! Events are stored as their identity columns plus the file, byte offset and length
+//! of their line, not the line itself, so the index stays small next to a large log.
+//! Reads go back to the JSONL files; a line that no longer holds the indexed event
+//! (the file was rewritten since the last sync) is skipped.
+//!
+//! Every event also receives a replication sequence number the first time it is
+//! indexed. Sequence numbers are monotonic, never reused, and kept across index
+//! rebuilds, so replicas can resume a pull from their last acknowledged `seq`. An
+//! event whose line was rewritten in place (`redact --rewrite`) gets a fresh number,
+//! so replicas that already pulled it receive the new version too.
+
+use crate::log_index::{discover_logs, is_compressed, open_log};
+use crate::types::{MasterLog, SeqEntry};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, TransactionBehavior};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Bump when the table layout changes; older index files are rebuilt.
+const SCHEMA_VERSION: i64 = 4;
+/// Number of leading bytes hashed to recognise a file after it has been renamed.
+const HEAD_BYTES: u64 = 4096;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS indexed_files (
+    path TEXT PRIMARY KEY,
+    indexed_bytes INTEGER NOT NULL,
+    head_len INTEGER NOT NULL,
+    head_hash INTEGER NOT NULL,
+    sealed INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    event_id TEXT NOT NULL,
+    ts_ms INTEGER NOT NULL,
+    source_tool TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    project_context TEXT NOT NULL,
+    role TEXT NOT NULL,
+    content_hash INTEGER NOT NULL,
+    file TEXT NOT NULL,
+    offset INTEGER NOT NULL,
+    len INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_ts ON events(ts_ms);
+CREATE INDEX IF NOT EXISTS events_session ON events(source_tool, session_id, ts_ms);
+CREATE INDEX IF NOT EXISTS events_project ON events(project_context, ts_ms);
+CREATE INDEX IF NOT EXISTS events_event_id ON events(event_id);
+CREATE INDEX IF NOT EXISTS events_file ON events(file);
+CREATE TABLE IF NOT EXISTS sequence (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_id TEXT NOT NULL UNIQUE,
+    line_hash INTEGER
+);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+// ── Public types ────────────────────────────────────────────────────────
+
+/// Filters for index queries. Unset fields match everything; time bounds are inclusive.
+#[derive(Debug, Default, Clone)]
+pub struct EventQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub source_tool: Option<String>,
+    pub session_id: Option<String>,
+    pub project_context: Option<String>,
+    pub event_id: Option<Uuid>,
+    /// When set, keeps the `limit` most recent matches (still returned oldest first).
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncStats {
+    pub indexed: usize,
+    pub skipped: usize,
+    pub files_reset: usize,
+    pub files_renamed: usize,
+}
+
+/// Identity columns of an indexed event, enough to rebuild dedup keys without
+/// loading the full JSON line.
+#[derive(Debug, Clone)]
+pub struct EventKey {
+    pub event_id: String,
+    pub timestamp_ms: i64,
+    pub source_tool: String,
+    pub session_id: String,
+    pub project_context: String,
+    pub role: String,
+    pub content_hash: u64,
+}
+
+pub struct EventIndex {
+    conn: Connection,
+    log_path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+struct FileRecord {
+    path: String,
+    indexed_bytes: u64,
+    head_len: u64,
+    head_hash: u64,
+}
+
+// ── Index ───────────────────────────────────────────────────────────────
+
+/// Location of the index that belongs to `log_path`.
+pub fn index_path_for(log_path: &Path) -> PathBuf {
+    let stem = log_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("master_log");
+    log_path.with_file_name(format!("{stem}.index.sqlite"))
+}
+
+/// Stable hash of an interaction's content, as stored in the `content_hash` column.
+pub fn content_hash(content: &str) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(content.as_bytes())
+}
+
+/// Dedup key used by the history importers: (source_tool, session_id, content).
+pub fn history_key(source: &str, session: &str, content: &str) -> u64 {
+    history_key_from_hash(source, session, content_hash(content))
+}
+
+fn history_key_from_hash(source: &str, session: &str, content_hash: u64) -> u64 {
+    let mut combined = Vec::with_capacity(source.len() + session.len() + 10);
+    combined.extend_from_slice(source.as_bytes());
+    combined.push(0);
+    combined.extend_from_slice(session.as_bytes());
+    combined.push(0);
+    combined.extend_from_slice(&content_hash.to_le_bytes());
+    xxhash_rust::xxh3::xxh3_64(&combined)
+}
+
+impl EventIndex {
+    /// Open (creating if needed) the index that belongs to `log_path`.
+    pub fn open(log_path: &Path) -> Result<Self> {
+        Self::open_at(&index_path_for(log_path), log_path)
+    }
+
+    pub fn open_at(index_path: &Path, log_path: &Path) -> Result<Self> {
+        if let Some(parent) = index_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create {}", parent.display()))?;
+        }
+        let conn = Connection::open(index_path)
+            .with_context(|| format!("open event index at {}", index_path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            // `sequence` and `meta` are not derivable from the log, so they survive.
+            conn.execute_batch("DROP TABLE IF EXISTS events; DROP TABLE IF EXISTS indexed_files;")?;
+        }
+        conn.execute_batch(SCHEMA)?;
+        let hashes_lines = conn
+            .prepare("SELECT 1 FROM pragma_table_info('sequence') WHERE name = 'line_hash'")?
+            .exists([])?;
+        if !hashes_lines {
+            // Numbered before lines were hashed; the next sync fills the hashes in.
+            conn.execute_batch("ALTER TABLE sequence ADD COLUMN line_hash INTEGER")?;
+        }
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO meta (key, value) VALUES ('log_id', ?1)",
+            params![Uuid::new_v4().to_string()],
+        )?;
+
+        Ok(Self {
+            conn,
+            log_path: log_path.to_path_buf(),
+        })
+    }
+
+    /// Open the index for `log_path` and catch it up with the files on disk.
+    pub fn open_synced(log_path: &Path) -> Result<Self> {
+        let mut index = Self::open(log_path)?;
+        index.sync()?;
+        Ok(index)
+    }
+
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Bring the index in line with the master log and its archives.
+    pub fn sync(&mut self) -> Result<SyncStats> {
+        let files = discover_logs(&self.log_path)?;
+        let mut stats = SyncStats::default();
+
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let records = load_file_records(&tx)?;
+        let mut claimed: HashSet<String> = HashSet::new();
+        let mut pending: Vec<(PathBuf, Option<u64>)> = Vec::new();
+
+        // Files whose record still describes them only need their tail indexed.
+        for path in &files {
+            let Ok(meta) = std::fs::metadata(path) else {
+                continue;
+            };
+            let key = path_key(path);
+            let len = logical_len(path, &meta);
+            if let Some(record) = records.get(&key) {
+                if fits(record, len) && head_matches(path, record)? {
+                    claimed.insert(key);
+                    continue;
+                }
+            }
+            pending.push((path.clone(), len));
+        }
+
+        // A file we have not seen may be a renamed one (rotation): adopt its record.
+        let mut fresh = Vec::new();
+        let mut moved: HashSet<String> = HashSet::new();
+        for (path, len) in pending {
+            let key = path_key(&path);
+            let mut adopted = None;
+            for record in records.values() {
+                if claimed.contains(&record.path) || record.path == key || record.head_len == 0 {
+                    continue;
+                }
+                if fits(record, len) && head_matches(&path, record)? {
+                    adopted = Some(record.path.clone());
+                    break;
+                }
+            }
+            match adopted {
+                Some(old) => {
+                    tx.execute("DELETE FROM events WHERE file = ?1", params![key])?;
+                    tx.execute("DELETE FROM indexed_files WHERE path = ?1", params![key])?;
+                    tx.execute(
+                        "UPDATE events SET file = ?1 WHERE file = ?2",
+                        params![key, old],
+                    )?;
+                    tx.execute(
+                        "UPDATE indexed_files SET path = ?1 WHERE path = ?2",
+                        params![key, old],
+                    )?;
+                    claimed.insert(old.clone());
+                    claimed.insert(key);
+                    moved.insert(old);
+                    stats.files_renamed += 1;
+                }
+                None => fresh.push(key),
+            }
+        }
+
+        for key in fresh {
+            if records.contains_key(&key) && !moved.contains(&key) {
+                stats.files_reset += 1;
+            }
+            tx.execute("DELETE FROM events WHERE file = ?1", params![key])?;
+            tx.execute(
+                "INSERT OR REPLACE INTO indexed_files (path, indexed_bytes, head_len, head_hash, sealed)
+                 VALUES (?1, 0, 0, 0, 0)",
+                params![key],
+            )?;
+            claimed.insert(key);
+        }
+
+        // Records for files that no longer exist (pruned archives) are dropped.
+        for key in records.keys() {
+            if !claimed.contains(key) {
+                tx.execute("DELETE FROM events WHERE file = ?1", params![key])?;
+                tx.execute("DELETE FROM indexed_files WHERE path = ?1", params![key])?;
+            }
+        }
+
+        for path in &files {
+            if path.is_file() {
+                index_tail(&tx, path, &mut stats)?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(stats)
+    }
+
+    /// Drop everything indexed from `path`, so the next [`sync`](Self::sync) reads it
+    /// afresh rather than trusting its record. Used after a purge rewrites the file.
+    pub fn forget_file(&mut self, path: &Path) -> Result<()> {
+        let key = path_key(path);
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM events WHERE file = ?1", params![key])?;
+        tx.execute("DELETE FROM indexed_files WHERE path = ?1", params![key])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Reclaim the space of deleted rows so their contents no longer linger in the
+    /// database or its write-ahead log.
+    pub fn compact(&self) -> Result<()> {
+        self.conn.execute_batch("VACUUM;")?;
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Raw JSON lines matching `query`, ordered by timestamp.
+    pub fn query_raw(&self, query: &EventQuery) -> Result<Vec<String>> {
+        let mut out = Vec::new();
+        self.for_each_raw(query, |raw| {
+            out.push(raw);
+            Ok(())
+        })?;
+        Ok(out)
+    }
+
+    /// Parsed events matching `query`, ordered by timestamp.
+    pub fn query(&self, query: &EventQuery) -> Result<Vec<MasterLog>> {
+        let mut out = Vec::new();
+        self.for_each_event(query, |log| {
+            out.push(log);
+            Ok(())
+        })?;
+        Ok(out)
+    }
+
+    /// Stream parsed events matching `query` without collecting them first.
+    pub fn for_each_event<F>(&self, query: &EventQuery, mut f: F) -> Result<()>
+    where
+        F: FnMut(MasterLog) -> Result<()>,
+    {
+        self.for_each_raw(query, |raw| match serde_json::from_str::<MasterLog>(&raw) {
+            Ok(log) => f(log),
+            Err(err) => {
+                tracing::warn!(err = %err, "skipping unparsable indexed event");
+                Ok(())
+            }
+        })
+    }
+
+    pub fn for_each_raw<F>(&self, query: &EventQuery, mut f: F) -> Result<()>
+    where
+        F: FnMut(String) -> Result<()>,
+    {
+        let (where_sql, args) = build_where(query);
+        let sql = match query.limit {
+            Some(limit) => format!(
+                "SELECT event_id, file, offset, len FROM
+                    (SELECT id, ts_ms, event_id, file, offset, len FROM events{where_sql}
+                     ORDER BY ts_ms DESC, id DESC LIMIT {limit})
+                 ORDER BY ts_ms, id"
+            ),
+            None => format!(
+                "SELECT event_id, file, offset, len FROM events{where_sql} ORDER BY ts_ms, id"
+            ),
+        };
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(params_from_iter(args))?;
+        let mut lines = LineReader::default();
+        while let Some(row) = rows.next()? {
+            if let Some(raw) = lines.read(&LineRef::from_row(row)?)? {
+                f(raw)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Identity columns of every indexed event, for dedup without JSON parsing.
+    pub fn for_each_key<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(EventKey),
+    {
+        let mut stmt = self.conn.prepare(
+            "SELECT event_id, ts_ms, source_tool, session_id, project_context, role, content_hash
+             FROM events",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            f(EventKey {
+                event_id: row.get(0)?,
+                timestamp_ms: row.get(1)?,
+                source_tool: row.get(2)?,
+                session_id: row.get(3)?,
+                project_context: row.get(4)?,
+                role: row.get(5)?,
+                content_hash: row.get::<_, i64>(6)? as u64,
+            });
+        }
+        Ok(())
+    }
+
+    /// Dedup keys (see [`history_key`]) for every indexed event.
+    pub fn history_keys(&self) -> Result<HashSet<u64>> {
+        let mut keys = HashSet::new();
+        self.for_each_key(|key| {
+            if !key.source_tool.is_empty() && !key.session_id.is_empty() {
+                keys.insert(history_key_from_hash(
+                    &key.source_tool,
+                    &key.session_id,
+                    key.content_hash,
+                ));
+            }
+        })?;
+        Ok(keys)
+    }
+
+    /// Dedup keys for one session only, for checks that shouldn't load the whole log.
+    pub fn session_history_keys(&self, source: &str, session: &str) -> Result<HashSet<u64>> {
+        Ok(self
+            .session_history_key_counts(source, session)?
+            .into_keys()
+            .collect())
+    }
+
+    /// How many events of one session carry each dedup key, for sources whose
+    /// sessions legitimately repeat a turn ("yes", "continue").
+    pub fn session_history_key_counts(
+        &self,
+        source: &str,
+        session: &str,
+    ) -> Result<HashMap<u64, usize>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT content_hash FROM events WHERE source_tool = ?1 AND session_id = ?2",
+        )?;
+        let hashes = stmt.query_map(params![source, session], |row| row.get::<_, i64>(0))?;
+        let mut counts = HashMap::new();
+        for hash in hashes {
+            *counts
+                .entry(history_key_from_hash(source, session, hash? as u64))
+                .or_default() += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Cheap fingerprint of the indexed contents: it changes whenever an event is
+    /// added, removed or rewritten, so derived indexes can skip a full comparison.
+    pub fn content_digest(&self) -> Result<String> {
+        let (count, sum, max_id): (i64, i64, i64) = self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(content_hash & 4294967295), 0), COALESCE(MAX(id), 0)
+             FROM events",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        Ok(format!("{count}:{sum}:{max_id}"))
+    }
+
+    pub fn count(&self, query: &EventQuery) -> Result<usize> {
+        let (where_sql, args) = build_where(query);
+        let sql = format!("SELECT COUNT(*) FROM events{where_sql}");
+        let count: i64 = self
+            .conn
+            .query_row(&sql, params_from_iter(args), |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Most recent event for an id, if indexed.
+    pub fn get(&self, event_id: Uuid) -> Result<Option<MasterLog>> {
+        let line: Option<LineRef> = self
+            .conn
+            .query_row(
+                "SELECT event_id, file, offset, len FROM events
+                 WHERE event_id = ?1 ORDER BY id DESC LIMIT 1",
+                params![event_id.to_string()],
+                LineRef::from_row,
+            )
+            .optional()?;
+        let Some(line) = line else {
+            return Ok(None);
+        };
+        LineReader::default()
+            .read(&line)?
+            .map(|raw| serde_json::from_str(&raw).context("parse indexed event"))
+            .transpose()
+    }
+
+    /// Identifies this index's sequence space; see [`SyncBatch`](crate::types::SyncBatch).
+    pub fn log_id(&self) -> Result<Uuid> {
+        let raw: String =
+            self.conn
+                .query_row("SELECT value FROM meta WHERE key = 'log_id'", [], |row| {
+                    row.get(0)
+                })?;
+        Uuid::parse_str(&raw).context("parse index log_id")
+    }
+
+    /// Highest sequence number assigned so far (0 when empty).
+    pub fn head_seq(&self) -> Result<u64> {
+        let seq: i64 =
+            self.conn
+                .query_row("SELECT COALESCE(MAX(seq), 0) FROM sequence", [], |row| {
+                    row.get(0)
+                })?;
+        Ok(seq as u64)
+    }
+
+    /// Up to `limit` sequenced events with `seq > ack_seq`, in sequence order, and
+    /// the highest `seq` looked at (`ack_seq` when there was none). Events that no
+    /// longer exist in any log file or can't be read back are skipped rather than
+    /// resent, so a page may come back with fewer entries, or none, while later
+    /// events remain: callers resume after the returned `seq`, not the last entry.
+    pub fn entries_after(&self, ack_seq: u64, limit: usize) -> Result<(Vec<SeqEntry>, u64)> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.seq, e.event_id, e.file, e.offset, e.len
+             FROM sequence s
+             JOIN events e ON e.id =
+                 (SELECT MAX(id) FROM events latest WHERE latest.event_id = s.event_id)
+             WHERE s.seq > ?1
+             ORDER BY s.seq
+             LIMIT ?2",
+        )?;
+        let mut rows = stmt.query(params![ack_seq as i64, limit as i64])?;
+        let mut lines = LineReader::default();
+        let mut out = Vec::new();
+        let mut scanned = ack_seq;
+        while let Some(row) = rows.next()? {
+            let seq: i64 = row.get(0)?;
+            scanned = seq as u64;
+            let line = LineRef {
+                event_id: row.get(1)?,
+                file: row.get(2)?,
+                offset: row.get::<_, i64>(3)? as u64,
+                len: row.get::<_, i64>(4)? as u64,
+            };
+            let Some(raw) = lines.read(&line)? else {
+                continue;
+            };
+            match serde_json::from_str::<MasterLog>(&raw) {
+                Ok(entry) => out.push(SeqEntry {
+                    seq: seq as u64,
+                    entry,
+                }),
+                Err(err) => tracing::warn!(seq, err = %err, "skipping unparsable sequenced event"),
+            }
+        }
+        Ok((out, scanned))
+    }
+}
+
+// ── Reading lines back ──────────────────────────────────────────────────
+
+/// Where an indexed event's line lives.
+struct LineRef {
+    event_id: String,
+    file: String,
+    offset: u64,
+    len: u64,
+}
+
+impl LineRef {
+    /// From `event_id, file, offset, len` columns.
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            event_id: row.get(0)?,
+            file: row.get(1)?,
+            offset: row.get::<_, i64>(2)? as u64,
+            len: row.get::<_, i64>(3)? as u64,
+        })
+    }
+}
+
+/// Reads indexed lines, keeping each file open across reads. Compressed archives
+/// can't seek, so their reader only moves forward and is reopened when a read
+/// goes backwards; query results come in file order often enough for this to be
+/// cheap.
+#[derive(Default)]
+struct LineReader {
+    files: HashMap<String, OpenLog>,
+}
+
+enum OpenLog {
+    Plain(File),
+    Compressed {
+        reader: Box<dyn BufRead + Send>,
+        pos: u64,
+    },
+    Missing,
+}
+
+impl LineReader {
+    /// The line `line` points at, or `None` if it no longer holds that event.
+    fn read(&mut self, line: &LineRef) -> Result<Option<String>> {
+        let path = Path::new(&line.file);
+        let open = match self.files.entry(line.file.clone()) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => entry.insert(open_for_read(path)?),
+        };
+
+        let mut buf = vec![0; line.len as usize];
+        let complete = match open {
+            OpenLog::Missing => false,
+            OpenLog::Plain(file) => {
+                file.seek(SeekFrom::Start(line.offset))?;
+                file.read_exact(&mut buf).is_ok()
+            }
+            OpenLog::Compressed { reader, pos } => {
+                if line.offset < *pos {
+                    *reader = open_log(path)?;
+                    *pos = 0;
+                }
+                let skip = line.offset - *pos;
+                let skipped = std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())?;
+                *pos += skipped;
+                let ok = skipped == skip && reader.read_exact(&mut buf).is_ok();
+                *pos = if ok { line.offset + line.len } else { u64::MAX };
+                ok
+            }
+        };
+        if !complete {
+            tracing::warn!(file = %line.file, "indexed line is gone; run a sync");
+            return Ok(None);
+        }
+        let raw = String::from_utf8_lossy(&buf).trim().to_string();
+        if !raw.contains(&line.event_id) {
+            tracing::warn!(file = %line.file, "indexed line has changed; run a sync");
+            return Ok(None);
+        }
+        Ok(Some(raw))
+    }
+}
+
+fn open_for_read(path: &Path) -> Result<OpenLog> {
+    if !path.exists() {
+        return Ok(OpenLog::Missing);
+    }
+    if is_compressed(path) {
+        Ok(OpenLog::Compressed {
+            reader: open_log(path)?,
+            pos: 0,
+        })
+    } else {
+        let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        Ok(OpenLog::Plain(file))
+    }
+}
+
+// ── Helpers ─────────────────────────────────────────────────────────────
+
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn load_file_records(conn: &Connection) -> Result<HashMap<String, FileRecord>> {
+    let mut stmt =
+        conn.prepare("SELECT path, indexed_bytes, head_len, head_hash FROM indexed_files")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(FileRecord {
+            path: row.get(0)?,
+            indexed_bytes: row.get::<_, i64>(1)? as u64,
+            head_len: row.get::<_, i64>(2)? as u64,
+            head_hash: row.get::<_, i64>(3)? as u64,
+        })
+    })?;
+    let mut out = HashMap::new();
+    for record in rows {
+        let record = record?;
+        out.insert(record.path.clone(), record);
+    }
+    Ok(out)
+}
+
+/// Length of a plain log file; `None` for a compressed archive, whose content length
+/// isn't known without decompressing it.
+fn logical_len(path: &Path, meta: &std::fs::Metadata) -> Option<u64> {
+    (!is_compressed(path)).then_some(meta.len())
+}
+
+/// Whether a file of `len` bytes can be the one `record` indexed.
+fn fits(record: &FileRecord, len: Option<u64>) -> bool {
+    len.is_none_or(|len| record.indexed_bytes <= len)
+}
+
+fn hash_head(path: &Path, len: u64) -> Result<Option<u64>> {
+    let mut reader = open_log(path)?;
+    let mut buf = Vec::with_capacity(len as usize);
+    reader.by_ref().take(len).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < len {
+        return Ok(None);
+    }
+    Ok(Some(xxhash_rust::xxh3::xxh3_64(&buf)))
+}
+
+fn head_matches(path: &Path, record: &FileRecord) -> Result<bool> {
+    if record.head_len == 0 {
+        return Ok(true);
+    }
+    Ok(hash_head(path, record.head_len)? == Some(record.head_hash))
+}
+
+/// Index complete lines appended to `path` since the last sync. A trailing line
+/// without a newline is left for the next pass, since a writer may still be mid-append.
+fn index_tail(conn: &Connection, path: &Path, stats: &mut SyncStats) -> Result<()> {
+    let key = path_key(path);
+    let (start, head_len, sealed): (i64, i64, bool) = conn.query_row(
+        "SELECT indexed_bytes, head_len, sealed FROM indexed_files WHERE path = ?1",
+        params![key],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    if sealed {
+        return Ok(());
+    }
+    let start = start as u64;
+
+    let compressed = is_compressed(path);
+    let mut reader: Box<dyn BufRead> = if compressed {
+        // No seeking inside a zstd stream: decompress past what is already indexed.
+        let mut reader = open_log(path)?;
+        std::io::copy(&mut reader.by_ref().take(start), &mut std::io::sink())?;
+        reader
+    } else {
+        let mut file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        file.seek(SeekFrom::Start(start))?;
+        Box::new(BufReader::new(file))
+    };
+
+    let mut insert = conn.prepare_cached(
+        "INSERT INTO events
+            (event_id, ts_ms, source_tool, session_id, project_context, role, content_hash, file,
+             offset, len)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    // Checked explicitly rather than via OR IGNORE, which still burns AUTOINCREMENT values.
+    let mut sequence = conn.prepare_cached(
+        "INSERT INTO sequence (event_id, line_hash)
+         SELECT ?1, ?2 WHERE NOT EXISTS (SELECT 1 FROM sequence WHERE event_id = ?1)",
+    )?;
+    let mut stamp = conn.prepare_cached(
+        "UPDATE sequence SET line_hash = ?2 WHERE event_id = ?1 AND line_hash IS NULL",
+    )?;
+    // A line that changed under the same event_id is renumbered by the insert above.
+    let mut revised =
+        conn.prepare_cached("DELETE FROM sequence WHERE event_id = ?1 AND line_hash IS NOT ?2")?;
+
+    let mut offset = start;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 || buf.last() != Some(&b'\n') {
+            break;
+        }
+        let line_offset = offset;
+        offset += read as u64;
+
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let log = match serde_json::from_str::<MasterLog>(line) {
+            Ok(log) => log,
+            Err(_) => {
+                stats.skipped += 1;
+                continue;
+            }
+        };
+        let event_id = log.event_id.to_string();
+        let line_hash = xxhash_rust::xxh3::xxh3_64(line.as_bytes()) as i64;
+        stamp.execute(params![event_id, line_hash])?;
+        revised.execute(params![event_id, line_hash])?;
+        sequence.execute(params![event_id, line_hash])?;
+        insert.execute(params![
+            event_id,
+            log.timestamp.timestamp_millis(),
+            log.source_tool,
+            log.session_id,
+            log.project_context,
+            log.interaction.role,
+            content_hash(&log.interaction.content) as i64,
+            key,
+            line_offset as i64,
+            (read - 1) as i64,
+        ])?;
+        stats.indexed += 1;
+    }
+
+    if compressed {
+        conn.execute(
+            "UPDATE indexed_files SET sealed = 1 WHERE path = ?1",
+            params![key],
+        )?;
+    }
+    if offset == start {
+        return Ok(());
+    }
+    let new_head_len = offset.min(HEAD_BYTES);
+    if new_head_len as i64 != head_len {
+        let head_hash = hash_head(path, new_head_len)?.unwrap_or(0);
+        conn.execute(
+            "UPDATE indexed_files SET indexed_bytes = ?1, head_len = ?2, head_hash = ?3
+             WHERE path = ?4",
+            params![offset as i64, new_head_len as i64, head_hash as i64, key],
+        )?;
+    } else {
+        conn.execute(
+            "UPDATE indexed_files SET indexed_bytes = ?1 WHERE path = ?2",
+            params![offset as i64, key],
+        )?;
+    }
+    Ok(())
+}
+
+fn build_where(query: &EventQuery) -> (String, Vec<SqlValue>) {
+    let mut clauses = Vec::new();
+    let mut args = Vec::new();
+    if let Some(since) = query.since {
+        clauses.push("ts_ms >= ?");
+        args.push(SqlValue::Integer(since.timestamp_millis()));
+    }
+    if let Some(until) = query.until {
+        clauses.push("ts_ms <= ?");
+        args.push(SqlValue::Integer(until.timestamp_millis()));
+    }
+    if let Some(tool) = &query.source_tool {
+        clauses.push("source_tool = ?");
+        args.push(SqlValue::Text(tool.clone()));
+    }
+    if let Some(session) = &query.session_id {
+        clauses.push("session_id = ?");
+        args.push(SqlValue::Text(session.clone()));
+    }
+    if let Some(project) = &query.project_context {
+        clauses.push("project_context = ?");
+        args.push(SqlValue::Text(project.clone()));
+    }
+    if let Some(event_id) = query.event_id {
+        clauses.push("event_id = ?");
+        args.push(SqlValue::Text(event_id.to_string()));
+    }
+    if clauses.is_empty() {
+        (String::new(), args)
+    } else {
+        (format!(" WHERE {}", clauses.join(" AND ")), args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Interaction, Metadata, SecurityFlags, SCHEMA_VERSION};
+    use chrono::TimeZone;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn event(source: &str, session: &str, content: &str, ts: DateTime<Utc>) -> MasterLog {
+        MasterLog {
+            schema_version: SCHEMA_VERSION,
+            event_id: Uuid::new_v4(),
+            timestamp: ts,
+            source_tool: source.to_string(),
+            project_context: "/tmp/project".to_string(),
+            session_id: session.to_string(),
+            interaction: Interaction {
+                role: "user".to_string(),
+                content: content.to_string(),
+                artifacts: None,
+            },
+            security_flags: SecurityFlags {
+                has_pii: false,
+                redacted_secrets: Vec::new(),
+            },
+            metadata: Metadata::default(),
+        }
+    }
+
+    fn append(path: &Path, logs: &[MasterLog]) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        for log in logs {
+            serde_json::to_writer(&mut file, log).unwrap();
+            file.write_all(b"\n").unwrap();
+        }
+    }
+
+    fn ts(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn sync_is_incremental_and_queries_filter() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        append(
+            &log_path,
+            &[
+                event("codex-cli", "s1", "first", ts(1, 10)),
+                event("cursor", "s2", "second", ts(2, 10)),
+            ],
+        );
+
+        let mut index = EventIndex::open(&log_path).unwrap();
+        assert_eq!(index.sync().unwrap().indexed, 2);
+
+        append(&log_path, &[event("codex-cli", "s1", "third", ts(3, 10))]);
+        let stats = index.sync().unwrap();
+        assert_eq!(stats.indexed, 1);
+        assert_eq!(stats.files_reset, 0);
+
+        let codex = index
+            .query(&EventQuery {
+                source_tool: Some("codex-cli".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        let contents: Vec<_> = codex
+            .iter()
+            .map(|l| l.interaction.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["first", "third"]);
+
+        let windowed = index
+            .query(&EventQuery {
+                since: Some(ts(2, 0)),
+                until: Some(ts(2, 23)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(windowed.len(), 1);
+        assert_eq!(windowed[0].session_id, "s2");
+
+        let latest = index
+            .query(&EventQuery {
+                limit: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+        let contents: Vec<_> = latest
+            .iter()
+            .map(|l| l.interaction.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["second", "third"]);
+    }
+
+    #[test]
+    fn partial_and_malformed_lines_are_handled() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        append(&log_path, &[event("cursor", "s1", "ok", ts(1, 10))]);
+        {
+            let mut file = OpenOptions::new().append(true).open(&log_path).unwrap();
+            file.write_all(b"not json\n{\"event_id\":").unwrap();
+        }
+
+        let mut index = EventIndex::open(&log_path).unwrap();
+        let stats = index.sync().unwrap();
+        assert_eq!(stats.indexed, 1);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(index.count(&EventQuery::default()).unwrap(), 1);
+    }
+
+    #[test]
+    fn rotation_is_detected_without_duplicating_events() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        append(
+            &log_path,
+            &[
+                event("cursor", "s1", "before rotation", ts(1, 10)),
+                event("cursor", "s1", "also before", ts(1, 11)),
+            ],
+        );
+
+        let mut index = EventIndex::open(&log_path).unwrap();
+        index.sync().unwrap();
+
+        let archive = dir.path().join("master_log.20250301T120000Z.jsonl");
+        fs::rename(&log_path, &archive).unwrap();
+        append(
+            &log_path,
+            &[event("cursor", "s1", "after rotation", ts(2, 10))],
+        );
+
+        let stats = index.sync().unwrap();
+        assert_eq!(stats.files_renamed, 1);
+        assert_eq!(stats.indexed, 1);
+        assert_eq!(index.count(&EventQuery::default()).unwrap(), 3);
+
+        fs::remove_file(&archive).unwrap();
+        index.sync().unwrap();
+        assert_eq!(index.count(&EventQuery::default()).unwrap(), 1);
+    }
+
+    #[test]
+    fn compressed_archives_are_adopted_and_sealed() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        append(
+            &log_path,
+            &[
+                event("codex-cli", "s1", "one", ts(1, 10)),
+                event("codex-cli", "s1", "two", ts(1, 11)),
+            ],
+        );
+        let mut index = EventIndex::open(&log_path).unwrap();
+        index.sync().unwrap();
+
+        // Lines appended after the last sync must still be picked up from the
+        // compressed copy.
+        append(&log_path, &[event("codex-cli", "s1", "three", ts(1, 12))]);
+        let archive = crate::rotation::rotate(&log_path).unwrap().unwrap();
+        crate::rotation::compress_archives(&log_path).unwrap();
+        assert!(!archive.exists());
+
+        let stats = index.sync().unwrap();
+        assert_eq!(stats.files_renamed, 1);
+        assert_eq!(stats.indexed, 1);
+        assert_eq!(index.count(&EventQuery::default()).unwrap(), 3);
+
+        let stats = index.sync().unwrap();
+        assert_eq!(stats, SyncStats::default());
+
+        // A fresh index reads the compressed archive from scratch.
+        fs::remove_file(index_path_for(&log_path)).unwrap();
+        let rebuilt = EventIndex::open_synced(&log_path).unwrap();
+        assert_eq!(rebuilt.count(&EventQuery::default()).unwrap(), 3);
+    }
+
+    #[test]
+    fn rewritten_file_is_reindexed() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        append(&log_path, &[event("cursor", "s1", "old", ts(1, 10))]);
+
+        let mut index = EventIndex::open(&log_path).unwrap();
+        index.sync().unwrap();
+
+        fs::remove_file(&log_path).unwrap();
+        append(
+            &log_path,
+            &[
+                event("codex-cli", "s9", "new one", ts(4, 10)),
+                event("codex-cli", "s9", "new two", ts(4, 11)),
+            ],
+        );
+        let stats = index.sync().unwrap();
+        assert_eq!(stats.files_reset, 1);
+        let all = index.query(&EventQuery::default()).unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all.iter().all(|l| l.session_id == "s9"));
+    }
+
+    #[test]
+    fn lines_are_read_back_from_the_log() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let first = event("cursor", "s1", "a distinctive sentence", ts(1, 10));
+        append(&log_path, std::slice::from_ref(&first));
+
+        let mut index = EventIndex::open(&log_path).unwrap();
+        index.sync().unwrap();
+        assert_eq!(
+            index
+                .get(first.event_id)
+                .unwrap()
+                .unwrap()
+                .interaction
+                .content,
+            "a distinctive sentence"
+        );
+        let stored = fs::read(index_path_for(&log_path)).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("distinctive"));
+
+        // Rewritten behind the index's back: the stale location is skipped.
+        fs::remove_file(&log_path).unwrap();
+        append(
+            &log_path,
+            &[event("cursor", "s1", "a distinctive sentence", ts(1, 10))],
+        );
+        assert!(index.get(first.event_id).unwrap().is_none());
+        assert!(index.query(&EventQuery::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn history_keys_match_content_keys() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let log = event("claude-code", "s1", "hello", ts(1, 10));
+        append(&log_path, std::slice::from_ref(&log));
+        append(&log_path, &[event("claude-code", "s1", "hello", ts(1, 11))]);
+
+        let index = EventIndex::open_synced(&log_path).unwrap();
+        let keys = index.history_keys().unwrap();
+        assert!(keys.contains(&history_key("claude-code", "s1", "hello")));
+        assert!(!keys.contains(&history_key("claude-code", "s1", "other")));
+        assert_eq!(
+            index.session_history_keys("claude-code", "s1").unwrap(),
+            HashSet::from([history_key("claude-code", "s1", "hello")])
+        );
+        assert!(index
+            .session_history_keys("claude-code", "s2")
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            index
+                .session_history_key_counts("claude-code", "s1")
+                .unwrap(),
+            HashMap::from([(history_key("claude-code", "s1", "hello"), 2)])
+        );
+        assert_eq!(
+            index
+                .get(log.event_id)
+                .unwrap()
+                .map(|l| l.interaction.content),
+            Some("hello".to_string())
+        );
+    }
+
+    #[test]
+    fn sequence_numbers_survive_reopen_and_rebuild() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        append(
+            &log_path,
+            &[
+                event("cursor", "s1", "one", ts(1, 10)),
+                event("cursor", "s1", "two", ts(1, 11)),
+            ],
+        );
+
+        let index = EventIndex::open_synced(&log_path).unwrap();
+        let log_id = index.log_id().unwrap();
+        let seqs: Vec<u64> = index
+            .entries_after(0, 10)
+            .unwrap()
+            .0
+            .iter()
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, vec![1, 2]);
+        // Force a schema rebuild on the next open.
+        index.conn.pragma_update(None, "user_version", 0).unwrap();
+        drop(index);
+
+        append(&log_path, &[event("cursor", "s1", "three", ts(1, 12))]);
+        let index = EventIndex::open_synced(&log_path).unwrap();
+        assert_eq!(index.log_id().unwrap(), log_id);
+        assert_eq!(index.head_seq().unwrap(), 3);
+        let after: Vec<_> = index
+            .entries_after(1, 10)
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|e| (e.seq, e.entry.interaction.content))
+            .collect();
+        assert_eq!(
+            after,
+            vec![(2, "two".to_string()), (3, "three".to_string())]
+        );
+        assert_eq!(index.entries_after(3, 10).unwrap().1, 3);
+    }
+
+    #[test]
+    fn rewritten_events_are_renumbered() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let events = [
+            event("cursor", "s1", "password hunter2", ts(1, 10)),
+            event("cursor", "s1", "two", ts(1, 11)),
+        ];
+        append(&log_path, &events);
+        let mut index = EventIndex::open_synced(&log_path).unwrap();
+        assert_eq!(index.head_seq().unwrap(), 2);
+
+        // Same lines again, e.g. a purge rewriting the file: nothing is renumbered.
+        let text = fs::read_to_string(&log_path).unwrap();
+        fs::write(&log_path, &text).unwrap();
+        index.conn.execute("DELETE FROM indexed_files", []).unwrap();
+        index.sync().unwrap();
+        assert_eq!(index.head_seq().unwrap(), 2);
+
+        fs::write(&log_path, text.replace("hunter2", "[REDACTED]")).unwrap();
+        index.sync().unwrap();
+        assert_eq!(index.head_seq().unwrap(), 3);
+        let (entries, _) = index.entries_after(2, 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].entry.event_id, events[0].event_id);
+        assert_eq!(entries[0].entry.interaction.content, "password [REDACTED]");
+    }
+
+    #[test]
+    fn unreadable_pages_still_advance() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let events = [
+            event("cursor", "s1", "one", ts(1, 10)),
+            event("cursor", "s1", "two", ts(1, 11)),
+            event("cursor", "s1", "three", ts(1, 12)),
+        ];
+        append(&log_path, &events);
+        let index = EventIndex::open_synced(&log_path).unwrap();
+
+        // Rewritten behind the index's back: the first two lines no longer hold them.
+        let mut text = fs::read_to_string(&log_path).unwrap();
+        for log in &events[..2] {
+            text = text.replace(&log.event_id.to_string(), &Uuid::nil().to_string());
+        }
+        fs::write(&log_path, text).unwrap();
+        let (entries, next_seq) = index.entries_after(0, 2).unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(next_seq, 2);
+        let (entries, next_seq) = index.entries_after(next_seq, 2).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(next_seq, 3);
+    }
+}
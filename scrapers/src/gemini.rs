@@ -0,0 +1,304 @@
+//! Gemini CLI chat transcripts.
+//!
+//! Gemini CLI records each chat as one JSON document at
+//! `~/.gemini/tmp/<project>/chats/session-*.json` and rewrites the whole file as the
+//! conversation grows, so readers track messages rather than byte offsets. The
+//! `<project>` directory is the SHA-256 of the project root (`projectHash` in the
+//! transcript); when Gemini CLI also writes a `.project_root` file there, that path
+//! is used as the project context.
+
+use crate::parse::{
+    append_usage, clip_artifacts, extract_text, insert_scalar, parse_timestamp_value, ParsedLine,
+};
+use crate::types::Artifact;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Chat files live in this subdirectory of each project directory.
+const CHATS_DIR: &str = "chats";
+
+/// Written next to `chats/` by Gemini CLI releases that record the project path.
+const PROJECT_ROOT_FILE: &str = ".project_root";
+
+#[derive(Debug, Clone)]
+pub struct GeminiSession {
+    pub session_id: String,
+    pub project_hash: Option<String>,
+    pub messages: Vec<ParsedLine>,
+}
+
+/// Parse a whole chat file. Messages without text (and without tool calls) are
+/// dropped; `info`/`warning`/`error` entries become `system` turns.
+pub fn parse_gemini_session(raw: &str) -> Option<GeminiSession> {
+    let json = serde_json::from_str::<Value>(raw).ok()?;
+    let session_id = json
+        .get("sessionId")
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())?
+        .to_string();
+    let project_hash = json
+        .get("projectHash")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let messages = json
+        .get("messages")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|m| parse_gemini_message(m, &session_id, project_hash.as_deref()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(GeminiSession {
+        session_id,
+        project_hash,
+        messages,
+    })
+}
+
+fn parse_gemini_message(
+    json: &Value,
+    session_id: &str,
+    project_hash: Option<&str>,
+) -> Option<ParsedLine> {
+    let mut metadata = Map::new();
+    metadata.insert(
+        "conversation_id".to_string(),
+        Value::String(session_id.to_string()),
+    );
+    if let Some(hash) = project_hash {
+        metadata.insert(
+            "gemini_project_hash".to_string(),
+            Value::String(hash.to_string()),
+        );
+    }
+    if let Some(id) = json.get("id") {
+        insert_scalar(&mut metadata, "gemini_message_id", id);
+    }
+    if let Some(model) = json.get("model") {
+        insert_scalar(&mut metadata, "model", model);
+    }
+    if let Some(tokens) = json.get("tokens") {
+        append_usage(&mut metadata, tokens);
+        if let Some(cached) = tokens.get("cached") {
+            insert_scalar(&mut metadata, "usage_cached_input_tokens", cached);
+        }
+        if let Some(thoughts) = tokens.get("thoughts") {
+            insert_scalar(&mut metadata, "usage_reasoning_output_tokens", thoughts);
+        }
+    }
+
+    let timestamp = json.get("timestamp").and_then(parse_timestamp_value);
+    if let Some(ts) = timestamp.as_ref() {
+        metadata.insert(
+            "original_timestamp".to_string(),
+            Value::String(ts.to_rfc3339()),
+        );
+    }
+
+    let role = match json.get("type").and_then(Value::as_str)? {
+        "user" => "user",
+        "gemini" | "model" => "assistant",
+        _ => "system",
+    }
+    .to_string();
+
+    let tool_calls = json
+        .get("toolCalls")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let tool_names: Vec<Value> = tool_calls
+        .iter()
+        .filter_map(|call| call.get("name").and_then(Value::as_str))
+        .map(|name| Value::String(name.to_string()))
+        .collect();
+    if !tool_names.is_empty() {
+        metadata.insert("tool_calls".to_string(), Value::Array(tool_names));
+    }
+
+    let content = json
+        .get("content")
+        .and_then(extract_text)
+        .filter(|text| !text.trim().is_empty())
+        .or_else(|| summarize_tool_calls(tool_calls))?;
+
+    let artifacts = tool_calls
+        .iter()
+        .filter_map(|call| {
+            Some(Artifact::ToolCall {
+                call_id: call.get("id").and_then(Value::as_str).map(str::to_string),
+                name: call.get("name").and_then(Value::as_str)?.to_string(),
+                input: call
+                    .get("args")
+                    .map(Value::to_string)
+                    .unwrap_or_else(|| "{}".to_string()),
+            })
+        })
+        .collect();
+
+    Some(ParsedLine {
+        role,
+        content,
+        timestamp,
+        session_id: Some(session_id.to_string()),
+        project_context: None,
+        metadata,
+        artifacts: clip_artifacts(artifacts),
+    })
+}
+
+/// Assistant turns that only invoke tools have no text; describe the calls instead.
+fn summarize_tool_calls(calls: &[Value]) -> Option<String> {
+    let lines: Vec<String> = calls
+        .iter()
+        .filter_map(|call| {
+            let name = call.get("name").and_then(Value::as_str)?;
+            let args = call
+                .get("args")
+                .map(Value::to_string)
+                .unwrap_or_else(|| "{}".to_string());
+            Some(format!("Tool call: {name} {args}"))
+        })
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Every `session-*.json` under `<root>/*/chats/`, sorted by path.
+pub fn find_session_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(projects) = fs::read_dir(root) else {
+        return files;
+    };
+    for project in projects.flatten() {
+        let Ok(chats) = fs::read_dir(project.path().join(CHATS_DIR)) else {
+            continue;
+        };
+        for chat in chats.flatten() {
+            let path = chat.path();
+            let is_session = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .is_some_and(|name| name.starts_with("session-") && name.ends_with(".json"));
+            if is_session && path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// The project path recorded next to a chat file's `chats/` directory, if any.
+pub fn recorded_project_root(chat_file: &Path) -> Option<String> {
+    let project_dir = chat_file.parent()?.parent()?;
+    let raw = fs::read_to_string(project_dir.join(PROJECT_ROOT_FILE)).ok()?;
+    let root = raw.trim();
+    (!root.is_empty()).then(|| root.to_string())
+}
+
+/// Gemini CLI's project hash: hex SHA-256 of the project root path.
+pub fn project_hash(project_root: &str) -> String {
+    Sha256::digest(project_root.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_user_model_and_tool_only_turns() {
+        let raw = json!({
+            "sessionId": "abc-123",
+            "projectHash": "deadbeef",
+            "startTime": "2026-03-01T10:00:00.000Z",
+            "messages": [
+                {"id": "m1", "timestamp": "2026-03-01T10:00:01.000Z", "type": "user", "content": "fix the build"},
+                {
+                    "id": "m2",
+                    "timestamp": "2026-03-01T10:00:05.000Z",
+                    "type": "gemini",
+                    "content": "",
+                    "model": "gemini-2.5-pro",
+                    "tokens": {"input": 1200, "output": 40, "cached": 800, "thoughts": 12, "total": 1252},
+                    "toolCalls": [{"id": "t1", "name": "read_file", "args": {"absolute_path": "/tmp/a.rs"}}]
+                },
+                {"id": "m3", "timestamp": "2026-03-01T10:00:09.000Z", "type": "gemini", "content": [{"text": "Done."}]},
+                {"id": "m4", "type": "info", "content": "Request cancelled."},
+                {"id": "m5", "type": "gemini", "content": "  "}
+            ]
+        })
+        .to_string();
+
+        let session = parse_gemini_session(&raw).unwrap();
+        assert_eq!(session.session_id, "abc-123");
+        assert_eq!(session.project_hash.as_deref(), Some("deadbeef"));
+        assert_eq!(session.messages.len(), 4);
+
+        let user = &session.messages[0];
+        assert_eq!(user.role, "user");
+        assert_eq!(user.content, "fix the build");
+        assert_eq!(user.session_id.as_deref(), Some("abc-123"));
+
+        let tool_turn = &session.messages[1];
+        assert_eq!(tool_turn.role, "assistant");
+        assert!(tool_turn.content.starts_with("Tool call: read_file "));
+        assert_eq!(tool_turn.metadata["model"], "gemini-2.5-pro");
+        assert_eq!(tool_turn.metadata["usage_prompt_tokens"], 1200);
+        assert_eq!(tool_turn.metadata["usage_completion_tokens"], 40);
+        assert_eq!(tool_turn.metadata["usage_cached_input_tokens"], 800);
+        assert_eq!(tool_turn.metadata["tool_calls"], json!(["read_file"]));
+        assert_eq!(
+            tool_turn.artifacts,
+            vec![Artifact::ToolCall {
+                call_id: Some("t1".to_string()),
+                name: "read_file".to_string(),
+                input: r#"{"absolute_path":"/tmp/a.rs"}"#.to_string(),
+            }]
+        );
+
+        assert_eq!(session.messages[2].content, "Done.");
+        assert_eq!(session.messages[3].role, "system");
+    }
+
+    #[test]
+    fn rejects_files_without_a_session_id() {
+        assert!(parse_gemini_session("{\"messages\": []}").is_none());
+        assert!(parse_gemini_session("not json").is_none());
+    }
+
+    #[test]
+    fn finds_chat_files_and_recorded_project_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join(project_hash("/work/app"));
+        fs::create_dir_all(project.join(CHATS_DIR)).unwrap();
+        let chat = project
+            .join(CHATS_DIR)
+            .join("session-2026-03-01T10-00-abc.json");
+        fs::write(&chat, "{}").unwrap();
+        fs::write(project.join(CHATS_DIR).join("notes.txt"), "").unwrap();
+        fs::create_dir_all(dir.path().join("bin")).unwrap();
+
+        assert_eq!(find_session_files(dir.path()), vec![chat.clone()]);
+        assert_eq!(recorded_project_root(&chat), None);
+        fs::write(project.join(PROJECT_ROOT_FILE), "/work/app\n").unwrap();
+        assert_eq!(recorded_project_root(&chat).as_deref(), Some("/work/app"));
+    }
+
+    #[test]
+    fn project_hash_is_hex_sha256() {
+        assert_eq!(
+            project_hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Working-tree snapshots taken when an agent session goes quiet.
+//!
+//! A snapshot is `git status` plus `git diff --numstat HEAD` for the repository that
+//! contains a session's `project_context`. It is what the session left behind, not a
+//! per-turn attribution: edits made by hand in the same tree show up too.
+
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Paths kept per list, so a freshly generated tree can't bloat one log line.
+/// The diff counts are never truncated.
+const MAX_PATHS: usize = 200;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffStat {
+    pub files_changed: u64,
+    pub insertions: u64,
+    pub deletions: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitSnapshot {
+    /// `None` in a repository without commits.
+    pub head_sha: Option<String>,
+    pub branch: Option<String>,
+    /// Short status lines (`M src/main.rs`, `?? notes.md`).
+    pub status: Vec<String>,
+    pub created: Vec<String>,
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+    /// Tracked changes against HEAD; untracked files aren't counted.
+    pub diffstat: DiffStat,
+}
+
+/// Snapshot the repository containing `dir`. `None` when `dir` isn't inside a git
+/// work tree or git isn't installed.
+pub fn snapshot(dir: &Path) -> Option<GitSnapshot> {
+    if !dir.is_dir() {
+        return None;
+    }
+    let root = repo_root(dir)?;
+    let root = root.as_path();
+
+    let head_sha = git(root, &["rev-parse", "--verify", "--quiet", "HEAD"])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let branch = git(root, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty() && s != "HEAD");
+
+    let mut snapshot = parse_status(&git(root, &["status", "--porcelain=v1", "-z"])?);
+    snapshot.head_sha = head_sha;
+    snapshot.branch = branch;
+    if snapshot.head_sha.is_some() {
+        if let Some(numstat) = git(root, &["diff", "--numstat", "HEAD"]) {
+            snapshot.diffstat = parse_numstat(&numstat);
+        }
+    }
+    Some(snapshot)
+}
+
+/// Top level of the work tree containing `dir`.
+pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+    let root = git(dir, &["rev-parse", "--show-toplevel"])?;
+    let root = root.trim();
+    (!root.is_empty()).then(|| PathBuf::from(root))
+}
+
+impl GitSnapshot {
+    /// Record the snapshot under the typed metadata keys (`git_commit_hash`,
+    /// `file_effects`, `files_created`, `diff_insertions`, ...). Empty lists and a
+    /// clean diff are left out, so a clean tree adds only the HEAD and branch.
+    pub fn insert_into(&self, metadata: &mut Map<String, Value>) {
+        if let Some(sha) = &self.head_sha {
+            metadata.insert("git_commit_hash".to_string(), Value::String(sha.clone()));
+        }
+        if let Some(branch) = &self.branch {
+            metadata.insert("git_branch".to_string(), Value::String(branch.clone()));
+        }
+        for (key, paths) in [
+            ("file_effects", &self.status),
+            ("files_created", &self.created),
+            ("files_modified", &self.modified),
+            ("files_deleted", &self.deleted),
+        ] {
+            if !paths.is_empty() {
+                let capped = paths.iter().take(MAX_PATHS).cloned().map(Value::String);
+                metadata.insert(key.to_string(), Value::Array(capped.collect()));
+            }
+        }
+        if self.diffstat.files_changed > 0 {
+            for (key, count) in [
+                ("diff_files_changed", self.diffstat.files_changed),
+                ("diff_insertions", self.diffstat.insertions),
+                ("diff_deletions", self.diffstat.deletions),
+            ] {
+                metadata.insert(key.to_string(), Value::from(count));
+            }
+        }
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `git status --porcelain=v1 -z`. A rename counts as the new path created
+/// and the old one deleted.
+fn parse_status(raw: &str) -> GitSnapshot {
+    let mut snapshot = GitSnapshot::default();
+    let mut entries = raw.split('\0').filter(|e| !e.is_empty());
+    while let Some(entry) = entries.next() {
+        let (Some(code), Some(path)) = (entry.get(..2), entry.get(3..)) else {
+            continue;
+        };
+        let path = path.to_string();
+        snapshot.status.push(format!("{} {}", code.trim(), path));
+
+        let (index, worktree) = (code.as_bytes()[0], code.as_bytes()[1]);
+        if matches!(index, b'R' | b'C') {
+            if let Some(from) = entries.next() {
+                if index == b'R' {
+                    snapshot.deleted.push(from.to_string());
+                }
+            }
+            snapshot.created.push(path);
+        } else if code == "??" || index == b'A' {
+            snapshot.created.push(path);
+        } else if index == b'D' || worktree == b'D' {
+            snapshot.deleted.push(path);
+        } else {
+            snapshot.modified.push(path);
+        }
+    }
+    snapshot
+}
+
+/// Sum `git diff --numstat` output. Binary files (`-\t-\tpath`) count as changed
+/// files with no line counts.
+fn parse_numstat(raw: &str) -> DiffStat {
+    let mut stat = DiffStat::default();
+    for line in raw.lines() {
+        let mut cols = line.splitn(3, '\t');
+        let (Some(added), Some(removed), Some(_path)) = (cols.next(), cols.next(), cols.next())
+        else {
+            continue;
+        };
+        stat.files_changed += 1;
+        stat.insertions += added.parse::<u64>().unwrap_or(0);
+        stat.deletions += removed.parse::<u64>().unwrap_or(0);
+    }
+    stat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn classifies_porcelain_entries() {
+        let raw = " M src/main.rs\0A  src/new.rs\0?? notes.md\0 D old.rs\0R  lib2.rs\0lib.rs\0";
+        let snapshot = parse_status(raw);
+        assert_eq!(
+            snapshot.status,
+            vec![
+                "M src/main.rs",
+                "A src/new.rs",
+                "?? notes.md",
+                "D old.rs",
+                "R lib2.rs"
+            ]
+        );
+        assert_eq!(snapshot.created, vec!["src/new.rs", "notes.md", "lib2.rs"]);
+        assert_eq!(snapshot.modified, vec!["src/main.rs"]);
+        assert_eq!(snapshot.deleted, vec!["old.rs", "lib.rs"]);
+    }
+
+    #[test]
+    fn sums_numstat_including_binary_files() {
+        let stat = parse_numstat("10\t2\tsrc/main.rs\n-\t-\tlogo.png\n3\t0\tREADME.md\n");
+        assert_eq!(
+            stat,
+            DiffStat {
+                files_changed: 3,
+                insertions: 13,
+                deletions: 2
+            }
+        );
+    }
+
+    #[test]
+    fn snapshots_a_real_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        let run = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(repo)
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .output()
+                .map(|o| o.status.success())
+                .unwrap_or(false)
+        };
+        if !run(&["init", "-q"]) {
+            return; // git isn't installed
+        }
+        fs::write(repo.join("keep.txt"), "one\n").unwrap();
+        fs::write(repo.join("gone.txt"), "bye\n").unwrap();
+        assert!(run(&["add", "."]));
+        assert!(run(&["commit", "-q", "-m", "init"]));
+
+        fs::write(repo.join("keep.txt"), "one\ntwo\n").unwrap();
+        fs::remove_file(repo.join("gone.txt")).unwrap();
+        fs::write(repo.join("fresh.txt"), "hi\n").unwrap();
+        fs::create_dir(repo.join("sub")).unwrap();
+
+        let snapshot = snapshot(&repo.join("sub")).unwrap();
+        assert_eq!(snapshot.head_sha.as_ref().map(String::len), Some(40));
+        assert_eq!(snapshot.created, vec!["fresh.txt"]);
+        assert_eq!(snapshot.modified, vec!["keep.txt"]);
+        assert_eq!(snapshot.deleted, vec!["gone.txt"]);
+        assert_eq!(
+            snapshot.diffstat,
+            DiffStat {
+                files_changed: 2,
+                insertions: 1,
+                deletions: 1
+            }
+        );
+
+        let mut metadata = Map::new();
+        snapshot.insert_into(&mut metadata);
+        assert_eq!(metadata["files_deleted"], serde_json::json!(["gone.txt"]));
+        assert_eq!(metadata["diff_insertions"], 1);
+
+        assert!(super::snapshot(&std::env::temp_dir().join("no-such-dir-xyz")).is_none());
+    }
+}
@@ -1,16 +1,20 @@
+use crate::antigravity::{find_conversation_files, read_conversation};
 use crate::claude::{parse_claude_line, parse_claude_session_line};
 use crate::codex::parse_codex_line;
 use crate::config::ContrailConfig;
-use crate::cursor::{read_cursor_messages, timestamp_from_metadata};
+use crate::cursor::{read_workspace_messages, timestamp_from_metadata};
+use crate::event_index::{history_key, EventIndex};
+use crate::gemini::{find_session_files, parse_gemini_session, recorded_project_root};
+use crate::log_lock::{repair_tail, LogLock};
 use crate::parse::parse_timestamp_value;
+use crate::purge::Tombstones;
 use crate::sentry::Sentry;
-use crate::types::{Interaction, MasterLog};
+use crate::types::{Artifact, Interaction, MasterLog, SecurityFlags, SCHEMA_VERSION};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde_json::{Map, Value};
 use std::collections::HashSet;
 use std::fs;
-use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
@@ -32,6 +36,9 @@ pub fn import_history(config: &ContrailConfig) -> Result<ImportStats> {
         fs::create_dir_all(dir).with_context(|| format!("create log dir {dir:?}"))?;
     }
 
+    // Held until the import returns, after `writer` has been flushed.
+    let _lock = LogLock::acquire(&config.log_path)?;
+    repair_tail(&config.log_path)?;
     let mut existing = load_existing_keys(&config.log_path)?;
     let mut file = fs::OpenOptions::new()
         .create(true)
@@ -39,7 +46,7 @@ pub fn import_history(config: &ContrailConfig) -> Result<ImportStats> {
         .open(&config.log_path)
         .with_context(|| format!("open master log at {:?}", config.log_path))?;
     let mut writer = std::io::BufWriter::new(&mut file);
-    let sentry = Sentry::new();
+    let sentry = Sentry::from_config(config)?;
 
     if config.enable_codex {
         import_codex_root(
@@ -76,9 +83,19 @@ pub fn import_history(config: &ContrailConfig) -> Result<ImportStats> {
             &mut stats,
         )?;
     }
+    if config.enable_gemini {
+        import_gemini_root(
+            &config.gemini_root,
+            &mut writer,
+            &sentry,
+            &mut existing,
+            &mut stats,
+        )?;
+    }
     if config.enable_antigravity {
         import_antigravity_root(
             &config.antigravity_brain,
+            &config.antigravity_conversations,
             &mut writer,
             &sentry,
             &mut existing,
@@ -173,6 +190,7 @@ fn import_codex_file(
         let mut content = line.clone();
         let mut project_context = "Imported History".to_string();
         let mut timestamp = None;
+        let mut artifacts = Vec::new();
 
         if let Some(parsed) = parse_codex_line(&line) {
             if let Some(id) = parsed.session_id {
@@ -181,6 +199,7 @@ fn import_codex_file(
             role = parsed.role;
             content = parsed.content;
             timestamp = parsed.timestamp;
+            artifacts = parsed.artifacts;
             if let Some(ctx) = parsed.project_context {
                 project_context = ctx;
             }
@@ -212,7 +231,8 @@ fn import_codex_file(
         };
         last_ts = Some(ts);
 
-        let (content, flags) = sentry.scan_and_redact(&content);
+        let (content, mut flags) = sentry.scan_and_redact(&content);
+        let artifacts = redacted_artifacts(sentry, artifacts, &mut flags);
 
         let key = dedupe_key("codex-cli", &session_id, &content);
         if existing.contains(&key) {
@@ -222,6 +242,7 @@ fn import_codex_file(
         existing.insert(key);
 
         let log = MasterLog {
+            schema_version: SCHEMA_VERSION,
             event_id: Uuid::new_v4(),
             timestamp: ts,
             source_tool: "codex-cli".to_string(),
@@ -230,10 +251,10 @@ fn import_codex_file(
             interaction: Interaction {
                 role,
                 content,
-                artifacts: None,
+                artifacts,
             },
             security_flags: flags,
-            metadata: Value::Object(metadata),
+            metadata: metadata.into(),
         };
 
         if log.validate_schema().is_ok() {
@@ -345,6 +366,7 @@ fn import_claude_file(
         existing.insert(key);
 
         let log = MasterLog {
+            schema_version: SCHEMA_VERSION,
             event_id: Uuid::new_v4(),
             timestamp: timestamp.unwrap_or_else(Utc::now),
             source_tool: "claude-code".to_string(),
@@ -356,7 +378,7 @@ fn import_claude_file(
                 artifacts: None,
             },
             security_flags: flags,
-            metadata: Value::Object(metadata),
+            metadata: metadata.into(),
         };
 
         if log.validate_schema().is_ok() {
@@ -452,7 +474,8 @@ fn import_claude_session_file(
             .project_context
             .unwrap_or_else(|| "Claude Session".to_string());
 
-        let (content, flags) = sentry.scan_and_redact(&parsed.content);
+        let (content, mut flags) = sentry.scan_and_redact(&parsed.content);
+        let artifacts = redacted_artifacts(sentry, parsed.artifacts, &mut flags);
 
         let key = dedupe_key("claude-code", &session_id, &content);
         if existing.contains(&key) {
@@ -462,6 +485,7 @@ fn import_claude_session_file(
         existing.insert(key);
 
         let log = MasterLog {
+            schema_version: SCHEMA_VERSION,
             event_id: Uuid::new_v4(),
             timestamp: parsed.timestamp.unwrap_or_else(Utc::now),
             source_tool: "claude-code".to_string(),
@@ -470,10 +494,87 @@ fn import_claude_session_file(
             interaction: Interaction {
                 role: parsed.role,
                 content,
-                artifacts: None,
+                artifacts,
             },
             security_flags: flags,
-            metadata: Value::Object(metadata),
+            metadata: metadata.into(),
+        };
+
+        if log.validate_schema().is_ok() {
+            writeln!(writer, "{}", serde_json::to_string(&log)?)?;
+            stats.imported += 1;
+        } else {
+            stats.errors += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Import Gemini CLI chat transcripts from ~/.gemini/tmp/*/chats/session-*.json
+fn import_gemini_root(
+    root: &Path,
+    writer: &mut dyn Write,
+    sentry: &Sentry,
+    existing: &mut HashSet<u64>,
+    stats: &mut ImportStats,
+) -> Result<()> {
+    for path in find_session_files(root) {
+        if let Err(e) = import_gemini_file(&path, writer, sentry, existing, stats) {
+            tracing::warn!(path = ?path, err = %e, "import gemini chat file failed");
+            stats.errors += 1;
+        }
+    }
+    Ok(())
+}
+
+fn import_gemini_file(
+    path: &Path,
+    writer: &mut dyn Write,
+    sentry: &Sentry,
+    existing: &mut HashSet<u64>,
+    stats: &mut ImportStats,
+) -> Result<()> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("read gemini chat file {path:?}"))?;
+    let session =
+        parse_gemini_session(&raw).with_context(|| format!("parse gemini chat file {path:?}"))?;
+    let project_root = recorded_project_root(path);
+    let project_context = project_root
+        .clone()
+        .unwrap_or_else(|| "Gemini Session".to_string());
+
+    for parsed in session.messages {
+        let mut metadata = parsed.metadata;
+        metadata.insert("imported".to_string(), Value::Bool(true));
+        if let Some(cwd) = project_root.as_ref() {
+            metadata.insert("cwd".to_string(), Value::String(cwd.clone()));
+        }
+
+        let (content, mut flags) = sentry.scan_and_redact(&parsed.content);
+        let artifacts = redacted_artifacts(sentry, parsed.artifacts, &mut flags);
+
+        let key = dedupe_key("gemini-cli", &session.session_id, &content);
+        if existing.contains(&key) {
+            stats.skipped += 1;
+            continue;
+        }
+        existing.insert(key);
+
+        let log = MasterLog {
+            schema_version: SCHEMA_VERSION,
+            event_id: Uuid::new_v4(),
+            timestamp: parsed.timestamp.unwrap_or_else(Utc::now),
+            source_tool: "gemini-cli".to_string(),
+            project_context: project_context.clone(),
+            session_id: session.session_id.clone(),
+            interaction: Interaction {
+                role: parsed.role,
+                content,
+                artifacts,
+            },
+            security_flags: flags,
+            metadata: metadata.into(),
         };
 
         if log.validate_schema().is_ok() {
@@ -536,7 +637,7 @@ fn import_cursor_db(
     let project_context = resolve_cursor_project_context(workspace_dir)
         .unwrap_or_else(|| workspace_dir.to_string_lossy().to_string());
 
-    let messages = read_cursor_messages(db_path)?;
+    let messages = read_workspace_messages(db_path)?;
     if messages.is_empty() {
         return Ok(());
     }
@@ -576,7 +677,8 @@ fn import_cursor_db(
             }
         };
 
-        let (content, flags) = sentry.scan_and_redact(&message.content);
+        let (content, mut flags) = sentry.scan_and_redact(&message.content);
+        let artifacts = redacted_artifacts(sentry, message.artifacts, &mut flags);
         let key = dedupe_key("cursor", &session_id, &content);
         if existing.contains(&key) {
             stats.skipped += 1;
@@ -585,6 +687,7 @@ fn import_cursor_db(
         existing.insert(key);
 
         let log = MasterLog {
+            schema_version: SCHEMA_VERSION,
             event_id: Uuid::new_v4(),
             timestamp: ts,
             source_tool: "cursor".to_string(),
@@ -593,10 +696,10 @@ fn import_cursor_db(
             interaction: Interaction {
                 role: message.role,
                 content,
-                artifacts: None,
+                artifacts,
             },
             security_flags: flags,
-            metadata: Value::Object(metadata),
+            metadata: metadata.into(),
         };
 
         if log.validate_schema().is_ok() {
@@ -626,11 +729,20 @@ fn resolve_cursor_project_context(workspace_dir: &Path) -> Option<String> {
 
 fn import_antigravity_root(
     brain_dir: &Path,
+    conversations_dir: &Path,
     writer: &mut dyn Write,
     sentry: &Sentry,
     existing: &mut HashSet<u64>,
     stats: &mut ImportStats,
 ) -> Result<()> {
+    // Decoded conversations first: they carry the actual turns.
+    for path in find_conversation_files(conversations_dir) {
+        if let Err(e) = import_antigravity_conversation(&path, writer, sentry, existing, stats) {
+            tracing::warn!(path = ?path, err = %e, "import antigravity conversation failed");
+            stats.errors += 1;
+        }
+    }
+
     if !brain_dir.exists() {
         return Ok(());
     }
@@ -652,6 +764,61 @@ fn import_antigravity_root(
     Ok(())
 }
 
+fn import_antigravity_conversation(
+    path: &Path,
+    writer: &mut dyn Write,
+    sentry: &Sentry,
+    existing: &mut HashSet<u64>,
+    stats: &mut ImportStats,
+) -> Result<()> {
+    let fallback_timestamp = path
+        .metadata()
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(system_time_to_utc)
+        .unwrap_or_else(Utc::now);
+
+    for parsed in read_conversation(path)? {
+        let mut metadata = parsed.metadata;
+        metadata.insert("imported".to_string(), Value::Bool(true));
+        let session_id = parsed.session_id.unwrap_or_default();
+
+        let (content, flags) = sentry.scan_and_redact(&parsed.content);
+
+        let key = dedupe_key("antigravity", &session_id, &content);
+        if existing.contains(&key) {
+            stats.skipped += 1;
+            continue;
+        }
+        existing.insert(key);
+
+        let log = MasterLog {
+            schema_version: SCHEMA_VERSION,
+            event_id: Uuid::new_v4(),
+            timestamp: parsed.timestamp.unwrap_or(fallback_timestamp),
+            source_tool: "antigravity".to_string(),
+            project_context: "Antigravity Brain".to_string(),
+            session_id,
+            interaction: Interaction {
+                role: parsed.role,
+                content,
+                artifacts: None,
+            },
+            security_flags: flags,
+            metadata: metadata.into(),
+        };
+
+        if log.validate_schema().is_ok() {
+            writeln!(writer, "{}", serde_json::to_string(&log)?)?;
+            stats.imported += 1;
+        } else {
+            stats.errors += 1;
+        }
+    }
+
+    Ok(())
+}
+
 fn import_antigravity_session(
     session_dir: &Path,
     writer: &mut dyn Write,
@@ -722,6 +889,7 @@ fn import_antigravity_session(
         existing.insert(summary_key);
 
         let summary_log = MasterLog {
+            schema_version: SCHEMA_VERSION,
             event_id: Uuid::new_v4(),
             timestamp: session_dir
                 .metadata()
@@ -738,7 +906,7 @@ fn import_antigravity_session(
                 artifacts: None,
             },
             security_flags: summary_flags,
-            metadata: Value::Object(summary_meta),
+            metadata: summary_meta.into(),
         };
 
         if summary_log.validate_schema().is_ok() {
@@ -869,6 +1037,7 @@ fn import_antigravity_file(
     existing.insert(key);
 
     let log = MasterLog {
+        schema_version: SCHEMA_VERSION,
         event_id: Uuid::new_v4(),
         timestamp,
         source_tool: "antigravity".to_string(),
@@ -880,7 +1049,7 @@ fn import_antigravity_file(
             artifacts: None,
         },
         security_flags: flags,
-        metadata: Value::Object(metadata),
+        metadata: metadata.into(),
     };
 
     if log.validate_schema().is_ok() {
@@ -899,6 +1068,20 @@ fn read_antigravity_metadata(base_path: &Path) -> Option<Value> {
     serde_json::from_str::<Value>(&raw).ok()
 }
 
+/// Redacts parsed artifacts with the same sentry as their event's content, folding
+/// findings into `flags`. `None` when the line had no artifacts.
+fn redacted_artifacts(
+    sentry: &Sentry,
+    mut artifacts: Vec<Artifact>,
+    flags: &mut SecurityFlags,
+) -> Option<Vec<Artifact>> {
+    if artifacts.is_empty() {
+        return None;
+    }
+    sentry.redact_artifacts(&mut artifacts, flags);
+    Some(artifacts)
+}
+
 fn system_time_to_utc(t: std::time::SystemTime) -> Option<DateTime<Utc>> {
     let duration = t.duration_since(std::time::UNIX_EPOCH).ok()?;
     DateTime::<Utc>::from_timestamp(duration.as_secs() as i64, duration.subsec_nanos())
@@ -929,39 +1112,15 @@ fn extract_timestamp(value: &Value) -> Option<DateTime<Utc>> {
     as_i64.and_then(|n| DateTime::<Utc>::from_timestamp(n, 0))
 }
 
+/// Keys for everything already recorded, including rotated archives and purged events.
 fn load_existing_keys(path: &Path) -> Result<HashSet<u64>> {
-    let mut keys = HashSet::new();
-    if !path.exists() {
-        return Ok(keys);
-    }
-    let file = fs::File::open(path)?;
-    let reader = BufReader::new(file);
-    for line in reader.lines() {
-        let line = line?;
-        let Ok(json) = serde_json::from_str::<Value>(&line) else {
-            continue;
-        };
-        let source = json
-            .get("source_tool")
-            .and_then(Value::as_str)
-            .unwrap_or("");
-        let session = json.get("session_id").and_then(Value::as_str).unwrap_or("");
-        let content = json
-            .pointer("/interaction/content")
-            .and_then(Value::as_str)
-            .unwrap_or("");
-        if source.is_empty() || session.is_empty() {
-            continue;
-        }
-        keys.insert(dedupe_key(source, session, content));
-    }
+    let mut keys = EventIndex::open_synced(path)
+        .and_then(|index| index.history_keys())
+        .with_context(|| format!("load existing events from {}", path.display()))?;
+    keys.extend(Tombstones::load(path)?.history_keys());
     Ok(keys)
 }
 
 fn dedupe_key(source: &str, session: &str, content: &str) -> u64 {
-    let mut h = std::collections::hash_map::DefaultHasher::new();
-    source.hash(&mut h);
-    session.hash(&mut h);
-    content.hash(&mut h);
-    h.finish()
+    history_key(source, session, content)
 }
@@ -1,16 +1,27 @@
+pub mod antigravity;
+pub mod checkpoint;
 pub mod claude;
 pub mod claude_profile_import;
 pub mod codex;
 pub mod config;
 pub mod cursor;
+pub mod event_index;
+pub mod gemini;
+pub mod git_effects;
 pub mod history_import;
 pub mod learnings;
 pub mod log_index;
+pub mod log_lock;
 pub mod log_writer;
 pub mod merge;
 pub mod notifier;
 pub mod parse;
+pub mod purge;
+pub mod redact;
 pub mod rotation;
+pub mod search_index;
+pub mod semantic;
 pub mod sentry;
 pub mod types;
+pub mod vault;
 pub mod watchers;
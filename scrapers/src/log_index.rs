@@ -1,12 +1,126 @@
-use anyhow::Result;
-use std::fs;
+//! Locating the master log and its rotated archives, and reading them whether or
+//! not they are compressed.
+//!
+//! Rotation leaves `master_log.<UTC timestamp>.jsonl`, which is then compressed with
+//! zstd to `master_log.<UTC timestamp>.jsonl.zst`. Readers go through [`open_log`] so both
+//! look the same to them.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
+/// Extension added to a rotated archive when it is compressed.
+pub const COMPRESSED_EXTENSION: &str = "zst";
+
 fn is_archive_name(name: &str) -> bool {
-    name.starts_with("master_log.") && name.ends_with(".jsonl") && name != "master_log.jsonl"
+    name.starts_with("master_log.")
+        && (name.ends_with(".jsonl") || name.ends_with(".jsonl.zst"))
+        && name != "master_log.jsonl"
+}
+
+/// Whether `path` is a zstd-compressed archive.
+pub fn is_compressed(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(COMPRESSED_EXTENSION)
+}
+
+/// `master_log.<ts>.jsonl.zst` for `master_log.<ts>.jsonl`.
+pub fn compressed_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(COMPRESSED_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Read a log file's JSONL, decompressing archives transparently.
+pub fn open_log(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    if is_compressed(path) {
+        let decoder = zstd::stream::read::Decoder::new(file)
+            .with_context(|| format!("open {}", path.display()))?;
+        Ok(Box::new(BufReader::new(decoder)))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Bytes of a log file from `offset` (in its uncompressed content) to the end. A
+/// just-rotated archive may already have been compressed; its compressed copy is
+/// read instead.
+pub fn read_log_from(path: &Path, offset: u64) -> Result<Vec<u8>> {
+    let compressed = compressed_path(path);
+    let path = if !path.exists() && !is_compressed(path) && compressed.exists() {
+        compressed.as_path()
+    } else {
+        path
+    };
+    let mut reader = open_log(path)?;
+    io::copy(&mut reader.by_ref().take(offset), &mut io::sink())?;
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// Writer for a new log file, compressed or not. [`LogSink::finish`] must be called
+/// to complete it; a dropped zstd sink may be missing its last frame.
+pub enum LogSink {
+    Plain(BufWriter<File>),
+    Zstd(zstd::stream::write::Encoder<'static, BufWriter<File>>),
+}
+
+impl LogSink {
+    pub fn create(path: &Path, compressed: bool) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("create {}", path.display()))?;
+        let out = BufWriter::new(file);
+        Ok(if compressed {
+            let encoder = zstd::stream::write::Encoder::new(out, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .with_context(|| format!("create {}", path.display()))?;
+            Self::Zstd(encoder)
+        } else {
+            Self::Plain(out)
+        })
+    }
+
+    /// Flush everything and fsync the file.
+    pub fn finish(self) -> Result<()> {
+        let out = match self {
+            Self::Plain(out) => out,
+            Self::Zstd(encoder) => encoder.finish()?,
+        };
+        let file = out.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+impl Write for LogSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(out) => out.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(out) => out.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
 }
 
+/// Rotated archives next to `log_path`, oldest first.
 pub fn discover_archives(log_path: &Path) -> Result<Vec<PathBuf>> {
+    // An archive is hidden while its compressed copy exists, so readers never see
+    // the same events twice between compression and removing the original.
+    let mut archives = archive_files(log_path)?;
+    archives.retain(|path| is_compressed(path) || !compressed_path(path).exists());
+    Ok(archives)
+}
+
+/// Every archive file on disk, including originals already superseded by their
+/// compressed copy.
+pub(crate) fn archive_files(log_path: &Path) -> Result<Vec<PathBuf>> {
     let Some(dir) = log_path.parent() else {
         return Ok(Vec::new());
     };
@@ -50,11 +164,42 @@ mod tests {
         fs::write(&log_path, "").expect("write log");
         fs::write(dir.path().join("master_log.20240101T000000Z.jsonl"), "").expect("write a1");
         fs::write(dir.path().join("master_log.20240201T000000Z.jsonl"), "").expect("write a2");
+        // Mid-compression: only the compressed copy is listed.
+        fs::write(dir.path().join("master_log.20240301T000000Z.jsonl"), "").expect("write a3");
+        fs::write(dir.path().join("master_log.20240301T000000Z.jsonl.zst"), "")
+            .expect("write a3 zst");
 
         let files = discover_logs(&log_path).expect("discover");
-        assert_eq!(files.len(), 3);
+        assert_eq!(files.len(), 4);
         assert!(files[0].ends_with("master_log.20240101T000000Z.jsonl"));
         assert!(files[1].ends_with("master_log.20240201T000000Z.jsonl"));
-        assert!(files[2].ends_with("master_log.jsonl"));
+        assert!(files[2].ends_with("master_log.20240301T000000Z.jsonl.zst"));
+        assert!(files[3].ends_with("master_log.jsonl"));
+    }
+
+    #[test]
+    fn compressed_and_plain_logs_read_the_same() {
+        let dir = tempdir().expect("tempdir");
+        let plain = dir.path().join("master_log.20240101T000000Z.jsonl");
+        let zst = compressed_path(&plain);
+        assert!(zst.ends_with("master_log.20240101T000000Z.jsonl.zst"));
+
+        for (path, compressed) in [(&plain, false), (&zst, true)] {
+            let mut sink = LogSink::create(path, compressed).expect("create");
+            sink.write_all(b"{\"a\":1}\n{\"b\":2}\n").expect("write");
+            sink.finish().expect("finish");
+        }
+        assert!(is_compressed(&zst) && !is_compressed(&plain));
+        assert_ne!(fs::read(&plain).unwrap(), fs::read(&zst).unwrap());
+
+        let lines = |path: &Path| -> Vec<String> {
+            open_log(path)
+                .unwrap()
+                .lines()
+                .map(|l| l.unwrap())
+                .collect()
+        };
+        assert_eq!(lines(&plain), lines(&zst));
+        assert_eq!(lines(&zst), vec!["{\"a\":1}", "{\"b\":2}"]);
     }
 }
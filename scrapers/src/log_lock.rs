@@ -0,0 +1,208 @@
+//! Advisory lock shared by everything that appends to the master log.
+//!
+//! The daemon's [`LogWriter`](crate::log_writer::LogWriter), `merge-log`/`sync`,
+//! history import and the Claude profile import all take an exclusive lock on
+//! `master_log.lock` (next to the log) while they write, so their lines never
+//! interleave. The lock is advisory: readers never take it, and a writer that
+//! crashes releases it with its file handle.
+//!
+//! [`repair_tail`] cleans up after such a crash: a writer that died mid-line leaves
+//! a trailing fragment that the next appended line would be glued onto.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Bytes read per step while scanning backwards for the last newline.
+const SCAN_CHUNK: u64 = 64 * 1024;
+
+pub struct LogLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl LogLock {
+    /// Open (creating) the lock file for `log_path` without taking the lock.
+    pub fn open(log_path: &Path) -> Result<Self> {
+        let path = lock_path(log_path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("open log lock {}", path.display()))?;
+        Ok(Self { file, path })
+    }
+
+    /// Open and lock, waiting for any other writer to finish. The lock is held
+    /// until the returned value is dropped.
+    pub fn acquire(log_path: &Path) -> Result<Self> {
+        let lock = Self::open(log_path)?;
+        lock.lock()?;
+        Ok(lock)
+    }
+
+    /// Run `f` with the lock held, releasing it afterwards.
+    pub fn with_lock<T>(log_path: &Path, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let _lock = Self::acquire(log_path)?;
+        f()
+    }
+
+    pub fn lock(&self) -> Result<()> {
+        self.file
+            .lock()
+            .with_context(|| format!("lock {}", self.path.display()))
+    }
+
+    /// Take the lock if no other writer holds it.
+    pub fn try_lock(&self) -> Result<bool> {
+        match self.file.try_lock() {
+            Ok(()) => Ok(true),
+            Err(TryLockError::WouldBlock) => Ok(false),
+            Err(TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("lock {}", self.path.display()))
+            }
+        }
+    }
+
+    pub fn unlock(&self) -> Result<()> {
+        self.file
+            .unlock()
+            .with_context(|| format!("unlock {}", self.path.display()))
+    }
+}
+
+/// `master_log.lock` for `master_log.jsonl`.
+pub fn lock_path(log_path: &Path) -> PathBuf {
+    log_path.with_extension("lock")
+}
+
+/// Whether the file at `log_path` is no longer (or not yet) the one whose
+/// metadata is `open`: rewrites such as `redact --rewrite` rename a new file over
+/// the log, and a writer holding the old one must reopen before appending.
+pub fn is_replaced(log_path: &Path, open: &fs::Metadata) -> Result<bool> {
+    match fs::metadata(log_path) {
+        Ok(current) => Ok(!same_file(&current, open)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e).with_context(|| format!("stat {}", log_path.display())),
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+/// Replacing a file that is open isn't possible on Windows, so it never happens.
+#[cfg(not(unix))]
+fn same_file(_a: &fs::Metadata, _b: &fs::Metadata) -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TailRepair {
+    /// The last line was complete JSON missing only its newline, which was added.
+    Terminated,
+    /// An incomplete last line of this many bytes was cut off.
+    Truncated(u64),
+}
+
+/// Make sure `log_path` ends at a line boundary, so the next append starts a fresh
+/// line. Call with the [`LogLock`] held. A trailing fragment that parses as JSON is
+/// kept and terminated; anything else was a write cut short and is removed.
+pub fn repair_tail(log_path: &Path) -> Result<Option<TailRepair>> {
+    let mut file = match OpenOptions::new().read(true).write(true).open(log_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("open {}", log_path.display())),
+    };
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::End(-1))?;
+    let mut last = [0u8; 1];
+    file.read_exact(&mut last)?;
+    if last[0] == b'\n' {
+        return Ok(None);
+    }
+
+    let line_start = last_line_start(&mut file, len)?;
+    file.seek(SeekFrom::Start(line_start))?;
+    let mut fragment = Vec::with_capacity((len - line_start) as usize);
+    file.read_to_end(&mut fragment)?;
+
+    if serde_json::from_slice::<serde_json::Value>(&fragment).is_ok() {
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(b"\n")?;
+        file.sync_data()?;
+        return Ok(Some(TailRepair::Terminated));
+    }
+    file.set_len(line_start)
+        .with_context(|| format!("truncate {}", log_path.display()))?;
+    file.sync_data()?;
+    Ok(Some(TailRepair::Truncated(len - line_start)))
+}
+
+/// Offset just past the last newline in the first `len` bytes, or 0.
+fn last_line_start(file: &mut File, len: u64) -> Result<u64> {
+    let mut end = len;
+    let mut buf = vec![0u8; SCAN_CHUNK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(SCAN_CHUNK);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(pos) = chunk.iter().rposition(|b| *b == b'\n') {
+            return Ok(start + pos as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_excludes_other_writers_until_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("master_log.jsonl");
+
+        let held = LogLock::acquire(&log).unwrap();
+        let other = LogLock::open(&log).unwrap();
+        assert!(!other.try_lock().unwrap());
+        drop(held);
+        assert!(other.try_lock().unwrap());
+        other.unlock().unwrap();
+        assert!(lock_path(&log).ends_with("master_log.lock"));
+    }
+
+    #[test]
+    fn repairs_trailing_partial_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("master_log.jsonl");
+        assert_eq!(repair_tail(&log).unwrap(), None);
+
+        fs::write(&log, "{\"a\":1}\n{\"b\":2}\n").unwrap();
+        assert_eq!(repair_tail(&log).unwrap(), None);
+
+        fs::write(&log, "{\"a\":1}\n{\"b\":").unwrap();
+        assert_eq!(repair_tail(&log).unwrap(), Some(TailRepair::Truncated(5)));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "{\"a\":1}\n");
+
+        fs::write(&log, "{\"a\":1}\n{\"b\":2}").unwrap();
+        assert_eq!(repair_tail(&log).unwrap(), Some(TailRepair::Terminated));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "{\"a\":1}\n{\"b\":2}\n");
+
+        fs::write(&log, "{\"only\":").unwrap();
+        assert_eq!(repair_tail(&log).unwrap(), Some(TailRepair::Truncated(8)));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "");
+    }
+}
@@ -1,34 +1,142 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::time::Instant;
 
+use crate::event_index::EventIndex;
+use crate::log_lock::{is_replaced, repair_tail, LogLock, TailRepair};
+use crate::rotation::{self, RotationLimits};
 use crate::types::MasterLog;
 
 const CHANNEL_CAPACITY: usize = 1024;
+const MAX_BATCH: usize = 256;
+/// How often archive retention is applied when nothing rotates.
+const ARCHIVE_UPKEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+enum Command {
+    Write(Box<MasterLog>),
+    /// Answered once everything queued before it is on disk.
+    Flush(oneshot::Sender<()>),
+}
 
 #[derive(Clone)]
 pub struct LogWriter {
-    sender: mpsc::Sender<MasterLog>,
+    sender: mpsc::Sender<Command>,
 }
 
 impl LogWriter {
-    pub fn new(log_path: PathBuf) -> Self {
-        let (sender, mut receiver) = mpsc::channel::<MasterLog>(CHANNEL_CAPACITY);
+    /// Spawn the writer task. After each batch of appended lines the event index
+    /// next to `log_path` is caught up, so readers never have to rescan the log and
+    /// each new line gets its replication sequence number right away.
+    ///
+    /// Each batch is written under the shared [`LogLock`], so other writers
+    /// (`merge-log`, imports) can append while the daemon runs. Appended data is
+    /// fsynced at most `fsync_interval` after it was written; zero syncs every batch.
+    ///
+    /// Before each batch (and once at startup) the log is rotated if `rotation` says
+    /// so. Queued events simply land in the fresh file. Archives are compressed and
+    /// pruned by a separate task, so that never holds up writing.
+    pub fn new(log_path: PathBuf, fsync_interval: Duration, rotation: RotationLimits) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<Command>(CHANNEL_CAPACITY);
+        let rotated = Arc::new(Notify::new());
+        let upkeep = tokio::spawn(maintain_archives(
+            log_path.clone(),
+            rotation,
+            rotated.clone(),
+        ));
 
         tokio::spawn(async move {
             if let Err(e) = async move {
-                let mut file = tokio::fs::OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(&log_path)
-                    .await
-                    .with_context(|| format!("failed to open log file at {:?}", log_path))?;
-
-                while let Some(log) = receiver.recv().await {
-                    let mut line = serde_json::to_vec(&log)?;
-                    line.push(b'\n');
-                    file.write_all(&line).await?;
+                let lock = Arc::new(LogLock::open(&log_path)?);
+                let mut file = open_log(&log_path).await?;
+                with_lock(&lock, async {
+                    match repair_tail(&log_path)? {
+                        Some(TailRepair::Terminated) => {
+                            tracing::warn!(path = ?log_path, "terminated unfinished last log line")
+                        }
+                        Some(TailRepair::Truncated(bytes)) => {
+                            tracing::warn!(path = ?log_path, bytes, "dropped partial last log line")
+                        }
+                        None => {}
+                    }
+                    rotate_if_due(&log_path, &mut file, &rotation, 0, &rotated).await?;
+                    Ok(())
+                })
+                .await?;
+
+                let mut index = match open_index(log_path.clone()).await {
+                    Ok(index) => Some(index),
+                    Err(err) => {
+                        tracing::warn!(err = ?err, "event index unavailable; writing log only");
+                        None
+                    }
+                };
+
+                let mut batch = Vec::with_capacity(MAX_BATCH);
+                let mut flushed = Vec::new();
+                // When the oldest line not yet fsynced was written.
+                let mut unsynced_since: Option<Instant> = None;
+                loop {
+                    let received = match unsynced_since {
+                        Some(since) => tokio::select! {
+                            n = receiver.recv_many(&mut batch, MAX_BATCH) => n,
+                            _ = tokio::time::sleep_until(since + fsync_interval) => {
+                                file.sync_data().await?;
+                                unsynced_since = None;
+                                continue;
+                            }
+                        },
+                        None => receiver.recv_many(&mut batch, MAX_BATCH).await,
+                    };
+                    if received == 0 {
+                        break;
+                    }
+
+                    let mut lines = Vec::new();
+                    for command in batch.drain(..) {
+                        match command {
+                            Command::Write(log) => {
+                                serde_json::to_writer(&mut lines, &log)?;
+                                lines.push(b'\n');
+                            }
+                            Command::Flush(done) => flushed.push(done),
+                        }
+                    }
+                    if !lines.is_empty() {
+                        with_lock(&lock, async {
+                            reopen_if_replaced(&log_path, &mut file).await?;
+                            let incoming = lines.len() as u64;
+                            if rotate_if_due(&log_path, &mut file, &rotation, incoming, &rotated)
+                                .await?
+                            {
+                                unsynced_since = None;
+                            }
+                            file.write_all(&lines).await?;
+                            file.flush().await?;
+                            Ok(())
+                        })
+                        .await?;
+                        let since = *unsynced_since.get_or_insert_with(Instant::now);
+                        if since.elapsed() >= fsync_interval {
+                            file.sync_data().await?;
+                            unsynced_since = None;
+                        }
+                    }
+                    for done in flushed.drain(..) {
+                        let _ = done.send(());
+                    }
+
+                    if let Some(current) = index.take() {
+                        index = sync_index(current).await;
+                    }
+                }
+                if unsynced_since.is_some() {
+                    file.sync_data().await?;
                 }
                 Ok::<_, anyhow::Error>(())
             }
@@ -36,6 +144,7 @@ impl LogWriter {
             {
                 tracing::error!(err = ?e, "log writer task failed");
             }
+            upkeep.abort();
         });
 
         Self { sender }
@@ -43,8 +152,234 @@ impl LogWriter {
 
     pub async fn write(&self, log: MasterLog) -> Result<()> {
         self.sender
-            .send(log)
+            .send(Command::Write(Box::new(log)))
             .await
             .map_err(|_| anyhow::anyhow!("log writer channel closed"))
     }
+
+    /// Wait until every event written so far has reached the log file. Watchers call
+    /// this before persisting a checkpoint, so a checkpoint never gets ahead of the log.
+    pub async fn flush(&self) -> Result<()> {
+        let (done, wait) = oneshot::channel();
+        self.sender
+            .send(Command::Flush(done))
+            .await
+            .map_err(|_| anyhow::anyhow!("log writer channel closed"))?;
+        wait.await
+            .map_err(|_| anyhow::anyhow!("log writer stopped before flushing"))
+    }
+}
+
+async fn open_log(log_path: &Path) -> Result<File> {
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .await
+        .with_context(|| format!("failed to open log file at {:?}", log_path))
+}
+
+/// Reopen the log if the file at `log_path` is no longer the one `file` has open.
+/// `redact --rewrite` and `purge --apply` rename a rewritten log over the live one
+/// under the log lock; without this the daemon would keep appending to the old,
+/// unlinked file and those events would be lost. Call with the log lock held.
+async fn reopen_if_replaced(log_path: &Path, file: &mut File) -> Result<bool> {
+    if !is_replaced(log_path, &file.metadata().await?)? {
+        return Ok(false);
+    }
+    tracing::info!(path = ?log_path, "log file was replaced; reopening");
+    *file = open_log(log_path).await?;
+    Ok(true)
+}
+
+/// Rotate `file` away if it is due and reopen the fresh log in its place. Call with
+/// the log lock held. Whatever was written to the old file is synced first, so the
+/// archive is complete on disk.
+async fn rotate_if_due(
+    log_path: &Path,
+    file: &mut File,
+    rotation: &RotationLimits,
+    incoming: u64,
+    rotated: &Notify,
+) -> Result<bool> {
+    // Other writers append through their own handles, so ask the path, not `file`.
+    let meta = match tokio::fs::metadata(log_path).await {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("stat {}", log_path.display())),
+    };
+    let last_write: DateTime<Utc> = meta
+        .modified()
+        .map(Into::into)
+        .unwrap_or_else(|_| Utc::now());
+    if !rotation.due(meta.len(), incoming, last_write, Utc::now()) {
+        return Ok(false);
+    }
+
+    file.sync_data().await?;
+    let path = log_path.to_path_buf();
+    let outcome = tokio::task::spawn_blocking(move || rotation::rotate(&path)).await?;
+    // Reopen even after a failure: the rename may have happened before it.
+    *file = open_log(log_path).await?;
+    match outcome {
+        Ok(archive) => {
+            tracing::info!(archive = ?archive, "rotated master log");
+            rotated.notify_one();
+            Ok(true)
+        }
+        Err(err) => {
+            tracing::error!(err = ?err, "log rotation failed; continuing without rotation");
+            Ok(false)
+        }
+    }
+}
+
+/// Compress and prune archives: at startup, after every rotation, and periodically
+/// so the age limit also applies while the log is idle.
+async fn maintain_archives(log_path: PathBuf, limits: RotationLimits, rotated: Arc<Notify>) {
+    loop {
+        let path = log_path.clone();
+        let outcome = tokio::task::spawn_blocking(move || -> Result<(usize, usize)> {
+            let compressed = rotation::compress_archives(&path)?;
+            let pruned = rotation::prune_archives(&path, &limits, Utc::now())?;
+            Ok((compressed, pruned))
+        })
+        .await;
+        match outcome {
+            Ok(Ok((0, 0))) => {}
+            Ok(Ok((compressed, pruned))) => {
+                tracing::info!(compressed, pruned, "maintained log archives")
+            }
+            Ok(Err(err)) => tracing::warn!(err = ?err, "log archive maintenance failed"),
+            Err(err) => tracing::error!(err = ?err, "log archive task panicked"),
+        }
+        tokio::select! {
+            _ = rotated.notified() => {}
+            _ = tokio::time::sleep(ARCHIVE_UPKEEP_INTERVAL) => {}
+        }
+    }
+}
+
+/// Run `f` holding the log lock. Waiting for it happens off the async runtime, since
+/// an import may hold it for a while.
+async fn with_lock<F>(lock: &Arc<LogLock>, f: F) -> Result<()>
+where
+    F: std::future::Future<Output = Result<()>>,
+{
+    let waiting = lock.clone();
+    tokio::task::spawn_blocking(move || waiting.lock()).await??;
+    let result = f.await;
+    lock.unlock()?;
+    result
+}
+
+async fn open_index(log_path: PathBuf) -> Result<EventIndex> {
+    tokio::task::spawn_blocking(move || EventIndex::open_synced(&log_path)).await?
+}
+
+/// Index failures are logged but never block writing the log itself.
+async fn sync_index(mut index: EventIndex) -> Option<EventIndex> {
+    let result = tokio::task::spawn_blocking(move || {
+        if let Err(err) = index.sync() {
+            tracing::warn!(err = ?err, "event index sync failed");
+        }
+        index
+    })
+    .await;
+    match result {
+        Ok(index) => Some(index),
+        Err(err) => {
+            tracing::error!(err = ?err, "event index task panicked");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_index::{discover_logs, read_log_from};
+    use crate::rotation::{last_rotation, RotationPolicy};
+
+    fn event(n: usize) -> MasterLog {
+        serde_json::from_value(serde_json::json!({
+            "event_id": uuid::Uuid::new_v4(),
+            "timestamp": Utc::now(),
+            "source_tool": "codex-cli",
+            "project_context": "/tmp/project",
+            "session_id": "s1",
+            "interaction": { "role": "user", "content": format!("message {n}") },
+            "security_flags": { "has_pii": false, "redacted_secrets": [] },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rotates_while_running_without_losing_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let rotation = RotationLimits {
+            policy: RotationPolicy::Size,
+            max_bytes: 600,
+            keep_files: 10,
+            max_age_days: 0,
+        };
+        let writer = LogWriter::new(log_path.clone(), Duration::ZERO, rotation);
+
+        for n in 0..6 {
+            writer.write(event(n)).await.unwrap();
+            writer.flush().await.unwrap();
+        }
+
+        let files = discover_logs(&log_path).unwrap();
+        assert!(files.len() > 1, "expected archives, got {files:?}");
+        assert!(last_rotation(&log_path).is_some());
+        let mut contents = Vec::new();
+        for file in &files {
+            // Archives may be compressed by now; read them the way readers do.
+            let text = String::from_utf8(read_log_from(file, 0).unwrap()).unwrap();
+            assert!(text.len() <= 600 || text.lines().count() == 1);
+            for line in text.lines() {
+                let log: MasterLog = serde_json::from_str(line).unwrap();
+                contents.push(log.interaction.content);
+            }
+        }
+        let expected: Vec<_> = (0..6).map(|n| format!("message {n}")).collect();
+        assert_eq!(contents, expected);
+    }
+
+    #[tokio::test]
+    async fn reopens_a_log_replaced_under_the_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let rotation = RotationLimits {
+            policy: RotationPolicy::Size,
+            max_bytes: u64::MAX,
+            keep_files: 10,
+            max_age_days: 0,
+        };
+        let writer = LogWriter::new(log_path.clone(), Duration::ZERO, rotation);
+        writer.write(event(0)).await.unwrap();
+        writer.flush().await.unwrap();
+
+        // What `redact --rewrite` does: write a new file, rename it over the log.
+        let original = std::fs::read_to_string(&log_path).unwrap();
+        let rewritten = dir.path().join("master_log.jsonl.tmp");
+        std::fs::write(&rewritten, &original).unwrap();
+        LogLock::with_lock(&log_path, || Ok(std::fs::rename(&rewritten, &log_path)?)).unwrap();
+
+        writer.write(event(1)).await.unwrap();
+        writer.flush().await.unwrap();
+        let contents: Vec<_> = std::fs::read_to_string(&log_path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<MasterLog>(line)
+                    .unwrap()
+                    .interaction
+                    .content
+            })
+            .collect();
+        assert_eq!(contents, ["message 0", "message 1"]);
+    }
 }
@@ -3,8 +3,14 @@
 //! `export_log` writes a (optionally filtered) copy of the local master log to a file.
 //! `merge_log` imports events from an external log, deduplicating by event_id UUID first,
 //! then by a content fingerprint to catch the same underlying event ingested independently
-//! on two machines (which would have different UUIDs).
-
+//! on two machines (which would have different UUIDs). Events removed by a purge are
+//! never merged back (see [`Tombstones`]).
+
+use crate::event_index::{content_hash, EventIndex};
+use crate::log_index::open_log;
+use crate::log_lock::{is_replaced, repair_tail, LogLock};
+use crate::purge::Tombstones;
+use crate::rotation::last_rotation;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde_json::Value;
@@ -12,7 +18,7 @@ use std::borrow::Cow;
 use std::collections::HashSet;
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 // ── Public types ────────────────────────────────────────────────────────
@@ -39,6 +45,8 @@ pub struct MergeStats {
     pub merged: usize,
     pub skipped_uuid: usize,
     pub skipped_fingerprint: usize,
+    /// Events deleted locally by `contrail purge`.
+    pub skipped_purged: usize,
     pub errors: usize,
 }
 
@@ -153,97 +161,156 @@ fn parse_timestamp(json: &Value) -> Option<DateTime<Utc>> {
 ///    This catches the same underlying event ingested on two machines with different UUIDs
 ///    (e.g. both ran `import-history` independently).
 ///
-/// **Important**: this should run with the contrail daemon stopped.
-/// We write each entry in a single append call to reduce interleaving risk,
-/// but this function does not coordinate a cross-process lock.
+/// Events tombstoned by a local purge are skipped as well.
+///
+/// Safe to run while the daemon is up: each line is appended under the shared
+/// [`LogLock`]. `input` may be a compressed archive (`.jsonl.zst`).
 pub fn merge_log(log_path: &Path, input: &Path) -> Result<MergeStats> {
-    let (existing_uuids, existing_fps) = load_existing_keys(log_path)?;
+    let mut merger = Merger::open(log_path)?;
 
-    let file =
-        File::open(input).with_context(|| format!("open import file {}", input.display()))?;
-    let reader = BufReader::new(file);
-
-    let mut writer = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(log_path)
-        .with_context(|| format!("open master log for append at {}", log_path.display()))?;
+    let reader =
+        open_log(input).with_context(|| format!("open import file {}", input.display()))?;
 
     let mut stats = MergeStats::default();
-    let mut seen_uuids = existing_uuids;
-    let mut seen_fps = existing_fps;
-
     for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => {
-                stats.errors += 1;
-                continue;
-            }
-        };
+        match line {
+            Ok(line) => merger.push_line(&line, &mut stats)?,
+            Err(_) => stats.errors += 1,
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Appends events to a master log with `merge_log`'s dedup rules, keeping the
+/// seen-key sets in memory so repeated batches (e.g. `contrail sync`) only load
+/// existing keys once.
+pub struct Merger {
+    writer: File,
+    log_path: PathBuf,
+    lock: LogLock,
+    /// The last rotation when `writer` was opened; a newer one means the daemon moved
+    /// the file `writer` appends to into an archive.
+    rotation: Option<PathBuf>,
+    seen_uuids: HashSet<Uuid>,
+    seen_fps: HashSet<u64>,
+    tombstones: Tombstones,
+}
+
+impl Merger {
+    pub fn open(log_path: &Path) -> Result<Self> {
+        let lock = LogLock::open(log_path)?;
+        lock.lock()?;
+        let repaired = repair_tail(log_path);
+        lock.unlock()?;
+        repaired?;
+        let (seen_uuids, seen_fps) = load_existing_keys(log_path)?;
+        let tombstones = Tombstones::load(log_path)?;
+        let rotation = last_rotation(log_path);
+        let writer = open_for_append(log_path)?;
+        Ok(Self {
+            writer,
+            log_path: log_path.to_path_buf(),
+            lock,
+            rotation,
+            seen_uuids,
+            seen_fps,
+            tombstones,
+        })
+    }
+
+    /// Merge one JSONL line, recording the outcome in `stats`.
+    pub fn push_line(&mut self, line: &str, stats: &mut MergeStats) -> Result<()> {
         if line.trim().is_empty() {
-            continue;
+            return Ok(());
         }
 
-        let json: Value = match serde_json::from_str(&line) {
+        let json: Value = match serde_json::from_str(line) {
             Ok(v) => v,
             Err(_) => {
                 stats.errors += 1;
-                continue;
+                return Ok(());
             }
         };
 
+        if self.tombstones.covers(&json) {
+            stats.skipped_purged += 1;
+            return Ok(());
+        }
+
         // Primary dedup: event_id UUID.
         if let Some(uuid) = extract_uuid(&json) {
-            if seen_uuids.contains(&uuid) {
+            if self.seen_uuids.contains(&uuid) {
                 stats.skipped_uuid += 1;
-                continue;
+                return Ok(());
             }
-            seen_uuids.insert(uuid);
+            self.seen_uuids.insert(uuid);
         }
 
         // Fallback dedup: content fingerprint.
         let fp = fingerprint(&json);
-        if seen_fps.contains(&fp) {
+        if self.seen_fps.contains(&fp) {
             stats.skipped_fingerprint += 1;
-            continue;
+            return Ok(());
         }
-        seen_fps.insert(fp);
-
-        write_jsonl_line(&mut writer, &line)?;
+        self.seen_fps.insert(fp);
+
+        self.lock.lock()?;
+        let written = self
+            .reopen_if_rotated()
+            .and_then(|()| write_jsonl_line(&mut self.writer, line));
+        self.lock.unlock()?;
+        written?;
         stats.merged += 1;
+        Ok(())
     }
 
-    Ok(stats)
-}
-
-/// Build sets of existing UUIDs and fingerprints from the local master log.
-fn load_existing_keys(log_path: &Path) -> Result<(HashSet<Uuid>, HashSet<u64>)> {
-    let mut uuids = HashSet::new();
-    let mut fps = HashSet::new();
+    /// Also reopens after a rewrite (`redact`, `purge`, a synced revision) renamed a
+    /// new file over the log.
+    fn reopen_if_rotated(&mut self) -> Result<()> {
+        let rotation = last_rotation(&self.log_path);
+        if rotation != self.rotation || is_replaced(&self.log_path, &self.writer.metadata()?)? {
+            self.writer = open_for_append(&self.log_path)?;
+            self.rotation = rotation;
+        }
+        Ok(())
+    }
 
-    if !log_path.exists() {
-        return Ok((uuids, fps));
+    /// Whether an event with this id is already in the log.
+    pub fn has_event(&self, event_id: &Uuid) -> bool {
+        self.seen_uuids.contains(event_id)
     }
+}
 
-    let file = File::open(log_path)?;
-    let reader = BufReader::new(file);
+fn open_for_append(log_path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .with_context(|| format!("open master log for append at {}", log_path.display()))
+}
 
-    for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => continue,
-        };
-        let json: Value = match serde_json::from_str(&line) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
+/// Build sets of existing UUIDs and fingerprints from the event index, which covers
+/// the local master log and its rotated archives.
+fn load_existing_keys(log_path: &Path) -> Result<(HashSet<Uuid>, HashSet<u64>)> {
+    let index = EventIndex::open_synced(log_path)
+        .with_context(|| format!("index master log at {}", log_path.display()))?;
 
-        if let Some(uuid) = extract_uuid(&json) {
+    let mut uuids = HashSet::new();
+    let mut fps = HashSet::new();
+    index.for_each_key(|key| {
+        if let Ok(uuid) = Uuid::parse_str(&key.event_id) {
             uuids.insert(uuid);
         }
-        fps.insert(fingerprint(&json));
-    }
+        fps.insert(fingerprint_parts(
+            &key.source_tool,
+            &key.project_context,
+            &key.session_id,
+            &key.timestamp_ms.to_string(),
+            &key.role,
+            key.content_hash,
+        ));
+    })?;
 
     Ok((uuids, fps))
 }
@@ -259,8 +326,6 @@ fn extract_uuid(json: &Value) -> Option<Uuid> {
 /// RFC3339 representations dedupe consistently.
 /// Uses `std::hash::DefaultHasher` — not cryptographic, but sufficient for dedup.
 fn fingerprint(json: &Value) -> u64 {
-    use std::hash::{Hash, Hasher};
-
     let source = json
         .get("source_tool")
         .and_then(Value::as_str)
@@ -281,17 +346,39 @@ fn fingerprint(json: &Value) -> u64 {
         .and_then(Value::as_str)
         .unwrap_or("");
 
+    fingerprint_parts(
+        source,
+        project,
+        session,
+        &timestamp,
+        role,
+        content_hash(content),
+    )
+}
+
+/// Content is hashed separately (see `event_index::content_hash`) so fingerprints can
+/// be rebuilt from index columns without loading full events.
+fn fingerprint_parts(
+    source: &str,
+    project: &str,
+    session: &str,
+    timestamp: &str,
+    role: &str,
+    content_hash: u64,
+) -> u64 {
+    use std::hash::{Hash, Hasher};
+
     let mut h = std::collections::hash_map::DefaultHasher::new();
     source.hash(&mut h);
     project.hash(&mut h);
     session.hash(&mut h);
     timestamp.hash(&mut h);
     role.hash(&mut h);
-    content.hash(&mut h);
+    content_hash.hash(&mut h);
     h.finish()
 }
 
-fn canonical_timestamp_repr(raw: &str) -> Cow<'_, str> {
+pub(crate) fn canonical_timestamp_repr(raw: &str) -> Cow<'_, str> {
     if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
         return Cow::Owned(dt.with_timezone(&Utc).timestamp_millis().to_string());
     }
@@ -346,21 +433,34 @@ mod tests {
         f
     }
 
+    /// The local log lives in its own directory so its event index does too.
+    fn write_local_log(events: &[Value]) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("master_log.jsonl");
+        let mut contents = String::new();
+        for e in events {
+            contents.push_str(&e.to_string());
+            contents.push('\n');
+        }
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
     #[test]
     fn merge_appends_new_events() {
         let local_event = make_event(Uuid::new_v4(), "cursor", "s1", "hello", "macA");
         let remote_event = make_event(Uuid::new_v4(), "codex-cli", "s2", "world", "macB");
 
-        let local_file = write_events(&[local_event]);
+        let (_dir, local_log) = write_local_log(&[local_event]);
         let remote_file = write_events(&[remote_event]);
 
-        let stats = merge_log(local_file.path(), remote_file.path()).unwrap();
+        let stats = merge_log(&local_log, remote_file.path()).unwrap();
         assert_eq!(stats.merged, 1);
         assert_eq!(stats.skipped_uuid, 0);
         assert_eq!(stats.skipped_fingerprint, 0);
 
         // Local log should now have 2 lines.
-        let contents = fs::read_to_string(local_file.path()).unwrap();
+        let contents = fs::read_to_string(&local_log).unwrap();
         let lines: Vec<&str> = contents.lines().collect();
         assert_eq!(lines.len(), 2);
     }
@@ -370,14 +470,14 @@ mod tests {
         let id = Uuid::new_v4();
         let event = make_event(id, "cursor", "s1", "hello", "macA");
 
-        let local_file = write_events(std::slice::from_ref(&event));
+        let (_dir, local_log) = write_local_log(std::slice::from_ref(&event));
         let remote_file = write_events(std::slice::from_ref(&event));
 
-        let stats = merge_log(local_file.path(), remote_file.path()).unwrap();
+        let stats = merge_log(&local_log, remote_file.path()).unwrap();
         assert_eq!(stats.merged, 0);
         assert_eq!(stats.skipped_uuid, 1);
 
-        let contents = fs::read_to_string(local_file.path()).unwrap();
+        let contents = fs::read_to_string(&local_log).unwrap();
         assert_eq!(contents.lines().count(), 1);
     }
 
@@ -395,14 +495,14 @@ mod tests {
         event_a["interaction"]["role"] = json!("user");
         event_b["interaction"]["role"] = json!("user");
 
-        let local_file = write_events(&[event_a]);
+        let (_dir, local_log) = write_local_log(&[event_a]);
         let remote_file = write_events(&[event_b]);
 
-        let stats = merge_log(local_file.path(), remote_file.path()).unwrap();
+        let stats = merge_log(&local_log, remote_file.path()).unwrap();
         assert_eq!(stats.merged, 0);
         assert_eq!(stats.skipped_fingerprint, 1);
 
-        let contents = fs::read_to_string(local_file.path()).unwrap();
+        let contents = fs::read_to_string(&local_log).unwrap();
         assert_eq!(contents.lines().count(), 1);
     }
 
@@ -414,37 +514,64 @@ mod tests {
         event_a["timestamp"] = json!("2026-06-01T00:00:00Z");
         event_b["timestamp"] = json!("2026-06-01T00:00:00.000+00:00");
 
-        let local_file = write_events(&[event_a]);
+        let (_dir, local_log) = write_local_log(&[event_a]);
         let remote_file = write_events(&[event_b]);
 
-        let stats = merge_log(local_file.path(), remote_file.path()).unwrap();
+        let stats = merge_log(&local_log, remote_file.path()).unwrap();
         assert_eq!(stats.merged, 0);
         assert_eq!(stats.skipped_fingerprint, 1);
     }
 
+    #[test]
+    fn merge_skips_purged_events() {
+        let secret = make_event(Uuid::new_v4(), "codex-cli", "s1", "forget me", "macA");
+        let (dir, local_log) = write_local_log(std::slice::from_ref(&secret));
+        let targets = crate::purge::PurgeTargets {
+            log_path: local_log.clone(),
+            memories_path: dir.path().join("memories.jsonl"),
+            memory_blocks_path: dir.path().join("memory_blocks.json"),
+            search_index_dir: dir.path().join("index"),
+        };
+        let filter = crate::purge::PurgeFilter {
+            session_id: Some("s1".to_string()),
+            ..Default::default()
+        };
+        crate::purge::purge(&targets, &filter, true).unwrap();
+
+        // The other machine still has it, under its own event_id too.
+        let mut copy = secret.clone();
+        copy["event_id"] = json!(Uuid::new_v4().to_string());
+        let remote_file = write_events(&[secret, copy]);
+
+        let stats = merge_log(&local_log, remote_file.path()).unwrap();
+        assert_eq!(stats.merged, 0);
+        assert_eq!(stats.skipped_purged, 2);
+        assert_eq!(fs::read_to_string(&local_log).unwrap(), "");
+    }
+
     #[test]
     fn merge_is_idempotent() {
         let event = make_event(Uuid::new_v4(), "cursor", "s1", "hello", "macA");
-        let local_file = write_events(&[]);
+        let (_dir, local_log) = write_local_log(&[]);
         let remote_file = write_events(&[event]);
 
         // First merge.
-        let stats1 = merge_log(local_file.path(), remote_file.path()).unwrap();
+        let stats1 = merge_log(&local_log, remote_file.path()).unwrap();
         assert_eq!(stats1.merged, 1);
 
         // Second merge of the same file.
-        let stats2 = merge_log(local_file.path(), remote_file.path()).unwrap();
+        let stats2 = merge_log(&local_log, remote_file.path()).unwrap();
         assert_eq!(stats2.merged, 0);
         assert_eq!(stats2.skipped_uuid, 1);
 
-        let contents = fs::read_to_string(local_file.path()).unwrap();
+        let contents = fs::read_to_string(&local_log).unwrap();
         assert_eq!(contents.lines().count(), 1);
     }
 
     #[test]
     fn merge_handles_malformed_lines() {
         let event = make_event(Uuid::new_v4(), "cursor", "s1", "hello", "macA");
-        let local_file = write_events(&[]);
+        let (_dir, local_log) = write_local_log(&[]);
 
         let mut remote = NamedTempFile::new().unwrap();
         writeln!(remote, "not json at all").unwrap();
@@ -453,7 +580,7 @@ mod tests {
         writeln!(remote, "{{broken").unwrap();
         remote.flush().unwrap();
 
-        let stats = merge_log(local_file.path(), remote.path()).unwrap();
+        let stats = merge_log(&local_log, remote.path()).unwrap();
         assert_eq!(stats.merged, 1);
         assert_eq!(stats.errors, 2);
     }
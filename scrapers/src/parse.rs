@@ -1,3 +1,4 @@
+use crate::types::Artifact;
 use chrono::{DateTime, TimeZone, Utc};
 use serde_json::{Map, Value};
 
@@ -10,6 +11,8 @@ pub struct ParsedLine {
     pub session_id: Option<String>,
     pub project_context: Option<String>,
     pub metadata: Map<String, Value>,
+    /// Tool calls, patches, commands etc. recorded on this line.
+    pub artifacts: Vec<Artifact>,
 }
 
 // ── Shared metadata helpers ─────────────────────────────────────────────
@@ -70,6 +73,19 @@ pub fn insert_scalar(meta: &mut Map<String, Value>, key: &str, value: &Value) {
     }
 }
 
+// ── Artifacts ───────────────────────────────────────────────────────────
+
+/// Per-field cap on artifact text; tool output can be whole files or build logs.
+pub const MAX_ARTIFACT_CHARS: usize = 8000;
+
+/// Apply [`MAX_ARTIFACT_CHARS`] to every artifact.
+pub fn clip_artifacts(mut artifacts: Vec<Artifact>) -> Vec<Artifact> {
+    for artifact in &mut artifacts {
+        artifact.truncate_text(MAX_ARTIFACT_CHARS);
+    }
+    artifacts
+}
+
 // ── Text extraction ─────────────────────────────────────────────────────
 
 pub fn extract_text(value: &Value) -> Option<String> {
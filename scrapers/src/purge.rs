@@ -0,0 +1,805 @@
+//! `contrail purge`: delete matching events everywhere Contrail keeps a copy.
+//!
+//! A purge rewrites the master log and its archives without the matching lines,
+//! drops analysis memories and memory blocks derived from them, and removes memex's
+//! rendered session transcripts (`<repo>/.context/sessions/`) for the purged
+//! sessions. The event index is rebuilt for the rewritten files and compacted, so no
+//! copy of the deleted text survives there either. The search index directory only
+//! holds caches derived from those files (full-text indexes and embeddings), so it
+//! is removed outright and rebuilt on the next search.
+//!
+//! Each purged event leaves a tombstone in `master_log.tombstones`, next to the log:
+//! its event_id plus hashes of its identity and content, never the content itself.
+//! [`Merger`](crate::merge::Merger) and the history importer consult them, so the
+//! same event is not brought back from another machine's log or re-imported from
+//! the tool's own storage.
+
+use crate::event_index::{content_hash, history_key, EventIndex};
+use crate::log_index::{discover_logs, is_compressed, open_log, LogSink};
+use crate::log_lock::LogLock;
+use crate::merge::canonical_timestamp_repr;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Which events to delete. Every set field must match; a record lacking a field
+/// that the filter constrains does not match.
+#[derive(Debug, Default, Clone)]
+pub struct PurgeFilter {
+    /// Project path prefix, compared component-wise against `project_context`.
+    pub project_prefix: Option<String>,
+    pub session_id: Option<String>,
+    pub tool: Option<String>,
+    /// Only events at or after this time.
+    pub after: Option<DateTime<Utc>>,
+    /// Only events before this time.
+    pub before: Option<DateTime<Utc>>,
+    /// Matched against the event's content and its artifacts' text.
+    pub content: Option<Regex>,
+}
+
+/// The fields of a record that a [`PurgeFilter`] looks at.
+#[derive(Debug, Default, Clone, Copy)]
+struct Fields<'a> {
+    project: Option<&'a str>,
+    session: Option<&'a str>,
+    tool: Option<&'a str>,
+    timestamp: Option<DateTime<Utc>>,
+    content: Option<&'a str>,
+    /// An event's `interaction.artifacts` array.
+    artifacts: Option<&'a Value>,
+}
+
+impl PurgeFilter {
+    /// A filter with nothing set would delete everything; callers refuse it.
+    pub fn is_empty(&self) -> bool {
+        self.project_prefix.is_none()
+            && self.session_id.is_none()
+            && self.tool.is_none()
+            && self.after.is_none()
+            && self.before.is_none()
+            && self.content.is_none()
+    }
+
+    /// Only a project prefix is set, so everything recorded for that project goes.
+    fn whole_project(&self) -> Option<&str> {
+        let only_project = PurgeFilter {
+            project_prefix: None,
+            ..self.clone()
+        }
+        .is_empty();
+        self.project_prefix.as_deref().filter(|_| only_project)
+    }
+
+    fn matches(&self, fields: &Fields<'_>) -> bool {
+        fn check<T>(want: Option<T>, ok: impl FnOnce(T) -> bool) -> bool {
+            want.is_none_or(ok)
+        }
+        check(self.project_prefix.as_deref(), |prefix| {
+            fields
+                .project
+                .is_some_and(|p| Path::new(p).starts_with(prefix))
+        }) && check(self.session_id.as_deref(), |session| {
+            fields.session == Some(session)
+        }) && check(self.tool.as_deref(), |tool| fields.tool == Some(tool))
+            && check(self.after, |after| {
+                fields.timestamp.is_some_and(|t| t >= after)
+            })
+            && check(self.before, |before| {
+                fields.timestamp.is_some_and(|t| t < before)
+            })
+            && check(self.content.as_ref(), |re| {
+                fields.content.is_some_and(|c| re.is_match(c))
+                    || artifact_texts(fields.artifacts).any(|t| re.is_match(t))
+            })
+    }
+}
+
+/// Everything a purge touches besides the master log's own directory.
+#[derive(Debug, Clone)]
+pub struct PurgeTargets {
+    pub log_path: PathBuf,
+    pub memories_path: PathBuf,
+    pub memory_blocks_path: PathBuf,
+    /// `[search] index_dir`.
+    pub search_index_dir: PathBuf,
+}
+
+#[derive(Debug, Default)]
+pub struct PurgeReport {
+    pub files_scanned: usize,
+    pub files_rewritten: usize,
+    pub events_purged: usize,
+    pub memories_purged: usize,
+    pub memory_blocks_purged: usize,
+    /// memex transcripts deleted (or that would be).
+    pub session_copies: Vec<PathBuf>,
+    /// Whether the search index directory was removed.
+    pub search_index_cleared: bool,
+}
+
+/// Find, and with `apply` delete, everything `filter` matches. Without `apply`
+/// nothing is written. Applying holds the [`LogLock`]; a running daemon's writer
+/// waits for it and reopens the log once it sees it was replaced.
+pub fn purge(targets: &PurgeTargets, filter: &PurgeFilter, apply: bool) -> Result<PurgeReport> {
+    anyhow::ensure!(!filter.is_empty(), "refusing to purge without a filter");
+    let mut report = PurgeReport::default();
+    let mut sessions: HashSet<String> = HashSet::new();
+    let mut projects: BTreeSet<PathBuf> = BTreeSet::new();
+
+    let log_path = &targets.log_path;
+    let _lock = if apply {
+        Some(LogLock::acquire(log_path)?)
+    } else {
+        None
+    };
+    let mut rewritten = Vec::new();
+    for path in discover_logs(log_path)? {
+        report.files_scanned += 1;
+        let purged = purge_log_file(log_path, &path, filter, apply, &mut sessions, &mut projects)?;
+        if purged > 0 {
+            report.events_purged += purged;
+            rewritten.push(path);
+        }
+    }
+    if apply && !rewritten.is_empty() {
+        report.files_rewritten = rewritten.len();
+        let mut index = EventIndex::open(log_path)?;
+        for path in &rewritten {
+            index.forget_file(path)?;
+        }
+        index.sync()?;
+        index.compact()?;
+    }
+
+    report.memories_purged = purge_memories(&targets.memories_path, filter, apply)?;
+    report.memory_blocks_purged = purge_memory_blocks(&targets.memory_blocks_path, filter, apply)?;
+
+    if let Some(prefix) = filter.whole_project() {
+        projects.insert(PathBuf::from(prefix));
+    }
+    report.session_copies =
+        purge_session_copies(&projects, &sessions, filter.whole_project(), apply)?;
+
+    let purged_any = report.events_purged
+        + report.memories_purged
+        + report.memory_blocks_purged
+        + report.session_copies.len()
+        > 0;
+    if apply && purged_any && targets.search_index_dir.is_dir() {
+        fs::remove_dir_all(&targets.search_index_dir)
+            .with_context(|| format!("remove {}", targets.search_index_dir.display()))?;
+        report.search_index_cleared = true;
+    }
+    Ok(report)
+}
+
+// ── Master log ──────────────────────────────────────────────────────────
+
+/// Returns how many events in `path` match. With `apply`, tombstones them and
+/// replaces the file with a copy that leaves them out.
+fn purge_log_file(
+    log_path: &Path,
+    path: &Path,
+    filter: &PurgeFilter,
+    apply: bool,
+    sessions: &mut HashSet<String>,
+    projects: &mut BTreeSet<PathBuf>,
+) -> Result<usize> {
+    let mut reader = open_log(path)?;
+    let tmp = tmp_path(path);
+    let mut writer = if apply {
+        Some(LogSink::create(&tmp, is_compressed(path))?)
+    } else {
+        None
+    };
+
+    let mut tombstones = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let n = reader
+            .read_line(&mut line)
+            .with_context(|| format!("read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        let matched = serde_json::from_str::<Value>(line.trim())
+            .ok()
+            .filter(|json| filter.matches(&event_fields(json)));
+        match matched {
+            Some(json) => {
+                let fields = event_fields(&json);
+                if let Some(session) = fields.session {
+                    sessions.insert(session.to_string());
+                }
+                if let Some(project) = fields.project.filter(|p| Path::new(p).is_absolute()) {
+                    projects.insert(PathBuf::from(project));
+                }
+                tombstones.push(Tombstone::for_event(&json));
+            }
+            None => {
+                if let Some(writer) = writer.as_mut() {
+                    writer.write_all(line.as_bytes())?;
+                }
+            }
+        }
+    }
+
+    let Some(writer) = writer else {
+        return Ok(tombstones.len());
+    };
+    if tombstones.is_empty() {
+        drop(writer);
+        fs::remove_file(&tmp).ok();
+        return Ok(0);
+    }
+    writer
+        .finish()
+        .with_context(|| format!("write {}", tmp.display()))?;
+    // Tombstones go first: if the rewrite is interrupted, the events are at worst
+    // still present but can no longer be merged back once they are gone.
+    append_tombstones(&tombstone_path(log_path), &tombstones)?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))?;
+    Ok(tombstones.len())
+}
+
+fn event_fields(json: &Value) -> Fields<'_> {
+    Fields {
+        project: json.get("project_context").and_then(Value::as_str),
+        session: json.get("session_id").and_then(Value::as_str),
+        tool: json.get("source_tool").and_then(Value::as_str),
+        timestamp: parse_timestamp(json.get("timestamp")),
+        content: json.pointer("/interaction/content").and_then(Value::as_str),
+        artifacts: json.pointer("/interaction/artifacts"),
+    }
+}
+
+/// Keys of the free-text artifact fields, as in `Artifact::text_fields_mut`: tool
+/// call input, tool and command output, patch diffs and commands.
+const ARTIFACT_TEXT_KEYS: [&str; 4] = ["input", "output", "diff", "command"];
+
+fn artifact_texts(artifacts: Option<&Value>) -> impl Iterator<Item = &str> {
+    artifacts
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .flat_map(|artifact| {
+            ARTIFACT_TEXT_KEYS
+                .iter()
+                .filter_map(move |key| artifact.get(*key).and_then(Value::as_str))
+        })
+}
+
+fn parse_timestamp(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(Value::as_str)
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".purge.tmp");
+    path.with_file_name(name)
+}
+
+// ── Tombstones ──────────────────────────────────────────────────────────
+
+/// `master_log.tombstones` for `master_log.jsonl`.
+pub fn tombstone_path(log_path: &Path) -> PathBuf {
+    log_path.with_extension("tombstones")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Tombstone {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    event_id: Option<Uuid>,
+    /// [`stable_fingerprint`] of the event, matching copies with another event_id.
+    fingerprint: u64,
+    /// [`history_key`] of the event, matching a re-import from the tool's storage.
+    history_key: u64,
+    purged_at: DateTime<Utc>,
+}
+
+impl Tombstone {
+    fn for_event(json: &Value) -> Self {
+        let fields = event_fields(json);
+        Self {
+            event_id: json
+                .get("event_id")
+                .and_then(Value::as_str)
+                .and_then(|s| Uuid::parse_str(s).ok()),
+            fingerprint: stable_fingerprint(json),
+            history_key: history_key(
+                fields.tool.unwrap_or(""),
+                fields.session.unwrap_or(""),
+                fields.content.unwrap_or(""),
+            ),
+            purged_at: Utc::now(),
+        }
+    }
+}
+
+fn append_tombstones(path: &Path, tombstones: &[Tombstone]) -> Result<()> {
+    let mut buf = Vec::new();
+    for tombstone in tombstones {
+        serde_json::to_writer(&mut buf, tombstone)?;
+        buf.push(b'\n');
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    file.write_all(&buf)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Events deleted by earlier purges, which must not come back.
+#[derive(Debug, Default)]
+pub struct Tombstones {
+    event_ids: HashSet<Uuid>,
+    fingerprints: HashSet<u64>,
+    history_keys: HashSet<u64>,
+}
+
+impl Tombstones {
+    /// Tombstones recorded next to `log_path`; none if nothing was ever purged.
+    pub fn load(log_path: &Path) -> Result<Self> {
+        let path = tombstone_path(log_path);
+        let mut out = Self::default();
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(out),
+            Err(e) => return Err(e).with_context(|| format!("open {}", path.display())),
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line.with_context(|| format!("read {}", path.display()))?;
+            let Ok(tombstone) = serde_json::from_str::<Tombstone>(&line) else {
+                continue;
+            };
+            if let Some(id) = tombstone.event_id {
+                out.event_ids.insert(id);
+            }
+            out.fingerprints.insert(tombstone.fingerprint);
+            out.history_keys.insert(tombstone.history_key);
+        }
+        Ok(out)
+    }
+
+    /// Whether `json` is (a copy of) a purged event.
+    pub fn covers(&self, json: &Value) -> bool {
+        if self.fingerprints.is_empty() {
+            return false;
+        }
+        let by_id = json
+            .get("event_id")
+            .and_then(Value::as_str)
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .is_some_and(|id| self.event_ids.contains(&id));
+        by_id || self.fingerprints.contains(&stable_fingerprint(json))
+    }
+
+    /// [`history_key`]s of purged events, for importers that dedup by them.
+    pub fn history_keys(&self) -> &HashSet<u64> {
+        &self.history_keys
+    }
+}
+
+/// Like the merge fingerprint (source, project, session, timestamp, role, content),
+/// but with a hash that is stable across builds, since tombstones are persisted.
+fn stable_fingerprint(json: &Value) -> u64 {
+    let fields = event_fields(json);
+    let timestamp =
+        canonical_timestamp_repr(json.get("timestamp").and_then(Value::as_str).unwrap_or(""));
+    let role = json
+        .pointer("/interaction/role")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    let mut buf = Vec::new();
+    for part in [
+        fields.tool.unwrap_or(""),
+        fields.project.unwrap_or(""),
+        fields.session.unwrap_or(""),
+        &timestamp,
+        role,
+    ] {
+        buf.extend_from_slice(part.as_bytes());
+        buf.push(0);
+    }
+    buf.extend_from_slice(&content_hash(fields.content.unwrap_or("")).to_le_bytes());
+    xxhash_rust::xxh3::xxh3_64(&buf)
+}
+
+// ── Analysis memories ───────────────────────────────────────────────────
+
+/// Drops memories (`memories.jsonl`) that quote a matching event, or whose own
+/// query, prompt or response matches a content-only filter.
+fn purge_memories(path: &Path, filter: &PurgeFilter, apply: bool) -> Result<usize> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+    };
+    let mut kept = String::with_capacity(raw.len());
+    let mut purged = 0;
+    for line in raw.lines() {
+        let matched = serde_json::from_str::<Value>(line)
+            .ok()
+            .is_some_and(|record| memory_matches(&record, filter));
+        if matched {
+            purged += 1;
+        } else {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+    if apply && purged > 0 {
+        replace_file(path, kept.as_bytes())?;
+    }
+    Ok(purged)
+}
+
+fn memory_matches(record: &Value, filter: &PurgeFilter) -> bool {
+    let quoted = record
+        .get("matches")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .any(|m| {
+            filter.matches(&Fields {
+                project: m.get("project_context").and_then(Value::as_str),
+                session: m.get("session_id").and_then(Value::as_str),
+                tool: m.get("source_tool").and_then(Value::as_str),
+                timestamp: parse_timestamp(m.get("timestamp")),
+                content: m.get("content_snippet").and_then(Value::as_str),
+                artifacts: None,
+            })
+        });
+    quoted
+        || ["query", "prompt", "llm_response"].iter().any(|key| {
+            let text = match record.get(*key) {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => return false,
+                Some(other) => other.to_string(),
+            };
+            filter.matches(&Fields {
+                content: Some(&text),
+                ..Fields::default()
+            })
+        })
+}
+
+/// Drops memory blocks (`memory_blocks.json`) whose project, tool, creation time
+/// and value match.
+fn purge_memory_blocks(path: &Path, filter: &PurgeFilter, apply: bool) -> Result<usize> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+    };
+    if raw.trim().is_empty() {
+        return Ok(0);
+    }
+    let blocks: Vec<Value> =
+        serde_json::from_str(&raw).with_context(|| format!("parse {}", path.display()))?;
+    let total = blocks.len();
+    let kept: Vec<Value> = blocks
+        .into_iter()
+        .filter(|block| {
+            !filter.matches(&Fields {
+                project: block.get("project_context").and_then(Value::as_str),
+                session: None,
+                tool: block.get("source_tool").and_then(Value::as_str),
+                timestamp: parse_timestamp(block.get("created_at")),
+                content: block.get("value").and_then(Value::as_str),
+                artifacts: None,
+            })
+        })
+        .collect();
+    let purged = total - kept.len();
+    if apply && purged > 0 {
+        let json = serde_json::to_string_pretty(&kept)? + "\n";
+        replace_file(path, json.as_bytes())?;
+    }
+    Ok(purged)
+}
+
+fn replace_file(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = tmp_path(path);
+    let mut file = File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))
+}
+
+// ── memex transcripts ───────────────────────────────────────────────────
+
+/// memex names transcripts `<time>_<tool>_<session id>.md` (the id sanitized and
+/// cut to 32 characters, with `__<n>` added on collisions). Deletes those of purged
+/// sessions under each project's `.context/sessions/`, or all of them when a whole
+/// project is purged.
+fn purge_session_copies(
+    projects: &BTreeSet<PathBuf>,
+    sessions: &HashSet<String>,
+    whole_project: Option<&str>,
+    apply: bool,
+) -> Result<Vec<PathBuf>> {
+    let ids: HashSet<String> = sessions.iter().map(|s| memex_session_id(s)).collect();
+    let mut removed = Vec::new();
+    for project in projects {
+        let dir = project.join(".context/sessions");
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let everything = whole_project.is_some_and(|prefix| project.starts_with(prefix));
+        for entry in entries {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let Some(stem) = name.strip_suffix(".md") else {
+                continue;
+            };
+            let stem = stem.split("__").next().unwrap_or(stem);
+            let session = stem.rsplit('_').next().unwrap_or("");
+            if everything || ids.contains(session) {
+                if apply {
+                    fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+                }
+                removed.push(path);
+            }
+        }
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+fn memex_session_id(session: &str) -> String {
+    session
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '-',
+        })
+        .take(32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn line(id: Uuid, session: &str, project: &str, ts: &str, content: &str) -> String {
+        serde_json::json!({
+            "event_id": id,
+            "timestamp": ts,
+            "source_tool": "codex-cli",
+            "project_context": project,
+            "session_id": session,
+            "interaction": { "role": "user", "content": content },
+            "security_flags": { "has_pii": false, "redacted_secrets": [] },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn purges_logs_memories_and_transcripts_and_leaves_tombstones() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let sessions_dir = repo.join(".context/sessions");
+        fs::create_dir_all(&sessions_dir).unwrap();
+        let project = repo.to_string_lossy().into_owned();
+
+        let log_path = dir.path().join("logs/master_log.jsonl");
+        fs::create_dir_all(log_path.parent().unwrap()).unwrap();
+        let (gone, kept) = (Uuid::new_v4(), Uuid::new_v4());
+        let gone_line = line(
+            gone,
+            "s-secret",
+            &project,
+            "2025-01-01T00:00:00Z",
+            "my secret plan",
+        );
+        fs::write(
+            &log_path,
+            format!(
+                "{gone_line}\n{}\n",
+                line(
+                    kept,
+                    "s-other",
+                    &project,
+                    "2025-01-02T00:00:00Z",
+                    "harmless"
+                )
+            ),
+        )
+        .unwrap();
+        EventIndex::open_synced(&log_path).unwrap();
+
+        let memories_path = dir.path().join("memories.jsonl");
+        fs::write(
+            &memories_path,
+            concat!(
+                r#"{"query":"plan","matches":[{"session_id":"s-secret","source_tool":"codex-cli","project_context":"x","timestamp":"2025-01-01T00:00:00Z","role":"user","content_snippet":"my secret plan"}]}"#,
+                "\n",
+                r#"{"query":"other","matches":[]}"#,
+                "\n"
+            ),
+        )
+        .unwrap();
+        let blocks_path = dir.path().join("memory_blocks.json");
+        let index_dir = dir.path().join("index");
+        fs::create_dir_all(&index_dir).unwrap();
+        fs::write(index_dir.join("events.sqlite"), "cached").unwrap();
+        fs::write(
+            &blocks_path,
+            r#"[{"label":"a","value":"the secret plan"},{"label":"b","value":"fine"}]"#,
+        )
+        .unwrap();
+        fs::write(
+            sessions_dir.join("2025-01-01T00-00-00_codex_s-secret.md"),
+            "x",
+        )
+        .unwrap();
+        fs::write(
+            sessions_dir.join("2025-01-01T00-00-00_codex_s-secret__1.md"),
+            "x",
+        )
+        .unwrap();
+        fs::write(
+            sessions_dir.join("2025-01-02T00-00-00_codex_s-other.md"),
+            "y",
+        )
+        .unwrap();
+
+        let targets = PurgeTargets {
+            log_path: log_path.clone(),
+            memories_path: memories_path.clone(),
+            memory_blocks_path: blocks_path.clone(),
+            search_index_dir: index_dir.clone(),
+        };
+        let filter = PurgeFilter {
+            content: Some(Regex::new("secret").unwrap()),
+            ..Default::default()
+        };
+
+        let dry = purge(&targets, &filter, false).unwrap();
+        assert_eq!(dry.events_purged, 1);
+        assert_eq!(dry.memories_purged, 1);
+        assert_eq!(dry.memory_blocks_purged, 1);
+        assert_eq!(dry.session_copies.len(), 2);
+        assert!(fs::read_to_string(&log_path)
+            .unwrap()
+            .contains("secret plan"));
+
+        let report = purge(&targets, &filter, true).unwrap();
+        assert_eq!(report.files_rewritten, 1);
+        assert!(!fs::read_to_string(&log_path)
+            .unwrap()
+            .contains("secret plan"));
+        assert!(!fs::read_to_string(&memories_path)
+            .unwrap()
+            .contains("secret"));
+        assert!(!fs::read_to_string(&blocks_path).unwrap().contains("secret"));
+        assert_eq!(fs::read_dir(&sessions_dir).unwrap().count(), 1);
+        assert!(report.search_index_cleared);
+        assert!(!index_dir.exists());
+
+        let index = EventIndex::open_synced(&log_path).unwrap();
+        assert_eq!(index.count(&Default::default()).unwrap(), 1);
+        assert!(index.get(gone).unwrap().is_none());
+
+        let tombstones = Tombstones::load(&log_path).unwrap();
+        let original: Value = serde_json::from_str(&gone_line).unwrap();
+        assert!(tombstones.covers(&original));
+        // The same event captured on another machine has another event_id.
+        let copy: Value = serde_json::from_str(&line(
+            Uuid::new_v4(),
+            "s-secret",
+            &project,
+            "2025-01-01T00:00:00.000+00:00",
+            "my secret plan",
+        ))
+        .unwrap();
+        assert!(tombstones.covers(&copy));
+        let survivor: Value =
+            serde_json::from_str(fs::read_to_string(&log_path).unwrap().trim()).unwrap();
+        assert!(!tombstones.covers(&survivor));
+        assert!(tombstones.history_keys().contains(&history_key(
+            "codex-cli",
+            "s-secret",
+            "my secret plan"
+        )));
+    }
+
+    #[test]
+    fn filters_combine_and_empty_filters_are_refused() {
+        let filter = PurgeFilter {
+            project_prefix: Some("/work/acme".to_string()),
+            before: Some("2025-02-01T00:00:00Z".parse().unwrap()),
+            ..Default::default()
+        };
+        let fields = |project, ts: &str| Fields {
+            project: Some(project),
+            timestamp: ts.parse().ok(),
+            ..Fields::default()
+        };
+        assert!(filter.matches(&fields("/work/acme/api", "2025-01-31T23:59:59Z")));
+        assert!(!filter.matches(&fields("/work/acme/api", "2025-02-01T00:00:00Z")));
+        assert!(!filter.matches(&fields("/work/other", "2025-01-01T00:00:00Z")));
+        // A sibling directory sharing the name's prefix is a different project.
+        assert!(!filter.matches(&fields("/work/acme-old", "2025-01-01T00:00:00Z")));
+        assert!(!filter.matches(&fields("/work/acmefoo/api", "2025-01-01T00:00:00Z")));
+        assert!(filter.matches(&fields("/work/acme", "2025-01-01T00:00:00Z")));
+        assert!(!filter.matches(&Fields::default()));
+        assert_eq!(filter.whole_project(), None);
+
+        let project_only = PurgeFilter {
+            project_prefix: Some("/work/acme".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(project_only.whole_project(), Some("/work/acme"));
+
+        let targets = PurgeTargets {
+            log_path: PathBuf::from("/nonexistent/master_log.jsonl"),
+            memories_path: PathBuf::from("/nonexistent/memories.jsonl"),
+            memory_blocks_path: PathBuf::from("/nonexistent/memory_blocks.json"),
+            search_index_dir: PathBuf::from("/nonexistent/index"),
+        };
+        assert!(purge(&targets, &PurgeFilter::default(), false).is_err());
+    }
+
+    #[test]
+    fn content_filter_matches_artifact_text() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let (gone, kept) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut leaked: Value = serde_json::from_str(&line(
+            gone,
+            "s1",
+            "/work/acme",
+            "2025-01-01T00:00:00Z",
+            "ran the deploy",
+        ))
+        .unwrap();
+        leaked["interaction"]["artifacts"] = serde_json::json!([
+            {"type": "command", "command": "./deploy.sh", "exit_code": 0},
+            {"type": "tool_result", "output": "token=sk-live-abc123", "is_error": false}
+        ]);
+        fs::write(
+            &log_path,
+            format!(
+                "{leaked}\n{}\n",
+                line(kept, "s1", "/work/acme", "2025-01-01T00:01:00Z", "done")
+            ),
+        )
+        .unwrap();
+
+        let targets = PurgeTargets {
+            log_path: log_path.clone(),
+            memories_path: dir.path().join("memories.jsonl"),
+            memory_blocks_path: dir.path().join("memory_blocks.json"),
+            search_index_dir: dir.path().join("index"),
+        };
+        let filter = PurgeFilter {
+            content: Some(Regex::new("sk-live-[a-z0-9]+").unwrap()),
+            ..Default::default()
+        };
+        let report = purge(&targets, &filter, true).unwrap();
+        assert_eq!(report.events_purged, 1);
+        let remaining = fs::read_to_string(&log_path).unwrap();
+        assert!(!remaining.contains("sk-live"));
+        assert!(remaining.contains(&kept.to_string()));
+    }
+}
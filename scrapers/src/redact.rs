@@ -0,0 +1,393 @@
+//! Retroactive redaction: run the current [`Sentry`] over logs that were captured
+//! before a pattern existed, so new rules also cover `master_log.jsonl` and its
+//! rotated archives.
+//!
+//! Lines the Sentry leaves alone are copied byte-for-byte; changed events keep their
+//! `event_id` and gain the new labels in `security_flags`. Each file is replaced
+//! atomically via a temp file and rename. The event index gives rewritten events
+//! new sequence numbers, and replicas pulling them apply the new versions through
+//! [`apply_revisions`].
+
+use crate::event_index::EventIndex;
+use crate::log_index::{discover_logs, is_compressed, open_log, LogSink};
+use crate::log_lock::LogLock;
+use crate::sentry::Sentry;
+use crate::types::MasterLog;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default)]
+pub struct RedactReport {
+    pub files_scanned: usize,
+    pub files_rewritten: usize,
+    pub events_scanned: usize,
+    pub events_changed: usize,
+    /// Lines that aren't valid events; always left untouched.
+    pub malformed: usize,
+    /// Events changed per Sentry label.
+    pub labels: BTreeMap<String, usize>,
+}
+
+/// Scan every log under `log_path` and, with `rewrite`, replace the files that
+/// changed. Without `rewrite` this only reports what would change.
+///
+/// A rewrite holds the [`LogLock`], so `merge-log`, `sync`, imports and the daemon's
+/// writer wait for it; the writer reopens the log once it sees it was replaced.
+///
+/// **Important**: the daemon should still be stopped while this runs; a file that
+/// grows mid-rewrite is left as-is and reported as an error.
+pub fn redact_logs(log_path: &Path, sentry: &Sentry, rewrite: bool) -> Result<RedactReport> {
+    let mut report = RedactReport::default();
+    let scan = |report: &mut RedactReport| -> Result<()> {
+        for path in discover_logs(log_path)? {
+            report.files_scanned += 1;
+            if rewrite_file(&path, rewrite, |line| redact_line(line, sentry, report))? {
+                report.files_rewritten += 1;
+            }
+        }
+        Ok(())
+    };
+    if rewrite {
+        LogLock::with_lock(log_path, || scan(&mut report))?;
+    } else {
+        scan(&mut report)?;
+    }
+
+    if rewrite && report.files_rewritten > 0 {
+        // Rewritten files no longer match their indexed heads, so this re-indexes them.
+        EventIndex::open_synced(log_path)?;
+    }
+    Ok(report)
+}
+
+/// Replace the local copies of events a sync source re-sent after rewriting them
+/// (its own `redact --rewrite`), so a redaction reaches every replica. A revision
+/// is only applied if it keeps every redaction label the local copy has, so an
+/// older, unredacted copy never overwrites a redacted one. Returns how many events
+/// were replaced.
+pub fn apply_revisions(log_path: &Path, revisions: &[MasterLog]) -> Result<usize> {
+    if revisions.is_empty() {
+        return Ok(0);
+    }
+    let index = EventIndex::open_synced(log_path)?;
+    let mut replacements: HashMap<String, String> = HashMap::new();
+    for revision in revisions {
+        let Some(local) = index.get(revision.event_id)? else {
+            continue;
+        };
+        let keeps_labels = local
+            .security_flags
+            .redacted_secrets
+            .iter()
+            .all(|label| revision.security_flags.redacted_secrets.contains(label));
+        if keeps_labels && serde_json::to_value(&local)? != serde_json::to_value(revision)? {
+            replacements.insert(
+                revision.event_id.to_string(),
+                serde_json::to_string(revision)?,
+            );
+        }
+    }
+    drop(index);
+    if replacements.is_empty() {
+        return Ok(0);
+    }
+
+    let mut replaced = 0;
+    LogLock::with_lock(log_path, || {
+        for path in discover_logs(log_path)? {
+            rewrite_file(&path, true, |line| {
+                let replacement = replacements.get(&line_event_id(line)?)?;
+                replaced += 1;
+                Some(replacement.clone())
+            })?;
+        }
+        Ok(())
+    })?;
+    EventIndex::open_synced(log_path)?;
+    Ok(replaced)
+}
+
+fn line_event_id(line: &str) -> Option<String> {
+    #[derive(serde::Deserialize)]
+    struct EventId {
+        event_id: String,
+    }
+    serde_json::from_str::<EventId>(line.trim())
+        .ok()
+        .map(|e| e.event_id)
+}
+
+/// Pass each line of `path` through `edit`, which returns a replacement for lines
+/// it changes. With `rewrite`, a file with changes is replaced atomically; returns
+/// whether that happened.
+fn rewrite_file(
+    path: &Path,
+    rewrite: bool,
+    mut edit: impl FnMut(&str) -> Option<String>,
+) -> Result<bool> {
+    let compressed = is_compressed(path);
+    let original_len = fs::metadata(path)
+        .with_context(|| format!("stat {}", path.display()))?
+        .len();
+    let mut reader = open_log(path)?;
+
+    let tmp = tmp_path(path);
+    let mut writer = if rewrite {
+        Some(LogSink::create(&tmp, compressed)?)
+    } else {
+        None
+    };
+
+    let mut bytes_read = 0u64;
+    let mut changed = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let n = reader
+            .read_line(&mut line)
+            .with_context(|| format!("read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        bytes_read += n as u64;
+
+        let output = edit(&line);
+        changed |= output.is_some();
+        if let Some(writer) = writer.as_mut() {
+            match output {
+                Some(rewritten) => {
+                    writer.write_all(rewritten.as_bytes())?;
+                    if line.ends_with('\n') {
+                        writer.write_all(b"\n")?;
+                    }
+                }
+                None => writer.write_all(line.as_bytes())?,
+            }
+        }
+    }
+
+    let Some(writer) = writer else {
+        return Ok(false);
+    };
+    if !changed {
+        drop(writer);
+        fs::remove_file(&tmp).ok();
+        return Ok(false);
+    }
+
+    writer
+        .finish()
+        .with_context(|| format!("sync {}", tmp.display()))?;
+    let current_len = fs::metadata(path)
+        .with_context(|| format!("stat {}", path.display()))?
+        .len();
+    // Compressed archives are never appended to; for them only the size on disk is
+    // comparable.
+    let expected_len = if compressed { original_len } else { bytes_read };
+    if current_len != expected_len {
+        fs::remove_file(&tmp).ok();
+        anyhow::bail!(
+            "{} changed while it was being rewritten; stop the daemon and retry",
+            path.display()
+        );
+    }
+    fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))?;
+    Ok(true)
+}
+
+/// Returns the re-serialized event if redaction changed it.
+fn redact_line(line: &str, sentry: &Sentry, report: &mut RedactReport) -> Option<String> {
+    let trimmed = line.trim_end_matches(['\n', '\r']);
+    if trimmed.trim().is_empty() {
+        return None;
+    }
+    let Ok(mut event) = serde_json::from_str::<MasterLog>(trimmed) else {
+        report.malformed += 1;
+        return None;
+    };
+    report.events_scanned += 1;
+
+    let labels = redact_event(&mut event, sentry);
+    if labels.is_empty() {
+        return None;
+    }
+
+    report.events_changed += 1;
+    for label in labels {
+        *report.labels.entry(label).or_default() += 1;
+    }
+    serde_json::to_string(&event).ok()
+}
+
+/// Redacts content and artifacts in place, returning the labels that changed something.
+fn redact_event(event: &mut MasterLog, sentry: &Sentry) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut apply = |text: &mut String| {
+        let (redacted, flags) = sentry.scan_and_redact(text);
+        if redacted == *text {
+            return;
+        }
+        *text = redacted;
+        for label in flags.redacted_secrets {
+            if !labels.contains(&label) {
+                labels.push(label);
+            }
+        }
+    };
+
+    apply(&mut event.interaction.content);
+    for artifact in event.interaction.artifacts.iter_mut().flatten() {
+        for text in artifact.text_fields_mut() {
+            apply(text);
+        }
+    }
+
+    if !labels.is_empty() {
+        let flags = &mut event.security_flags;
+        flags.has_pii = true;
+        for label in &labels {
+            if !flags.redacted_secrets.contains(label) {
+                flags.redacted_secrets.push(label.clone());
+            }
+        }
+    }
+    labels
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".redact.tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn event(event_id: Uuid, content: &str) -> String {
+        json!({
+            "event_id": event_id.to_string(),
+            "timestamp": "2026-01-01T00:00:00Z",
+            "source_tool": "codex-cli",
+            "project_context": "/tmp/project",
+            "session_id": "s1",
+            "interaction": { "role": "user", "content": content },
+            "security_flags": { "has_pii": false, "redacted_secrets": [] },
+            "metadata": {}
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn rewrites_archives_and_current_log_preserving_event_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let archive = dir.path().join("master_log.20260101T000000Z.jsonl");
+        let leaked = Uuid::new_v4();
+        let clean_line = event(Uuid::new_v4(), "nothing to see");
+        fs::write(
+            &archive,
+            format!(
+                "{}\n{clean_line}\nnot json\n",
+                event(leaked, "DB_PASSWORD=hunter2")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            &log_path,
+            format!("{}\n", event(Uuid::new_v4(), "still clean")),
+        )
+        .unwrap();
+
+        let report = redact_logs(&log_path, &Sentry::new(), false).unwrap();
+        assert_eq!(report.files_scanned, 2);
+        assert_eq!(report.files_rewritten, 0);
+        assert_eq!(report.events_changed, 1);
+        assert_eq!(report.malformed, 1);
+        assert!(fs::read_to_string(&archive).unwrap().contains("hunter2"));
+
+        let report = redact_logs(&log_path, &Sentry::new(), true).unwrap();
+        assert_eq!(report.files_rewritten, 1);
+        assert_eq!(report.labels.get("env_secret"), Some(&1));
+
+        let rewritten = fs::read_to_string(&archive).unwrap();
+        let lines: Vec<&str> = rewritten.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let first: MasterLog = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first.event_id, leaked);
+        assert_eq!(first.interaction.content, "DB_PASSWORD=[REDACTED]");
+        assert!(first.security_flags.has_pii);
+        assert_eq!(first.security_flags.redacted_secrets, vec!["env_secret"]);
+        assert_eq!(lines[1], clean_line);
+        assert_eq!(lines[2], "not json");
+
+        let index = EventIndex::open_synced(&log_path).unwrap();
+        let indexed = index.get(leaked).unwrap().unwrap();
+        assert_eq!(indexed.interaction.content, "DB_PASSWORD=[REDACTED]");
+
+        let again = redact_logs(&log_path, &Sentry::new(), true).unwrap();
+        assert_eq!(again.events_changed, 0);
+        assert_eq!(again.files_rewritten, 0);
+    }
+
+    #[test]
+    fn revisions_replace_local_copies_unless_they_undo_redaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let (leaked, other) = (Uuid::new_v4(), Uuid::new_v4());
+        fs::write(
+            &log_path,
+            format!(
+                "{}\n{}\n",
+                event(leaked, "DB_PASSWORD=hunter2"),
+                event(other, "fine")
+            ),
+        )
+        .unwrap();
+        let original: MasterLog =
+            serde_json::from_str(&event(leaked, "DB_PASSWORD=hunter2")).unwrap();
+
+        // What the source sends after its own `redact --rewrite`.
+        let mut redacted = original.clone();
+        redact_event(&mut redacted, &Sentry::new());
+        let unknown: MasterLog = serde_json::from_str(&event(Uuid::new_v4(), "new")).unwrap();
+        let applied = apply_revisions(&log_path, &[redacted.clone(), unknown]).unwrap();
+        assert_eq!(applied, 1);
+        let text = fs::read_to_string(&log_path).unwrap();
+        assert!(!text.contains("hunter2"));
+        assert_eq!(text.lines().count(), 2);
+
+        // Pulled back (or resent unredacted): nothing to do.
+        assert_eq!(apply_revisions(&log_path, &[redacted]).unwrap(), 0);
+        assert_eq!(apply_revisions(&log_path, &[original]).unwrap(), 0);
+        assert!(!fs::read_to_string(&log_path).unwrap().contains("hunter2"));
+    }
+
+    #[test]
+    fn rewrite_waits_for_other_log_writers() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        fs::write(
+            &log_path,
+            format!("{}\n", event(Uuid::new_v4(), "DB_PASSWORD=hunter2")),
+        )
+        .unwrap();
+
+        let held = LogLock::acquire(&log_path).unwrap();
+        let path = log_path.clone();
+        let rewrite = std::thread::spawn(move || redact_logs(&path, &Sentry::new(), true));
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(!rewrite.is_finished());
+        assert!(fs::read_to_string(&log_path).unwrap().contains("hunter2"));
+
+        drop(held);
+        let report = rewrite.join().unwrap().unwrap();
+        assert_eq!(report.files_rewritten, 1);
+        assert!(!fs::read_to_string(&log_path).unwrap().contains("hunter2"));
+    }
+}
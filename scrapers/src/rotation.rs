@@ -1,80 +1,310 @@
-use crate::log_index::discover_archives;
-use anyhow::Result;
-use chrono::Utc;
-use std::fs::{self, OpenOptions};
+//! Rotation of `master_log.jsonl` into timestamped archives, and their retention.
+//!
+//! The daemon's [`LogWriter`](crate::log_writer::LogWriter) checks [`RotationLimits`]
+//! before every batch and calls [`rotate`] while holding the log lock. Each rotation
+//! records the archive's name in `master_log.rotated`. The dashboard's live stream
+//! uses it to finish the old file before restarting at the top of the new one, and
+//! [`Merger`](crate::merge::Merger) to reopen the log it appends to.
+//!
+//! Afterwards, outside the lock, [`compress_archives`] compresses the new archive with zstd and
+//! [`prune_archives`] applies the count and age limits.
+
+use crate::log_index::{archive_files, compressed_path, discover_archives, is_compressed, LogSink};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+use serde::Deserialize;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const ARCHIVE_TIMESTAMP: &str = "%Y%m%dT%H%M%SZ";
 
-#[derive(Debug, Default)]
-pub struct RotationResult {
-    pub rotated: bool,
-    pub archive_path: Option<PathBuf>,
-    pub pruned: usize,
+/// When the log is rotated besides exceeding its size limit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationPolicy {
+    /// Only when the log grows past `max_bytes`.
+    #[default]
+    Size,
+    /// Also at the first write of each UTC day.
+    Daily,
+    /// Also at the first write of each UTC month.
+    Monthly,
 }
 
-pub fn rotate_if_needed(
-    log_path: &Path,
-    max_bytes: u64,
-    keep_files: usize,
-) -> Result<RotationResult> {
-    let mut result = RotationResult::default();
-    let Some(dir) = log_path.parent() else {
-        return Ok(result);
-    };
+impl FromStr for RotationPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "size" => Ok(Self::Size),
+            "daily" => Ok(Self::Daily),
+            "monthly" => Ok(Self::Monthly),
+            other => anyhow::bail!("unknown rotation policy {other:?}"),
+        }
+    }
+}
 
-    fs::create_dir_all(dir)?;
+impl fmt::Display for RotationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Size => "size",
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+        })
+    }
+}
 
-    let Ok(meta) = fs::metadata(log_path) else {
-        return Ok(result);
-    };
+#[derive(Debug, Clone, Copy)]
+pub struct RotationLimits {
+    pub policy: RotationPolicy,
+    pub max_bytes: u64,
+    pub keep_files: usize,
+    /// Archives rotated more than this many days ago are deleted; 0 keeps them
+    /// regardless of age.
+    pub max_age_days: u64,
+}
 
-    if meta.len() <= max_bytes {
-        return Ok(result);
+impl RotationLimits {
+    /// Whether a log of `len` bytes, last written at `last_write`, should be rotated
+    /// before `incoming` more bytes are appended at `now`. An empty log never is.
+    pub fn due(
+        &self,
+        len: u64,
+        incoming: u64,
+        last_write: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        if len == 0 {
+            return false;
+        }
+        if len.saturating_add(incoming) > self.max_bytes {
+            return true;
+        }
+        match self.policy {
+            RotationPolicy::Size => false,
+            RotationPolicy::Daily => last_write.date_naive() != now.date_naive(),
+            RotationPolicy::Monthly => {
+                (last_write.year(), last_write.month()) != (now.year(), now.month())
+            }
+        }
+    }
+}
+
+/// Move the current log to `master_log.<UTC timestamp>.jsonl`, record the archive
+/// in the rotation marker and start an empty log. Returns the archive, or `None`
+/// when there was no log to rotate. Call with the
+/// [`LogLock`](crate::log_lock::LogLock) held.
+pub fn rotate(log_path: &Path) -> Result<Option<PathBuf>> {
+    let Some(dir) = log_path.parent() else {
+        return Ok(None);
+    };
+    if !log_path.is_file() {
+        return Ok(None);
     }
 
-    let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ");
-    let archive_path = dir.join(format!("master_log.{timestamp}.jsonl"));
-    fs::rename(log_path, &archive_path)?;
+    let archive_path = unused_archive_path(dir, Utc::now());
+    fs::rename(log_path, &archive_path).with_context(|| {
+        format!(
+            "rename {} to {}",
+            log_path.display(),
+            archive_path.display()
+        )
+    })?;
+    // The marker is written before the new log exists, so a follower that sees a
+    // fresh `master_log.jsonl` already knows where the old one went.
+    let name = archive_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    fs::write(rotation_marker_path(log_path), format!("{name}\n"))
+        .context("write rotation marker")?;
     OpenOptions::new()
         .create(true)
         .append(true)
-        .open(log_path)?;
+        .open(log_path)
+        .with_context(|| format!("recreate {}", log_path.display()))?;
 
-    result.rotated = true;
-    result.archive_path = Some(archive_path);
+    Ok(Some(archive_path))
+}
+
+/// Compress every uncompressed archive next to `log_path`, returning how many were
+/// compressed. Each is written to a temporary file and renamed into place before the
+/// original is removed, so an interruption never loses an archive.
+pub fn compress_archives(log_path: &Path) -> Result<usize> {
+    let mut compressed = 0;
+    for path in archive_files(log_path)? {
+        if is_compressed(&path) {
+            continue;
+        }
+        let target = compressed_path(&path);
+        if !target.exists() {
+            let mut tmp = target.clone().into_os_string();
+            tmp.push(".tmp");
+            let tmp = PathBuf::from(tmp);
+            let mut source =
+                File::open(&path).with_context(|| format!("open {}", path.display()))?;
+            let mut sink = LogSink::create(&tmp, true)?;
+            io::copy(&mut source, &mut sink)
+                .with_context(|| format!("compress {}", path.display()))?;
+            sink.finish()?;
+            fs::rename(&tmp, &target)
+                .with_context(|| format!("rename {} to {}", tmp.display(), target.display()))?;
+            compressed += 1;
+        }
+        fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+    }
+    Ok(compressed)
+}
 
+/// Delete the oldest archives beyond `limits.keep_files`, and any rotated more than
+/// `limits.max_age_days` ago. Returns how many were deleted.
+pub fn prune_archives(
+    log_path: &Path,
+    limits: &RotationLimits,
+    now: DateTime<Utc>,
+) -> Result<usize> {
     let mut archives = discover_archives(log_path)?;
-    let target_keep = keep_files.max(1);
-    if archives.len() > target_keep {
-        let to_prune = archives.len() - target_keep;
-        archives.truncate(to_prune);
-        for path in archives {
-            fs::remove_file(path)?;
-            result.pruned += 1;
+    let excess = archives.len().saturating_sub(limits.keep_files.max(1));
+    let cutoff = match limits.max_age_days {
+        0 => None,
+        days => i64::try_from(days)
+            .ok()
+            .and_then(Duration::try_days)
+            .and_then(|age| now.checked_sub_signed(age)),
+    };
+
+    let mut pruned = 0;
+    for (i, path) in archives.drain(..).enumerate() {
+        let expired = cutoff.is_some_and(|cutoff| archive_time(&path).is_some_and(|t| t < cutoff));
+        if i < excess || expired {
+            fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+            pruned += 1;
         }
     }
+    Ok(pruned)
+}
+
+/// When an archive was rotated, from its name, falling back to its mtime.
+fn archive_time(path: &Path) -> Option<DateTime<Utc>> {
+    let name = path.file_name()?.to_str()?;
+    let stamp = name.strip_prefix("master_log.")?.split('.').next()?;
+    match NaiveDateTime::parse_from_str(stamp, ARCHIVE_TIMESTAMP) {
+        Ok(at) => Some(at.and_utc()),
+        Err(_) => fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(Into::into),
+    }
+}
 
-    Ok(result)
+/// `master_log.rotated` for `master_log.jsonl`.
+pub fn rotation_marker_path(log_path: &Path) -> PathBuf {
+    log_path.with_extension("rotated")
+}
+
+/// The archive written by the most recent rotation, if the log was ever rotated.
+pub fn last_rotation(log_path: &Path) -> Option<PathBuf> {
+    let name = fs::read_to_string(rotation_marker_path(log_path)).ok()?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some(log_path.parent()?.join(name))
+}
+
+/// Archive names sort chronologically, so a second rotation within the same second
+/// takes the next free one rather than overwriting the first.
+fn unused_archive_path(dir: &Path, mut at: DateTime<Utc>) -> PathBuf {
+    loop {
+        let timestamp = at.format(ARCHIVE_TIMESTAMP);
+        let path = dir.join(format!("master_log.{timestamp}.jsonl"));
+        if !path.exists() && !compressed_path(&path).exists() {
+            return path;
+        }
+        at += Duration::seconds(1);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
     use tempfile::tempdir;
 
+    fn limits(policy: RotationPolicy) -> RotationLimits {
+        RotationLimits {
+            policy,
+            max_bytes: 100,
+            keep_files: 5,
+            max_age_days: 0,
+        }
+    }
+
     #[test]
-    fn rotates_and_prunes_old_archives() {
+    fn rotates_compresses_and_prunes_archives() {
         let dir = tempdir().expect("tempdir");
         let log_path = dir.path().join("master_log.jsonl");
-        fs::write(&log_path, "0123456789").expect("write log");
+        fs::write(&log_path, "{\"n\":1}\n").expect("write log");
         fs::write(dir.path().join("master_log.20240101T000000Z.jsonl"), "a").expect("write a1");
         fs::write(dir.path().join("master_log.20240201T000000Z.jsonl"), "b").expect("write a2");
+        assert_eq!(last_rotation(&log_path), None);
 
-        let res = rotate_if_needed(&log_path, 5, 2).expect("rotate");
-        assert!(res.rotated);
+        let archive = rotate(&log_path).expect("rotate").expect("archive");
         assert!(log_path.exists());
-        assert_eq!(res.pruned, 1);
+        assert_eq!(fs::metadata(&log_path).unwrap().len(), 0);
+        assert_eq!(last_rotation(&log_path).as_ref(), Some(&archive));
+
+        // A second rotation in the same second must not overwrite the first archive.
+        fs::write(&log_path, "more").expect("write log");
+        let again = rotate(&log_path).expect("rotate again").expect("archive");
+        assert_ne!(again, archive);
+
+        assert_eq!(compress_archives(&log_path).unwrap(), 4);
+        let archives = discover_archives(&log_path).unwrap();
+        assert_eq!(archives.len(), 4);
+        assert!(archives.iter().all(|p| is_compressed(p)));
+        assert_eq!(
+            crate::log_index::read_log_from(&archive, 0).unwrap(),
+            b"{\"n\":1}\n"
+        );
+
+        // Count first: keep the newest three.
+        let now = Utc::now();
+        let mut keep = limits(RotationPolicy::Size);
+        keep.keep_files = 3;
+        assert_eq!(prune_archives(&log_path, &keep, now).unwrap(), 1);
+        // Then age: the 2024 archive is far older than a week.
+        keep.max_age_days = 7;
+        assert_eq!(prune_archives(&log_path, &keep, now).unwrap(), 1);
+        let left = discover_archives(&log_path).unwrap();
+        assert_eq!(left.len(), 2);
+        assert_eq!(left[0], compressed_path(&archive));
+    }
+
+    #[test]
+    fn rotation_is_due_by_size_or_calendar() {
+        let at = |d: u32, m: u32| Utc.with_ymd_and_hms(2025, m, d, 12, 0, 0).unwrap();
+
+        let size = limits(RotationPolicy::Size);
+        assert!(!size.due(0, 500, at(1, 1), at(1, 1)));
+        assert!(!size.due(60, 40, at(1, 1), at(1, 3)));
+        assert!(size.due(60, 41, at(1, 1), at(1, 1)));
+
+        let daily = limits(RotationPolicy::Daily);
+        assert!(!daily.due(10, 0, at(1, 1), at(1, 1)));
+        assert!(daily.due(10, 0, at(1, 1), at(2, 1)));
+
+        let monthly = limits(RotationPolicy::Monthly);
+        assert!(!monthly.due(10, 0, at(1, 1), at(28, 1)));
+        assert!(monthly.due(10, 0, at(28, 1), at(1, 2)));
 
-        let archives = discover_archives(&log_path).expect("discover");
-        assert_eq!(archives.len(), 2);
+        assert_eq!(
+            "Daily".parse::<RotationPolicy>().unwrap(),
+            RotationPolicy::Daily
+        );
+        assert!("weekly".parse::<RotationPolicy>().is_err());
     }
 }
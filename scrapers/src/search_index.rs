@@ -0,0 +1,1067 @@
+//! Persistent BM25 full-text index behind `/api/search` and `memex search --ranked`.
+//!
+//! Each index is a SQLite file under the search index directory (`index/` in the data dir
+//! by default). `events.sqlite` covers the master log and `memex/<repo>.sqlite` covers
+//! one repo's `.context/` files. Documents are grouped by source (an event, or a file).
+//! Each source carries a stamp, so [`SearchIndex::reconcile`] only re-reads sources
+//! that were added, changed or removed since the last search.
+//!
+//! Queries ([`SearchQuery`]) support:
+//! - bare words, which must all match;
+//! - `"quoted phrases"`;
+//! - `OR`, `AND`, `NOT` or `-word`, and parentheses;
+//! - field filters `tool:`, `role:`, `project:` (substring), `session:` (prefix),
+//!   and `after:` / `before:` (a date or RFC 3339 time; `before` is exclusive).
+//!
+//! Hits are ranked by BM25 over the words and phrases that are not negated. Each hit
+//! carries a [`Snippet`] with the matched words highlighted.
+
+use crate::event_index::{EventIndex, EventQuery};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Bump when the table layout or tokenization changes; older indexes are rebuilt.
+const SCHEMA_VERSION: i64 = 1;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+/// Roughly how much text a snippet shows, in bytes.
+const SNIPPET_BYTES: usize = 240;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS docs (
+    id INTEGER PRIMARY KEY,
+    key TEXT NOT NULL UNIQUE,
+    source TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    role TEXT NOT NULL,
+    project TEXT NOT NULL,
+    session TEXT NOT NULL,
+    ts_ms INTEGER,
+    len INTEGER NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS docs_source ON docs(source);
+CREATE TABLE IF NOT EXISTS postings (
+    term TEXT NOT NULL,
+    doc INTEGER NOT NULL,
+    positions BLOB NOT NULL,
+    PRIMARY KEY (term, doc)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS postings_doc ON postings(doc);
+CREATE TABLE IF NOT EXISTS sources (
+    source TEXT PRIMARY KEY,
+    stamp TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+/// `events.sqlite`: the index over the master log.
+pub fn events_index_path(index_dir: &Path) -> PathBuf {
+    index_dir.join("events.sqlite")
+}
+
+/// `memex/<hash of repo_root>.sqlite`: the index over one repo's `.context/`.
+pub fn memex_index_path(index_dir: &Path, repo_root: &Path) -> PathBuf {
+    let hash = xxhash_rust::xxh3::xxh3_64(repo_root.to_string_lossy().as_bytes());
+    index_dir.join("memex").join(format!("{hash:016x}.sqlite"))
+}
+
+// ── Documents ───────────────────────────────────────────────────────────
+
+/// One searchable unit: an event's content, or a paragraph of a file.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    /// Unique across the index.
+    pub key: String,
+    pub tool: String,
+    pub role: String,
+    pub project: String,
+    pub session: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Hit {
+    pub key: String,
+    pub source: String,
+    pub tool: String,
+    pub role: String,
+    pub project: String,
+    pub session: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub score: f64,
+    pub snippet: Snippet,
+}
+
+/// An excerpt of a hit's body. `highlights` are byte ranges of matched words in `text`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Snippet {
+    pub text: String,
+    pub highlights: Vec<(usize, usize)>,
+}
+
+impl Snippet {
+    /// The text with each highlight wrapped in `open` and `close`.
+    pub fn marked(&self, open: &str, close: &str) -> String {
+        let mut out = String::with_capacity(self.text.len() + self.highlights.len() * 8);
+        let mut at = 0;
+        for &(start, end) in &self.highlights {
+            out.push_str(&self.text[at..start]);
+            out.push_str(open);
+            out.push_str(&self.text[start..end]);
+            out.push_str(close);
+            at = end;
+        }
+        out.push_str(&self.text[at..]);
+        out
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReconcileStats {
+    pub added: usize,
+    pub removed: usize,
+}
+
+// ── Tokenization ────────────────────────────────────────────────────────
+
+/// Lowercased runs of letters, digits and `_`, with their byte ranges in `text`.
+pub fn tokenize(text: &str) -> Vec<(usize, usize, String)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        let word = c.is_alphanumeric() || c == '_';
+        match (word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                out.push((s, i, text[s..i].to_lowercase()));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        out.push((s, text.len(), text[s..].to_lowercase()));
+    }
+    out
+}
+
+fn words(text: &str) -> Vec<String> {
+    tokenize(text).into_iter().map(|(_, _, w)| w).collect()
+}
+
+// ── Queries ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// One word, or consecutive words (a phrase).
+    Words(Vec<String>),
+    Field(Filter),
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Tool(String),
+    Role(String),
+    Project(String),
+    Session(String),
+    After(DateTime<Utc>),
+    Before(DateTime<Utc>),
+}
+
+/// A parsed query; see the module docs for the syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    root: Option<Node>,
+}
+
+impl SearchQuery {
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Words and phrases that count toward ranking and highlighting.
+    fn positive_terms(&self) -> Vec<Vec<String>> {
+        fn walk(node: &Node, out: &mut Vec<Vec<String>>) {
+            match node {
+                Node::Words(words) => out.push(words.clone()),
+                Node::And(nodes) | Node::Or(nodes) => nodes.iter().for_each(|n| walk(n, out)),
+                Node::Field(_) | Node::Not(_) => {}
+            }
+        }
+        let mut out = Vec::new();
+        if let Some(root) = &self.root {
+            walk(root, &mut out);
+        }
+        out
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = lex(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let root = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            anyhow::bail!("unexpected {:?} in query", parser.tokens[parser.pos]);
+        }
+        Ok(Self { root })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Minus,
+    And,
+    Or,
+    Not,
+    Phrase(String),
+    /// `field:value`, the value possibly quoted.
+    Field(String, String),
+    Word(String),
+}
+
+fn lex(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            // Only at the start of a word; `foo-bar` is read whole below.
+            '-' => {
+                chars.next();
+                tokens.push(Token::Minus);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Phrase(read_quoted(&mut chars)?));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                if let Some((field, value)) = word.split_once(':') {
+                    let field = field.to_lowercase();
+                    if is_field(&field) {
+                        let value = if value.is_empty() && chars.peek() == Some(&'"') {
+                            chars.next();
+                            read_quoted(&mut chars)?
+                        } else {
+                            value.to_string()
+                        };
+                        tokens.push(Token::Field(field, value));
+                        continue;
+                    }
+                }
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<String> {
+    let mut out = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(out);
+        }
+        out.push(c);
+    }
+    anyhow::bail!("unterminated quote in query")
+}
+
+fn is_field(name: &str) -> bool {
+    matches!(
+        name,
+        "tool" | "role" | "project" | "session" | "after" | "before"
+    )
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Option<Node>> {
+        let mut nodes = Vec::new();
+        nodes.extend(self.and()?);
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            nodes.extend(self.and()?);
+        }
+        Ok(match nodes.len() {
+            0 => None,
+            1 => nodes.pop(),
+            _ => Some(Node::Or(nodes)),
+        })
+    }
+
+    fn and(&mut self) -> Result<Option<Node>> {
+        let mut nodes = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                Some(Token::And) => self.pos += 1,
+                Some(_) => nodes.extend(self.unary()?),
+            }
+        }
+        Ok(match nodes.len() {
+            0 => None,
+            1 => nodes.pop(),
+            _ => Some(Node::And(nodes)),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Option<Node>> {
+        match self.peek() {
+            Some(Token::Not) | Some(Token::Minus) => {
+                self.pos += 1;
+                Ok(self.unary()?.map(|n| Node::Not(Box::new(n))))
+            }
+            _ => self.atom(),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Option<Node>> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Ok(None);
+        };
+        self.pos += 1;
+        match token {
+            Token::Open => {
+                let inner = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    anyhow::bail!("missing ) in query");
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Token::Phrase(text) | Token::Word(text) => {
+                let words = words(&text);
+                Ok((!words.is_empty()).then_some(Node::Words(words)))
+            }
+            Token::Field(field, value) => Ok(Some(Node::Field(parse_filter(&field, &value)?))),
+            Token::Close => anyhow::bail!("unexpected ) in query"),
+            // A stray operator: `a AND OR b` reads as `a OR b`.
+            Token::And | Token::Or | Token::Not | Token::Minus => Ok(None),
+        }
+    }
+}
+
+fn parse_filter(field: &str, value: &str) -> Result<Filter> {
+    let value = value.trim();
+    anyhow::ensure!(!value.is_empty(), "{field}: needs a value");
+    Ok(match field {
+        "tool" => Filter::Tool(value.to_lowercase()),
+        "role" => Filter::Role(value.to_lowercase()),
+        "project" => Filter::Project(value.to_lowercase()),
+        "session" => Filter::Session(value.to_string()),
+        "after" => Filter::After(parse_time(value)?),
+        "before" => Filter::Before(parse_time(value)?),
+        _ => unreachable!("checked by is_field"),
+    })
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let day = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("invalid date {value:?} (expected YYYY-MM-DD or RFC 3339)"))?;
+    Ok(day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+}
+
+// ── Index ───────────────────────────────────────────────────────────────
+
+pub struct SearchIndex {
+    conn: Connection,
+}
+
+impl SearchIndex {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("open search index at {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            conn.execute_batch(
+                "DROP TABLE IF EXISTS docs; DROP TABLE IF EXISTS postings;
+                 DROP TABLE IF EXISTS sources; DROP TABLE IF EXISTS meta;",
+            )?;
+        }
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self { conn })
+    }
+
+    /// Bring the index in line with `desired` (source → stamp). Sources that are gone
+    /// are dropped; `load` is called once with those that are new or whose stamp
+    /// changed, and hands back each one's documents through its second argument.
+    pub fn reconcile<F>(
+        &mut self,
+        desired: &HashMap<String, String>,
+        load: F,
+    ) -> Result<ReconcileStats>
+    where
+        F: FnOnce(
+            &HashSet<String>,
+            &mut dyn FnMut(&str, Vec<Document>) -> Result<()>,
+        ) -> Result<()>,
+    {
+        // Immediate, so concurrent syncs queue up instead of both loading the same sources.
+        let tx = self
+            .conn
+            .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let current: HashMap<String, String> = {
+            let mut stmt = tx.prepare("SELECT source, stamp FROM sources")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut stats = ReconcileStats::default();
+        for source in current.keys() {
+            if !desired.contains_key(source) {
+                delete_source(&tx, source)?;
+                stats.removed += 1;
+            }
+        }
+        let needed: HashSet<String> = desired
+            .iter()
+            .filter(|(source, stamp)| current.get(*source) != Some(*stamp))
+            .map(|(source, _)| source.clone())
+            .collect();
+        if !needed.is_empty() {
+            let mut store = |source: &str, docs: Vec<Document>| -> Result<()> {
+                let Some(stamp) = desired.get(source) else {
+                    return Ok(());
+                };
+                delete_source(&tx, source)?;
+                for doc in &docs {
+                    insert_doc(&tx, source, doc)?;
+                }
+                tx.execute(
+                    "INSERT OR REPLACE INTO sources (source, stamp) VALUES (?1, ?2)",
+                    params![source, stamp],
+                )?;
+                stats.added += 1;
+                Ok(())
+            };
+            load(&needed, &mut store)?;
+        }
+        tx.commit()?;
+        Ok(stats)
+    }
+
+    /// Catch up with the master log at `log_path`, one source per event.
+    pub fn sync_events(&mut self, log_path: &Path) -> Result<ReconcileStats> {
+        let events = EventIndex::open_synced(log_path)?;
+        let digest = events.content_digest()?;
+        if self.meta("events_digest")?.as_deref() == Some(digest.as_str()) {
+            return Ok(ReconcileStats::default());
+        }
+
+        let mut desired = HashMap::new();
+        events.for_each_key(|key| {
+            desired.insert(key.event_id, key.content_hash.to_string());
+        })?;
+        let stats = self.reconcile(&desired, |needed, store| {
+            events.for_each_event(&EventQuery::default(), |log| {
+                let key = log.event_id.to_string();
+                if !needed.contains(&key) {
+                    return Ok(());
+                }
+                let doc = Document {
+                    key: key.clone(),
+                    tool: log.source_tool,
+                    role: log.interaction.role,
+                    project: log.project_context,
+                    session: log.session_id,
+                    timestamp: Some(log.timestamp),
+                    body: log.interaction.content,
+                };
+                store(&key, vec![doc])
+            })
+        })?;
+        self.set_meta("events_digest", &digest)?;
+        Ok(stats)
+    }
+
+    /// The best `limit` hits for `query`, skipping `offset`. With `within`, only
+    /// documents from those sources are considered.
+    pub fn search(
+        &self,
+        query: &SearchQuery,
+        limit: usize,
+        offset: usize,
+        within: Option<&HashSet<String>>,
+    ) -> Result<Vec<Hit>> {
+        let Some(root) = &query.root else {
+            return Ok(Vec::new());
+        };
+        let mut eval = Eval {
+            conn: &self.conn,
+            postings: HashMap::new(),
+            universe: None,
+            within,
+        };
+        let mut matched = eval.node(root)?;
+        if let Some(within) = within {
+            let allowed = eval.sources_docs(within)?;
+            matched.retain(|doc| allowed.contains(doc));
+        }
+        if matched.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // BM25 over the positive words; phrases count each of their words.
+        let (total, avg_len): (i64, f64) = self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(AVG(len), 0) FROM docs",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let terms = query.positive_terms();
+        let mut scores: HashMap<i64, f64> = matched.iter().map(|&d| (d, 0.0)).collect();
+        let stats = eval.doc_stats(&matched)?;
+        let mut seen = HashSet::new();
+        for word in terms.iter().flatten() {
+            if !seen.insert(word.clone()) {
+                continue;
+            }
+            let postings = eval.postings(word)?;
+            let df = postings.len() as f64;
+            let idf = ((total as f64 - df + 0.5) / (df + 0.5) + 1.0).ln();
+            for (doc, positions) in postings.iter() {
+                let Some(score) = scores.get_mut(doc) else {
+                    continue;
+                };
+                let tf = positions.len() as f64;
+                let len = stats.get(doc).map_or(0, |&(len, _)| len) as f64;
+                let norm = 1.0 - BM25_B + BM25_B * len / avg_len.max(1.0);
+                *score += idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm);
+            }
+        }
+
+        let timestamp = |doc: &i64| stats.get(doc).map(|&(_, ts)| ts);
+        let mut ranked: Vec<(i64, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| {
+            b.1.total_cmp(&a.1)
+                .then_with(|| timestamp(&b.0).cmp(&timestamp(&a.0)))
+                .then_with(|| a.0.cmp(&b.0))
+        });
+
+        let highlight: HashSet<&str> = terms.iter().flatten().map(String::as_str).collect();
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT key, source, tool, role, project, session, ts_ms, body FROM docs WHERE id = ?1",
+        )?;
+        let mut hits = Vec::new();
+        for (doc, score) in ranked.into_iter().skip(offset).take(limit) {
+            let hit = stmt.query_row(params![doc], |row| {
+                let body: String = row.get(7)?;
+                Ok(Hit {
+                    key: row.get(0)?,
+                    source: row.get(1)?,
+                    tool: row.get(2)?,
+                    role: row.get(3)?,
+                    project: row.get(4)?,
+                    session: row.get(5)?,
+                    timestamp: row
+                        .get::<_, Option<i64>>(6)?
+                        .and_then(DateTime::from_timestamp_millis),
+                    score,
+                    snippet: snippet(&body, &highlight),
+                })
+            })?;
+            hits.push(hit);
+        }
+        Ok(hits)
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+}
+
+fn delete_source(conn: &Connection, source: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM postings WHERE doc IN (SELECT id FROM docs WHERE source = ?1)",
+        params![source],
+    )?;
+    conn.execute("DELETE FROM docs WHERE source = ?1", params![source])?;
+    conn.execute("DELETE FROM sources WHERE source = ?1", params![source])?;
+    Ok(())
+}
+
+fn insert_doc(conn: &Connection, source: &str, doc: &Document) -> Result<()> {
+    let tokens = words(&doc.body);
+    conn.execute(
+        "INSERT OR REPLACE INTO docs (key, source, tool, role, project, session, ts_ms, len, body)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            doc.key,
+            source,
+            doc.tool.to_lowercase(),
+            doc.role.to_lowercase(),
+            doc.project,
+            doc.session,
+            doc.timestamp.map(|t| t.timestamp_millis()),
+            tokens.len() as i64,
+            doc.body,
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+
+    let mut positions: HashMap<&str, Vec<u32>> = HashMap::new();
+    for (i, word) in tokens.iter().enumerate() {
+        positions.entry(word).or_default().push(i as u32);
+    }
+    let mut insert =
+        conn.prepare_cached("INSERT INTO postings (term, doc, positions) VALUES (?1, ?2, ?3)")?;
+    for (term, at) in positions {
+        let blob: Vec<u8> = at.iter().flat_map(|p| p.to_le_bytes()).collect();
+        insert.execute(params![term, id, blob])?;
+    }
+    Ok(())
+}
+
+/// Evaluates a query tree to the set of matching document ids.
+struct Eval<'a> {
+    conn: &'a Connection,
+    /// term → (doc → positions), loaded once per query.
+    postings: HashMap<String, HashMap<i64, Vec<u32>>>,
+    universe: Option<HashSet<i64>>,
+    within: Option<&'a HashSet<String>>,
+}
+
+impl Eval<'_> {
+    fn node(&mut self, node: &Node) -> Result<HashSet<i64>> {
+        match node {
+            Node::Words(words) => self.words(words),
+            Node::Field(filter) => self.filter(filter),
+            Node::Not(inner) => {
+                let excluded = self.node(inner)?;
+                Ok(self
+                    .universe()?
+                    .iter()
+                    .filter(|d| !excluded.contains(d))
+                    .copied()
+                    .collect())
+            }
+            Node::Or(nodes) => {
+                let mut out = HashSet::new();
+                for node in nodes {
+                    out.extend(self.node(node)?);
+                }
+                Ok(out)
+            }
+            Node::And(nodes) => {
+                let mut out: Option<HashSet<i64>> = None;
+                for node in nodes {
+                    let set = self.node(node)?;
+                    out = Some(match out {
+                        None => set,
+                        Some(acc) => acc.intersection(&set).copied().collect(),
+                    });
+                    if out.as_ref().is_some_and(HashSet::is_empty) {
+                        break;
+                    }
+                }
+                Ok(out.unwrap_or_default())
+            }
+        }
+    }
+
+    /// Documents containing `words` consecutively.
+    fn words(&mut self, words: &[String]) -> Result<HashSet<i64>> {
+        let lists: Vec<HashMap<i64, Vec<u32>>> = words
+            .iter()
+            .map(|w| self.postings(w).cloned())
+            .collect::<Result<_>>()?;
+        let Some((first, rest)) = lists.split_first() else {
+            return Ok(HashSet::new());
+        };
+        Ok(first
+            .iter()
+            .filter(|(doc, starts)| {
+                starts.iter().any(|&start| {
+                    rest.iter().enumerate().all(|(i, list)| {
+                        list.get(doc)
+                            .is_some_and(|at| at.binary_search(&(start + i as u32 + 1)).is_ok())
+                    })
+                })
+            })
+            .map(|(doc, _)| *doc)
+            .collect())
+    }
+
+    fn postings(&mut self, term: &str) -> Result<&HashMap<i64, Vec<u32>>> {
+        if !self.postings.contains_key(term) {
+            let mut stmt = self
+                .conn
+                .prepare_cached("SELECT doc, positions FROM postings WHERE term = ?1")?;
+            let rows = stmt.query_map(params![term], |row| {
+                let blob: Vec<u8> = row.get(1)?;
+                let positions = blob
+                    .chunks_exact(4)
+                    .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect();
+                Ok((row.get::<_, i64>(0)?, positions))
+            })?;
+            let list = rows.collect::<rusqlite::Result<_>>()?;
+            self.postings.insert(term.to_string(), list);
+        }
+        Ok(&self.postings[term])
+    }
+
+    fn filter(&self, filter: &Filter) -> Result<HashSet<i64>> {
+        let (sql, arg): (&str, rusqlite::types::Value) = match filter {
+            Filter::Tool(tool) => ("tool = ?1", tool.clone().into()),
+            Filter::Role(role) => ("role = ?1", role.clone().into()),
+            Filter::Project(part) => ("instr(lower(project), ?1) > 0", part.clone().into()),
+            Filter::Session(prefix) => {
+                ("substr(session, 1, length(?1)) = ?1", prefix.clone().into())
+            }
+            Filter::After(at) => ("ts_ms >= ?1", at.timestamp_millis().into()),
+            Filter::Before(at) => ("ts_ms < ?1", at.timestamp_millis().into()),
+        };
+        self.ids(&format!("SELECT id FROM docs WHERE {sql}"), Some(arg))
+    }
+
+    fn universe(&mut self) -> Result<&HashSet<i64>> {
+        if self.universe.is_none() {
+            let all = match self.within {
+                Some(within) => self.sources_docs(within)?,
+                None => self.ids("SELECT id FROM docs", None)?,
+            };
+            self.universe = Some(all);
+        }
+        Ok(self.universe.get_or_insert_with(HashSet::new))
+    }
+
+    fn sources_docs(&self, sources: &HashSet<String>) -> Result<HashSet<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT id FROM docs WHERE source = ?1")?;
+        let mut out = HashSet::new();
+        for source in sources {
+            let rows = stmt.query_map(params![source], |row| row.get::<_, i64>(0))?;
+            for id in rows {
+                out.insert(id?);
+            }
+        }
+        Ok(out)
+    }
+
+    fn ids(&self, sql: &str, arg: Option<rusqlite::types::Value>) -> Result<HashSet<i64>> {
+        let mut stmt = self.conn.prepare_cached(sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(arg), |row| row.get::<_, i64>(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// `(length, timestamp in ms)` of each of `docs`, fetched in one query.
+    fn doc_stats(&self, docs: &HashSet<i64>) -> Result<HashMap<i64, (i64, i64)>> {
+        let ids = serde_json::to_string(&docs.iter().collect::<Vec<_>>())?;
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, len, COALESCE(ts_ms, 0) FROM docs
+             WHERE id IN (SELECT value FROM json_each(?1))",
+        )?;
+        let rows = stmt.query_map(params![ids], |row| {
+            Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+/// About [`SNIPPET_BYTES`] of `body` around its first highlighted word.
+fn snippet(body: &str, highlight: &HashSet<&str>) -> Snippet {
+    let tokens = tokenize(body);
+    let matches: Vec<(usize, usize)> = tokens
+        .iter()
+        .filter(|(_, _, word)| highlight.contains(word.as_str()))
+        .map(|&(start, end, _)| (start, end))
+        .collect();
+
+    let anchor = matches.first().map_or(0, |m| m.0);
+    let mut start = floor_char_boundary(body, anchor.saturating_sub(SNIPPET_BYTES / 3));
+    // Start at a word boundary rather than mid-word.
+    if start > 0 {
+        if let Some(&(s, _, _)) = tokens.iter().find(|(s, _, _)| *s >= start) {
+            start = s.min(anchor);
+        }
+    }
+    let end = floor_char_boundary(body, (start + SNIPPET_BYTES).min(body.len()));
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let mut text = String::from(prefix);
+    text.push_str(&body[start..end]);
+    if end < body.len() {
+        text.push('…');
+    }
+    let highlights = matches
+        .into_iter()
+        .filter(|&(s, e)| s >= start && e <= end)
+        .map(|(s, e)| (s - start + prefix.len(), e - start + prefix.len()))
+        .collect();
+    Snippet { text, highlights }
+}
+
+fn floor_char_boundary(s: &str, mut at: usize) -> usize {
+    at = at.min(s.len());
+    while !s.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn doc(key: &str, tool: &str, role: &str, day: u32, body: &str) -> Document {
+        Document {
+            key: key.to_string(),
+            tool: tool.to_string(),
+            role: role.to_string(),
+            project: "/work/Acme-API".to_string(),
+            session: format!("sess-{key}"),
+            timestamp: Some(Utc.with_ymd_and_hms(2026, 1, day, 12, 0, 0).unwrap()),
+            body: body.to_string(),
+        }
+    }
+
+    fn index_with(docs: Vec<Document>) -> (tempfile::TempDir, SearchIndex) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = SearchIndex::open(&dir.path().join("test.sqlite")).unwrap();
+        let desired = docs
+            .iter()
+            .map(|d| (d.key.clone(), "1".to_string()))
+            .collect();
+        index
+            .reconcile(&desired, |_, store| {
+                for d in docs {
+                    store(&d.key.clone(), vec![d])?;
+                }
+                Ok(())
+            })
+            .unwrap();
+        (dir, index)
+    }
+
+    fn keys(index: &SearchIndex, query: &str) -> Vec<String> {
+        let query: SearchQuery = query.parse().unwrap();
+        index
+            .search(&query, 10, 0, None)
+            .unwrap()
+            .into_iter()
+            .map(|h| h.key)
+            .collect()
+    }
+
+    #[test]
+    fn phrases_booleans_and_fields() {
+        let (_dir, index) = index_with(vec![
+            doc(
+                "a",
+                "codex-cli",
+                "user",
+                1,
+                "the schema change broke the migration",
+            ),
+            doc(
+                "b",
+                "codex-cli",
+                "assistant",
+                2,
+                "rolled back the schema, will change it later",
+            ),
+            doc(
+                "c",
+                "claude-code",
+                "user",
+                3,
+                "flaky test in the migration suite",
+            ),
+        ]);
+
+        assert_eq!(keys(&index, "\"schema change\""), ["a"]);
+        let mut both = keys(&index, "schema change");
+        both.sort();
+        assert_eq!(both, ["a", "b"]);
+        assert_eq!(keys(&index, "migration -flaky"), ["a"]);
+        assert_eq!(keys(&index, "migration AND NOT schema"), ["c"]);
+        let mut either = keys(&index, "flaky OR rolled");
+        either.sort();
+        assert_eq!(either, ["b", "c"]);
+        assert_eq!(keys(&index, "(flaky OR rolled) role:user"), ["c"]);
+        assert_eq!(keys(&index, "migration tool:codex-cli"), ["a"]);
+        assert_eq!(
+            keys(&index, "the project:acme after:2026-01-02"),
+            ["c", "b"]
+        );
+        assert_eq!(keys(&index, "the before:2026-01-02"), ["a"]);
+        assert_eq!(keys(&index, "session:sess-b"), ["b"]);
+        assert!(keys(&index, "nothing-like-this").is_empty());
+
+        assert!("after:yesterday".parse::<SearchQuery>().is_err());
+        assert!("\"open".parse::<SearchQuery>().is_err());
+        assert!("(a b".parse::<SearchQuery>().is_err());
+        // Unknown prefixes are ordinary text.
+        assert_eq!(
+            "http://x".parse::<SearchQuery>().unwrap(),
+            SearchQuery {
+                root: Some(Node::Words(vec!["http".into(), "x".into()]))
+            }
+        );
+    }
+
+    #[test]
+    fn ranks_by_bm25_and_highlights_snippets() {
+        let (_dir, index) = index_with(vec![
+            doc(
+                "once",
+                "codex-cli",
+                "user",
+                1,
+                "a panic in the parser, see log",
+            ),
+            doc(
+                "twice",
+                "codex-cli",
+                "user",
+                1,
+                "panic again: panic in the parser",
+            ),
+        ]);
+        let query: SearchQuery = "panic".parse().unwrap();
+        let hits = index.search(&query, 10, 0, None).unwrap();
+        assert_eq!(hits[0].key, "twice");
+        assert!(hits[0].score > hits[1].score);
+        assert_eq!(
+            hits[0].snippet.marked("[", "]"),
+            "[panic] again: [panic] in the parser"
+        );
+
+        let long = format!("{} needle {}", "hay ".repeat(200), "straw ".repeat(200));
+        let s = snippet(&long, &HashSet::from(["needle"]));
+        assert!(s.text.starts_with('…') && s.text.ends_with('…'));
+        let (start, end) = s.highlights[0];
+        assert_eq!(&s.text[start..end], "needle");
+    }
+
+    #[test]
+    fn reconcile_only_reloads_changed_sources() {
+        let (_dir, mut index) = index_with(vec![
+            doc("a", "codex-cli", "user", 1, "alpha"),
+            doc("b", "codex-cli", "user", 1, "beta"),
+        ]);
+        let desired = HashMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("c".to_string(), "1".to_string()),
+        ]);
+        let stats = index
+            .reconcile(&desired, |needed, store| {
+                assert_eq!(needed, &HashSet::from(["c".to_string()]));
+                store("c", vec![doc("c", "codex-cli", "user", 1, "gamma beta")])
+            })
+            .unwrap();
+        assert_eq!((stats.added, stats.removed), (1, 1));
+        assert_eq!(keys(&index, "beta"), ["c"]);
+        assert_eq!(keys(&index, "alpha"), ["a"]);
+    }
+
+    #[test]
+    fn indexes_the_master_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("master_log.jsonl");
+        let line = |content: &str| {
+            serde_json::json!({
+                "event_id": uuid::Uuid::new_v4(),
+                "timestamp": "2026-01-05T00:00:00Z",
+                "source_tool": "codex-cli",
+                "project_context": "/work/acme",
+                "session_id": "s1",
+                "interaction": { "role": "user", "content": content },
+                "security_flags": { "has_pii": false, "redacted_secrets": [] },
+                "metadata": {},
+            })
+            .to_string()
+        };
+        std::fs::write(&log_path, format!("{}\n", line("deploy the api"))).unwrap();
+
+        let mut index = SearchIndex::open(&events_index_path(dir.path())).unwrap();
+        assert_eq!(index.sync_events(&log_path).unwrap().added, 1);
+        assert_eq!(index.sync_events(&log_path).unwrap().added, 0);
+
+        std::fs::write(&log_path, format!("{}\n", line("rollback the api"))).unwrap();
+        let stats = index.sync_events(&log_path).unwrap();
+        assert_eq!((stats.added, stats.removed), (1, 1));
+        assert!(keys(&index, "deploy").is_empty());
+        assert_eq!(keys(&index, "rollback tool:codex-cli").len(), 1);
+    }
+}
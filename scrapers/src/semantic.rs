@@ -0,0 +1,687 @@
+//! Embedding-based retrieval, shared by the analysis probe and `memex search`.
+//!
+//! An [`Embedder`] turns text into vectors. [`HttpEmbedder`] calls an
+//! OpenAI-compatible `/v1/embeddings` endpoint, which covers local servers such as
+//! Ollama and llama.cpp. Its vectors are cached in `embeddings.sqlite` under the
+//! search index directory, keyed by a hash of the text, so each turn is embedded once.
+//! [`HashingEmbedder`] needs no model at all. It hashes words and their character
+//! trigrams into a fixed-size vector, so it only matches shared vocabulary and
+//! spelling variants, but it is deterministic and offline, which makes it the
+//! default and what tests use.
+//!
+//! [`rank_hybrid`] combines [`Bm25`] keyword scores with cosine similarity, so
+//! documents that say the same thing in other words can still rank.
+//! [`DocumentVectors`] keeps one vector per document (e.g. per event) in the same
+//! cache file, so repeated searches over the same documents only embed the query.
+
+use crate::config::ContrailConfig;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long a cache connection waits for another writer before giving up.
+const CACHE_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Share of the hybrid score that comes from BM25; the rest is cosine similarity.
+pub const HYBRID_LEXICAL_WEIGHT: f32 = 0.5;
+
+/// Cache file under the search index directory.
+pub const EMBEDDING_CACHE_FILE: &str = "embeddings.sqlite";
+
+const HASHING_DIMENSIONS: usize = 512;
+/// Trigram features count for less than whole words.
+const TRIGRAM_WEIGHT: f32 = 0.5;
+const HTTP_BATCH: usize = 64;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(120);
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+// ── Embedders ───────────────────────────────────────────────────────────
+
+pub trait Embedder: Send + Sync {
+    /// Identifies the model; vectors from different ids are not comparable.
+    fn id(&self) -> String;
+
+    /// One vector per text, in order.
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Which [`Embedder`] [`embedder_from_config`] builds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedderKind {
+    /// [`HashingEmbedder`]: offline and deterministic.
+    #[default]
+    Hashing,
+    /// [`HttpEmbedder`] against the configured endpoint and model.
+    Http,
+}
+
+impl FromStr for EmbedderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "hashing" => Ok(Self::Hashing),
+            "http" => Ok(Self::Http),
+            other => anyhow::bail!("unknown embedder {other:?}"),
+        }
+    }
+}
+
+impl fmt::Display for EmbedderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hashing => "hashing",
+            Self::Http => "http",
+        })
+    }
+}
+
+/// The embedder selected by `search.embedder`. HTTP embeddings are cached on disk;
+/// the API key, if the endpoint needs one, comes from `CONTRAIL_EMBEDDING_API_KEY`.
+pub fn embedder_from_config(config: &ContrailConfig) -> Result<Box<dyn Embedder>> {
+    match config.search_embedder {
+        EmbedderKind::Hashing => Ok(Box::new(HashingEmbedder::default())),
+        EmbedderKind::Http => {
+            let http = HttpEmbedder::new(
+                &config.embedding_url,
+                &config.embedding_model,
+                std::env::var("CONTRAIL_EMBEDDING_API_KEY").ok(),
+            )?;
+            let cache = CachedEmbedder::open(
+                &config.search_index_dir.join(EMBEDDING_CACHE_FILE),
+                Box::new(http),
+            )?;
+            Ok(Box::new(cache))
+        }
+    }
+}
+
+/// Feature hashing of words and character trigrams into an L2-normalized vector.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self {
+            dimensions: HASHING_DIMENSIONS,
+        }
+    }
+}
+
+impl HashingEmbedder {
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        let mut add = |feature: &[u8], weight: f32| {
+            let hash = xxhash_rust::xxh3::xxh3_64(feature);
+            let bucket = (hash % self.dimensions as u64) as usize;
+            // The top bit picks a sign, so collisions tend to cancel out.
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign * weight;
+        };
+        for term in terms(text) {
+            add(term.as_bytes(), 1.0);
+            let padded: Vec<char> = format!("^{term}$").chars().collect();
+            for gram in padded.windows(3) {
+                let gram: String = gram.iter().collect();
+                add(format!("#{gram}").as_bytes(), TRIGRAM_WEIGHT);
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn id(&self) -> String {
+        format!("hashing-{}", self.dimensions)
+    }
+
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// An OpenAI-compatible embeddings endpoint, e.g.
+/// `http://127.0.0.1:11434/v1/embeddings` for Ollama.
+pub struct HttpEmbedder {
+    client: reqwest::blocking::Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    #[serde(default)]
+    index: Option<usize>,
+    embedding: Vec<f32>,
+}
+
+impl HttpEmbedder {
+    pub fn new(url: &str, model: &str, api_key: Option<String>) -> Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .context("build embedding client")?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            model: model.to_string(),
+            api_key: api_key.filter(|k| !k.trim().is_empty()),
+        })
+    }
+
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut request = self.client.post(&self.url).json(&EmbeddingRequest {
+            model: &self.model,
+            input: texts,
+        });
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response: EmbeddingResponse = request
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.json())
+            .with_context(|| format!("embed {} texts via {}", texts.len(), self.url))?;
+        anyhow::ensure!(
+            response.data.len() == texts.len(),
+            "embedding endpoint returned {} vectors for {} texts",
+            response.data.len(),
+            texts.len()
+        );
+        let mut data = response.data;
+        data.sort_by_key(|d| d.index);
+        Ok(data
+            .into_iter()
+            .map(|d| {
+                let mut v = d.embedding;
+                normalize(&mut v);
+                v
+            })
+            .collect())
+    }
+}
+
+impl Embedder for HttpEmbedder {
+    fn id(&self) -> String {
+        format!("http:{}@{}", self.model, self.url)
+    }
+
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut out = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(HTTP_BATCH) {
+            out.extend(self.embed_batch(batch)?);
+        }
+        Ok(out)
+    }
+}
+
+/// Keeps another embedder's vectors in SQLite, keyed by embedder id and text hash.
+pub struct CachedEmbedder {
+    inner: Box<dyn Embedder>,
+    id: String,
+    conn: Mutex<Connection>,
+}
+
+impl CachedEmbedder {
+    pub fn open(path: &Path, inner: Box<dyn Embedder>) -> Result<Self> {
+        let conn = open_cache(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS embeddings (
+                embedder TEXT NOT NULL,
+                text_hash INTEGER NOT NULL,
+                vector BLOB NOT NULL,
+                PRIMARY KEY (embedder, text_hash)
+            );",
+        )?;
+        Ok(Self {
+            id: inner.id(),
+            inner,
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl Embedder for CachedEmbedder {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("embedding cache lock poisoned"))?;
+        let keys: Vec<i64> = texts
+            .iter()
+            .map(|t| xxhash_rust::xxh3::xxh3_64(t.as_bytes()) as i64)
+            .collect();
+
+        let mut found: Vec<Option<Vec<f32>>> = Vec::with_capacity(texts.len());
+        {
+            let mut stmt = conn.prepare_cached(
+                "SELECT vector FROM embeddings WHERE embedder = ?1 AND text_hash = ?2",
+            )?;
+            for key in &keys {
+                let blob: Option<Vec<u8>> = stmt
+                    .query_row(params![self.id, key], |row| row.get(0))
+                    .optional()?;
+                found.push(blob.map(|b| decode_vector(&b)));
+            }
+        }
+
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| found[i].is_none()).collect();
+        if !missing.is_empty() {
+            let batch: Vec<&str> = missing.iter().map(|&i| texts[i]).collect();
+            let vectors = self.inner.embed(&batch)?;
+            let tx = conn.unchecked_transaction()?;
+            {
+                let mut insert = tx.prepare_cached(
+                    "INSERT OR REPLACE INTO embeddings (embedder, text_hash, vector)
+                     VALUES (?1, ?2, ?3)",
+                )?;
+                for (&i, vector) in missing.iter().zip(vectors) {
+                    insert.execute(params![self.id, keys[i], encode_vector(&vector)])?;
+                    found[i] = Some(vector);
+                }
+            }
+            tx.commit()?;
+        }
+        Ok(found.into_iter().map(Option::unwrap_or_default).collect())
+    }
+}
+
+/// Vectors of whole documents, stored in the embedding cache under the document's
+/// id with a digest of its text. A document is embedded again only when its text
+/// changes; the stored vectors are loaded into memory on first use.
+pub struct DocumentVectors {
+    conn: Mutex<Connection>,
+    /// Per embedder id.
+    loaded: Mutex<HashMap<String, StoredVectors>>,
+}
+
+/// Document id to (text digest, vector).
+type StoredVectors = HashMap<String, (i64, Vec<f32>)>;
+
+impl DocumentVectors {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = open_cache(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS document_vectors (
+                embedder TEXT NOT NULL,
+                doc_id TEXT NOT NULL,
+                text_hash INTEGER NOT NULL,
+                vector BLOB NOT NULL,
+                PRIMARY KEY (embedder, doc_id)
+            );",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            loaded: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// One vector per `(id, text)` document, in order, embedding only documents
+    /// that are new or whose text changed since they were stored.
+    pub fn vectors(&self, embedder: &dyn Embedder, docs: &[(&str, &str)]) -> Result<Vec<Vec<f32>>> {
+        let id = embedder.id();
+        let mut loaded = self
+            .loaded
+            .lock()
+            .map_err(|_| anyhow::anyhow!("document vector lock poisoned"))?;
+        if !loaded.contains_key(&id) {
+            let stored = self.load(&id)?;
+            loaded.insert(id.clone(), stored);
+        }
+        let known = loaded.get_mut(&id).expect("loaded above");
+
+        let hashes: Vec<i64> = docs
+            .iter()
+            .map(|(_, text)| xxhash_rust::xxh3::xxh3_64(text.as_bytes()) as i64)
+            .collect();
+        let missing: Vec<usize> = (0..docs.len())
+            .filter(|&i| {
+                known
+                    .get(docs[i].0)
+                    .is_none_or(|(hash, _)| *hash != hashes[i])
+            })
+            .collect();
+        if !missing.is_empty() {
+            let texts: Vec<&str> = missing.iter().map(|&i| docs[i].1).collect();
+            let vectors = embedder.embed(&texts)?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| anyhow::anyhow!("document vector cache lock poisoned"))?;
+            let tx = conn.unchecked_transaction()?;
+            {
+                let mut insert = tx.prepare_cached(
+                    "INSERT OR REPLACE INTO document_vectors (embedder, doc_id, text_hash, vector)
+                     VALUES (?1, ?2, ?3, ?4)",
+                )?;
+                for (&i, vector) in missing.iter().zip(vectors) {
+                    insert.execute(params![id, docs[i].0, hashes[i], encode_vector(&vector)])?;
+                    known.insert(docs[i].0.to_string(), (hashes[i], vector));
+                }
+            }
+            tx.commit()?;
+        }
+        Ok(docs
+            .iter()
+            .map(|(doc_id, _)| {
+                known
+                    .get(*doc_id)
+                    .map(|(_, v)| v.clone())
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+
+    fn load(&self, embedder: &str) -> Result<StoredVectors> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("document vector cache lock poisoned"))?;
+        let mut stmt = conn.prepare(
+            "SELECT doc_id, text_hash, vector FROM document_vectors WHERE embedder = ?1",
+        )?;
+        let rows = stmt.query_map(params![embedder], |row| {
+            let blob: Vec<u8> = row.get(2)?;
+            Ok((row.get(0)?, (row.get(1)?, decode_vector(&blob))))
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Into::into)
+    }
+}
+
+fn open_cache(path: &Path) -> Result<Connection> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    let conn = Connection::open(path)
+        .with_context(|| format!("open embedding cache at {}", path.display()))?;
+    conn.busy_timeout(CACHE_BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Cosine similarity; 0 when either vector is empty or zero.
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms =
+        a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norms > 0.0 {
+        dot / norms
+    } else {
+        0.0
+    }
+}
+
+// ── Ranking ─────────────────────────────────────────────────────────────
+
+/// Lowercased alphanumeric runs of three or more characters.
+pub fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Okapi BM25 over an in-memory set of documents.
+pub struct Bm25 {
+    docs: Vec<HashMap<String, u32>>,
+    lengths: Vec<f32>,
+    avg_len: f32,
+    doc_freq: HashMap<String, u32>,
+}
+
+impl Bm25 {
+    pub fn new<I, T>(docs: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoIterator<Item = String>,
+    {
+        let mut counts = Vec::new();
+        let mut lengths = Vec::new();
+        let mut doc_freq: HashMap<String, u32> = HashMap::new();
+        for doc in docs {
+            let mut tf: HashMap<String, u32> = HashMap::new();
+            let mut len = 0;
+            for term in doc {
+                *tf.entry(term).or_default() += 1;
+                len += 1;
+            }
+            for term in tf.keys() {
+                *doc_freq.entry(term.clone()).or_default() += 1;
+            }
+            counts.push(tf);
+            lengths.push(len as f32);
+        }
+        let avg_len = lengths.iter().sum::<f32>() / lengths.len().max(1) as f32;
+        Self {
+            docs: counts,
+            lengths,
+            avg_len,
+            doc_freq,
+        }
+    }
+
+    pub fn score(&self, query: &[String], doc: usize) -> f32 {
+        let n = self.docs.len() as f32;
+        let tf = &self.docs[doc];
+        let len_norm = 1.0 - BM25_B + BM25_B * self.lengths[doc] / self.avg_len.max(1.0);
+        query
+            .iter()
+            .filter_map(|term| {
+                let f = *tf.get(term)? as f32;
+                let df = *self.doc_freq.get(term)? as f32;
+                let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                Some(idf * f * (BM25_K1 + 1.0) / (f + BM25_K1 * len_norm))
+            })
+            .sum()
+    }
+}
+
+/// Rank `docs` against `query` by BM25 (normalized to the best match) blended with
+/// embedding similarity. Returns `(doc index, score in 0..=1)`, best first, leaving
+/// out documents with no relevance at all.
+pub fn rank_hybrid(
+    embedder: &dyn Embedder,
+    query: &str,
+    docs: &[&str],
+) -> Result<Vec<(usize, f32)>> {
+    if docs.is_empty() || query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut texts = Vec::with_capacity(docs.len() + 1);
+    texts.push(query);
+    texts.extend_from_slice(docs);
+    let mut vectors = embedder.embed(&texts)?;
+    let query_vector = vectors.remove(0);
+    Ok(blend(query, &query_vector, docs, vectors))
+}
+
+/// Like [`rank_hybrid`], over `(id, text)` documents whose vectors come from
+/// `stored`, so only the query and new or changed documents are embedded.
+pub fn rank_hybrid_stored(
+    embedder: &dyn Embedder,
+    stored: &DocumentVectors,
+    query: &str,
+    docs: &[(&str, &str)],
+) -> Result<Vec<(usize, f32)>> {
+    if docs.is_empty() || query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let query_vector = embedder.embed(&[query])?.pop().unwrap_or_default();
+    let vectors = stored.vectors(embedder, docs)?;
+    let texts: Vec<&str> = docs.iter().map(|(_, text)| *text).collect();
+    Ok(blend(query, &query_vector, &texts, vectors))
+}
+
+fn blend(
+    query: &str,
+    query_vector: &[f32],
+    docs: &[&str],
+    vectors: Vec<Vec<f32>>,
+) -> Vec<(usize, f32)> {
+    let query_terms = terms(query);
+    let bm25 = Bm25::new(docs.iter().map(|d| terms(d)));
+    let lexical: Vec<f32> = (0..docs.len())
+        .map(|i| bm25.score(&query_terms, i))
+        .collect();
+    let best = lexical.iter().copied().fold(0.0, f32::max);
+
+    let mut ranked: Vec<(usize, f32)> = vectors
+        .into_iter()
+        .zip(lexical)
+        .enumerate()
+        .map(|(i, (vector, lex))| {
+            let lex = if best > 0.0 { lex / best } else { 0.0 };
+            let sem = cosine(query_vector, &vector).max(0.0);
+            (
+                i,
+                HYBRID_LEXICAL_WEIGHT * lex + (1.0 - HYBRID_LEXICAL_WEIGHT) * sem,
+            )
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn hybrid_ranking_prefers_related_wording() {
+        let docs = [
+            "the schema migrations failed halfway, rolled back",
+            "updated the readme with install steps",
+            "migration failure: rollback of the schema change",
+        ];
+        let ranked = rank_hybrid(
+            &HashingEmbedder::default(),
+            "why did the migration fail",
+            &docs,
+        )
+        .unwrap();
+        let order: Vec<usize> = ranked.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order[..2], [2, 0]);
+        let readme = ranked.iter().find(|(i, _)| *i == 1).map_or(0.0, |r| r.1);
+        assert!(readme < ranked[1].1);
+
+        // Deterministic: same text, same vector.
+        let embedder = HashingEmbedder::default();
+        assert_eq!(
+            embedder.embed(&["a b c rollback"]).unwrap(),
+            embedder.embed(&["a b c rollback"]).unwrap()
+        );
+        let v = embedder.embed(&["rollback", "rollbacks"]).unwrap();
+        assert!(cosine(&v[0], &v[1]) > 0.5);
+    }
+
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Embedder for Counting {
+        fn id(&self) -> String {
+            "counting".to_string()
+        }
+
+        fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            self.0.fetch_add(texts.len(), Ordering::SeqCst);
+            HashingEmbedder::default().embed(texts)
+        }
+    }
+
+    #[test]
+    fn cached_embedder_embeds_each_text_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(EMBEDDING_CACHE_FILE);
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let cache = CachedEmbedder::open(&path, Box::new(Counting(calls.clone()))).unwrap();
+        let first = cache.embed(&["alpha", "beta"]).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        drop(cache);
+
+        let cache = CachedEmbedder::open(&path, Box::new(Counting(calls.clone()))).unwrap();
+        let second = cache.embed(&["beta", "alpha", "gamma"]).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(second[0], first[1]);
+        assert_eq!(second[1], first[0]);
+    }
+
+    #[test]
+    fn document_vectors_are_stored_per_document() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(EMBEDDING_CACHE_FILE);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let embedder = Counting(calls.clone());
+        let docs = [
+            ("e1", "the schema migrations failed halfway, rolled back"),
+            ("e2", "updated the readme with install steps"),
+        ];
+
+        let stored = DocumentVectors::open(&path).unwrap();
+        let first = rank_hybrid_stored(&embedder, &stored, "migration failure", &docs).unwrap();
+        assert_eq!(first[0].0, 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        rank_hybrid_stored(&embedder, &stored, "readme", &docs).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        drop(stored);
+
+        // Reopened, only the query and the changed document are embedded.
+        let stored = DocumentVectors::open(&path).unwrap();
+        let changed = [docs[0], ("e2", "readme rewritten for the migration")];
+        let vectors = stored.vectors(&embedder, &changed).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+        assert_eq!(
+            vectors[1],
+            HashingEmbedder::default().embed(&[changed[1].1]).unwrap()[0]
+        );
+    }
+}
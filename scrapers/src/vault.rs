@@ -0,0 +1,292 @@
+//! Encrypted side vault for reversible redaction.
+//!
+//! With `reversible = true` in `redaction.toml`, the [`Sentry`](crate::sentry::Sentry)
+//! replaces each secret with a stable `[REDACTED:<label>:<hash>]` token and stores
+//! the original here, encrypted to the vault's age X25519 recipient. Capture only
+//! needs the public recipient; reading secrets back needs the identity, which is
+//! itself encrypted with the passphrase chosen at `contrail init-vault`.
+//!
+//! Layout under `~/.contrail/vault/` (or `CONTRAIL_VAULT_DIR`):
+//!
+//! - `vault.json`: recipient and the seed for token hashes.
+//! - `identity.age`: the X25519 identity, passphrase-encrypted.
+//! - `secrets.jsonl`: one `{token, secret}` line per distinct secret, where `secret`
+//!   is an armored age message.
+
+use age::secrecy::{ExposeSecret, SecretString};
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+const META_FILE: &str = "vault.json";
+const IDENTITY_FILE: &str = "identity.age";
+const SECRETS_FILE: &str = "secrets.jsonl";
+
+#[derive(Serialize, Deserialize)]
+struct VaultMeta {
+    recipient: String,
+    /// Seeds token hashes so a token can't be checked against guessed secrets
+    /// without access to this file.
+    hash_seed: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultEntry {
+    token: String,
+    secret: String,
+}
+
+/// Write side of the vault, used during capture. Safe to share across threads.
+pub struct SecretVault {
+    secrets_path: PathBuf,
+    recipient: age::x25519::Recipient,
+    hash_seed: u64,
+    known_tokens: Mutex<HashSet<String>>,
+}
+
+impl SecretVault {
+    /// Create a new vault in `dir`, refusing to overwrite an existing one.
+    pub fn init(dir: &Path, passphrase: &str) -> Result<()> {
+        let meta_path = dir.join(META_FILE);
+        anyhow::ensure!(
+            !meta_path.exists(),
+            "a vault already exists at {}",
+            dir.display()
+        );
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+
+        let identity = age::x25519::Identity::generate();
+        let encrypted_identity =
+            encrypt_bytes(passphrase, identity.to_string().expose_secret().as_bytes())?;
+        let identity_path = dir.join(IDENTITY_FILE);
+        write_private(&identity_path, &encrypted_identity)?;
+
+        let meta = VaultMeta {
+            recipient: identity.to_public().to_string(),
+            hash_seed: uuid::Uuid::new_v4().as_u64_pair().0,
+        };
+        write_private(&meta_path, &serde_json::to_vec_pretty(&meta)?)?;
+        Ok(())
+    }
+
+    pub fn open(dir: &Path) -> Result<Self> {
+        let meta = read_meta(dir)?;
+        let recipient = age::x25519::Recipient::from_str(&meta.recipient)
+            .map_err(|e| anyhow::anyhow!("invalid vault recipient: {e}"))?;
+        let secrets_path = dir.join(SECRETS_FILE);
+        let known_tokens = read_entries(&secrets_path)?
+            .into_iter()
+            .map(|entry| entry.token)
+            .collect();
+        Ok(Self {
+            secrets_path,
+            recipient,
+            hash_seed: meta.hash_seed,
+            known_tokens: Mutex::new(known_tokens),
+        })
+    }
+
+    pub fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    /// Encrypt and append `secret` under `token`, unless the token is already stored.
+    pub fn store(&self, token: &str, secret: &str) -> Result<()> {
+        let mut known = self
+            .known_tokens
+            .lock()
+            .map_err(|_| anyhow::anyhow!("vault lock poisoned"))?;
+        if known.contains(token) {
+            return Ok(());
+        }
+
+        let armored = age::encrypt_and_armor(&self.recipient, secret.as_bytes())
+            .map_err(|e| anyhow::anyhow!("encryption failed: {e}"))?;
+        let mut line = serde_json::to_vec(&VaultEntry {
+            token: token.to_string(),
+            secret: armored,
+        })?;
+        line.push(b'\n');
+        let mut file = owner_only(OpenOptions::new().create(true).append(true))
+            .open(&self.secrets_path)
+            .with_context(|| format!("open {}", self.secrets_path.display()))?;
+        file.write_all(&line)
+            .with_context(|| format!("append to {}", self.secrets_path.display()))?;
+
+        known.insert(token.to_string());
+        Ok(())
+    }
+}
+
+/// Vault files hold the token seed and encrypted secrets: readable by the owner only.
+/// Files created through `options` are readable by their owner only.
+pub(crate) fn owner_only(options: &mut OpenOptions) -> &mut OpenOptions {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+}
+
+fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut file = owner_only(OpenOptions::new().write(true).create(true).truncate(true))
+        .open(path)
+        .with_context(|| format!("create {}", path.display()))?;
+    file.write_all(bytes)
+        .with_context(|| format!("write {}", path.display()))
+}
+
+/// Decrypted view of the vault, for `contrail unredact`.
+pub struct UnlockedVault {
+    secrets: HashMap<String, String>,
+}
+
+impl UnlockedVault {
+    pub fn open(dir: &Path, passphrase: &str) -> Result<Self> {
+        let identity_path = dir.join(IDENTITY_FILE);
+        let encrypted = fs::read(&identity_path)
+            .with_context(|| format!("read {}", identity_path.display()))?;
+        let raw_identity = String::from_utf8(decrypt_bytes(passphrase, &encrypted)?)
+            .context("vault identity is not UTF-8")?;
+        let identity = age::x25519::Identity::from_str(raw_identity.trim())
+            .map_err(|e| anyhow::anyhow!("invalid vault identity: {e}"))?;
+
+        let mut secrets = HashMap::new();
+        for entry in read_entries(&dir.join(SECRETS_FILE))? {
+            let plaintext = age::decrypt(&identity, entry.secret.as_bytes())
+                .map_err(|e| anyhow::anyhow!("decrypt secret for {}: {e}", entry.token))?;
+            secrets.insert(
+                entry.token,
+                String::from_utf8_lossy(&plaintext).into_owned(),
+            );
+        }
+        Ok(Self { secrets })
+    }
+
+    pub fn get(&self, token: &str) -> Option<&str> {
+        self.secrets.get(token).map(String::as_str)
+    }
+
+    /// Swap every vault token in `text` for its original. Returns the restored
+    /// text and the tokens that had no entry in the vault.
+    pub fn restore(&self, text: &str) -> (String, Vec<String>) {
+        let mut missing = Vec::new();
+        let restored = token_regex()
+            .replace_all(text, |caps: &regex::Captures| {
+                let token = &caps[0];
+                match self.get(token) {
+                    Some(secret) => secret.to_string(),
+                    None => {
+                        missing.push(token.to_string());
+                        token.to_string()
+                    }
+                }
+            })
+            .into_owned();
+        (restored, missing)
+    }
+}
+
+/// Passphrase-based (scrypt) age encryption, shared with memex's vault and bundles.
+pub fn encrypt_bytes(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let secret = SecretString::from(passphrase.to_string());
+    let recipient = age::scrypt::Recipient::new(secret.clone());
+    age::encrypt(&recipient, plaintext).map_err(|e| anyhow::anyhow!("encryption failed: {e}"))
+}
+
+pub fn decrypt_bytes(passphrase: &str, encrypted: &[u8]) -> Result<Vec<u8>> {
+    let secret = SecretString::from(passphrase.to_string());
+    let identity = age::scrypt::Identity::new(secret);
+    age::decrypt(&identity, encrypted)
+        .map_err(|e| anyhow::anyhow!("decryption failed (wrong passphrase?): {e}"))
+}
+
+/// Tokens carry a 64-bit hash; vaults written before that hold 32-bit ones.
+fn token_regex() -> &'static Regex {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    TOKEN.get_or_init(|| Regex::new(r"\[REDACTED:[^\]\s:]+:[0-9a-f]{8}(?:[0-9a-f]{8})?\]").unwrap())
+}
+
+fn read_meta(dir: &Path) -> Result<VaultMeta> {
+    let path = dir.join(META_FILE);
+    let raw = fs::read(&path).with_context(|| {
+        format!(
+            "no vault at {}; run `contrail init-vault` first",
+            dir.display()
+        )
+    })?;
+    serde_json::from_slice(&raw).with_context(|| format!("parse {}", path.display()))
+}
+
+fn read_entries(path: &Path) -> Result<Vec<VaultEntry>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("open {}", path.display())),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // A torn final line from an interrupted append is skipped, not fatal.
+        if let Ok(entry) = serde_json::from_str::<VaultEntry>(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_secrets_and_restores_them_with_the_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        SecretVault::init(dir.path(), "correct horse").unwrap();
+        assert!(SecretVault::init(dir.path(), "again").is_err());
+
+        let vault = SecretVault::open(dir.path()).unwrap();
+        vault
+            .store("[REDACTED:github_token:0a1b2c3d]", "ghp_one")
+            .unwrap();
+        vault
+            .store("[REDACTED:github_token:0a1b2c3d]", "ghp_one")
+            .unwrap();
+        vault
+            .store("[REDACTED:email:deadbeef00c0ffee]", "a@b.co")
+            .unwrap();
+        let stored = fs::read_to_string(dir.path().join(SECRETS_FILE)).unwrap();
+        assert_eq!(stored.lines().count(), 2);
+        assert!(!stored.contains("ghp_one"));
+
+        assert!(UnlockedVault::open(dir.path(), "wrong").is_err());
+        let unlocked = UnlockedVault::open(dir.path(), "correct horse").unwrap();
+        let (text, missing) = unlocked.restore(
+            "token [REDACTED:github_token:0a1b2c3d] mail [REDACTED:email:deadbeef00c0ffee] [REDACTED:jwt:00000000]",
+        );
+        assert_eq!(text, "token ghp_one mail a@b.co [REDACTED:jwt:00000000]");
+        assert_eq!(missing, vec!["[REDACTED:jwt:00000000]".to_string()]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in [META_FILE, IDENTITY_FILE, SECRETS_FILE] {
+                let mode = fs::metadata(dir.path().join(file))
+                    .unwrap()
+                    .permissions()
+                    .mode();
+                assert_eq!(mode & 0o777, 0o600, "{file}");
+            }
+        }
+    }
+}
@@ -1,19 +1,59 @@
 use super::Harvester;
+use crate::antigravity::{
+    conversation_id, find_conversation_files, read_conversation, workspace_hint,
+};
+use crate::checkpoint::{CheckpointStore, FileCheckpoint};
 use anyhow::Result;
 use chrono::Utc;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde_json::Map;
+use std::collections::HashMap;
 use std::fs;
 use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
-use std::time::Duration;
-use tokio::time::sleep;
-use tracing::{debug, info};
+use std::time::{Duration, SystemTime};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info, warn};
+
+/// How long a conversation file must be unchanged before its newest turn, which
+/// may still be streaming, is logged.
+const CONVERSATION_SETTLE: Duration = Duration::from_secs(5);
+
+struct ConversationState {
+    /// `(mtime, len)` of the last version read.
+    signature: Option<(SystemTime, u64)>,
+    /// Turns already logged from the front of the conversation.
+    logged: usize,
+    last_change: Instant,
+    pending: bool,
+    warned: bool,
+}
+
+impl ConversationState {
+    fn new() -> Self {
+        Self {
+            signature: None,
+            logged: 0,
+            last_change: Instant::now(),
+            pending: false,
+            warned: false,
+        }
+    }
+
+    fn checkpoint(&self) -> FileCheckpoint {
+        FileCheckpoint {
+            messages: Some(self.logged),
+            ..Default::default()
+        }
+    }
+}
 
 impl Harvester {
     pub async fn run_antigravity_watcher(&self) -> Result<()> {
         info!("starting antigravity watcher");
         let brain_dir = self.config.antigravity_brain.clone();
+        let mut checkpoints = CheckpointStore::load(&self.state_dir.join("antigravity_brain.json"));
 
         loop {
             let mut latest_session = None;
@@ -58,11 +98,22 @@ impl Harvester {
 
                 if watching {
                     info!(path = ?session_path, "watching antigravity session");
-                    let mut last_task_pos = fs::metadata(&task_md).map(|m| m.len()).unwrap_or(0);
-                    let mut last_plan_pos = fs::metadata(&plan_md).map(|m| m.len()).unwrap_or(0);
+                    // Resume from the checkpoint, checking once up front for anything
+                    // written while the daemon was down.
+                    let resume = |path: &Path| {
+                        checkpoints
+                            .resume(path)
+                            .map(|c| c.offset)
+                            .unwrap_or_else(|| fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+                    };
+                    let mut last_task_pos = resume(&task_md);
+                    let mut last_plan_pos = resume(&plan_md);
+                    let mut check = true;
 
                     loop {
-                        if let Ok(Ok(_event)) = rx.try_recv() {
+                        if check || matches!(rx.try_recv(), Ok(Ok(_))) {
+                            check = false;
+                            let before = (last_task_pos, last_plan_pos);
                             // Check task.md
                             if let Ok(metadata) = fs::metadata(&task_md) {
                                 let current_size = metadata.len();
@@ -88,6 +139,7 @@ impl Harvester {
                                                 &buf,
                                                 "assistant",
                                                 Map::new(),
+                                                Vec::new(),
                                                 Some(Utc::now()),
                                             )
                                             .await?;
@@ -121,6 +173,7 @@ impl Harvester {
                                                 &buf,
                                                 "assistant",
                                                 Map::new(),
+                                                Vec::new(),
                                                 Some(Utc::now()),
                                             )
                                             .await?;
@@ -129,6 +182,24 @@ impl Harvester {
                                     last_plan_pos = current_size;
                                 }
                             }
+                            if (last_task_pos, last_plan_pos) != before {
+                                checkpoints.record(
+                                    &task_md,
+                                    last_task_pos,
+                                    Some(&session_id),
+                                    None,
+                                );
+                                checkpoints.record(
+                                    &plan_md,
+                                    last_plan_pos,
+                                    Some(&session_id),
+                                    None,
+                                );
+                                self.log_writer.flush().await?;
+                                if let Err(e) = checkpoints.save() {
+                                    warn!(err = ?e, "failed to save antigravity checkpoint");
+                                }
+                            }
                         }
                         sleep(Duration::from_millis(500)).await;
 
@@ -161,3 +232,144 @@ impl Harvester {
         }
     }
 }
+
+impl Harvester {
+    /// Log turns decoded from `conversations/*.pb` as Antigravity rewrites them.
+    pub async fn run_antigravity_conversations_watcher(&self) -> Result<()> {
+        info!("starting antigravity conversations watcher");
+        let conversations_dir = self.config.antigravity_conversations.clone();
+
+        if !conversations_dir.exists() {
+            info!(path = ?conversations_dir, "antigravity conversations directory not found yet");
+        }
+
+        // Conversations with a checkpoint, or changed while the daemon was down, are
+        // re-read and caught up on the first pass. Others are left to history import.
+        let mut checkpoints =
+            CheckpointStore::load(&self.state_dir.join("antigravity_conversations.json"));
+        let mut files: HashMap<PathBuf, ConversationState> = HashMap::new();
+        for path in find_conversation_files(&conversations_dir) {
+            let mut state = ConversationState::new();
+            if let Some(logged) = checkpoints.get(&path).and_then(|c| c.messages) {
+                state.logged = logged;
+            } else if !checkpoints.changed_since_save(&path) {
+                state.signature = file_signature(&path);
+                state.logged = read_conversation(&path).map_or(0, |turns| turns.len());
+            }
+            files.insert(path, state);
+        }
+        let mut catching_up = true;
+
+        loop {
+            let mut progressed = false;
+            for path in find_conversation_files(&conversations_dir) {
+                let state = files
+                    .entry(path.clone())
+                    .or_insert_with(ConversationState::new);
+                let signature = file_signature(&path);
+                if signature == state.signature {
+                    continue;
+                }
+                state.signature = signature;
+                state.last_change = Instant::now();
+
+                let turns = match read_conversation(&path) {
+                    Ok(turns) => turns,
+                    Err(e) => {
+                        if !state.warned {
+                            warn!(path = ?path, err = %e, "cannot decode antigravity conversation");
+                            state.warned = true;
+                        }
+                        continue;
+                    }
+                };
+                if turns.len() < state.logged {
+                    state.logged = 0;
+                }
+
+                // Everything before the newest turn is settled.
+                let settled = turns.len().saturating_sub(1);
+                progressed |= settled > state.logged;
+                for parsed in turns.iter().take(settled).skip(state.logged) {
+                    self.log_antigravity_turn(parsed, catching_up).await?;
+                }
+                state.logged = state.logged.max(settled);
+                state.pending = turns.len() > state.logged;
+                checkpoints.set(&path, state.checkpoint());
+            }
+            if catching_up {
+                catching_up = false;
+                self.finish_catch_up("antigravity")?;
+            }
+
+            for (path, state) in files.iter_mut() {
+                if !state.pending || state.last_change.elapsed() <= CONVERSATION_SETTLE {
+                    continue;
+                }
+                state.pending = false;
+                let Ok(turns) = read_conversation(path) else {
+                    continue;
+                };
+                for parsed in turns.iter().skip(state.logged) {
+                    self.log_antigravity_turn(parsed, false).await?;
+                }
+                state.logged = turns.len();
+                checkpoints.set(path, state.checkpoint());
+                progressed = true;
+
+                let project = workspace_hint(&turns)
+                    .map(|root| root.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "Antigravity Brain".to_string());
+                self.log_session_end("antigravity", &conversation_id(path), &project, Map::new())
+                    .await?;
+            }
+
+            files.retain(|path, _| {
+                let exists = path.exists();
+                if !exists {
+                    checkpoints.remove(path);
+                }
+                exists
+            });
+            // Only checkpoint counts whose turns are already in the log.
+            if progressed {
+                self.log_writer.flush().await?;
+            }
+            if let Err(e) = checkpoints.save() {
+                warn!(err = ?e, "failed to save antigravity conversations checkpoint");
+            }
+            sleep(Duration::from_secs(2)).await;
+        }
+    }
+
+    async fn log_antigravity_turn(
+        &self,
+        parsed: &crate::parse::ParsedLine,
+        catching_up: bool,
+    ) -> Result<()> {
+        let session_id = parsed.session_id.as_deref().unwrap_or("unknown");
+        if catching_up
+            && self
+                .already_logged("antigravity", session_id, &parsed.content)
+                .await?
+        {
+            return Ok(());
+        }
+        self.log_interaction_with_metadata(
+            "antigravity",
+            session_id,
+            "Antigravity Brain",
+            &parsed.content,
+            &parsed.role,
+            parsed.metadata.clone(),
+            parsed.artifacts.clone(),
+            parsed.timestamp,
+        )
+        .await
+    }
+}
+
+fn file_signature(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
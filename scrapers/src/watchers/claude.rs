@@ -1,4 +1,5 @@
 use super::Harvester;
+use crate::checkpoint::CheckpointStore;
 use crate::claude::{parse_claude_line, parse_claude_session_line};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
@@ -6,11 +7,18 @@ use serde_json::Map;
 use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::time::{sleep, Instant};
 use tracing::{debug, info, warn};
 
+/// A project session file that has logged turns since it last went quiet.
+struct ActiveSession {
+    session_id: String,
+    project_context: String,
+    last_activity: Instant,
+}
+
 impl Harvester {
     pub async fn run_claude_watcher(&self) -> Result<()> {
         info!("starting claude watcher");
@@ -18,12 +26,15 @@ impl Harvester {
 
         if claude_history.exists() {
             info!(path = ?claude_history, "watching claude history");
+            let mut checkpoints =
+                CheckpointStore::load(&self.state_dir.join("claude_history.json"));
             let file = fs::File::open(&claude_history)?;
             let mut reader = BufReader::new(file);
-            let mut pos = reader.seek(SeekFrom::End(0))?;
+            let mut pos = checkpoints.start_offset(&claude_history);
 
             let mut last_activity = Instant::now();
             let mut generating = false;
+            let mut catching_up = true;
             let mut cwd_cache: HashMap<String, String> = HashMap::new();
 
             loop {
@@ -33,8 +44,13 @@ impl Harvester {
                 }
                 if current_len > pos {
                     reader.seek(SeekFrom::Start(pos))?;
+                    let start = pos;
                     let mut line = String::new();
                     while reader.read_line(&mut line)? > 0 {
+                        if !line.ends_with('\n') {
+                            // Still being written; picked up on the next poll.
+                            break;
+                        }
                         debug!("new claude line");
                         let mut metadata = Map::new();
                         let mut project_context = "Claude Global".to_string();
@@ -61,16 +77,23 @@ impl Harvester {
                             }
                         }
 
-                        self.log_interaction_with_metadata(
-                            "claude-code",
-                            &session_id,
-                            &project_context,
-                            &content,
-                            &role,
-                            metadata,
-                            timestamp,
-                        )
-                        .await?;
+                        let replayed = catching_up
+                            && self
+                                .already_logged("claude-code", &session_id, &content)
+                                .await?;
+                        if !replayed {
+                            self.log_interaction_with_metadata(
+                                "claude-code",
+                                &session_id,
+                                &project_context,
+                                &content,
+                                &role,
+                                metadata,
+                                Vec::new(),
+                                timestamp,
+                            )
+                            .await?;
+                        }
 
                         pos += line.len() as u64;
                         line.clear();
@@ -79,6 +102,17 @@ impl Harvester {
                             generating = true;
                         }
                     }
+                    if pos != start {
+                        checkpoints.record(&claude_history, pos, None, None);
+                        self.log_writer.flush().await?;
+                        if let Err(e) = checkpoints.save() {
+                            warn!(err = ?e, "failed to save claude history checkpoint");
+                        }
+                    }
+                }
+                if catching_up {
+                    catching_up = false;
+                    self.finish_catch_up("claude-code")?;
                 }
 
                 if generating
@@ -111,98 +145,166 @@ impl Harvester {
 
         info!(path = ?claude_projects, "watching claude projects");
 
-        let mut file_positions: HashMap<PathBuf, u64> = HashMap::new();
-        let mut last_activity = Instant::now();
-        let mut generating = false;
+        let mut checkpoints = CheckpointStore::load(&self.state_dir.join("claude_projects.json"));
+        // Files present at startup resume from their checkpoint; files that appear
+        // later are new sessions and are read from the top.
+        let mut file_positions: HashMap<PathBuf, u64> = find_session_files(&claude_projects)
+            .into_iter()
+            .map(|path| {
+                let pos = checkpoints.start_offset(&path);
+                (path, pos)
+            })
+            .collect();
+        let mut active: HashMap<PathBuf, ActiveSession> = HashMap::new();
+        let mut catching_up = true;
 
         loop {
-            if let Ok(project_dirs) = fs::read_dir(&claude_projects) {
-                for project_entry in project_dirs.flatten() {
-                    let project_path = project_entry.path();
-                    if !project_path.is_dir() {
-                        continue;
-                    }
-
-                    if let Ok(session_files) = fs::read_dir(&project_path) {
-                        for session_entry in session_files.flatten() {
-                            let session_path = session_entry.path();
-                            if session_path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
-                                continue;
-                            }
-
-                            let pos = file_positions.entry(session_path.clone()).or_insert(0);
-
-                            if let Ok(file) = fs::File::open(&session_path) {
-                                let mut reader = BufReader::new(file);
-                                let current_len =
-                                    reader.get_ref().metadata().map(|m| m.len()).unwrap_or(0);
-
-                                if current_len < *pos {
-                                    *pos = 0;
-                                }
-
-                                if current_len > *pos {
-                                    if reader.seek(SeekFrom::Start(*pos)).is_err() {
-                                        continue;
-                                    }
-
-                                    let mut line = String::new();
-                                    while reader.read_line(&mut line).unwrap_or(0) > 0 {
-                                        let len = line.len() as u64;
-
-                                        if let Some(parsed) = parse_claude_session_line(&line) {
-                                            let project_context = parsed
-                                                .project_context
-                                                .clone()
-                                                .unwrap_or_else(|| "Claude Session".to_string());
-
-                                            let session_id =
-                                                parsed.session_id.clone().unwrap_or_else(|| {
-                                                    session_path
-                                                        .file_stem()
-                                                        .and_then(|s| s.to_str())
-                                                        .unwrap_or("unknown")
-                                                        .to_string()
-                                                });
-
-                                            self.log_interaction_with_metadata(
-                                                "claude-code",
-                                                &session_id,
-                                                &project_context,
-                                                &parsed.content,
-                                                &parsed.role,
-                                                parsed.metadata,
-                                                parsed.timestamp,
-                                            )
-                                            .await?;
-
-                                            last_activity = Instant::now();
-                                            if !generating {
-                                                generating = true;
-                                                info!(project = %project_context, "claude code active");
-                                            }
-                                        }
-
-                                        *pos += len;
-                                        line.clear();
-                                    }
-                                }
-                            }
-                        }
-                    }
+            let mut progressed = false;
+            for session_path in find_session_files(&claude_projects) {
+                let pos = file_positions.entry(session_path.clone()).or_insert(0);
+                let start = *pos;
+                self.read_session_file(&session_path, pos, &mut active, catching_up)
+                    .await?;
+                if *pos != start {
+                    checkpoints.record(&session_path, *pos, None, None);
+                    progressed = true;
+                }
+            }
+            file_positions.retain(|path, _| {
+                let exists = path.exists();
+                if !exists {
+                    checkpoints.remove(path);
+                }
+                exists
+            });
+            if catching_up {
+                catching_up = false;
+                self.finish_catch_up("claude-code")?;
+            }
+            if progressed {
+                self.log_writer.flush().await?;
+                if let Err(e) = checkpoints.save() {
+                    warn!(err = ?e, "failed to save claude projects checkpoint");
                 }
             }
 
             // Session end detection
-            if generating
-                && last_activity.elapsed() > Duration::from_secs(self.config.claude_silence_secs)
-            {
-                generating = false;
+            let silence = Duration::from_secs(self.config.claude_silence_secs);
+            let ended: Vec<PathBuf> = active
+                .iter()
+                .filter(|(_, session)| session.last_activity.elapsed() > silence)
+                .map(|(path, _)| path.clone())
+                .collect();
+            for path in ended {
+                let Some(session) = active.remove(&path) else {
+                    continue;
+                };
                 self.notifier
                     .send_notification("AI Task Complete", "Claude Code finished.");
+                self.log_session_end(
+                    "claude-code",
+                    &session.session_id,
+                    &session.project_context,
+                    Map::new(),
+                )
+                .await?;
             }
 
             sleep(Duration::from_secs(2)).await;
         }
     }
+
+    /// Log complete lines of one project session file appended since `pos`.
+    async fn read_session_file(
+        &self,
+        session_path: &Path,
+        pos: &mut u64,
+        active: &mut HashMap<PathBuf, ActiveSession>,
+        catching_up: bool,
+    ) -> Result<()> {
+        let Ok(file) = fs::File::open(session_path) else {
+            return Ok(());
+        };
+        let mut reader = BufReader::new(file);
+        let current_len = reader.get_ref().metadata().map(|m| m.len()).unwrap_or(0);
+
+        if current_len < *pos {
+            *pos = 0;
+        }
+        if current_len == *pos || reader.seek(SeekFrom::Start(*pos)).is_err() {
+            return Ok(());
+        }
+
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap_or(0) > 0 {
+            if !line.ends_with('\n') {
+                break;
+            }
+            let len = line.len() as u64;
+
+            if let Some(parsed) = parse_claude_session_line(&line) {
+                let project_context = parsed
+                    .project_context
+                    .clone()
+                    .unwrap_or_else(|| "Claude Session".to_string());
+
+                let session_id = parsed.session_id.clone().unwrap_or_else(|| {
+                    session_path
+                        .file_stem()
+                        .and_then(|s| s.to_str())
+                        .unwrap_or("unknown")
+                        .to_string()
+                });
+
+                let replayed = catching_up
+                    && self
+                        .already_logged("claude-code", &session_id, &parsed.content)
+                        .await?;
+                if !replayed {
+                    self.log_interaction_with_metadata(
+                        "claude-code",
+                        &session_id,
+                        &project_context,
+                        &parsed.content,
+                        &parsed.role,
+                        parsed.metadata,
+                        parsed.artifacts,
+                        parsed.timestamp,
+                    )
+                    .await?;
+
+                    if !active.contains_key(session_path) {
+                        info!(project = %project_context, "claude code active");
+                    }
+                    active.insert(
+                        session_path.to_path_buf(),
+                        ActiveSession {
+                            session_id,
+                            project_context,
+                            last_activity: Instant::now(),
+                        },
+                    );
+                }
+            }
+
+            *pos += len;
+            line.clear();
+        }
+        Ok(())
+    }
+}
+
+/// `~/.claude/projects/*/*.jsonl`
+fn find_session_files(claude_projects: &Path) -> Vec<PathBuf> {
+    let Ok(project_dirs) = fs::read_dir(claude_projects) else {
+        return Vec::new();
+    };
+    project_dirs
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("jsonl"))
+        .collect()
 }
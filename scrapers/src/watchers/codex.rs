@@ -1,195 +1,289 @@
-use super::Harvester;
+use super::{Harvester, Interruption};
+use crate::checkpoint::CheckpointStore;
 use crate::codex::parse_codex_line;
 use anyhow::Result;
-use chrono::{DateTime, Datelike, Local, Utc};
+use chrono::{DateTime, Utc};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde_json::{Map, Value};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
 use std::time::Duration;
 use tokio::time::{sleep, Instant};
-use tracing::info;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+const CODEX_FALLBACK_CONTEXT: &str = "Codex Session";
+
+/// Re-check every tracked rollout this often, in case the OS dropped an event.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One rollout file under `codex_root`.
+struct RolloutState {
+    /// Bytes already logged; always at a line boundary.
+    offset: u64,
+    session_id: String,
+    project_context: String,
+    last_activity: Instant,
+    generating: bool,
+    saw_token_count: bool,
+}
+
+impl RolloutState {
+    fn new(path: &Path, offset: u64) -> Self {
+        Self {
+            offset,
+            session_id: path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            project_context: CODEX_FALLBACK_CONTEXT.to_string(),
+            last_activity: Instant::now(),
+            generating: false,
+            saw_token_count: false,
+        }
+    }
+}
 
 impl Harvester {
+    /// Follow every rollout under `codex_root` (all `YYYY/MM/DD` directories, so a
+    /// session resumed after midnight keeps being logged) via filesystem events.
+    /// Offsets are checkpointed, so a restart picks up exactly where the last run
+    /// stopped.
     pub async fn run_codex_watcher(&self) -> Result<()> {
         info!("starting codex watcher");
         let codex_root = self.config.codex_root.clone();
-        let mut file_positions: HashMap<PathBuf, u64> = HashMap::new();
-        let mut file_activity: HashMap<PathBuf, Instant> = HashMap::new();
-        let mut file_generating: HashMap<PathBuf, bool> = HashMap::new();
-        let mut file_saw_token_count: HashMap<PathBuf, bool> = HashMap::new();
-        let mut file_session_ids: HashMap<PathBuf, String> = HashMap::new();
-        let mut file_project_context: HashMap<PathBuf, String> = HashMap::new();
+        let mut checkpoints = CheckpointStore::load(&self.state_dir.join("codex.json"));
 
-        loop {
-            let now = Local::now();
-            let date_path = codex_root.join(format!(
-                "{}/{:02}/{:02}",
-                now.year(),
-                now.month(),
-                now.day()
-            ));
-
-            let mut candidates: HashSet<PathBuf> = HashSet::new();
-            if date_path.exists() {
-                for entry in fs::read_dir(&date_path)? {
-                    let entry = entry?;
-                    let path = entry.path();
-                    if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                        candidates.insert(path);
+        if !codex_root.exists() {
+            info!(path = ?codex_root, "codex sessions directory not found yet");
+            while !codex_root.exists() {
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+
+        let (tx, rx) = channel();
+        let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
+        watcher.watch(&codex_root, RecursiveMode::Recursive)?;
+        info!(path = ?codex_root, "watching codex sessions");
+
+        // See `CheckpointStore::start_offset`; the checkpoint also remembers the
+        // session and project, which later lines don't repeat.
+        let mut files: HashMap<PathBuf, RolloutState> = HashMap::new();
+        for path in find_rollout_files(&codex_root) {
+            let state = match checkpoints.resume(&path) {
+                Some(checkpoint) => {
+                    let mut state = RolloutState::new(&path, checkpoint.offset);
+                    if let Some(id) = &checkpoint.session_id {
+                        state.session_id = id.clone();
+                    }
+                    if let Some(project) = &checkpoint.project_context {
+                        state.project_context = project.clone();
                     }
+                    state
+                }
+                None => RolloutState::new(&path, checkpoints.start_offset(&path)),
+            };
+            checkpoints.record(
+                &path,
+                state.offset,
+                Some(&state.session_id),
+                Some(&state.project_context),
+            );
+            files.insert(path, state);
+        }
+        if let Err(e) = checkpoints.save() {
+            warn!(err = ?e, "failed to save codex checkpoint");
+        }
+        let mut pending: HashSet<PathBuf> = files.keys().cloned().collect();
+        let mut last_sweep = Instant::now();
+        // The first pass replays whatever was written while the daemon was down.
+        let mut catching_up = true;
+
+        loop {
+            while let Ok(res) = rx.try_recv() {
+                match res {
+                    Ok(event) => pending.extend(event.paths.into_iter().filter(|p| is_rollout(p))),
+                    Err(e) => warn!(err = ?e, "codex watch error"),
                 }
             }
-            if codex_root.exists() {
-                for entry in fs::read_dir(&codex_root)? {
-                    let entry = entry?;
-                    let path = entry.path();
-                    if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("jsonl")
-                    {
-                        candidates.insert(path);
-                    }
+            if last_sweep.elapsed() > SWEEP_INTERVAL {
+                pending.extend(find_rollout_files(&codex_root));
+                pending.extend(files.keys().cloned());
+                last_sweep = Instant::now();
+            }
+
+            let mut progressed = false;
+            for path in pending.drain() {
+                if !path.is_file() {
+                    files.remove(&path);
+                    checkpoints.remove(&path);
+                    continue;
+                }
+                // Files created after startup are new sessions: read from the top.
+                let state = files
+                    .entry(path.clone())
+                    .or_insert_with(|| RolloutState::new(&path, 0));
+                if self.read_rollout(&path, state, catching_up).await? {
+                    progressed = true;
+                    checkpoints.record(
+                        &path,
+                        state.offset,
+                        Some(&state.session_id),
+                        Some(&state.project_context),
+                    );
                 }
             }
-            for path in candidates {
-                file_positions.entry(path.clone()).or_insert_with(|| {
-                    fs::File::open(&path)
-                        .ok()
-                        .and_then(|f| {
-                            let mut r = BufReader::new(f);
-                            r.seek(SeekFrom::End(0)).ok()
-                        })
-                        .unwrap_or(0)
-                });
+
+            if catching_up {
+                catching_up = false;
+                self.finish_catch_up("codex-cli")?;
             }
 
-            if date_path.exists() || codex_root.exists() {
-                let mut to_remove = Vec::new();
-                for (path, pos) in file_positions.iter_mut() {
-                    if let Ok(file) = fs::File::open(path) {
-                        let mut reader = BufReader::new(file);
-                        let current_len = reader.get_ref().metadata().map(|m| m.len()).unwrap_or(0);
-                        if current_len < *pos {
-                            *pos = 0;
-                        }
-                        reader.seek(SeekFrom::Start(*pos))?;
-                        let mut line = String::new();
-                        let mut saw_token_count = *file_saw_token_count.get(path).unwrap_or(&false);
-                        let mut session_id =
-                            file_session_ids.get(path).cloned().unwrap_or_else(|| {
-                                path.file_name()
-                                    .and_then(|s| s.to_str())
-                                    .unwrap_or("unknown")
-                                    .to_string()
-                            });
-                        let mut default_project_context = file_project_context
-                            .get(path)
-                            .cloned()
-                            .unwrap_or_else(|| "Codex Session".to_string());
-
-                        while reader.read_line(&mut line)? > 0 {
-                            let len = line.len() as u64;
-                            let mut project_context = "Codex Session".to_string();
-                            let mut extra_metadata = Map::new();
-                            let mut role = "assistant".to_string();
-                            let mut content = line.clone();
-                            let mut timestamp: Option<DateTime<Utc>> = None;
-
-                            if let Some(parsed) = parse_codex_line(&line) {
-                                if let Some(id) = parsed.session_id {
-                                    session_id = id;
-                                }
-                                if let Some(cwd) = parsed.project_context {
-                                    project_context = cwd.clone();
-                                    default_project_context = cwd;
-                                } else {
-                                    project_context = default_project_context.clone();
-                                }
-                                role = parsed.role;
-                                content = parsed.content;
-                                timestamp = parsed.timestamp;
-                                for (k, v) in parsed.metadata {
-                                    if k.starts_with("usage_") {
-                                        saw_token_count = true;
-                                    }
-                                    extra_metadata.insert(k, v);
-                                }
-                            }
-
-                            self.log_interaction_with_metadata(
-                                "codex-cli",
-                                &session_id,
-                                &project_context,
-                                &content,
-                                &role,
-                                extra_metadata,
-                                timestamp,
-                            )
-                            .await?;
-
-                            *pos += len;
-                            line.clear();
-                            file_generating.insert(path.clone(), true);
-                            file_activity.insert(path.clone(), Instant::now());
-                        }
-
-                        file_session_ids.insert(path.clone(), session_id);
-                        file_project_context.insert(path.clone(), default_project_context);
-                        file_saw_token_count.insert(path.clone(), saw_token_count);
-                    } else {
-                        to_remove.push(path.clone());
-                    }
+            // Session end detection across iterations
+            let silence = Duration::from_secs(self.config.codex_silence_secs);
+            for state in files.values_mut() {
+                if !state.generating || state.last_activity.elapsed() <= silence {
+                    continue;
+                }
+                let mut completion_metadata = Map::new();
+                if state.saw_token_count {
+                    completion_metadata.insert("interrupted".to_string(), Value::Bool(false));
+                } else {
+                    Interruption::NoCompletion.mark(&mut completion_metadata);
                 }
+                self.notifier
+                    .send_notification("AI Task Complete", "Codex CLI finished.");
+                self.log_session_end(
+                    "codex-cli",
+                    &state.session_id,
+                    &state.project_context,
+                    completion_metadata,
+                )
+                .await?;
+                state.generating = false;
+                state.saw_token_count = false;
+            }
 
-                // Session end detection across iterations
-                for (path, last) in file_activity.clone() {
-                    if !file_generating.get(&path).copied().unwrap_or(false) {
-                        continue;
-                    }
-                    if last.elapsed() > Duration::from_secs(self.config.codex_silence_secs) {
-                        let saw_tokens = file_saw_token_count.get(&path).copied().unwrap_or(false);
-                        let session_id =
-                            file_session_ids.get(&path).cloned().unwrap_or_else(|| {
-                                path.file_name()
-                                    .and_then(|s| s.to_str())
-                                    .unwrap_or("unknown")
-                                    .to_string()
-                            });
-                        let project_context = file_project_context
-                            .get(&path)
-                            .cloned()
-                            .unwrap_or_else(|| "Codex Session".to_string());
-                        let mut completion_metadata = Map::new();
-                        completion_metadata
-                            .insert("interrupted".to_string(), Value::Bool(!saw_tokens));
-                        self.notifier
-                            .send_notification("AI Task Complete", "Codex CLI finished.");
-                        self.log_interaction_with_metadata(
-                            "codex-cli",
-                            &session_id,
-                            &project_context,
-                            "Session Ended",
-                            "system",
-                            completion_metadata,
-                            Some(Utc::now()),
-                        )
-                        .await?;
-                        file_generating.insert(path.clone(), false);
-                        file_saw_token_count.insert(path.clone(), false);
-                    }
+            // Only checkpoint offsets whose events are already in the log.
+            if progressed {
+                self.log_writer.flush().await?;
+                if let Err(e) = checkpoints.save() {
+                    warn!(err = ?e, "failed to save codex checkpoint");
                 }
+            }
+
+            sleep(Duration::from_millis(250)).await;
+        }
+    }
+
+    /// Log complete lines appended since `state.offset`. A trailing line without a
+    /// newline is left for the next pass, since Codex may still be writing it.
+    /// With `catching_up`, lines already in the master log are skipped. Returns
+    /// whether the offset moved.
+    async fn read_rollout(
+        &self,
+        path: &Path,
+        state: &mut RolloutState,
+        catching_up: bool,
+    ) -> Result<bool> {
+        let Ok(file) = fs::File::open(path) else {
+            return Ok(false);
+        };
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        if len < state.offset {
+            state.offset = 0;
+        }
+        if len == state.offset {
+            return Ok(false);
+        }
 
-                for path in to_remove {
-                    file_positions.remove(&path);
-                    file_activity.remove(&path);
-                    file_generating.remove(&path);
-                    file_saw_token_count.remove(&path);
-                    file_session_ids.remove(&path);
-                    file_project_context.remove(&path);
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(state.offset))?;
+        let start = state.offset;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            if !line.ends_with('\n') {
+                break;
+            }
+            self.log_codex_line(state, &line, catching_up).await?;
+            state.offset += line.len() as u64;
+            state.generating = true;
+            state.last_activity = Instant::now();
+            line.clear();
+        }
+        Ok(state.offset != start)
+    }
+
+    async fn log_codex_line(
+        &self,
+        state: &mut RolloutState,
+        line: &str,
+        catching_up: bool,
+    ) -> Result<()> {
+        let mut project_context = CODEX_FALLBACK_CONTEXT.to_string();
+        let mut extra_metadata = Map::new();
+        let mut role = "assistant".to_string();
+        let mut content = line.to_string();
+        let mut timestamp: Option<DateTime<Utc>> = None;
+        let mut artifacts = Vec::new();
+
+        if let Some(parsed) = parse_codex_line(line) {
+            if let Some(id) = parsed.session_id {
+                state.session_id = id;
+            }
+            if let Some(cwd) = parsed.project_context {
+                project_context = cwd.clone();
+                state.project_context = cwd;
+            } else {
+                project_context = state.project_context.clone();
+            }
+            role = parsed.role;
+            content = parsed.content;
+            timestamp = parsed.timestamp;
+            artifacts = parsed.artifacts;
+            for (k, v) in parsed.metadata {
+                if k.starts_with("usage_") {
+                    state.saw_token_count = true;
                 }
+                extra_metadata.insert(k, v);
             }
+        }
 
-            sleep(Duration::from_secs(2)).await;
+        if catching_up
+            && self
+                .already_logged("codex-cli", &state.session_id, &content)
+                .await?
+        {
+            return Ok(());
         }
+        self.log_interaction_with_metadata(
+            "codex-cli",
+            &state.session_id,
+            &project_context,
+            &content,
+            &role,
+            extra_metadata,
+            artifacts,
+            timestamp,
+        )
+        .await
     }
 }
+
+fn is_rollout(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("jsonl")
+}
+
+fn find_rollout_files(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && is_rollout(e.path()))
+        .map(|e| e.into_path())
+        .collect()
+}
@@ -1,15 +1,45 @@
 use super::Harvester;
-use crate::cursor::{fingerprint, read_cursor_messages, timestamp_from_metadata};
+use crate::checkpoint::{CheckpointStore, FileCheckpoint};
+use crate::cursor::{
+    composer_versions, fingerprint, global_state_db_for_storage, read_workspace_messages,
+    timestamp_from_metadata, workspace_composer_ids,
+};
 use anyhow::Result;
-use chrono::Utc;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::time::Duration;
 use tokio::time::{sleep, Instant};
 use tracing::{debug, error, info, warn};
 
+/// How often the global database is checked for touched composers while it changes.
+const GLOBAL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One `workspaceStorage/<hash>` directory, i.e. one Cursor window's workspace.
+struct WorkspaceState {
+    hash: String,
+    project: String,
+    last_activity: Instant,
+    generating: bool,
+}
+
+impl WorkspaceState {
+    fn new(dir: &Path) -> Self {
+        Self {
+            hash: dir
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unknown_hash")
+                .to_string(),
+            project: workspace_project(dir).unwrap_or_else(|| "Unknown".to_string()),
+            last_activity: Instant::now(),
+            generating: false,
+        }
+    }
+}
+
 impl Harvester {
     pub async fn run_cursor_watcher(&self) -> Result<()> {
         info!("starting cursor watcher");
@@ -29,160 +59,315 @@ impl Harvester {
 
         info!(path = ?cursor_base, "watching all cursor workspaces");
 
-        let mut last_activity = Instant::now();
-        let mut generating = false;
-        let mut active_project = "Unknown".to_string();
-        let mut workspace_hash = "unknown_hash".to_string();
-        let mut last_state_path: Option<PathBuf> = None;
-        let mut last_cursor_snapshot: Option<u64> = None;
+        // Composer and agent chats live in the global database, which a workspace's
+        // own `state.vscdb` may not change with; watch it too and map its changes
+        // back to the workspaces listing the touched composers.
+        let mut global = global_state_db_for_storage(&cursor_base)
+            .filter(|db| db.is_file())
+            .and_then(|db| {
+                let dir = db.parent()?.to_path_buf();
+                match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                    Ok(()) => Some(GlobalState::new(db)),
+                    Err(e) => {
+                        warn!(err = ?e, "failed to watch cursor global DB");
+                        None
+                    }
+                }
+            });
+
+        let mut checkpoints = CheckpointStore::load(&self.state_dir.join("cursor.json"));
+        self.catch_up_cursor(&cursor_base, &mut checkpoints).await?;
+
+        // Keyed by workspace directory, so concurrent windows each keep their own
+        // silence timer and project.
+        let mut workspaces: HashMap<PathBuf, WorkspaceState> = HashMap::new();
 
         loop {
-            if let Ok(res) = rx.try_recv() {
+            while let Ok(res) = rx.try_recv() {
                 match res {
                     Ok(event) => {
-                        let mut is_db_change = false;
-
-                        for path in event.paths {
-                            if path.file_name().and_then(|s| s.to_str()) == Some("state.vscdb") {
-                                is_db_change = true;
-                                last_state_path = Some(path.clone());
-                                if let Some(parent) = path.parent() {
-                                    if let Some(hash) = parent.file_name().and_then(|s| s.to_str())
-                                    {
-                                        workspace_hash = hash.to_string();
-                                    }
-
-                                    let workspace_json = parent.join("workspace.json");
-                                    if let Ok(content) = fs::read_to_string(&workspace_json) {
-                                        if let Ok(json) =
-                                            serde_json::from_str::<serde_json::Value>(&content)
-                                        {
-                                            if let Some(folder) =
-                                                json.get("folder").and_then(|v| v.as_str())
-                                            {
-                                                active_project =
-                                                    folder.replace("file://", "").to_string();
-                                                active_project = active_project.replace("%20", " ");
-                                            } else if let Some(name) =
-                                                json.get("name").and_then(|v| v.as_str())
-                                            {
-                                                active_project = name.to_string();
-                                            }
-                                        }
-                                    }
-                                }
-                                break;
-                            }
+                        for dir in event.paths.iter().filter_map(|p| changed_workspace(p)) {
+                            mark_active(&mut workspaces, dir);
                         }
-
-                        if is_db_change {
-                            last_activity = Instant::now();
-                            if !generating {
-                                generating = true;
-                                info!(project = %active_project, hash = %workspace_hash, "cursor active");
-                            }
+                        if let Some(global) = global.as_mut() {
+                            global.dirty |= event.paths.iter().any(|p| global.is_db_file(p));
                         }
                     }
                     Err(e) => warn!(err = ?e, "cursor watch error"),
                 }
             }
 
-            // Check silence
-            if generating
-                && last_activity.elapsed() > Duration::from_secs(self.config.cursor_silence_secs)
-            {
-                generating = false;
-                info!(project = %active_project, "cursor finished generating");
+            if let Some(global) = global.as_mut() {
+                for dir in global.touched_workspaces(&cursor_base) {
+                    mark_active(&mut workspaces, &dir);
+                }
+            }
+
+            // Check silence per workspace
+            let silence = Duration::from_secs(self.config.cursor_silence_secs);
+            for (dir, state) in workspaces.iter_mut() {
+                if !state.generating || state.last_activity.elapsed() <= silence {
+                    continue;
+                }
+                state.generating = false;
+                info!(project = %state.project, "cursor finished generating");
                 self.notifier.send_notification(
                     "AI Task Complete",
-                    &format!("Cursor finished in {}", active_project),
+                    &format!("Cursor finished in {}", state.project),
                 );
 
                 let mut extra_metadata = serde_json::Map::new();
-
-                if let Some(db_path) = last_state_path.as_ref() {
-                    match read_cursor_messages(db_path) {
-                        Ok(messages) if !messages.is_empty() => {
-                            let message_count = messages.len();
-                            let snapshot = fingerprint(&messages);
-                            if Some(snapshot) != last_cursor_snapshot {
-                                for message in messages {
-                                    let ts = timestamp_from_metadata(&message.metadata);
-                                    self.log_interaction_with_metadata(
-                                        "cursor",
-                                        &workspace_hash,
-                                        &active_project,
-                                        &message.content,
-                                        &message.role,
-                                        message.metadata,
-                                        ts,
-                                    )
-                                    .await?;
-                                }
-                                extra_metadata.insert(
-                                    "cursor_message_count".to_string(),
-                                    serde_json::json!(message_count),
-                                );
-                                last_cursor_snapshot = Some(snapshot);
-                            } else {
-                                debug!("cursor snapshot unchanged, skipping");
-                            }
-                        }
-                        Ok(_) => {
-                            debug!("cursor state snapshot contained no chat messages");
-                        }
-                        Err(e) => {
-                            error!(err = ?e, "failed to read cursor state");
-                        }
-                    }
-                }
-
-                // Capture git context & effects
-                if let Ok(repo) = std::process::Command::new("git")
-                    .arg("-C")
-                    .arg(&active_project)
-                    .arg("rev-parse")
-                    .arg("--abbrev-ref")
-                    .arg("HEAD")
-                    .output()
+                if let Some(count) = self
+                    .log_cursor_snapshot(
+                        &dir.join("state.vscdb"),
+                        &state.hash,
+                        &state.project,
+                        &mut checkpoints,
+                    )
+                    .await?
                 {
-                    if let Ok(branch) = String::from_utf8(repo.stdout) {
-                        extra_metadata.insert(
-                            "git_branch".to_string(),
-                            serde_json::Value::String(branch.trim().to_string()),
-                        );
-                    }
+                    extra_metadata
+                        .insert("cursor_message_count".to_string(), serde_json::json!(count));
                 }
 
-                if let Ok(status) = std::process::Command::new("git")
-                    .arg("-C")
-                    .arg(&active_project)
-                    .arg("status")
-                    .arg("--short")
-                    .output()
-                {
-                    if let Ok(changes) = String::from_utf8(status.stdout) {
-                        let effects: Vec<String> = changes.lines().map(|s| s.to_string()).collect();
-                        if !effects.is_empty() {
-                            extra_metadata
-                                .insert("file_effects".to_string(), serde_json::json!(effects));
-                        }
-                    }
-                }
+                self.log_session_end("cursor", &state.hash, &state.project, extra_metadata)
+                    .await?;
+            }
+
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
 
-                self.log_interaction_with_metadata(
-                    "cursor",
-                    &workspace_hash,
-                    &active_project,
-                    "Session Ended",
-                    "system",
-                    extra_metadata,
-                    Some(Utc::now()),
-                )
+    /// Log the chat in workspaces whose database (or, for composer chats, the global
+    /// one) changed while the daemon was down, skipping messages already logged.
+    async fn catch_up_cursor(
+        &self,
+        cursor_base: &Path,
+        checkpoints: &mut CheckpointStore,
+    ) -> Result<()> {
+        let Ok(entries) = fs::read_dir(cursor_base) else {
+            return Ok(());
+        };
+        // Composer chats may have changed only in the global database (or its WAL).
+        let global_changed = global_state_db_for_storage(cursor_base).is_some_and(|db| {
+            let wal = db.with_file_name("state.vscdb-wal");
+            checkpoints.changed_since_save(&db) || checkpoints.changed_since_save(&wal)
+        });
+        // Collected up front: logging a snapshot saves the store, moving `saved_at`.
+        let changed: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|dir| {
+                let db = dir.join("state.vscdb");
+                checkpoints.changed_since_save(&db)
+                    || (global_changed
+                        && workspace_composer_ids(&db).is_ok_and(|ids| !ids.is_empty()))
+            })
+            .collect();
+        for dir in changed {
+            let Some(hash) = dir.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let project = workspace_project(&dir).unwrap_or_else(|| "Unknown".to_string());
+            self.log_cursor_snapshot(&dir.join("state.vscdb"), hash, &project, checkpoints)
                 .await?;
+        }
+        if let Err(e) = checkpoints.save() {
+            warn!(err = ?e, "failed to save cursor checkpoint");
+        }
+        Ok(())
+    }
+
+    /// Log the chat history of the workspace at `db_path` unless it matches the
+    /// fingerprint last checkpointed for it. Cursor only offers whole snapshots, so
+    /// messages the master log already holds are skipped (see
+    /// [`Harvester::unlogged_turns`]). Returns the message count
+    /// when the snapshot changed.
+    async fn log_cursor_snapshot(
+        &self,
+        db_path: &Path,
+        workspace_hash: &str,
+        project: &str,
+        checkpoints: &mut CheckpointStore,
+    ) -> Result<Option<usize>> {
+        let messages = match read_workspace_messages(db_path) {
+            Ok(messages) if !messages.is_empty() => messages,
+            Ok(_) => {
+                debug!("cursor state snapshot contained no chat messages");
+                return Ok(None);
             }
+            Err(e) => {
+                error!(err = ?e, "failed to read cursor state");
+                return Ok(None);
+            }
+        };
+        let snapshot = fingerprint(&messages);
+        if checkpoints.get(db_path).and_then(|c| c.fingerprint) == Some(snapshot) {
+            debug!("cursor snapshot unchanged, skipping");
+            return Ok(None);
+        }
 
-            sleep(Duration::from_millis(100)).await;
+        let message_count = messages.len();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        let unlogged = match self
+            .unlogged_turns("cursor", workspace_hash, &contents)
+            .await
+        {
+            Ok(unlogged) => unlogged,
+            Err(e) => {
+                // Logging the whole snapshot would duplicate it; the next one retries.
+                warn!(err = ?e, "cannot read logged cursor turns; skipping snapshot");
+                return Ok(None);
+            }
+        };
+        for (message, unlogged) in messages.into_iter().zip(unlogged) {
+            if !unlogged {
+                continue;
+            }
+            let ts = timestamp_from_metadata(&message.metadata);
+            self.log_interaction_with_metadata(
+                "cursor",
+                workspace_hash,
+                project,
+                &message.content,
+                &message.role,
+                message.metadata,
+                message.artifacts,
+                ts,
+            )
+            .await?;
+        }
+
+        checkpoints.set(
+            db_path,
+            FileCheckpoint {
+                session_id: Some(workspace_hash.to_string()),
+                project_context: Some(project.to_string()),
+                fingerprint: Some(snapshot),
+                ..Default::default()
+            },
+        );
+        self.log_writer.flush().await?;
+        if let Err(e) = checkpoints.save() {
+            warn!(err = ?e, "failed to save cursor checkpoint");
+        }
+        Ok(Some(message_count))
+    }
+}
+
+/// Note activity in the workspace at `dir`, starting its silence timer.
+fn mark_active(workspaces: &mut HashMap<PathBuf, WorkspaceState>, dir: &Path) {
+    let state = workspaces
+        .entry(dir.to_path_buf())
+        .or_insert_with(|| WorkspaceState::new(dir));
+    state.last_activity = Instant::now();
+    if !state.generating {
+        state.generating = true;
+        // Picks up a folder reopened in a recycled workspace.
+        if let Some(project) = workspace_project(dir) {
+            state.project = project;
+        }
+        info!(project = %state.project, hash = %state.hash, "cursor active");
+    }
+}
+
+/// The global `state.vscdb` and the composer versions last seen in it.
+struct GlobalState {
+    db: PathBuf,
+    versions: HashMap<String, u64>,
+    /// The database (or its WAL) changed since the last check.
+    dirty: bool,
+    last_check: Instant,
+}
+
+impl GlobalState {
+    fn new(db: PathBuf) -> Self {
+        let versions = composer_versions(&db).unwrap_or_else(|e| {
+            warn!(err = ?e, "failed to read cursor composers");
+            HashMap::new()
+        });
+        Self {
+            db,
+            versions,
+            dirty: false,
+            last_check: Instant::now(),
         }
     }
+
+    /// `state.vscdb` itself or its `-wal`/`-journal` companions. Compared by name,
+    /// since the watcher may report the directory under another (canonical) path.
+    fn is_db_file(&self, path: &Path) -> bool {
+        let name = |p: Option<&Path>| p.and_then(|p| p.file_name()).map(|n| n.to_owned());
+        name(path.parent()) == name(self.db.parent())
+            && path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("state.vscdb"))
+    }
+
+    /// Workspaces listing a composer that changed since the last check. Checked at
+    /// most every [`GLOBAL_CHECK_INTERVAL`], since Cursor writes it while streaming.
+    fn touched_workspaces(&mut self, cursor_base: &Path) -> Vec<PathBuf> {
+        if !self.dirty || self.last_check.elapsed() < GLOBAL_CHECK_INTERVAL {
+            return Vec::new();
+        }
+        self.dirty = false;
+        self.last_check = Instant::now();
+        let versions = match composer_versions(&self.db) {
+            Ok(versions) => versions,
+            Err(e) => {
+                warn!(err = ?e, "failed to read cursor composers");
+                return Vec::new();
+            }
+        };
+        let touched: HashSet<&String> = versions
+            .iter()
+            .filter(|(id, version)| self.versions.get(*id) != Some(version))
+            .map(|(id, _)| id)
+            .collect();
+        let dirs = if touched.is_empty() {
+            Vec::new()
+        } else {
+            workspaces_listing(cursor_base, &touched)
+        };
+        self.versions = versions;
+        dirs
+    }
+}
+
+/// Workspace directories whose `composer.composerData` lists any of `composers`.
+fn workspaces_listing(cursor_base: &Path, composers: &HashSet<&String>) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(cursor_base) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|dir| {
+            workspace_composer_ids(&dir.join("state.vscdb"))
+                .is_ok_and(|ids| ids.iter().any(|id| composers.contains(id)))
+        })
+        .collect()
+}
+
+/// The workspace directory whose `state.vscdb` `path` is.
+fn changed_workspace(path: &Path) -> Option<&Path> {
+    if path.file_name().and_then(|s| s.to_str()) == Some("state.vscdb") {
+        path.parent()
+    } else {
+        None
+    }
+}
+
+/// The folder (or workspace name) recorded in a workspace's `workspace.json`.
+fn workspace_project(workspace_dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(workspace_dir.join("workspace.json")).ok()?;
+    let json = serde_json::from_str::<serde_json::Value>(&content).ok()?;
+    if let Some(folder) = json.get("folder").and_then(|v| v.as_str()) {
+        Some(folder.replace("file://", "").replace("%20", " "))
+    } else {
+        json.get("name")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    }
 }
@@ -0,0 +1,208 @@
+use super::Harvester;
+use crate::checkpoint::{CheckpointStore, FileCheckpoint};
+use crate::gemini::{
+    find_session_files, parse_gemini_session, recorded_project_root, GeminiSession,
+};
+use crate::parse::ParsedLine;
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::time::{sleep, Instant};
+use tracing::{info, warn};
+
+const GEMINI_FALLBACK_CONTEXT: &str = "Gemini Session";
+
+/// Gemini CLI rewrites a chat file in place, and its newest message can still gain
+/// tool calls and token counts, so that message is only logged once the file goes quiet.
+struct ChatFileState {
+    /// `(mtime, len)` of the last version read.
+    signature: Option<(SystemTime, u64)>,
+    /// Messages already logged from the front of the transcript.
+    logged: usize,
+    session_id: String,
+    project_context: String,
+    last_change: Instant,
+    pending: bool,
+}
+
+impl ChatFileState {
+    fn new(path: &Path) -> Self {
+        Self {
+            signature: None,
+            logged: 0,
+            session_id: path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            project_context: recorded_project_root(path)
+                .unwrap_or_else(|| GEMINI_FALLBACK_CONTEXT.to_string()),
+            last_change: Instant::now(),
+            pending: false,
+        }
+    }
+
+    fn checkpoint(&self) -> FileCheckpoint {
+        FileCheckpoint {
+            session_id: Some(self.session_id.clone()),
+            messages: Some(self.logged),
+            ..Default::default()
+        }
+    }
+}
+
+impl Harvester {
+    pub async fn run_gemini_watcher(&self) -> Result<()> {
+        info!("starting gemini watcher");
+        let gemini_root = self.config.gemini_root.clone();
+
+        if gemini_root.exists() {
+            info!(path = ?gemini_root, "watching gemini chats");
+        } else {
+            info!(path = ?gemini_root, "gemini chat directory not found yet");
+        }
+
+        // Transcripts with a checkpoint, or changed while the daemon was down, are
+        // re-read and caught up on the first pass. Others are left to history import.
+        let mut checkpoints = CheckpointStore::load(&self.state_dir.join("gemini.json"));
+        let mut files: HashMap<PathBuf, ChatFileState> = HashMap::new();
+        for path in find_session_files(&gemini_root) {
+            let mut state = ChatFileState::new(&path);
+            if let Some(logged) = checkpoints.get(&path).and_then(|c| c.messages) {
+                state.logged = logged;
+            } else if !checkpoints.changed_since_save(&path) {
+                state.signature = file_signature(&path);
+                state.logged = read_session(&path).map_or(0, |s| s.messages.len());
+            }
+            files.insert(path, state);
+        }
+        let mut catching_up = true;
+
+        loop {
+            let mut progressed = false;
+            for path in find_session_files(&gemini_root) {
+                let state = files
+                    .entry(path.clone())
+                    .or_insert_with(|| ChatFileState::new(&path));
+                let signature = file_signature(&path);
+                if signature == state.signature {
+                    continue;
+                }
+                state.last_change = Instant::now();
+
+                let Some(session) = read_session(&path) else {
+                    // Probably caught mid-rewrite; retried on the next poll.
+                    continue;
+                };
+                state.signature = signature;
+                state.session_id = session.session_id;
+                if session.messages.len() < state.logged {
+                    state.logged = 0;
+                }
+
+                // Everything before the newest message is settled.
+                let settled = session.messages.len().saturating_sub(1);
+                progressed |= settled > state.logged;
+                for parsed in session.messages.iter().take(settled).skip(state.logged) {
+                    self.log_gemini_message(state, parsed, catching_up).await?;
+                }
+                state.logged = state.logged.max(settled);
+                state.pending = session.messages.len() > state.logged;
+                checkpoints.set(&path, state.checkpoint());
+            }
+            if catching_up {
+                catching_up = false;
+                self.finish_catch_up("gemini-cli")?;
+            }
+
+            // Session end detection: flush the newest message once the file is quiet.
+            let silence = Duration::from_secs(self.config.gemini_silence_secs);
+            for (path, state) in files.iter_mut() {
+                if !state.pending || state.last_change.elapsed() <= silence {
+                    continue;
+                }
+                state.pending = false;
+                let Some(session) = read_session(path) else {
+                    continue;
+                };
+                for parsed in session.messages.iter().skip(state.logged) {
+                    self.log_gemini_message(state, parsed, false).await?;
+                }
+                state.logged = session.messages.len();
+                checkpoints.set(path, state.checkpoint());
+                progressed = true;
+                self.notifier
+                    .send_notification("AI Task Complete", "Gemini CLI finished.");
+                self.log_session_end(
+                    "gemini-cli",
+                    &state.session_id,
+                    &state.project_context,
+                    Map::new(),
+                )
+                .await?;
+            }
+
+            files.retain(|path, _| {
+                let exists = path.exists();
+                if !exists {
+                    checkpoints.remove(path);
+                }
+                exists
+            });
+            // Only checkpoint counts whose messages are already in the log.
+            if progressed {
+                self.log_writer.flush().await?;
+            }
+            if let Err(e) = checkpoints.save() {
+                warn!(err = ?e, "failed to save gemini checkpoint");
+            }
+            sleep(Duration::from_secs(2)).await;
+        }
+    }
+
+    async fn log_gemini_message(
+        &self,
+        state: &ChatFileState,
+        parsed: &ParsedLine,
+        catching_up: bool,
+    ) -> Result<()> {
+        if catching_up
+            && self
+                .already_logged("gemini-cli", &state.session_id, &parsed.content)
+                .await?
+        {
+            return Ok(());
+        }
+        let mut metadata = parsed.metadata.clone();
+        if state.project_context != GEMINI_FALLBACK_CONTEXT {
+            metadata.insert(
+                "cwd".to_string(),
+                Value::String(state.project_context.clone()),
+            );
+        }
+        self.log_interaction_with_metadata(
+            "gemini-cli",
+            &state.session_id,
+            &state.project_context,
+            &parsed.content,
+            &parsed.role,
+            metadata,
+            parsed.artifacts.clone(),
+            parsed.timestamp,
+        )
+        .await
+    }
+}
+
+fn file_signature(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn read_session(path: &Path) -> Option<GeminiSession> {
+    let raw = fs::read_to_string(path).ok()?;
+    parse_gemini_session(&raw)
+}
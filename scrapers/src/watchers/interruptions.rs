@@ -0,0 +1,221 @@
+//! Interruption detection shared by every watcher.
+//!
+//! Each logged turn passes through [`InterruptionDetector::observe`]. A flagged turn
+//! gets `interrupted: true` plus an `interruption` kind, and rapid re-prompts also
+//! record `reprompt_gap_ms`, so analysis sees one shape whichever tool it came from.
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// A user turn this soon after an assistant turn in the same session is a re-prompt
+/// sent over (or straight after) a reply the user didn't wait for.
+const RAPID_REPROMPT: Duration = Duration::milliseconds(1500);
+
+/// Sessions idle for longer than this are forgotten.
+const SESSION_TTL: Duration = Duration::hours(6);
+
+/// Notices the tools themselves write when the user stops a turn.
+const CANCEL_NOTICES: &[&str] = &[
+    "[Request interrupted by user", // Claude Code
+    "Request cancelled.",           // Gemini CLI
+    "Turn aborted",                 // Codex, via `parse_codex_line`
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interruption {
+    /// Assistant output that stops mid code block or mid JSON document.
+    Truncated,
+    /// The tool recorded that the user cancelled the turn.
+    UserCancelled,
+    /// A user turn within [`RAPID_REPROMPT`] of the previous assistant turn.
+    RapidReprompt { gap_ms: u64 },
+    /// A session went quiet before the tool reported finishing its turn.
+    NoCompletion,
+}
+
+impl Interruption {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Interruption::Truncated => "truncated",
+            Interruption::UserCancelled => "user_cancelled",
+            Interruption::RapidReprompt { .. } => "rapid_reprompt",
+            Interruption::NoCompletion => "no_completion",
+        }
+    }
+
+    /// Record this interruption in a turn's metadata.
+    pub fn mark(self, metadata: &mut Map<String, Value>) {
+        metadata.insert("interrupted".to_string(), Value::Bool(true));
+        metadata.insert(
+            "interruption".to_string(),
+            Value::String(self.as_str().to_string()),
+        );
+        if let Interruption::RapidReprompt { gap_ms } = self {
+            metadata.insert("reprompt_gap_ms".to_string(), Value::from(gap_ms));
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InterruptionDetector {
+    /// When each `(source, session)` last logged an assistant turn.
+    last_assistant: HashMap<(String, String), DateTime<Utc>>,
+}
+
+impl InterruptionDetector {
+    pub fn observe(
+        &mut self,
+        source: &str,
+        session: &str,
+        role: &str,
+        content: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Option<Interruption> {
+        let key = (source.to_string(), session.to_string());
+        let trimmed = content.trim_start();
+        if CANCEL_NOTICES.iter().any(|n| trimmed.starts_with(n)) {
+            self.last_assistant.remove(&key);
+            return Some(Interruption::UserCancelled);
+        }
+
+        match role {
+            "assistant" => {
+                self.remember(key, timestamp);
+                looks_truncated(content).then_some(Interruption::Truncated)
+            }
+            "user" => {
+                let last = self.last_assistant.remove(&key)?;
+                let gap = timestamp.signed_duration_since(last);
+                (gap >= Duration::zero() && gap <= RAPID_REPROMPT).then(|| {
+                    Interruption::RapidReprompt {
+                        gap_ms: gap.num_milliseconds() as u64,
+                    }
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn remember(&mut self, key: (String, String), timestamp: DateTime<Utc>) {
+        if self.last_assistant.len() >= 256 {
+            let cutoff = timestamp - SESSION_TTL;
+            self.last_assistant.retain(|_, seen| *seen > cutoff);
+        }
+        self.last_assistant.insert(key, timestamp);
+    }
+}
+
+/// An unclosed code fence, or something that opens like a JSON document and
+/// doesn't parse as one.
+fn looks_truncated(content: &str) -> bool {
+    if content.matches("```").count() % 2 == 1 {
+        return true;
+    }
+    let trimmed = content.trim();
+    let mut chars = trimmed.chars();
+    let opens_json = matches!(
+        (chars.next(), chars.find(|c| !c.is_whitespace())),
+        (Some('{'), Some('"' | '}')) | (Some('['), Some('{' | '[' | '"'))
+    );
+    opens_json && serde_json::from_str::<Value>(trimmed).is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_767_225_600_000 + ms).unwrap()
+    }
+
+    #[test]
+    fn flags_rapid_reprompts_per_session() {
+        let mut detector = InterruptionDetector::default();
+        assert_eq!(
+            detector.observe("codex-cli", "a", "assistant", "Working on it.", at(0)),
+            None
+        );
+        assert_eq!(
+            detector.observe("codex-cli", "b", "user", "unrelated", at(300)),
+            None
+        );
+        assert_eq!(
+            detector.observe("codex-cli", "a", "user", "no, stop", at(800)),
+            Some(Interruption::RapidReprompt { gap_ms: 800 })
+        );
+
+        detector.observe("codex-cli", "a", "assistant", "Done.", at(1_000));
+        assert_eq!(
+            detector.observe("codex-cli", "a", "user", "thanks", at(10_000)),
+            None
+        );
+    }
+
+    #[test]
+    fn flags_cancellations_and_truncated_output() {
+        let mut detector = InterruptionDetector::default();
+        assert_eq!(
+            detector.observe(
+                "claude-code",
+                "s",
+                "user",
+                "[Request interrupted by user for tool use]",
+                at(0)
+            ),
+            Some(Interruption::UserCancelled)
+        );
+        assert_eq!(
+            detector.observe("gemini-cli", "s", "system", "Request cancelled.", at(0)),
+            Some(Interruption::UserCancelled)
+        );
+        assert_eq!(
+            detector.observe(
+                "cursor",
+                "s",
+                "assistant",
+                "Here:\n```rust\nfn main() {",
+                at(0)
+            ),
+            Some(Interruption::Truncated)
+        );
+        assert_eq!(
+            detector.observe(
+                "cursor",
+                "s",
+                "assistant",
+                "{\"plan\": [\"step one\",",
+                at(0)
+            ),
+            Some(Interruption::Truncated)
+        );
+        assert_eq!(
+            detector.observe("cursor", "s", "assistant", "```sh\nls\n```\nDone.", at(0)),
+            None
+        );
+        assert_eq!(
+            detector.observe(
+                "cursor",
+                "s",
+                "assistant",
+                "[Docs](https://x.y) say so",
+                at(0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn marks_metadata_with_one_shape() {
+        let mut metadata = Map::new();
+        Interruption::RapidReprompt { gap_ms: 420 }.mark(&mut metadata);
+        assert_eq!(
+            Value::Object(metadata),
+            serde_json::json!({
+                "interrupted": true,
+                "interruption": "rapid_reprompt",
+                "reprompt_gap_ms": 420
+            })
+        );
+    }
+}
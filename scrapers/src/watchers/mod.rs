@@ -2,32 +2,131 @@ mod antigravity;
 mod claude;
 mod codex;
 mod cursor;
+mod gemini;
+mod interruptions;
 
-use crate::config::ContrailConfig;
+use crate::config::{data_dir, ContrailConfig, WATCHER_STATE_DIR_REL};
+use crate::event_index::{history_key, EventIndex};
+use crate::git_effects;
 use crate::log_writer::LogWriter;
 use crate::notifier::Notifier;
 use crate::sentry::Sentry;
-use crate::types::{Interaction, MasterLog};
+use crate::types::{Artifact, Interaction, MasterLog, SCHEMA_VERSION};
+pub use interruptions::{Interruption, InterruptionDetector};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tracing::warn;
 use uuid::Uuid;
 
+/// Dedup keys per `(source, session)`; see [`Harvester::already_logged`].
+type RecoveredKeys = HashMap<(String, String), HashSet<u64>>;
+
 pub struct Harvester {
     pub(crate) sentry: Sentry,
     pub(crate) notifier: Notifier,
     pub(crate) log_writer: LogWriter,
     pub(crate) config: ContrailConfig,
+    pub(crate) interruptions: Mutex<InterruptionDetector>,
+    /// Where watchers keep their checkpoints (`state/watchers` under the data dir).
+    pub(crate) state_dir: PathBuf,
+    /// Keys already in the master log for sessions replayed during catch-up.
+    recovered: Mutex<RecoveredKeys>,
 }
 
 impl Harvester {
-    pub fn new(log_writer: LogWriter, config: ContrailConfig) -> Self {
-        Self {
-            sentry: Sentry::new(),
+    pub fn new(log_writer: LogWriter, config: ContrailConfig) -> Result<Self> {
+        let home = dirs::home_dir().context("could not resolve home directory")?;
+        Ok(Self {
+            sentry: Sentry::from_config(&config)?,
             notifier: Notifier::new(),
             log_writer,
             config,
+            interruptions: Mutex::new(InterruptionDetector::default()),
+            state_dir: data_dir(&home).join(WATCHER_STATE_DIR_REL),
+            recovered: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Whether the master log already holds this turn, for watchers replaying a
+    /// transcript from their checkpoint after a restart. Turns match on source,
+    /// session and redacted content, like history import. A turn reported as new
+    /// counts as logged from then on, so call this only for turns about to be logged,
+    /// and only while catching up: [`Harvester::finish_catch_up`] ends the window.
+    pub(crate) async fn already_logged(
+        &self,
+        source: &str,
+        session: &str,
+        content: &str,
+    ) -> Result<bool> {
+        let (clean_content, _) = self.sentry.scan_and_redact(content);
+        let key = history_key(source, session, &clean_content);
+        let id = (source.to_string(), session.to_string());
+
+        if !self.recovered_guard()?.contains_key(&id) {
+            // The index only sees what has reached the file.
+            self.log_writer.flush().await?;
+            let log_path = self.config.log_path.clone();
+            let (src, sess) = id.clone();
+            let keys = tokio::task::spawn_blocking(move || {
+                EventIndex::open_synced(&log_path)
+                    .and_then(|index| index.session_history_keys(&src, &sess))
+            })
+            .await?
+            .unwrap_or_else(|e| {
+                warn!(err = ?e, source, session, "cannot read logged events; replaying without dedup");
+                HashSet::new()
+            });
+            self.recovered_guard()?.entry(id.clone()).or_insert(keys);
         }
+
+        let mut recovered = self.recovered_guard()?;
+        Ok(!recovered.entry(id).or_default().insert(key))
+    }
+
+    /// Which turns of a whole-session snapshot (Cursor) the master log doesn't hold
+    /// yet. A snapshot holds every turn, so repeats are matched by occurrence: the
+    /// third "yes" in a session is new only while the log holds fewer than three.
+    /// Reads the counts from the event index each time, so nothing accumulates.
+    pub(crate) async fn unlogged_turns(
+        &self,
+        source: &str,
+        session: &str,
+        contents: &[&str],
+    ) -> Result<Vec<bool>> {
+        let keys: Vec<u64> = contents
+            .iter()
+            .map(|content| {
+                let (clean_content, _) = self.sentry.scan_and_redact(content);
+                history_key(source, session, &clean_content)
+            })
+            .collect();
+        // The index only sees what has reached the file.
+        self.log_writer.flush().await?;
+        let log_path = self.config.log_path.clone();
+        let (src, sess) = (source.to_string(), session.to_string());
+        let logged = tokio::task::spawn_blocking(move || {
+            EventIndex::open_synced(&log_path)
+                .and_then(|index| index.session_history_key_counts(&src, &sess))
+        })
+        .await??;
+        Ok(unlogged_occurrences(logged, &keys))
+    }
+
+    /// Drop the dedup keys [`Harvester::already_logged`] loaded for `source` once its
+    /// watcher has caught up.
+    pub(crate) fn finish_catch_up(&self, source: &str) -> Result<()> {
+        self.recovered_guard()?.retain(|(s, _), _| s != source);
+        Ok(())
+    }
+
+    fn recovered_guard(&self) -> Result<MutexGuard<'_, RecoveredKeys>> {
+        self.recovered
+            .lock()
+            .map_err(|_| anyhow::anyhow!("catch-up dedup state poisoned"))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -39,9 +138,11 @@ impl Harvester {
         content: &str,
         role: &str,
         extra_metadata: serde_json::Map<String, serde_json::Value>,
+        mut artifacts: Vec<Artifact>,
         timestamp: Option<DateTime<Utc>>,
     ) -> Result<()> {
-        let (clean_content, flags) = self.sentry.scan_and_redact(content);
+        let (clean_content, mut flags) = self.sentry.scan_and_redact(content);
+        self.sentry.redact_artifacts(&mut artifacts, &mut flags);
 
         let mut metadata = serde_json::Map::new();
         metadata.insert(
@@ -76,23 +177,92 @@ impl Harvester {
             metadata.insert(k, v);
         }
 
+        let timestamp = timestamp.unwrap_or_else(Utc::now);
+        let interruption = self
+            .interruptions
+            .lock()
+            .map_err(|_| anyhow::anyhow!("interruption detector poisoned"))?
+            .observe(source, session, role, &clean_content, timestamp);
+        if let Some(interruption) = interruption {
+            interruption.mark(&mut metadata);
+        }
+
         let log = MasterLog {
+            schema_version: SCHEMA_VERSION,
             event_id: Uuid::new_v4(),
-            timestamp: timestamp.unwrap_or_else(Utc::now),
+            timestamp,
             source_tool: source.to_string(),
             project_context: project.to_string(),
             session_id: session.to_string(),
             interaction: Interaction {
                 role: role.to_string(),
                 content: clean_content,
-                artifacts: None,
+                artifacts: (!artifacts.is_empty()).then_some(artifacts),
             },
             security_flags: flags,
-            metadata: serde_json::Value::Object(metadata),
+            metadata: metadata.into(),
         };
 
         log.validate_schema()?;
         self.log_writer.write(log).await?;
         Ok(())
     }
+
+    /// Log the "Session Ended" marker. When `project` is inside a git repository the
+    /// marker also carries a snapshot of its working tree (see [`git_effects`]).
+    pub(crate) async fn log_session_end(
+        &self,
+        source: &str,
+        session: &str,
+        project: &str,
+        mut metadata: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<()> {
+        let dir = PathBuf::from(project);
+        if dir.is_absolute() {
+            let snapshot = tokio::task::spawn_blocking(move || git_effects::snapshot(&dir)).await;
+            if let Ok(Some(snapshot)) = snapshot {
+                snapshot.insert_into(&mut metadata);
+            }
+        }
+        self.log_interaction_with_metadata(
+            source,
+            session,
+            project,
+            "Session Ended",
+            "system",
+            metadata,
+            Vec::new(),
+            Some(Utc::now()),
+        )
+        .await
+    }
+}
+
+/// For each key in order, whether it is beyond the occurrences already `logged`.
+fn unlogged_occurrences(mut logged: HashMap<u64, usize>, keys: &[u64]) -> Vec<bool> {
+    keys.iter()
+        .map(|key| match logged.get_mut(key) {
+            Some(remaining) if *remaining > 0 => {
+                *remaining -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_turns_are_matched_by_occurrence() {
+        let (yes, done) = (1, 2);
+        let logged = HashMap::from([(yes, 1), (done, 1)]);
+        assert_eq!(
+            unlogged_occurrences(logged, &[yes, done, yes, yes]),
+            [false, false, true, true]
+        );
+        assert_eq!(unlogged_occurrences(HashMap::new(), &[yes]), [true]);
+    }
 }
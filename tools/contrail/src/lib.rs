@@ -36,6 +36,11 @@ const PROC_ANALYSIS: ManagedProcess = ManagedProcess {
     health_addr: Some("127.0.0.1:3210"),
 };
 
+/// systemd user unit written by `contrail install-service` on Linux.
+const SYSTEMD_UNIT_NAME: &str = "contrail.service";
+/// launchd label, matching the repo's `com.contrail.daemon.plist`.
+const LAUNCHD_LABEL: &str = "com.contrail.daemon";
+
 const PROCS_START_ORDER: [ManagedProcess; 3] = [PROC_CORE_DAEMON, PROC_DASHBOARD, PROC_ANALYSIS];
 const PROCS_STOP_ORDER: [ManagedProcess; 3] = [PROC_ANALYSIS, PROC_DASHBOARD, PROC_CORE_DAEMON];
 
@@ -53,6 +58,7 @@ enum LifecycleCommand {
     Up,
     Down,
     Status,
+    InstallService,
 }
 
 #[derive(Clone, Copy)]
@@ -71,6 +77,7 @@ fn parse_lifecycle_command(args: &[OsString]) -> Option<LifecycleCommand> {
         "up" => Some(LifecycleCommand::Up),
         "down" => Some(LifecycleCommand::Down),
         "status" => Some(LifecycleCommand::Status),
+        "install-service" => Some(LifecycleCommand::InstallService),
         _ => None,
     }
 }
@@ -103,6 +110,7 @@ fn run_lifecycle_command(command: LifecycleCommand) -> Result<()> {
                 print_process_status(&run_dir, process);
             }
         }
+        LifecycleCommand::InstallService => install_service()?,
     }
 
     Ok(())
@@ -302,6 +310,195 @@ fn wait_for_health(name: &str, addr: &str) -> bool {
     false
 }
 
+/// Register core_daemon with the platform service manager so capture starts at
+/// login: a systemd user unit on Linux, a LaunchAgent on macOS.
+fn install_service() -> Result<()> {
+    let binary = absolute_binary_path(PROC_CORE_DAEMON)?;
+    let home = env::var_os("HOME")
+        .map(PathBuf::from)
+        .context("HOME is not set")?;
+
+    if cfg!(target_os = "macos") {
+        let log_dir = contrail_root_dir()?;
+        fs::create_dir_all(&log_dir)
+            .with_context(|| format!("failed to create {}", log_dir.display()))?;
+        let plist_path = home
+            .join("Library/LaunchAgents")
+            .join(format!("{LAUNCHD_LABEL}.plist"));
+        write_service_file(&plist_path, &render_launchd_plist(&binary, &log_dir))?;
+        run_service_manager("launchctl", &["load", "-w", &plist_path.to_string_lossy()]);
+        return Ok(());
+    }
+
+    if cfg!(target_os = "linux") {
+        let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+            _ => home.join(".config"),
+        };
+        let unit_path = config_dir.join("systemd/user").join(SYSTEMD_UNIT_NAME);
+        // Only the config location is carried into the unit; other settings (and any
+        // secrets in the environment) belong in the config file, not a unit file.
+        let config_path = env::var_os("CONTRAIL_CONFIG")
+            .filter(|path| !path.is_empty())
+            .map(std::path::absolute)
+            .transpose()
+            .context("failed to resolve CONTRAIL_CONFIG")?;
+        let skipped: Vec<String> = env::vars_os()
+            .filter_map(|(key, _)| key.into_string().ok())
+            .filter(|key| key.starts_with("CONTRAIL_") && key != "CONTRAIL_CONFIG")
+            .collect();
+        if !skipped.is_empty() {
+            eprintln!(
+                "note: not copying {} into the unit; put these settings in the config file",
+                skipped.join(", ")
+            );
+        }
+        write_service_file(
+            &unit_path,
+            &render_systemd_unit(&binary, config_path.as_deref()),
+        )?;
+        if run_service_manager("systemctl", &["--user", "daemon-reload"]) {
+            run_service_manager(
+                "systemctl",
+                &["--user", "enable", "--now", SYSTEMD_UNIT_NAME],
+            );
+        }
+        return Ok(());
+    }
+
+    bail!("install-service supports Linux (systemd) and macOS (launchd) only")
+}
+
+fn write_service_file(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies when the file is created; tighten an existing one too.
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("failed to chmod {}", path.display()))?;
+        }
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    io::Write::write_all(&mut file, contents.as_bytes())
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+/// Runs a service-manager command, printing it for manual retry if it fails.
+fn run_service_manager(program: &str, args: &[&str]) -> bool {
+    let ok = Command::new(program)
+        .args(args)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if !ok {
+        eprintln!(
+            "warning: `{program} {}` failed; run it manually to finish setup",
+            args.join(" ")
+        );
+    }
+    ok
+}
+
+fn render_systemd_unit(binary: &Path, config_path: Option<&Path>) -> String {
+    let mut unit = String::from("[Unit]\nDescription=Contrail capture daemon\n\n[Service]\n");
+    unit.push_str(&format!(
+        "ExecStart={}\n",
+        systemd_quote(&binary.to_string_lossy())
+    ));
+    if let Some(config_path) = config_path {
+        unit.push_str(&format!(
+            "Environment={}\n",
+            systemd_quote(&format!(
+                "CONTRAIL_CONFIG={}",
+                config_path.to_string_lossy()
+            ))
+        ));
+    }
+    unit.push_str("Restart=on-failure\nRestartSec=5\n\n[Install]\nWantedBy=default.target\n");
+    unit
+}
+
+/// Quotes `value` for a unit file line, doubling `%` so systemd doesn't expand it as
+/// a specifier.
+fn systemd_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%");
+    format!("\"{escaped}\"")
+}
+
+fn render_launchd_plist(binary: &Path, log_dir: &Path) -> String {
+    let xml = |path: &Path| {
+        path.to_string_lossy()
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+
+    <key>ProgramArguments</key>
+    <array>
+        <string>{binary}</string>
+    </array>
+
+    <key>RunAtLoad</key>
+    <true/>
+
+    <key>KeepAlive</key>
+    <false/>
+
+    <key>StandardOutPath</key>
+    <string>{stdout}</string>
+
+    <key>StandardErrorPath</key>
+    <string>{stderr}</string>
+</dict>
+</plist>
+"#,
+        binary = xml(binary),
+        stdout = xml(&log_dir.join("daemon.log")),
+        stderr = xml(&log_dir.join("daemon_error.log")),
+    )
+}
+
+/// Service managers need an absolute path, so bare names are looked up on PATH.
+fn absolute_binary_path(process: ManagedProcess) -> Result<PathBuf> {
+    let binary = resolve_binary_path(process)?;
+    if binary.is_absolute() {
+        return Ok(binary);
+    }
+    env::var_os("PATH")
+        .into_iter()
+        .flat_map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .map(|dir| dir.join(&binary))
+        .find(|candidate| candidate.is_file())
+        .with_context(|| {
+            format!(
+                "{} binary not found in PATH. Install it or set {}.",
+                process.binary, process.binary_env
+            )
+        })
+}
+
 fn resolve_binary_path(process: ManagedProcess) -> Result<PathBuf> {
     if let Some(path) = env::var_os(process.binary_env)
         && !path.is_empty()
@@ -323,8 +520,11 @@ fn resolve_binary_path(process: ManagedProcess) -> Result<PathBuf> {
 
 #[cfg(test)]
 mod tests {
-    use super::{LifecycleCommand, parse_lifecycle_command};
+    use super::{
+        LifecycleCommand, parse_lifecycle_command, render_launchd_plist, render_systemd_unit,
+    };
     use std::ffi::OsString;
+    use std::path::Path;
 
     #[test]
     fn parses_lifecycle_commands() {
@@ -345,6 +545,38 @@ mod tests {
             parse_lifecycle_command(&args),
             Some(LifecycleCommand::Status)
         ));
+
+        let args = vec![
+            OsString::from("contrail"),
+            OsString::from("install-service"),
+        ];
+        assert!(matches!(
+            parse_lifecycle_command(&args),
+            Some(LifecycleCommand::InstallService)
+        ));
+    }
+
+    #[test]
+    fn renders_systemd_unit_with_quoted_paths_and_env() {
+        let unit = render_systemd_unit(
+            Path::new("/opt/my tools/core_daemon"),
+            Some(Path::new("/data/100%/config.toml")),
+        );
+        assert!(unit.contains("ExecStart=\"/opt/my tools/core_daemon\"\n"));
+        assert!(unit.contains("Environment=\"CONTRAIL_CONFIG=/data/100%%/config.toml\"\n"));
+        assert_eq!(unit.matches("Environment=").count(), 1);
+        assert!(unit.contains("WantedBy=default.target"));
+    }
+
+    #[test]
+    fn renders_launchd_plist_with_escaped_paths() {
+        let plist = render_launchd_plist(
+            Path::new("/Users/a&b/bin/core_daemon"),
+            Path::new("/Users/a&b/.contrail"),
+        );
+        assert!(plist.contains("<string>com.contrail.daemon</string>"));
+        assert!(plist.contains("<string>/Users/a&amp;b/bin/core_daemon</string>"));
+        assert!(plist.contains("<string>/Users/a&amp;b/.contrail/daemon.log</string>"));
     }
 
     #[test]
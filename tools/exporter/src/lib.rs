@@ -1,16 +1,14 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use chrono::DateTime;
+use scrapers::config::ContrailConfig;
+use scrapers::types::Artifact;
 use serde_json::Value;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
 
 pub fn run() -> Result<()> {
-    let input = std::env::var("CONTRAIL_LOG_PATH")
-        .map(PathBuf::from)
-        .ok()
-        .or_else(|| dirs::home_dir().map(|h| h.join(".contrail/logs/master_log.jsonl")))
-        .context("Could not resolve CONTRAIL_LOG_PATH or home directory")?;
+    let input = ContrailConfig::load()?.log_path;
     let output = PathBuf::from("export/curated_dataset.jsonl");
 
     if let Some(parent) = output.parent() {
@@ -29,7 +27,7 @@ pub fn run() -> Result<()> {
 
         if !matches!(
             json.get("source_tool").and_then(Value::as_str),
-            Some("codex-cli" | "cursor" | "claude-code" | "antigravity")
+            Some("codex-cli" | "cursor" | "claude-code" | "gemini-cli" | "antigravity")
         ) {
             continue;
         }
@@ -47,6 +45,15 @@ pub fn run() -> Result<()> {
             function_call_output.truncate(2000);
         }
 
+        if let Some(artifacts) = json.pointer_mut("/interaction/artifacts")
+            && let Ok(mut typed) = serde_json::from_value::<Vec<Artifact>>(artifacts.clone())
+        {
+            for artifact in &mut typed {
+                artifact.truncate_text(2000);
+            }
+            *artifacts = serde_json::to_value(typed)?;
+        }
+
         if let Some(ts) = json.get("timestamp").and_then(Value::as_str)
             && DateTime::parse_from_rfc3339(ts).is_err()
         {
@@ -1,4 +1,6 @@
 use crate::types::DetectedAgents;
+use scrapers::config::cursor_user_dir;
+use scrapers::gemini::{find_session_files, project_hash, recorded_project_root};
 use std::path::{Path, PathBuf};
 
 /// Detect which agents have been used in the given repo by checking their
@@ -18,7 +20,7 @@ pub fn detect_agents(repo_roots: &[String]) -> DetectedAgents {
 }
 
 fn detect_cursor(home: &Path, repo_roots: &[String]) -> bool {
-    let ws_storage = home.join("Library/Application Support/Cursor/User/workspaceStorage");
+    let ws_storage = cursor_user_dir(home).join("workspaceStorage");
     if !ws_storage.is_dir() {
         return false;
     }
@@ -77,6 +79,16 @@ fn detect_claude(home: &Path, repo_roots: &[String]) -> bool {
 }
 
 fn detect_gemini(home: &Path, repo_roots: &[String]) -> bool {
+    // Gemini CLI chats, keyed by project
+    let chats_root = home.join(".gemini/tmp");
+    if find_session_files(&chats_root)
+        .iter()
+        .any(|chat| gemini_chat_repo_root(chat, repo_roots).is_some())
+    {
+        return true;
+    }
+
+    // Antigravity brain sessions
     let brain = home.join(".gemini/antigravity/brain");
     if !brain.is_dir() {
         return false;
@@ -95,6 +107,21 @@ fn detect_gemini(home: &Path, repo_roots: &[String]) -> bool {
     false
 }
 
+/// The repo path a Gemini CLI chat belongs to: its recorded `.project_root` when that
+/// is inside one of the roots, else the root whose hash names the chat's project dir.
+pub fn gemini_chat_repo_root(chat_file: &Path, repo_roots: &[String]) -> Option<String> {
+    if let Some(recorded) = recorded_project_root(chat_file) {
+        if crate::aliases::matches_any_root(&recorded, repo_roots) {
+            return Some(recorded);
+        }
+    }
+    let project_dir = chat_file.parent()?.parent()?.file_name()?.to_str()?;
+    repo_roots
+        .iter()
+        .find(|root| project_hash(root) == project_dir)
+        .cloned()
+}
+
 /// Scan JSONL files in a directory (recursively) for lines containing the repo path.
 fn scan_jsonl_dir_for_repo(dir: &Path, repo_roots: &[String], max_files: usize) -> bool {
     let mut checked = 0usize;
@@ -203,7 +230,7 @@ fn codex_sessions_roots_from_home(home: &Path) -> Vec<PathBuf> {
 
 /// Get standard storage paths for reference.
 pub fn cursor_workspace_storage() -> Option<PathBuf> {
-    dirs::home_dir().map(|h| h.join("Library/Application Support/Cursor/User/workspaceStorage"))
+    dirs::home_dir().map(|h| cursor_user_dir(&h).join("workspaceStorage"))
 }
 
 pub fn codex_sessions_roots() -> Vec<PathBuf> {
@@ -220,9 +247,14 @@ pub fn claude_history_path() -> Option<PathBuf> {
     dirs::home_dir().map(|h| h.join(".claude/history.jsonl"))
 }
 
+pub fn gemini_chats_root() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".gemini/tmp"))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::contains_repo_reference;
+    use super::{contains_repo_reference, gemini_chat_repo_root};
+    use scrapers::gemini::project_hash;
 
     #[test]
     fn contains_repo_reference_matches_exact_or_child_path() {
@@ -242,4 +274,37 @@ mod tests {
             root
         ));
     }
+
+    #[test]
+    fn gemini_chat_matches_by_project_hash_or_recorded_root() {
+        let dir = std::env::temp_dir().join(format!(
+            "memex-detect-tests-gemini-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let roots = vec!["/work/app".to_string()];
+
+        let hashed = dir.join(project_hash("/work/app")).join("chats");
+        std::fs::create_dir_all(&hashed).unwrap();
+        let chat = hashed.join("session-1.json");
+        assert_eq!(
+            gemini_chat_repo_root(&chat, &roots).as_deref(),
+            Some("/work/app")
+        );
+
+        let other = dir.join(project_hash("/work/other"));
+        std::fs::create_dir_all(other.join("chats")).unwrap();
+        let chat = other.join("chats/session-2.json");
+        assert_eq!(gemini_chat_repo_root(&chat, &roots), None);
+        std::fs::write(other.join(".project_root"), "/work/app/sub\n").unwrap();
+        assert_eq!(
+            gemini_chat_repo_root(&chat, &roots).as_deref(),
+            Some("/work/app/sub")
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
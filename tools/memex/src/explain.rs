@@ -1,7 +1,10 @@
 use crate::link;
 use crate::{aliases, detect, readers};
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use scrapers::config::ContrailConfig;
+use scrapers::event_index::{EventIndex, EventQuery};
+use scrapers::types::MasterLog;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
@@ -70,6 +73,8 @@ pub fn run_explain(repo_root: &Path, commit_ref: &str) -> Result<()> {
     println!("Message: {}", link.message);
     println!();
 
+    print_recorded_effects(repo_root, link);
+
     if link.active_sessions.is_empty() {
         println!("No agent sessions were active when this commit was made.");
         return Ok(());
@@ -109,6 +114,83 @@ pub fn run_explain(repo_root: &Path, commit_ref: &str) -> Result<()> {
     Ok(())
 }
 
+/// Session-end snapshots the contrail daemon recorded in this repo while HEAD was the
+/// commit or its parent, i.e. what agent sessions left in the tree around it.
+fn print_recorded_effects(repo_root: &Path, link: &link::CommitLink) {
+    let snapshots = recorded_effects(repo_root, link).unwrap_or_default();
+    if snapshots.is_empty() {
+        return;
+    }
+
+    println!("Recorded file effects ({}):", snapshots.len());
+    for log in &snapshots {
+        let meta = &log.metadata;
+        let head = meta
+            .git_commit_hash
+            .as_deref()
+            .map(short_sha)
+            .unwrap_or_default();
+        let mut line = format!(
+            "  {} {} (HEAD {})",
+            log.source_tool,
+            log.timestamp.format("%Y-%m-%d %H:%M UTC"),
+            head
+        );
+        if let Some(files) = meta.diff_files_changed {
+            line.push_str(&format!(
+                ": {} file(s), +{} -{}",
+                files,
+                meta.diff_insertions.unwrap_or(0),
+                meta.diff_deletions.unwrap_or(0)
+            ));
+        }
+        println!("{}", line);
+        for (label, paths) in [
+            ("created", &meta.files_created),
+            ("modified", &meta.files_modified),
+            ("deleted", &meta.files_deleted),
+        ] {
+            if let Some(paths) = paths.as_ref().filter(|p| !p.is_empty()) {
+                println!("    {}: {}", label, paths.join(", "));
+            }
+        }
+    }
+    println!();
+}
+
+fn recorded_effects(repo_root: &Path, link: &link::CommitLink) -> Result<Vec<MasterLog>> {
+    let config = ContrailConfig::load()?;
+    if !config.log_path.exists() {
+        return Ok(Vec::new());
+    }
+    let index = EventIndex::open_synced(&config.log_path)?;
+
+    let mut heads = vec![link.sha.clone()];
+    heads.extend(git_rev_parse(repo_root, &format!("{}^", link.sha)));
+    let query = EventQuery {
+        since: Some(link.timestamp - Duration::days(7)),
+        until: Some(link.timestamp + Duration::days(1)),
+        ..Default::default()
+    };
+
+    let mut out = Vec::new();
+    index.for_each_event(&query, |log| {
+        let at_commit = log
+            .metadata
+            .git_commit_hash
+            .as_ref()
+            .is_some_and(|sha| heads.contains(sha));
+        if at_commit
+            && log.metadata.file_effects.is_some()
+            && Path::new(&log.project_context).starts_with(repo_root)
+        {
+            out.push(log);
+        }
+        Ok(())
+    })?;
+    Ok(out)
+}
+
 fn git_rev_parse(repo_root: &Path, commit_ref: &str) -> Option<String> {
     let output = Command::new("git")
         .args(["rev-parse", commit_ref])
@@ -49,7 +49,7 @@ enum Commands {
     },
     /// Greppable search across synced sessions + learnings
     Search {
-        /// Literal text query (substring match, not regex)
+        /// Literal text query (substring match, not regex), or a query with --ranked/--semantic
         query: String,
         /// Only search session files modified in the last N days (learnings always searched)
         #[arg(long, default_value_t = 30)]
@@ -63,6 +63,13 @@ enum Commands {
         /// Only print matching filenames (like `rg -l`)
         #[arg(long, default_value_t = false)]
         files: bool,
+        /// Rank paragraphs by BM25 plus embedding similarity instead of matching lines
+        #[arg(long, default_value_t = false, conflicts_with = "case_sensitive")]
+        semantic: bool,
+        /// Rank paragraphs by BM25 from a persistent index; supports "phrases", OR, NOT,
+        /// and tool:/role:/session:/after:/before: filters
+        #[arg(long, default_value_t = false, conflicts_with_all = ["case_sensitive", "semantic"])]
+        ranked: bool,
     },
     /// Encrypt sessions + learnings into .context/vault.age for sharing via git
     Share {
@@ -112,7 +119,17 @@ pub fn run() -> Result<()> {
             limit,
             case_sensitive,
             files,
-        } => search::run_search(&repo_root, &query, days, limit, case_sensitive, files),
+            semantic,
+            ranked,
+        } => {
+            if ranked {
+                search::run_ranked_search(&repo_root, &query, days, limit, files)
+            } else if semantic {
+                search::run_semantic_search(&repo_root, &query, days, limit, files)
+            } else {
+                search::run_search(&repo_root, &query, days, limit, case_sensitive, files)
+            }
+        }
         Commands::Share {
             passphrase,
             passphrase_env,
@@ -263,6 +263,7 @@ mod tests {
                 role: "user".to_string(),
                 content: "hi".to_string(),
                 timestamp: started_at,
+                artifacts: Vec::new(),
             }],
             files_changed: Vec::new(),
         }
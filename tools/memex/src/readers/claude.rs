@@ -119,6 +119,7 @@ fn read_session_jsonl(
             role: parsed.role,
             content: parsed.content,
             timestamp: parsed.timestamp,
+            artifacts: parsed.artifacts,
         };
 
         let session = sessions
@@ -204,6 +205,7 @@ fn read_history_file(
             role: parsed.role,
             content: parsed.content,
             timestamp: parsed.timestamp,
+            artifacts: parsed.artifacts,
         };
 
         let session = sessions.entry(key).or_insert_with(|| Session {
@@ -139,6 +139,7 @@ fn read_codex_jsonl(
             role: parsed.role,
             content: parsed.content,
             timestamp: parsed.timestamp,
+            artifacts: parsed.artifacts,
         };
 
         let session = sessions
@@ -1,12 +1,13 @@
 use crate::types::{Session, Turn};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use scrapers::cursor::{read_cursor_messages, timestamp_from_metadata};
+use scrapers::cursor::{read_workspace_messages, timestamp_from_metadata};
 use std::path::Path;
 
 /// Read Cursor sessions for the given repo.
 /// Finds the workspaceStorage directories that reference this repo, then
-/// extracts conversations from state.vscdb.
+/// extracts conversations from state.vscdb and the composer conversations the
+/// global cursorDiskKV table keeps for that workspace.
 pub fn read_sessions(
     repo_roots: &[String],
     cutoff: &DateTime<Utc>,
@@ -60,19 +61,29 @@ fn read_workspace_sessions(
     repo_str: &str,
     cutoff: &DateTime<Utc>,
 ) -> Result<Vec<Session>> {
-    let messages = read_cursor_messages(db_path)?;
+    let messages = read_workspace_messages(db_path)?;
     if messages.is_empty() {
         return Ok(Vec::new());
     }
 
     // Group messages into conversation chunks.
-    // Cursor doesn't have explicit session IDs, so we split on gaps > 30 min
-    // or when we see a "user" message after an "assistant" with a big time jump.
+    // Composer conversations carry their id; legacy chat data doesn't, so we split
+    // on gaps > 30 min or when we see a "user" message after an "assistant" with a
+    // big time jump.
     let mut conversations: Vec<Vec<(Turn, Option<DateTime<Utc>>)>> = Vec::new();
     let mut current: Vec<(Turn, Option<DateTime<Utc>>)> = Vec::new();
+    let mut current_composer: Option<&str> = None;
 
     for msg in &messages {
         let ts = timestamp_from_metadata(&msg.metadata);
+        let composer = msg
+            .metadata
+            .get("cursor_composer_id")
+            .and_then(|v| v.as_str());
+        if composer != current_composer && !current.is_empty() {
+            conversations.push(std::mem::take(&mut current));
+        }
+        current_composer = composer;
 
         // Check for session boundary: gap > 30 minutes
         if let Some(last) = current.last() {
@@ -89,6 +100,7 @@ fn read_workspace_sessions(
                 role: msg.role.clone(),
                 content: msg.content.clone(),
                 timestamp: ts,
+                artifacts: msg.artifacts.clone(),
             },
             ts,
         ));
@@ -0,0 +1,98 @@
+use crate::types::{Session, Turn};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use scrapers::gemini::{find_session_files, parse_gemini_session};
+use std::path::Path;
+
+/// Read Gemini CLI sessions for the given repo.
+/// Scans ~/.gemini/tmp/*/chats/session-*.json and matches each chat to the repo by
+/// its recorded project root or by Gemini's hash of the project path.
+pub fn read_sessions(
+    repo_roots: &[String],
+    cutoff: &DateTime<Utc>,
+    quiet: bool,
+) -> Result<Vec<Session>> {
+    let chats_root = match crate::detect::gemini_chats_root() {
+        Some(p) if p.is_dir() => p,
+        _ => return Ok(Vec::new()),
+    };
+
+    let mut sessions = Vec::new();
+    for path in find_session_files(&chats_root) {
+        match read_chat_file(&path, repo_roots, cutoff) {
+            Ok(Some(session)) => sessions.push(session),
+            Ok(None) => {}
+            Err(e) => {
+                if !quiet {
+                    eprintln!("warning: gemini chat {:?}: {e}", path);
+                }
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+fn read_chat_file(
+    path: &Path,
+    repo_roots: &[String],
+    cutoff: &DateTime<Utc>,
+) -> Result<Option<Session>> {
+    // Fast path: skip chats that haven't been written since the cutoff.
+    if let Ok(meta) = std::fs::metadata(path) {
+        if let Ok(modified) = meta.modified() {
+            let mod_time: DateTime<Utc> = modified.into();
+            if mod_time < *cutoff {
+                return Ok(None);
+            }
+        }
+    }
+
+    let Some(project_path) = crate::detect::gemini_chat_repo_root(path, repo_roots) else {
+        return Ok(None);
+    };
+
+    let raw = std::fs::read_to_string(path)?;
+    let Some(chat) = parse_gemini_session(&raw) else {
+        return Ok(None);
+    };
+
+    let mut session = Session {
+        tool: "gemini-cli".to_string(),
+        session_id: chat.session_id,
+        project_path,
+        branch: None,
+        started_at: None,
+        ended_at: None,
+        turns: Vec::new(),
+        files_changed: Vec::new(),
+    };
+
+    for parsed in chat.messages {
+        // Skip info/warning/error entries for cleaner transcripts
+        if parsed.role == "system" {
+            continue;
+        }
+        if let Some(ts) = parsed.timestamp {
+            if ts < *cutoff {
+                continue;
+            }
+            if session.started_at.is_none() || session.started_at.is_some_and(|s| ts < s) {
+                session.started_at = Some(ts);
+            }
+            if session.ended_at.is_none() || session.ended_at.is_some_and(|e| ts > e) {
+                session.ended_at = Some(ts);
+            }
+        }
+        session.turns.push(Turn {
+            role: parsed.role,
+            content: parsed.content,
+            timestamp: parsed.timestamp,
+            artifacts: parsed.artifacts,
+        });
+    }
+
+    if session.turns.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(session))
+}
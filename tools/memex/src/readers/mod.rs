@@ -1,6 +1,7 @@
 pub mod claude;
 pub mod codex;
 pub mod cursor;
+pub mod gemini;
 
 use crate::types::Session;
 
@@ -14,12 +15,6 @@ pub fn read_all_sessions(
     let cutoff = chrono::Utc::now() - chrono::Duration::days(max_age_days as i64);
     let mut sessions = Vec::new();
 
-    if agents.gemini && !quiet {
-        eprintln!(
-            "warning: gemini detected but reader is not implemented; skipping gemini sessions"
-        );
-    }
-
     if agents.claude {
         match claude::read_sessions(repo_roots, &cutoff, quiet) {
             Ok(s) => sessions.extend(s),
@@ -53,6 +48,17 @@ pub fn read_all_sessions(
         }
     }
 
+    if agents.gemini {
+        match gemini::read_sessions(repo_roots, &cutoff, quiet) {
+            Ok(s) => sessions.extend(s),
+            Err(e) => {
+                if !quiet {
+                    eprintln!("warning: gemini reader: {e}");
+                }
+            }
+        }
+    }
+
     // Sort by start time, oldest first
     sessions.sort_by_key(|s| s.started_at);
     sessions
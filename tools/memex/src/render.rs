@@ -1,9 +1,9 @@
 use crate::types::Session;
 use scrapers::sentry::Sentry;
+use scrapers::types::Artifact;
 
 /// Render a session as a readable markdown transcript.
-pub fn render_session(session: &Session) -> String {
-    let sentry = Sentry::new();
+pub fn render_session(session: &Session, sentry: &Sentry) -> String {
     let mut out = String::new();
 
     // Header
@@ -28,22 +28,106 @@ pub fn render_session(session: &Session) -> String {
     out.push_str("\n\n");
 
     // Turns
+    let mut files_changed = session.files_changed.clone();
     for turn in &session.turns {
         out.push_str(&format!("## {}\n", turn.role));
         out.push_str(&turn.content);
         out.push_str("\n\n");
+
+        let lines = artifact_lines(&turn.artifacts);
+        if !lines.is_empty() {
+            for line in lines {
+                out.push_str(&format!("- {}\n", line));
+            }
+            out.push('\n');
+        }
+        for path in turn.artifacts.iter().filter_map(patched_path) {
+            if !files_changed.iter().any(|f| f == path) {
+                files_changed.push(path.to_string());
+            }
+        }
     }
 
     // Footer
-    if !session.files_changed.is_empty() {
+    if !files_changed.is_empty() {
         out.push_str("---\n");
-        out.push_str(&format!(
-            "Files changed: {}\n",
-            session.files_changed.join(", ")
-        ));
+        out.push_str(&format!("Files changed: {}\n", files_changed.join(", ")));
     }
 
     // Redact secrets
     let (redacted, _flags) = sentry.scan_and_redact(&out);
     redacted
 }
+
+/// One line per artifact worth reading: a tool call whose patch/command/read follows
+/// is covered by that artifact, and successful tool results are left out.
+fn artifact_lines(artifacts: &[Artifact]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (i, artifact) in artifacts.iter().enumerate() {
+        let skip = match artifact {
+            Artifact::ToolCall { .. } => matches!(
+                artifacts.get(i + 1),
+                Some(Artifact::Patch { .. } | Artifact::Command { .. } | Artifact::FileRead { .. })
+            ),
+            Artifact::ToolResult { is_error, .. } => !is_error,
+            _ => false,
+        };
+        if !skip {
+            lines.push(artifact.summary());
+        }
+    }
+    lines
+}
+
+fn patched_path(artifact: &Artifact) -> Option<&str> {
+    match artifact {
+        Artifact::Patch { path, .. } => path.as_deref(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Turn;
+
+    #[test]
+    fn renders_artifacts_and_patched_files() {
+        let session = Session {
+            tool: "claude-code".to_string(),
+            session_id: "s1".to_string(),
+            project_path: "/repo".to_string(),
+            branch: None,
+            started_at: None,
+            ended_at: None,
+            turns: vec![Turn {
+                role: "assistant".to_string(),
+                content: "Fixing it.".to_string(),
+                timestamp: None,
+                artifacts: vec![
+                    Artifact::ToolCall {
+                        call_id: None,
+                        name: "Edit".to_string(),
+                        input: "{}".to_string(),
+                    },
+                    Artifact::Patch {
+                        path: Some("src/lib.rs".to_string()),
+                        diff: "-a\n+b\n".to_string(),
+                    },
+                    Artifact::ToolResult {
+                        call_id: None,
+                        output: "ok".to_string(),
+                        is_error: false,
+                    },
+                ],
+            }],
+            files_changed: Vec::new(),
+        };
+
+        let rendered = render_session(&session, &Sentry::new());
+        assert!(rendered.contains("- patch: src/lib.rs\n"));
+        assert!(!rendered.contains("tool_call: Edit"));
+        assert!(!rendered.contains("tool_result"));
+        assert!(rendered.contains("Files changed: src/lib.rs\n"));
+    }
+}
@@ -1,4 +1,9 @@
 use anyhow::Result;
+use chrono::NaiveDateTime;
+use scrapers::config::ContrailConfig;
+use scrapers::search_index::{memex_index_path, Document, SearchIndex, SearchQuery};
+use scrapers::semantic::{embedder_from_config, rank_hybrid, Embedder};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
@@ -105,6 +110,258 @@ pub fn run_search(
     Ok(())
 }
 
+/// `memex search --semantic`: rank paragraphs of the same files by BM25 blended with
+/// embedding similarity (see `scrapers::semantic`), best first.
+///
+/// Output is `<path>:<line>:<first line of paragraph>`, or each file once with
+/// `--files`. The embedder comes from Contrail's `[search]` config.
+pub fn run_semantic_search(
+    repo_root: &Path,
+    query: &str,
+    days: u64,
+    limit: usize,
+    files: bool,
+) -> Result<()> {
+    if query.trim().is_empty() {
+        return Ok(());
+    }
+    let config = ContrailConfig::load()?;
+    let embedder = embedder_from_config(&config)?;
+    let hits = semantic_hits(repo_root, query, days, limit, files, embedder.as_ref())?;
+    if hits.is_empty() {
+        process::exit(1);
+    }
+    for hit in hits {
+        println!("{hit}");
+    }
+    Ok(())
+}
+
+fn semantic_hits(
+    repo_root: &Path,
+    query: &str,
+    days: u64,
+    limit: usize,
+    files: bool,
+    embedder: &dyn Embedder,
+) -> Result<Vec<String>> {
+    let paths = context_files(repo_root, cutoff_time(days))?;
+    let mut chunks = Vec::new();
+    for path in &paths {
+        let Ok(text) = fs::read_to_string(path) else {
+            continue;
+        };
+        for (line, chunk) in paragraphs(&text) {
+            chunks.push((path, line, chunk));
+        }
+    }
+    let docs: Vec<&str> = chunks.iter().map(|(_, _, chunk)| chunk.as_str()).collect();
+
+    let mut out = Vec::new();
+    let mut seen_files = std::collections::HashSet::new();
+    for (i, _) in rank_hybrid(embedder, query, &docs)? {
+        if out.len() >= limit {
+            break;
+        }
+        let (path, line, chunk) = &chunks[i];
+        let display = repo_relative(repo_root, path);
+        if files {
+            if seen_files.insert(display.clone()) {
+                out.push(display);
+            }
+            continue;
+        }
+        let first = chunk.lines().next().unwrap_or("");
+        out.push(format!("{display}:{line}:{first}"));
+    }
+    Ok(out)
+}
+
+/// `memex search --ranked`: query the persistent BM25 index over the same files
+/// (see `scrapers::search_index`), which supports `"phrases"`, `OR`/`NOT`, and
+/// `tool:`, `role:`, `session:`, `after:` and `before:` filters.
+///
+/// The index lives under Contrail's search index directory and is refreshed from
+/// whichever files changed since the last search. Output is
+/// `<path>:<line>:<snippet>` with matches wrapped in `**`, or each file once with
+/// `--files`.
+pub fn run_ranked_search(
+    repo_root: &Path,
+    query: &str,
+    days: u64,
+    limit: usize,
+    files: bool,
+) -> Result<()> {
+    let query: SearchQuery = query.parse()?;
+    if query.is_empty() {
+        return Ok(());
+    }
+    let config = ContrailConfig::load()?;
+    let index_path = memex_index_path(&config.search_index_dir, repo_root);
+    let hits = ranked_hits(repo_root, &index_path, &query, days, limit, files)?;
+    if hits.is_empty() {
+        process::exit(1);
+    }
+    for hit in hits {
+        println!("{hit}");
+    }
+    Ok(())
+}
+
+fn ranked_hits(
+    repo_root: &Path,
+    index_path: &Path,
+    query: &SearchQuery,
+    days: u64,
+    limit: usize,
+    files: bool,
+) -> Result<Vec<String>> {
+    // Index every file, keyed by its repo-relative path and stamped with its mtime
+    // and size; `--days` only narrows which of them are searched.
+    let mut paths = HashMap::new();
+    let mut desired = HashMap::new();
+    for path in context_files(repo_root, None)? {
+        let Ok(meta) = fs::metadata(&path) else {
+            continue;
+        };
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let source = repo_relative(repo_root, &path);
+        desired.insert(
+            source.clone(),
+            format!("{}:{}", modified.as_nanos(), meta.len()),
+        );
+        paths.insert(source, path);
+    }
+    let within: HashSet<String> = context_files(repo_root, cutoff_time(days))?
+        .iter()
+        .map(|p| repo_relative(repo_root, p))
+        .collect();
+
+    let mut index = SearchIndex::open(index_path)?;
+    index.reconcile(&desired, |needed, store| {
+        for source in needed {
+            let text = fs::read_to_string(&paths[source]).unwrap_or_default();
+            store(source, file_documents(source, &paths[source], &text))?;
+        }
+        Ok(())
+    })?;
+
+    // Fetch extra hits with --files, since several may come from one file.
+    let fetch = if files {
+        limit.saturating_mul(20)
+    } else {
+        limit
+    };
+    let mut out = Vec::new();
+    let mut seen_files = HashSet::new();
+    for hit in index.search(query, fetch, 0, Some(&within))? {
+        if out.len() >= limit {
+            break;
+        }
+        if files {
+            if seen_files.insert(hit.source.clone()) {
+                out.push(hit.source);
+            }
+            continue;
+        }
+        let snippet = hit.snippet.marked("**", "**").replace('\n', " ");
+        out.push(format!("{}:{snippet}", hit.key));
+    }
+    Ok(out)
+}
+
+/// One document per paragraph, keyed `<source>:<line>`. Session files named
+/// `<timestamp>_<tool>_<session>.md` also fill in those fields, and each paragraph
+/// takes its role from the closest `## <role>` heading above it.
+fn file_documents(source: &str, path: &Path, text: &str) -> Vec<Document> {
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("");
+    let mut parts = stem.splitn(3, '_');
+    let timestamp = parts
+        .next()
+        .and_then(|ts| NaiveDateTime::parse_from_str(ts, "%Y-%m-%dT%H-%M-%S").ok())
+        .map(|ts| ts.and_utc());
+    let (tool, session) = match (timestamp, parts.next(), parts.next()) {
+        (Some(_), Some(tool), Some(session)) => (tool.to_string(), session.to_string()),
+        _ => (String::new(), String::new()),
+    };
+
+    let mut role = String::new();
+    paragraphs(text)
+        .into_iter()
+        .map(|(line, body)| {
+            if let Some(heading) = body.lines().next().and_then(|l| l.strip_prefix("## ")) {
+                role = heading.trim().to_lowercase();
+            }
+            Document {
+                key: format!("{source}:{line}"),
+                tool: tool.clone(),
+                role: role.clone(),
+                project: String::new(),
+                session: session.clone(),
+                timestamp,
+                body,
+            }
+        })
+        .collect()
+}
+
+/// `LEARNINGS.md` followed by the session files (sorted by name) modified after
+/// `cutoff`, if any.
+fn context_files(repo_root: &Path, cutoff: Option<SystemTime>) -> Result<Vec<PathBuf>> {
+    let context_dir = repo_root.join(".context");
+    let mut paths = Vec::new();
+    let learnings_path = context_dir.join("LEARNINGS.md");
+    if learnings_path.is_file() {
+        paths.push(learnings_path);
+    }
+    let sessions_dir = context_dir.join("sessions");
+    if sessions_dir.is_dir() {
+        let mut sessions: Vec<PathBuf> = fs::read_dir(&sessions_dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension() == Some(OsStr::new("md")))
+            .filter(|p| {
+                let modified = fs::metadata(p).and_then(|m| m.modified()).ok();
+                match (cutoff, modified) {
+                    (Some(cutoff), Some(modified)) => modified >= cutoff,
+                    _ => true,
+                }
+            })
+            .collect();
+        sessions.sort();
+        paths.extend(sessions);
+    }
+    Ok(paths)
+}
+
+/// Blank-line separated paragraphs, with headings starting a new one, paired with
+/// the 1-based line each starts on.
+fn paragraphs(text: &str) -> Vec<(usize, String)> {
+    let mut out = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (idx, line) in text.lines().enumerate() {
+        let blank = line.trim().is_empty();
+        if blank || line.starts_with('#') {
+            if let Some(done) = current.take() {
+                out.push(done);
+            }
+        }
+        if blank {
+            continue;
+        }
+        let (_, chunk) = current.get_or_insert_with(|| (idx + 1, String::new()));
+        if !chunk.is_empty() {
+            chunk.push('\n');
+        }
+        chunk.push_str(line);
+    }
+    out.extend(current);
+    out
+}
+
 fn cutoff_time(days: u64) -> Option<SystemTime> {
     if days == 0 {
         return None;
@@ -181,7 +438,8 @@ fn repo_relative(repo_root: &Path, path: &Path) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::line_matches;
+    use super::{line_matches, paragraphs, ranked_hits, semantic_hits};
+    use scrapers::semantic::HashingEmbedder;
 
     #[test]
     fn literal_substring_case_insensitive() {
@@ -200,4 +458,100 @@ mod tests {
         assert!(line_matches("Hello World", "World", None, true));
         assert!(!line_matches("Hello World", "world", None, true));
     }
+
+    #[test]
+    fn semantic_search_ranks_paragraphs() {
+        let dir = std::env::temp_dir().join(format!(
+            "memex-search-tests-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let sessions = dir.join(".context/sessions");
+        std::fs::create_dir_all(&sessions).unwrap();
+        std::fs::write(
+            sessions.join("2026-01-02T10-00-00_codex_abc.md"),
+            "# Session: 2026-01-02 10:00 UTC\nTool: codex\n\n## user\nthe schema migration failed on deploy\n\n## assistant\nRolled back the migration and fixed the schema.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(".context/LEARNINGS.md"),
+            "# Learnings\n\n- Prefer small commits.\n",
+        )
+        .unwrap();
+
+        let embedder = HashingEmbedder::default();
+        let hits =
+            semantic_hits(&dir, "why did the migration fail", 0, 2, false, &embedder).unwrap();
+        assert_eq!(
+            hits,
+            [
+                ".context/sessions/2026-01-02T10-00-00_codex_abc.md:4:## user",
+                ".context/sessions/2026-01-02T10-00-00_codex_abc.md:7:## assistant",
+            ]
+        );
+        let files = semantic_hits(&dir, "migration", 0, 10, true, &embedder).unwrap();
+        assert_eq!(
+            files,
+            [".context/sessions/2026-01-02T10-00-00_codex_abc.md"]
+        );
+
+        assert_eq!(
+            paragraphs("# A\ntext\n\nmore\n## B\nx"),
+            [
+                (1, "# A\ntext".to_string()),
+                (4, "more".to_string()),
+                (5, "## B\nx".to_string()),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn ranked_search_uses_the_index() {
+        let dir = std::env::temp_dir().join(format!(
+            "memex-ranked-tests-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let sessions = dir.join(".context/sessions");
+        std::fs::create_dir_all(&sessions).unwrap();
+        let session = sessions.join("2026-01-02T10-00-00_codex-cli_abc.md");
+        std::fs::write(
+            &session,
+            "# Session: 2026-01-02 10:00 UTC\n\n## user\nthe schema migration failed\n\n## assistant\nRolled back the schema migration.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(".context/LEARNINGS.md"),
+            "# Learnings\n\n- Run the migration twice.\n",
+        )
+        .unwrap();
+        let index_path = dir.join("index.sqlite");
+        let search = |q: &str, files: bool| {
+            ranked_hits(&dir, &index_path, &q.parse().unwrap(), 0, 10, files).unwrap()
+        };
+
+        assert_eq!(
+            search("\"schema migration\" role:user", false),
+            [".context/sessions/2026-01-02T10-00-00_codex-cli_abc.md:3:## user the **schema** **migration** failed"]
+        );
+        assert_eq!(
+            search("migration -tool:codex-cli", false),
+            [".context/LEARNINGS.md:3:- Run the **migration** twice."]
+        );
+        assert_eq!(search("migration after:2026-01-01", true).len(), 1);
+
+        // Edits are picked up on the next search.
+        std::fs::write(&session, "## user\nnothing to see\n").unwrap();
+        assert!(search("schema", false).is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
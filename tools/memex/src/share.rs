@@ -1,5 +1,5 @@
-use age::secrecy::SecretString;
 use anyhow::{Context, Result};
+pub use scrapers::vault::{decrypt_bytes, encrypt_bytes};
 use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Component, Path, PathBuf};
@@ -163,19 +163,6 @@ pub fn resolve_passphrase(
     anyhow::bail!("{action} requires --passphrase or --passphrase-env <VAR>")
 }
 
-pub fn encrypt_bytes(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
-    let secret = SecretString::from(passphrase.to_string());
-    let recipient = age::scrypt::Recipient::new(secret.clone());
-    age::encrypt(&recipient, plaintext).map_err(|e| anyhow::anyhow!("encryption failed: {e}"))
-}
-
-pub fn decrypt_bytes(passphrase: &str, encrypted: &[u8]) -> Result<Vec<u8>> {
-    let secret = SecretString::from(passphrase.to_string());
-    let identity = age::scrypt::Identity::new(secret);
-    age::decrypt(&identity, encrypted)
-        .map_err(|e| anyhow::anyhow!("decryption failed (wrong passphrase?): {e}"))
-}
-
 fn safe_context_join(context_dir: &Path, rel_path: &str) -> Result<PathBuf> {
     let rel = Path::new(rel_path);
     let mut out = context_dir.to_path_buf();
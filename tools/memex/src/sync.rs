@@ -3,6 +3,8 @@ use crate::detect;
 use crate::readers;
 use crate::render;
 use anyhow::{Context, Result};
+use scrapers::config::ContrailConfig;
+use scrapers::sentry::Sentry;
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
@@ -32,6 +34,7 @@ pub fn run_sync(repo_root: &Path, max_age_days: u64, quiet: bool) -> Result<()>
     // Read sessions from all detected agents
     let sessions = readers::read_all_sessions(&repo_roots, &agents, max_age_days, quiet);
 
+    let sentry = Sentry::from_config(&ContrailConfig::load()?)?;
     let mut written = 0usize;
     let mut skipped = 0usize;
 
@@ -41,7 +44,7 @@ pub fn run_sync(repo_root: &Path, max_age_days: u64, quiet: bool) -> Result<()>
             continue;
         }
 
-        let rendered = render::render_session(session);
+        let rendered = render::render_session(session, &sentry);
         let base_filename = session.filename();
 
         let filename = if existing.contains(&base_filename) {
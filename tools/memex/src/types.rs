@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use scrapers::types::Artifact;
 
 /// A single turn in a conversation (one user or assistant message).
 #[derive(Debug, Clone)]
@@ -7,6 +8,8 @@ pub struct Turn {
     pub content: String,
     #[allow(dead_code)]
     pub timestamp: Option<DateTime<Utc>>,
+    /// Tool calls, patches and commands the turn recorded.
+    pub artifacts: Vec<Artifact>,
 }
 
 /// A complete session: a sequence of turns from one agent in one project.
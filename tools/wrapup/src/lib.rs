@@ -1,11 +1,13 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Datelike, Local, Timelike, Utc};
-use contrail_types::MasterLog;
+use chrono::{DateTime, Datelike, Local, TimeZone, Timelike, Utc};
+use scrapers::config::ContrailConfig;
+use scrapers::event_index::{EventIndex, EventQuery};
+use scrapers::types::Metadata;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 mod report;
@@ -222,7 +224,10 @@ pub fn run() -> Result<()> {
             .or_else(|| start.as_ref().map(|d| d.year()))
             .unwrap_or_else(|| Local::now().year())
     });
-    let log_path = log_path.unwrap_or_else(default_log_path);
+    let log_path = match log_path {
+        Some(path) => path,
+        None => ContrailConfig::load()?.log_path,
+    };
     let start_filter = start;
     let end_filter = end;
     let mut wrapup = compute_wrapup(&log_path, year, start_filter, end_filter, top_n)?;
@@ -298,7 +303,7 @@ Options:
   --end DATE      Range end (YYYY-MM-DD or RFC3339); cannot combine with --year/--last-days
   --last-days N   Range end=now, start=now-N days; cannot combine with --year/--start/--end
   --cursor-usage  Fetch Cursor token usage from Cursor backend API (requires Cursor login; uses local access token)
-  --log PATH      Master log path (default: ~/.contrail/logs/master_log.jsonl or $CONTRAIL_LOG_PATH)
+  --log PATH      Master log path (default: log.path from ~/.contrail/config.toml or $CONTRAIL_LOG_PATH)
   --out PATH      Write JSON output to a file (default: stdout)
   --html PATH     Write HTML report to a file
   --top N         Top-N lists size (default: 10)
@@ -329,16 +334,6 @@ fn parse_date_arg(input: &str, boundary: DateBoundary) -> Result<DateTime<Utc>>
     ))
 }
 
-fn default_log_path() -> PathBuf {
-    if let Ok(path) = std::env::var("CONTRAIL_LOG_PATH")
-        && !path.trim().is_empty()
-    {
-        return PathBuf::from(path);
-    }
-    let home = dirs::home_dir().expect("Could not find home directory");
-    home.join(".contrail/logs/master_log.jsonl")
-}
-
 fn compute_wrapup(
     log_path: &Path,
     year: i32,
@@ -346,8 +341,20 @@ fn compute_wrapup(
     end: Option<DateTime<Utc>>,
     top_n: usize,
 ) -> Result<Wrapup> {
-    let file = File::open(log_path).with_context(|| format!("open {:?}", log_path))?;
-    let reader = BufReader::new(file);
+    let mut query = EventQuery::default();
+    if start.is_some() || end.is_some() {
+        query.since = start;
+        query.until = end;
+    } else {
+        query.since = Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).single();
+        query.until = Utc
+            .with_ymd_and_hms(year + 1, 1, 1, 0, 0, 0)
+            .single()
+            .map(|ts| ts - chrono::Duration::milliseconds(1));
+    }
+    let index =
+        EventIndex::open_synced(log_path).with_context(|| format!("index {:?}", log_path))?;
+    let logs = index.query(&query)?;
 
     let mut turns_total: u64 = 0;
     let mut roles: HashMap<String, u64> = HashMap::new();
@@ -379,13 +386,7 @@ fn compute_wrapup(
     let mut last_seen_map: HashMap<(String, String), DateTime<Utc>> = HashMap::new();
     let mut sub_session_index_map: HashMap<(String, String), usize> = HashMap::new();
 
-    for line in reader.lines() {
-        let line = line?;
-        let log = match serde_json::from_str::<MasterLog>(&line) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-
+    for log in logs {
         if start.is_some() || end.is_some() {
             if start.is_some_and(|s| log.timestamp < s) {
                 continue;
@@ -431,67 +432,47 @@ fn compute_wrapup(
             *redacted_labels.entry(label.clone()).or_insert(0) += 1;
         }
 
-        let meta_obj = log.metadata.as_object();
-        if let Some(obj) = meta_obj {
-            if obj
-                .get("copied_to_clipboard")
-                .and_then(Value::as_bool)
-                .unwrap_or(false)
-            {
-                clipboard_hits += 1;
-            }
-            if let Some(arr) = obj.get("file_effects").and_then(Value::as_array) {
-                file_effects += arr.len() as u64;
-                for effect in arr {
-                    let path_str = effect
-                        .as_str()
-                        .or_else(|| effect.get("path").and_then(Value::as_str));
-
-                    if let Some(path) = path_str
-                        && let Some(ext) = Path::new(path).extension().and_then(|e| e.to_str())
-                    {
-                        let ext = ext.to_lowercase();
-                        if !matches!(
-                            ext.as_str(),
-                            "json" | "md" | "txt" | "csv" | "png" | "jpg" | "lock"
-                        ) {
-                            *language_counts.entry(ext).or_insert(0) += 1;
-                        }
+        let meta = &log.metadata;
+        if meta.copied_to_clipboard == Some(true) {
+            clipboard_hits += 1;
+        }
+        if let Some(effects) = &meta.file_effects {
+            file_effects += effects.len() as u64;
+            for effect in effects {
+                if let Some(ext) = Path::new(effect).extension().and_then(|e| e.to_str()) {
+                    let ext = ext.to_lowercase();
+                    if !matches!(
+                        ext.as_str(),
+                        "json" | "md" | "txt" | "csv" | "png" | "jpg" | "lock"
+                    ) {
+                        *language_counts.entry(ext).or_insert(0) += 1;
                     }
                 }
             }
-            if obj
-                .get("interrupted")
-                .and_then(Value::as_bool)
-                .unwrap_or(false)
-            {
-                let key = (log.source_tool.clone(), effective_session_id.clone());
-                let sess = sessions.entry(key).or_insert_with(|| SessionAgg {
-                    source_tool: log.source_tool.clone(),
-                    session_id: effective_session_id.clone(),
-                    ..Default::default()
-                });
-                sess.interrupted = true;
-            }
-            if let Some(model) = obj.get("model").and_then(Value::as_str) {
-                let model = model.trim();
-                if !model.is_empty() {
-                    *model_counts.entry(model.to_string()).or_insert(0) += 1;
-                }
+        }
+        if meta.interrupted == Some(true) {
+            let key = (log.source_tool.clone(), effective_session_id.clone());
+            let sess = sessions.entry(key).or_insert_with(|| SessionAgg {
+                source_tool: log.source_tool.clone(),
+                session_id: effective_session_id.clone(),
+                ..Default::default()
+            });
+            sess.interrupted = true;
+        }
+        if let Some(model) = &meta.model {
+            let model = model.trim();
+            if !model.is_empty() {
+                *model_counts.entry(model.to_string()).or_insert(0) += 1;
             }
+        }
 
-            if log.source_tool == "antigravity"
-                && let Some(n) = obj
-                    .get("antigravity_image_count")
-                    .and_then(Value::as_u64)
-                    .or_else(|| {
-                        obj.get("antigravity_image_count")
-                            .and_then(Value::as_i64)
-                            .and_then(|v| u64::try_from(v).ok())
-                    })
-            {
-                antigravity_images = antigravity_images.saturating_add(n);
-            }
+        if log.source_tool == "antigravity"
+            && let Some(n) = meta
+                .extra
+                .get("antigravity_image_count")
+                .and_then(Value::as_u64)
+        {
+            antigravity_images = antigravity_images.saturating_add(n);
         }
 
         if log.interaction.role == "user" {
@@ -525,33 +506,27 @@ fn compute_wrapup(
                 .map_or(log.timestamp, |v| v.max(log.timestamp)),
         );
 
-        if let Some(obj) = meta_obj {
-            if let Some(arr) = obj.get("file_effects").and_then(Value::as_array) {
-                sess.file_effects += arr.len();
-            }
-            if obj
-                .get("copied_to_clipboard")
-                .and_then(Value::as_bool)
-                .unwrap_or(false)
-            {
-                sess.clipboard_hits += 1;
-            }
-            if let Some(branch) = obj.get("git_branch").and_then(Value::as_str) {
-                let branch = branch.trim();
-                if !branch.is_empty() {
-                    sess.git_branches.insert(branch.to_string());
-                }
+        if let Some(effects) = &meta.file_effects {
+            sess.file_effects += effects.len();
+        }
+        if meta.copied_to_clipboard == Some(true) {
+            sess.clipboard_hits += 1;
+        }
+        if let Some(branch) = &meta.git_branch {
+            let branch = branch.trim();
+            if !branch.is_empty() {
+                sess.git_branches.insert(branch.to_string());
             }
-            if let Some(model) = obj.get("model").and_then(Value::as_str) {
-                let model = model.trim();
-                if !model.is_empty() {
-                    sess.models.insert(model.to_string());
-                }
+        }
+        if let Some(model) = &meta.model {
+            let model = model.trim();
+            if !model.is_empty() {
+                sess.models.insert(model.to_string());
             }
-
-            update_cumulative_tokens_from_metadata(sess, obj);
         }
 
+        update_cumulative_tokens_from_metadata(sess, meta);
+
         if log.source_tool == "codex-cli"
             && log.interaction.content.contains("\"token_count\"")
             && let Some(usage) = extract_token_count_from_content(&log.interaction.content)
@@ -819,7 +794,7 @@ fn fetch_cursor_usage(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Cursor
 
 fn read_cursor_access_token() -> Result<String> {
     let home = dirs::home_dir().context("could not resolve home directory")?;
-    let db_path = home.join("Library/Application Support/Cursor/User/globalStorage/state.vscdb");
+    let db_path = scrapers::config::cursor_user_dir(&home).join("globalStorage/state.vscdb");
 
     let conn = rusqlite::Connection::open(&db_path)
         .with_context(|| format!("open Cursor globalStorage DB: {:?}", db_path))?;
@@ -996,44 +971,33 @@ fn summarize_tokens(sessions: &HashMap<(String, String), SessionAgg>) -> TokensS
     }
 }
 
-fn update_cumulative_tokens_from_metadata(
-    sess: &mut SessionAgg,
-    meta: &serde_json::Map<String, Value>,
-) {
-    let read_u64 = |key: &str| {
-        meta.get(key).and_then(Value::as_u64).or_else(|| {
-            meta.get(key)
-                .and_then(Value::as_i64)
-                .and_then(|n| u64::try_from(n).ok())
-        })
-    };
-
-    let total = read_u64("usage_cumulative_total_tokens").unwrap_or(0);
+fn update_cumulative_tokens_from_metadata(sess: &mut SessionAgg, meta: &Metadata) {
+    let total = meta.usage_cumulative_total_tokens.unwrap_or(0);
     if total > 0 {
         sess.saw_token_cumulative = true;
         sess.token_cumulative_total_max = sess.token_cumulative_total_max.max(total);
         sess.token_cumulative_prompt_max = sess
             .token_cumulative_prompt_max
-            .max(read_u64("usage_cumulative_prompt_tokens").unwrap_or(0));
+            .max(meta.usage_cumulative_prompt_tokens.unwrap_or(0));
         sess.token_cumulative_completion_max = sess
             .token_cumulative_completion_max
-            .max(read_u64("usage_cumulative_completion_tokens").unwrap_or(0));
+            .max(meta.usage_cumulative_completion_tokens.unwrap_or(0));
         sess.token_cumulative_cached_input_max = sess
             .token_cumulative_cached_input_max
-            .max(read_u64("usage_cumulative_cached_input_tokens").unwrap_or(0));
+            .max(meta.usage_cumulative_cached_input_tokens.unwrap_or(0));
         sess.token_cumulative_reasoning_output_max = sess
             .token_cumulative_reasoning_output_max
-            .max(read_u64("usage_cumulative_reasoning_output_tokens").unwrap_or(0));
+            .max(meta.usage_cumulative_reasoning_output_tokens.unwrap_or(0));
     }
 
-    let prompt_turn = read_u64("usage_prompt_tokens").unwrap_or(0);
-    let completion_turn = read_u64("usage_completion_tokens").unwrap_or(0);
+    let prompt_turn = meta.usage_prompt_tokens.unwrap_or(0);
+    let completion_turn = meta.usage_completion_tokens.unwrap_or(0);
     if prompt_turn > 0 || completion_turn > 0 {
         sess.saw_token_per_turn = true;
         sess.token_sum_prompt += prompt_turn;
         sess.token_sum_completion += completion_turn;
-        sess.token_sum_cached_input += read_u64("usage_cached_input_tokens").unwrap_or(0);
-        sess.token_sum_cache_creation += read_u64("usage_cache_creation_tokens").unwrap_or(0);
+        sess.token_sum_cached_input += meta.usage_cached_input_tokens.unwrap_or(0);
+        sess.token_sum_cache_creation += meta.usage_cache_creation_tokens.unwrap_or(0);
     }
 }
 